@@ -92,6 +92,17 @@ def api(base_url: str):
     attributes = kvs.deserialize_attributes(response.content)
     assert len(attributes) == 1
 
+    # the same with JSON
+    response = requests.get(f'{base_url}/kvs/get/some/attr', headers={'Accept': 'application/json'})
+    assert response.status_code == 200
+    attributes = response.json()
+    assert attributes[0]['namespace'] == 'some'
+
+    attributes[0]['name'] = 'json'
+    response = requests.post(f'{base_url}/kvs/set', json=attributes)
+    assert response.status_code == 200
+    assert kvs.get_attribute("some", "json") is not None
+
 
 if __name__ == "__main__":
     abi()
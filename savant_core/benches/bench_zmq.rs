@@ -45,6 +45,7 @@ fn bench_zmq_dealer_router(b: &mut Bencher) -> anyhow::Result<()> {
                     topic,
                     routing_id,
                     data,
+                    ..
                 } if topic == b"test" && routing_id.is_some() && data.len() == 1 => {}
                 _ => {
                     panic!("Unexpected result: {:?}", res);
@@ -107,6 +108,7 @@ fn bench_nonblocking_zmq_dealer_router(b: &mut Bencher) -> anyhow::Result<()> {
                         topic,
                         routing_id,
                         data,
+                        ..
                     } if topic == b"test" && routing_id.is_some() && data.len() == 1 => {}
                     _ => {
                         panic!("Unexpected result: {:?}", res);
@@ -0,0 +1,516 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use anyhow::bail;
+use hashbrown::HashMap;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::match_query::MatchQuery;
+use crate::pipeline::stage::PipelineStage;
+use crate::pipeline::{
+    Pipeline, PipelinePayload, PipelineStageFunction, PipelineStageFunctionOrder,
+};
+use crate::primitives::attribute_value::AttributeValue;
+use crate::primitives::frame::VideoFrameProxy;
+use crate::primitives::object::ObjectOperations;
+use crate::primitives::{IntersectionKind, Point, PolygonalArea, RBBox, Segment, WithAttributes};
+
+/// The point of the track box representing the object position.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrackAnchor {
+    Center,
+    BottomCenter,
+}
+
+impl TrackAnchor {
+    fn point(&self, bbox: &RBBox) -> Point {
+        match self {
+            TrackAnchor::Center => Point::new(bbox.get_xc(), bbox.get_yc()),
+            TrackAnchor::BottomCenter => {
+                let vertices = bbox.get_vertices();
+                // the middle of the edge between the 3rd and the 4th vertices is the
+                // bottom center of the non-rotated box
+                Point::new(
+                    (vertices[2].0 + vertices[3].0) / 2.0,
+                    (vertices[2].1 + vertices[3].1) / 2.0,
+                )
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Zone {
+    pub name: String,
+    /// The area, the edge tags are reported with the crossing events. A two-vertex
+    /// area acts as a line which can only be crossed.
+    pub area: PolygonalArea,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ZoneAnalyticsConfig {
+    pub zones: Vec<Zone>,
+    /// The namespace of the attributes written to frames and objects.
+    pub namespace: String,
+    pub anchor: TrackAnchor,
+    /// The number of trajectory points kept per track.
+    pub trajectory_length: usize,
+    /// Tracks not seen for the number of source frames are forgotten.
+    pub track_timeout: u64,
+}
+
+impl ZoneAnalyticsConfig {
+    pub fn new(zones: Vec<Zone>) -> Self {
+        Self {
+            zones,
+            namespace: "analytics".to_string(),
+            anchor: TrackAnchor::BottomCenter,
+            trajectory_length: 30,
+            track_timeout: 30,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ZoneEventKind {
+    Enter,
+    Leave,
+    Cross,
+}
+
+impl ZoneEventKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ZoneEventKind::Enter => "enter",
+            ZoneEventKind::Leave => "leave",
+            ZoneEventKind::Cross => "cross",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ZoneEvent {
+    pub source_id: String,
+    pub track_id: i64,
+    pub object_id: i64,
+    pub zone: String,
+    pub kind: ZoneEventKind,
+    /// The crossed edges of the zone with their tags in the order of crossing.
+    pub edges: Vec<(usize, Option<String>)>,
+    /// The dwell time in seconds for the leave events.
+    pub dwell: Option<f64>,
+}
+
+#[derive(Debug, Default)]
+struct TrackState {
+    trajectory: VecDeque<Point>,
+    last_seen: u64,
+    /// Zone index to the time of entering in nanoseconds.
+    inside: HashMap<usize, i128>,
+}
+
+#[derive(Debug, Default)]
+struct SourceState {
+    frame_count: u64,
+    tracks: HashMap<i64, TrackState>,
+}
+
+/// Zone and line analytics over tracked objects: keeps per-track trajectories,
+/// emits enter, leave and cross events and computes the dwell time.
+///
+/// The results are written as persistent attributes of the analytics namespace:
+/// objects get `events` (`"<kind>:<zone>"` strings) and `<zone>.dwell` (seconds)
+/// while inside the zone, frames get `<zone>.occupancy` with the number of tracks
+/// inside the zone.
+///
+#[derive(Debug)]
+pub struct ZoneAnalytics {
+    config: ZoneAnalyticsConfig,
+    query: MatchQuery,
+    sources: HashMap<String, SourceState>,
+}
+
+fn frame_time_ns(frame: &VideoFrameProxy) -> i128 {
+    let (num, den) = frame.get_time_base();
+    if den == 0 {
+        return 0;
+    }
+    frame.get_pts() as i128 * num as i128 * 1_000_000_000 / den as i128
+}
+
+impl ZoneAnalytics {
+    pub fn new(config: ZoneAnalyticsConfig, query: MatchQuery) -> anyhow::Result<Self> {
+        for (i, zone) in config.zones.iter().enumerate() {
+            if zone.name.is_empty() {
+                bail!("Zone #{} must have a name", i);
+            }
+            if zone.area.get_vertices().len() < 2 {
+                bail!("Zone {} must have at least two vertices", zone.name);
+            }
+            if config.zones[..i].iter().any(|z| z.name == zone.name) {
+                bail!("Zone {} is defined more than once", zone.name);
+            }
+        }
+        Ok(Self {
+            config,
+            query,
+            sources: HashMap::new(),
+        })
+    }
+
+    pub fn get_config(&self) -> &ZoneAnalyticsConfig {
+        &self.config
+    }
+
+    pub fn clear_source(&mut self, source_id: &str) {
+        self.sources.remove(source_id);
+    }
+
+    pub fn get_trajectory(&self, source_id: &str, track_id: i64) -> Option<Vec<Point>> {
+        self.sources
+            .get(source_id)
+            .and_then(|s| s.tracks.get(&track_id))
+            .map(|t| t.trajectory.iter().cloned().collect())
+    }
+
+    /// Returns the names of the zones the track is inside as of the last processed frame.
+    ///
+    pub fn get_zones(&self, source_id: &str, track_id: i64) -> Vec<String> {
+        self.sources
+            .get(source_id)
+            .and_then(|s| s.tracks.get(&track_id))
+            .map(|t| {
+                let mut zones = t.inside.keys().copied().collect::<Vec<_>>();
+                zones.sort_unstable();
+                zones
+                    .into_iter()
+                    .map(|z| self.config.zones[z].name.clone())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Processes the tracked objects selected by the query, objects without the track
+    /// id or the track box are skipped.
+    ///
+    pub fn process(&mut self, frame: &VideoFrameProxy) -> Vec<ZoneEvent> {
+        let now = frame_time_ns(frame);
+        let source_id = frame.get_source_id();
+        let source = self.sources.entry(source_id.clone()).or_default();
+        source.frame_count += 1;
+        let frame_count = source.frame_count;
+        let config = &mut self.config;
+
+        let mut objects = frame.access_objects(&self.query);
+        objects.sort_by_key(|o| o.get_id());
+        let mut events = Vec::new();
+        for object in objects.iter_mut() {
+            let (Some(track_id), Some(track_box)) = (object.get_track_id(), object.get_track_box())
+            else {
+                continue;
+            };
+            let position = config.anchor.point(&track_box);
+            let track = source.tracks.entry(track_id).or_default();
+            track.last_seen = frame_count;
+
+            let mut object_events = Vec::new();
+            match track.trajectory.back() {
+                None => {
+                    for (index, zone) in config.zones.iter_mut().enumerate() {
+                        if zone.area.contains(&position) {
+                            track.inside.insert(index, now);
+                        }
+                    }
+                }
+                Some(previous) => {
+                    let segment = Segment::new(previous.clone(), position.clone());
+                    for (index, zone) in config.zones.iter_mut().enumerate() {
+                        let mut intersection = zone.area.crossed_by_segment(&segment);
+                        if zone.area.get_vertices().len() == 2 {
+                            // the closing edge of a line duplicates the first one
+                            intersection.edges.retain(|(edge, _)| *edge == 0);
+                        }
+                        let (kind, dwell) = match intersection.kind {
+                            IntersectionKind::Enter => {
+                                track.inside.insert(index, now);
+                                (ZoneEventKind::Enter, None)
+                            }
+                            IntersectionKind::Leave => {
+                                let entered = track.inside.remove(&index).unwrap_or(now);
+                                (ZoneEventKind::Leave, Some((now - entered) as f64 / 1e9))
+                            }
+                            IntersectionKind::Cross => (ZoneEventKind::Cross, None),
+                            IntersectionKind::Inside | IntersectionKind::Outside => continue,
+                        };
+                        object_events.push(ZoneEvent {
+                            source_id: source_id.clone(),
+                            track_id,
+                            object_id: object.get_id(),
+                            zone: zone.name.clone(),
+                            kind,
+                            edges: intersection.edges,
+                            dwell,
+                        });
+                    }
+                }
+            }
+            track.trajectory.push_back(position);
+            while track.trajectory.len() > config.trajectory_length.max(1) {
+                track.trajectory.pop_front();
+            }
+
+            for (index, entered) in &track.inside {
+                object.set_persistent_attribute(
+                    &config.namespace,
+                    &format!("{}.dwell", config.zones[*index].name),
+                    &None,
+                    false,
+                    vec![AttributeValue::float((now - entered) as f64 / 1e9, None)],
+                );
+            }
+            if !object_events.is_empty() {
+                object.set_persistent_attribute(
+                    &config.namespace,
+                    "events",
+                    &None,
+                    false,
+                    object_events
+                        .iter()
+                        .map(|e| {
+                            AttributeValue::string(&format!("{}:{}", e.kind.as_str(), e.zone), None)
+                        })
+                        .collect(),
+                );
+            }
+            events.extend(object_events);
+        }
+
+        let timeout = config.track_timeout;
+        source
+            .tracks
+            .retain(|_, t| frame_count - t.last_seen <= timeout);
+
+        let mut frame = frame.clone();
+        for (index, zone) in config.zones.iter().enumerate() {
+            if zone.area.get_vertices().len() < 3 {
+                continue;
+            }
+            let occupancy = source
+                .tracks
+                .values()
+                .filter(|t| t.last_seen == frame_count && t.inside.contains_key(&index))
+                .count();
+            frame.set_persistent_attribute(
+                &config.namespace,
+                &format!("{}.occupancy", zone.name),
+                &None,
+                false,
+                vec![AttributeValue::integer(occupancy as i64, None)],
+            );
+        }
+        events
+    }
+}
+
+/// [`ZoneAnalytics`] as a stage function, processes frames and batches passing the
+/// stage and drops the tracks of ended sources.
+///
+pub struct ZoneAnalyticsFunction {
+    analytics: Arc<Mutex<ZoneAnalytics>>,
+    pipeline: Option<Pipeline>,
+}
+
+impl ZoneAnalyticsFunction {
+    pub fn new(analytics: ZoneAnalytics) -> Self {
+        Self::with_shared_analytics(Arc::new(Mutex::new(analytics)))
+    }
+
+    /// Creates the function over the analytics which is also used outside the pipeline.
+    ///
+    pub fn with_shared_analytics(analytics: Arc<Mutex<ZoneAnalytics>>) -> Self {
+        Self {
+            analytics,
+            pipeline: None,
+        }
+    }
+}
+
+impl PipelineStageFunction for ZoneAnalyticsFunction {
+    fn set_pipeline(&mut self, pipeline: Pipeline) {
+        self.pipeline = Some(pipeline);
+    }
+
+    fn get_pipeline(&self) -> &Option<Pipeline> {
+        &self.pipeline
+    }
+
+    fn call(
+        &self,
+        _: i64,
+        _: &PipelineStage,
+        _: PipelineStageFunctionOrder,
+        payload: &mut PipelinePayload,
+    ) -> anyhow::Result<()> {
+        let mut analytics = self.analytics.lock();
+        match payload {
+            PipelinePayload::Frame(frame, _, _, _, _) => {
+                analytics.process(frame);
+            }
+            PipelinePayload::Batch(batch, _, _, _, _) => {
+                let mut ids = batch.frames.keys().copied().collect::<Vec<_>>();
+                ids.sort();
+                for id in ids {
+                    analytics.process(&batch.frames[&id]);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn on_source_eos(&self, source_id: &str) -> anyhow::Result<()> {
+        self.analytics.lock().clear_source(source_id);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TrackAnchor, Zone, ZoneAnalytics, ZoneAnalyticsConfig, ZoneEventKind};
+    use crate::match_query::MatchQuery;
+    use crate::primitives::attribute_value::AttributeValueVariant;
+    use crate::primitives::frame::VideoFrameProxy;
+    use crate::primitives::object::{IdCollisionResolutionPolicy, VideoObjectBuilder};
+    use crate::primitives::{Point, PolygonalArea, RBBox, WithAttributes};
+    use crate::test::{gen_empty_frame, s};
+
+    fn square(left: f32, right: f32) -> PolygonalArea {
+        PolygonalArea::new(
+            vec![
+                Point::new(left, 0.0),
+                Point::new(right, 0.0),
+                Point::new(right, 100.0),
+                Point::new(left, 100.0),
+            ],
+            Some(vec![
+                Some(s("top")),
+                Some(s("right")),
+                None,
+                Some(s("left")),
+            ]),
+        )
+    }
+
+    fn frame_with_track(pts: i64, xc: f32) -> VideoFrameProxy {
+        let mut frame = gen_empty_frame();
+        frame.set_time_base((1, 1000));
+        frame.set_pts(pts);
+        frame
+            .add_object(
+                VideoObjectBuilder::default()
+                    .id(0)
+                    .namespace(s("detector"))
+                    .label(s("person"))
+                    .detection_box(RBBox::new(xc, 50.0, 4.0, 4.0, None))
+                    .track_id(Some(7))
+                    .track_box(Some(RBBox::new(xc, 50.0, 4.0, 4.0, None)))
+                    .build()
+                    .unwrap(),
+                IdCollisionResolutionPolicy::Error,
+            )
+            .unwrap();
+        frame
+    }
+
+    #[test]
+    fn test_enter_leave_cross() -> anyhow::Result<()> {
+        let mut config = ZoneAnalyticsConfig::new(vec![
+            Zone {
+                name: s("zone"),
+                area: square(10.0, 20.0),
+            },
+            Zone {
+                name: s("line"),
+                area: PolygonalArea::new(
+                    vec![Point::new(30.0, 0.0), Point::new(30.0, 100.0)],
+                    Some(vec![Some(s("counter")), None]),
+                ),
+            },
+        ]);
+        config.anchor = TrackAnchor::Center;
+        let mut analytics = ZoneAnalytics::new(config, MatchQuery::Idle)?;
+        assert!(analytics.process(&frame_with_track(0, 5.0)).is_empty());
+
+        let frame = frame_with_track(1000, 15.0);
+        let events = analytics.process(&frame);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].kind, ZoneEventKind::Enter);
+        assert_eq!(events[0].edges, vec![(3, Some(s("left")))]);
+        assert_eq!(analytics.get_zones("test", 7), vec![s("zone")]);
+        let occupancy = frame
+            .get_attribute("analytics", "zone.occupancy")
+            .unwrap()
+            .get_values()[0]
+            .value
+            .clone();
+        assert_eq!(occupancy, AttributeValueVariant::Integer(1));
+
+        let frame = frame_with_track(3000, 35.0);
+        let events = analytics.process(&frame);
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].kind, ZoneEventKind::Leave);
+        assert_eq!(events[0].dwell, Some(2.0));
+        assert_eq!(events[1].kind, ZoneEventKind::Cross);
+        assert_eq!(events[1].edges, vec![(0, Some(s("counter")))]);
+        let object = frame.get_object(0).unwrap();
+        let values = object
+            .get_attribute("analytics", "events")
+            .unwrap()
+            .get_values()
+            .iter()
+            .map(|v| v.value.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            values,
+            vec![
+                AttributeValueVariant::String(s("leave:zone")),
+                AttributeValueVariant::String(s("cross:line"))
+            ]
+        );
+        assert_eq!(analytics.get_trajectory("test", 7).unwrap().len(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_track_timeout() -> anyhow::Result<()> {
+        let mut config = ZoneAnalyticsConfig::new(vec![Zone {
+            name: s("zone"),
+            area: square(10.0, 20.0),
+        }]);
+        config.track_timeout = 1;
+        let mut analytics = ZoneAnalytics::new(config, MatchQuery::Idle)?;
+        analytics.process(&frame_with_track(0, 15.0));
+        assert_eq!(analytics.get_zones("test", 7), vec![s("zone")]);
+        analytics.process(&gen_empty_frame());
+        assert!(analytics.get_trajectory("test", 7).is_some());
+        analytics.process(&gen_empty_frame());
+        assert!(analytics.get_trajectory("test", 7).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_zones() {
+        let zone = Zone {
+            name: s("zone"),
+            area: square(10.0, 20.0),
+        };
+        assert!(ZoneAnalytics::new(
+            ZoneAnalyticsConfig::new(vec![zone.clone(), zone]),
+            MatchQuery::Idle
+        )
+        .is_err());
+    }
+}
@@ -1,14 +1,32 @@
-use savant_core::pipeline::stage_function_loader::load_stage_function_plugin;
+//! Loads the sample stage function plugin exported by savant_core itself, run with
+//! `cargo run --features sample-plugin --bin plugin-loader`.
+
+use savant_core::pipeline::stage_function_loader::{
+    load_stage_function_plugin, load_stage_function_plugin_info,
+};
 use savant_core::pipeline::PluginParams;
+use savant_core::primitives::attribute_value::AttributeValue;
 
 pub fn main() {
     let cargo_target_dir = std::env::var("CARGO_TARGET_DIR").unwrap_or("target".to_string());
-    let p = load_stage_function_plugin(
-        format!("{}/debug/libsavant_core.so", cargo_target_dir).as_str(),
-        "init_plugin_test",
-        "plugin",
-        PluginParams::default(),
-    )
-    .unwrap();
+    let libname = format!("{}/debug/libsavant_core.so", cargo_target_dir);
+    let info = load_stage_function_plugin_info(&libname).unwrap();
+    println!(
+        "Plugin {} (savant_core {}, {})",
+        info.name, info.savant_core_version, info.rustc_version
+    );
+
+    let mut params = PluginParams::default();
+    params
+        .params
+        .insert("label".to_string(), AttributeValue::string("test", None));
+    let p = load_stage_function_plugin(&libname, "init_plugin_test", "plugin", params).unwrap();
     drop(p);
+
+    let mut params = PluginParams::default();
+    params
+        .params
+        .insert("unknown".to_string(), AttributeValue::integer(1, None));
+    let e = load_stage_function_plugin(&libname, "init_plugin_test", "plugin", params).err();
+    println!("Undeclared parameter rejected: {}", e.unwrap());
 }
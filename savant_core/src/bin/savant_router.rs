@@ -0,0 +1,47 @@
+//! Forwards messages as configured by the YAML spec, run with
+//! `cargo run --features router-bin --bin savant-router -- <config.yaml>`.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{bail, Context};
+use nix::sys::signal::{sigaction, SaFlags, SigAction, SigHandler, SigSet, Signal};
+use savant_core::transport::router::{Router, RouterSpec};
+
+static SHUTDOWN: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn on_signal(_: i32) {
+    SHUTDOWN.store(true, Ordering::Relaxed);
+}
+
+pub fn main() -> anyhow::Result<()> {
+    env_logger::init();
+    let path = match std::env::args().nth(1) {
+        Some(path) => path,
+        None => bail!("Usage: savant-router <config.yaml>"),
+    };
+    let spec = RouterSpec::from_file(&path)?;
+    let router = Router::new(&spec)?;
+
+    let action = SigAction::new(
+        SigHandler::Handler(on_signal),
+        SaFlags::empty(),
+        SigSet::empty(),
+    );
+    for signal in [Signal::SIGINT, Signal::SIGTERM] {
+        unsafe { sigaction(signal, &action) }
+            .with_context(|| format!("Failed to install {} handler", signal))?;
+    }
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let watcher_stop = stop.clone();
+    thread::spawn(move || {
+        while !SHUTDOWN.load(Ordering::Relaxed) {
+            thread::sleep(Duration::from_millis(100));
+        }
+        watcher_stop.store(true, Ordering::Relaxed);
+    });
+    router.run(stop)
+}
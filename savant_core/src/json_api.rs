@@ -1,3 +1,67 @@
+use anyhow::{anyhow, bail};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+pub const JSON_SCHEMA_VERSION: u64 = 1;
+const JSON_SCHEMA_VERSION_FIELD: &str = "schema_version";
+
 pub trait ToSerdeJsonValue {
     fn to_serde_json_value(&self) -> serde_json::Value;
 }
+
+pub(crate) fn to_versioned_value<T: Serialize>(value: &T) -> anyhow::Result<Value> {
+    let mut value = serde_json::to_value(value)?;
+    match value.as_object_mut() {
+        Some(map) => {
+            map.insert(
+                JSON_SCHEMA_VERSION_FIELD.to_string(),
+                Value::from(JSON_SCHEMA_VERSION),
+            );
+        }
+        None => bail!("Only structures can be serialized as versioned documents"),
+    }
+    Ok(value)
+}
+
+pub(crate) fn from_versioned_value<T: DeserializeOwned>(mut value: Value) -> anyhow::Result<T> {
+    let version = value
+        .as_object_mut()
+        .and_then(|map| map.remove(JSON_SCHEMA_VERSION_FIELD))
+        .ok_or_else(|| anyhow!("The document has no '{}' field", JSON_SCHEMA_VERSION_FIELD))?;
+    let version = version.as_u64().ok_or_else(|| {
+        anyhow!(
+            "The '{}' field must be an unsigned integer",
+            JSON_SCHEMA_VERSION_FIELD
+        )
+    })?;
+    if version > JSON_SCHEMA_VERSION {
+        bail!(
+            "The document schema version {} is not supported, the latest supported version is {}",
+            version,
+            JSON_SCHEMA_VERSION
+        );
+    }
+    Ok(serde_json::from_value(value)?)
+}
+
+pub(crate) fn to_versioned_json<T: Serialize>(value: &T, pretty: bool) -> anyhow::Result<String> {
+    let value = to_versioned_value(value)?;
+    Ok(if pretty {
+        serde_json::to_string_pretty(&value)?
+    } else {
+        serde_json::to_string(&value)?
+    })
+}
+
+pub(crate) fn from_versioned_json<T: DeserializeOwned>(json: &str) -> anyhow::Result<T> {
+    from_versioned_value(serde_json::from_str(json)?)
+}
+
+pub(crate) fn to_versioned_yaml<T: Serialize>(value: &T) -> anyhow::Result<String> {
+    Ok(serde_yaml::to_string(&to_versioned_value(value)?)?)
+}
+
+pub(crate) fn from_versioned_yaml<T: DeserializeOwned>(yaml: &str) -> anyhow::Result<T> {
+    from_versioned_value(serde_yaml::from_str(yaml)?)
+}
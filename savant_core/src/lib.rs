@@ -3,6 +3,7 @@ use opentelemetry::global::BoxedTracer;
 use std::sync::OnceLock;
 use tokio::runtime::Runtime;
 
+pub mod analytics;
 pub mod atomic_f32;
 pub mod deadlock_detection;
 pub mod draw;
@@ -22,6 +23,7 @@ pub mod rwlock;
 pub mod symbol_mapper;
 pub mod telemetry;
 pub mod test;
+pub mod tracker;
 pub mod transport;
 pub mod utils;
 
@@ -73,6 +75,8 @@ pub mod rust {
     pub use super::pipeline::PipelineConfiguration;
     pub use super::pipeline::PipelineConfigurationBuilder;
     pub use super::pipeline::PipelineStagePayloadType;
+    pub use super::pipeline::StageQueueLimit;
+    pub use super::pipeline::StageQueuePolicy;
     pub use super::symbol_mapper::RegistrationPolicy;
     pub use super::symbol_mapper::SymbolMapper;
 }
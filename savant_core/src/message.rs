@@ -13,74 +13,245 @@ use crate::trace;
 use lazy_static::lazy_static;
 use lru::LruCache;
 use parking_lot::{const_mutex, Mutex};
+use std::collections::BTreeSet;
+use std::num::NonZeroUsize;
 
 lazy_static! {
     static ref SEQ_STORE: Mutex<SeqStore> = const_mutex(SeqStore::new());
 }
 
+/// The result of the `seq_id` check of a received message.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SeqIdStatus {
+    Valid,
+    /// The sequence started over, e.g. the sender was restarted without sending EOS.
+    Reset {
+        expected: u64,
+        received: u64,
+    },
+    /// The messages from `expected` up to `received` (exclusive) are lost.
+    Gap {
+        expected: u64,
+        received: u64,
+    },
+    /// The message is already received or arrived out of order.
+    Duplicate {
+        expected: u64,
+        received: u64,
+    },
+}
+
+impl SeqIdStatus {
+    /// The number of lost messages.
+    ///
+    pub fn lost(&self) -> u64 {
+        match self {
+            SeqIdStatus::Gap { expected, received } => received - expected,
+            _ => 0,
+        }
+    }
+}
+
 pub struct SeqStore {
     generators: LruCache<String, u64>,
     validators: LruCache<String, u64>,
+    deliveries: LruCache<(Vec<u8>, Vec<u8>), BTreeSet<u64>>,
 }
 
-const MAX_SEQ_STORE_SIZE: usize = 256;
+const MAX_DELIVERY_WINDOW: usize = 1024;
+
+/// The `seq_id` behind the expected one by more than this is treated as a reset of the
+/// sequence rather than a reordered message.
+const SEQ_ID_REORDER_TOLERANCE: u64 = 16;
+
+pub const DEFAULT_SEQ_STORE_SIZE: usize = 256;
+
+impl Default for SeqStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl SeqStore {
-    fn new() -> Self {
+    pub fn new() -> Self {
+        Self::with_capacity(NonZeroUsize::new(DEFAULT_SEQ_STORE_SIZE).unwrap())
+    }
+
+    /// Creates the store keeping at most `capacity` sources, the least recently seen ones
+    /// are forgotten.
+    ///
+    pub fn with_capacity(capacity: NonZeroUsize) -> Self {
         Self {
-            generators: LruCache::new(std::num::NonZeroUsize::new(MAX_SEQ_STORE_SIZE).unwrap()),
-            validators: LruCache::new(std::num::NonZeroUsize::new(MAX_SEQ_STORE_SIZE).unwrap()),
+            generators: LruCache::new(capacity),
+            validators: LruCache::new(capacity),
+            deliveries: LruCache::new(capacity),
         }
     }
 
+    pub fn resize(&mut self, capacity: NonZeroUsize) {
+        self.generators.resize(capacity);
+        self.validators.resize(capacity);
+        self.deliveries.resize(capacity);
+    }
+
     pub fn generate_message_seq_id(&mut self, source: &str) -> u64 {
         let v = self.generators.get_or_insert_mut(source.to_string(), || 0);
         *v += 1;
         *v
     }
 
-    fn validate_seq_i_raw(&mut self, source: &str, seq_id: u64) -> bool {
-        let v = self.validators.get_or_insert_mut(source.to_string(), || 0);
-        if seq_id <= *v {
-            log::trace!(target: "savant_rs::message::validate_seq_iq", 
-                "SeqId reset for {}, expected seq_id = {}, received seq_id = {}", 
-                source, *v + 1, seq_id);
-            *v = seq_id;
-            true
-        } else if *v + 1 == seq_id {
+    fn check_seq_id_raw(&mut self, source: &str, seq_id: u64) -> SeqIdStatus {
+        let Some(v) = self.validators.get_mut(source) else {
+            log::trace!(target: "savant_rs::message::validate_seq_iq",
+                "Started tracking seq_id for {} from seq_id={}",
+                source, seq_id);
+            self.validators.put(source.to_string(), seq_id);
+            return SeqIdStatus::Valid;
+        };
+        let expected = *v + 1;
+        if seq_id == expected {
             log::trace!(target: "savant_rs::message::validate_seq_iq", 
                 "Successfully validated seq_id={} for {}", 
                 seq_id, source);
-            *v += 1;
-            true
+            *v = seq_id;
+            SeqIdStatus::Valid
+        } else if seq_id == 1 {
+            log::trace!(target: "savant_rs::message::validate_seq_iq", 
+                "SeqId reset for {}, expected seq_id = {}, received seq_id = {}", 
+                source, expected, seq_id);
+            *v = seq_id;
+            SeqIdStatus::Reset {
+                expected,
+                received: seq_id,
+            }
+        } else if seq_id < expected && expected - seq_id > SEQ_ID_REORDER_TOLERANCE {
+            log::warn!(target: "savant_rs::message::validate_seq_iq",
+                "SeqId reset for {}, expected seq_id = {}, received seq_id = {} is too far behind",
+                source, expected, seq_id);
+            *v = seq_id;
+            SeqIdStatus::Reset {
+                expected,
+                received: seq_id,
+            }
+        } else if seq_id < expected {
+            log::warn!(target: "savant_rs::message::validate_seq_iq", 
+                "Duplicate or reordered seq_id={} for {}, expected={}", 
+                seq_id, source, expected);
+            SeqIdStatus::Duplicate {
+                expected,
+                received: seq_id,
+            }
         } else {
             log::warn!(target: "savant_rs::message::validate_seq_iq", 
                 "Failed to validate seq_id={} for {}, expected={}. SeqId discrepancy is a symptom of message loss or stream termination without EOS", 
-                seq_id, source, *v + 1);
+                seq_id, source, expected);
             *v = seq_id;
-            false
+            SeqIdStatus::Gap {
+                expected,
+                received: seq_id,
+            }
         }
     }
 
     pub fn reset_seq_id(&mut self, source: &str) {
         self.validators.pop(source);
         self.generators.pop(source);
+        self.reset_deliveries(source.as_bytes());
+    }
+
+    /// Forgets the delivered `seq_id`s received with the topic from all the peers.
+    ///
+    pub fn reset_deliveries(&mut self, topic: &[u8]) {
+        let deliveries = self
+            .deliveries
+            .iter()
+            .filter(|((_, t), _)| t == topic)
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<_>>();
+        for key in deliveries {
+            self.deliveries.pop(&key);
+        }
     }
 
-    pub fn validate_seq_id(&mut self, m: &Message) -> bool {
+    fn is_delivered_raw(delivered: &BTreeSet<u64>, seq_id: u64) -> bool {
+        delivered.contains(&seq_id)
+            || (delivered.len() >= MAX_DELIVERY_WINDOW
+                && delivered.first().is_some_and(|first| seq_id < *first))
+    }
+
+    fn is_delivery_reset(delivered: &BTreeSet<u64>, seq_id: u64) -> bool {
+        delivered
+            .last()
+            .is_some_and(|last| seq_id.saturating_add(MAX_DELIVERY_WINDOW as u64) < *last)
+    }
+
+    /// Checks if the `seq_id` received with the topic from the peer is already delivered
+    /// without registering it.
+    ///
+    pub fn is_delivered(&mut self, routing_id: &[u8], topic: &[u8], seq_id: u64) -> bool {
+        self.deliveries
+            .get(&(routing_id.to_vec(), topic.to_vec()))
+            .is_some_and(|delivered| {
+                !Self::is_delivery_reset(delivered, seq_id)
+                    && Self::is_delivered_raw(delivered, seq_id)
+            })
+    }
+
+    /// Registers the delivered `seq_id` received with the topic from the peer, returns
+    /// `false` when it was already delivered. The last `MAX_DELIVERY_WINDOW` ids are
+    /// remembered, older ones are treated as duplicates unless the `seq_id` is more than
+    /// `MAX_DELIVERY_WINDOW` behind the last one, which means that the sender started over.
+    ///
+    pub fn register_delivery(&mut self, routing_id: &[u8], topic: &[u8], seq_id: u64) -> bool {
+        let delivered = self
+            .deliveries
+            .get_or_insert_mut((routing_id.to_vec(), topic.to_vec()), BTreeSet::new);
+        if Self::is_delivery_reset(delivered, seq_id) {
+            log::debug!(target: "savant_rs::message::validate_seq_iq",
+                "Delivery sequence reset for {}, last seq_id = {:?}, received seq_id = {}",
+                String::from_utf8_lossy(topic), delivered.last(), seq_id);
+            delivered.clear();
+        }
+        if Self::is_delivered_raw(delivered, seq_id) {
+            return false;
+        }
+        delivered.insert(seq_id);
+        if delivered.len() > MAX_DELIVERY_WINDOW {
+            delivered.pop_first();
+        }
+        true
+    }
+
+    /// Checks the `seq_id` of the message against the last one received from the source,
+    /// the end-of-stream resets the source.
+    ///
+    pub fn check_seq_id(&mut self, m: &Message) -> SeqIdStatus {
         let seq_id = m.meta.seq_id;
         match &m.payload {
             MessageEnvelope::EndOfStream(eos) => {
                 self.reset_seq_id(&eos.source_id);
-                true
+                SeqIdStatus::Valid
             }
             MessageEnvelope::VideoFrame(vf) => {
-                self.validate_seq_i_raw(&vf.inner.read().source_id, seq_id)
+                self.check_seq_id_raw(&vf.inner.read().source_id, seq_id)
             }
-            MessageEnvelope::UserData(ud) => self.validate_seq_i_raw(&ud.source_id, seq_id),
-            _ => true,
+            MessageEnvelope::UserData(ud) => self.check_seq_id_raw(&ud.source_id, seq_id),
+            _ => SeqIdStatus::Valid,
         }
     }
+
+    pub fn validate_seq_id(&mut self, m: &Message) -> bool {
+        !matches!(self.check_seq_id(m), SeqIdStatus::Gap { .. })
+    }
+}
+
+/// Sets the number of sources tracked by the global store.
+///
+pub fn set_seq_store_size(size: usize) -> anyhow::Result<()> {
+    let size = NonZeroUsize::new(size).ok_or(anyhow::anyhow!("Seq store size must be positive"))?;
+    trace!(SEQ_STORE.lock()).resize(size);
+    Ok(())
 }
 
 pub fn validate_seq_id(m: &Message) -> bool {
@@ -321,7 +492,9 @@ pub fn save_message(m: &Message) -> anyhow::Result<Vec<u8>> {
 
 #[cfg(test)]
 mod tests {
-    use crate::message::{load_message, save_message, validate_seq_id, Message};
+    use crate::message::{
+        load_message, save_message, validate_seq_id, Message, SeqIdStatus, SeqStore,
+    };
     use crate::primitives::eos::EndOfStream;
     use crate::primitives::frame_batch::VideoFrameBatch;
     use crate::primitives::object::private::SealedWithFrame;
@@ -331,6 +504,118 @@ mod tests {
     use crate::test::gen_frame;
     use std::sync::Arc;
 
+    #[test]
+    fn test_register_delivery() {
+        let mut store = SeqStore::new();
+        assert!(store.register_delivery(b"peer", b"test", 2));
+        assert!(store.register_delivery(b"peer", b"test", 1));
+        assert!(store.is_delivered(b"peer", b"test", 2));
+        assert!(!store.register_delivery(b"peer", b"test", 2));
+        assert!(!store.is_delivered(b"other-peer", b"test", 2));
+        assert!(store.register_delivery(b"other-peer", b"test", 2));
+        assert!(store.register_delivery(b"peer", b"other", 2));
+        store.reset_seq_id("test");
+        assert!(!store.is_delivered(b"peer", b"test", 2));
+        assert!(!store.is_delivered(b"other-peer", b"test", 2));
+        assert!(store.is_delivered(b"peer", b"other", 2));
+        assert!(store.register_delivery(b"peer", b"test", 2));
+        store.reset_deliveries(b"other");
+        assert!(!store.is_delivered(b"peer", b"other", 2));
+    }
+
+    #[test]
+    fn test_register_delivery_reset() {
+        let mut store = SeqStore::new();
+        for seq_id in 1..=3000 {
+            assert!(store.register_delivery(b"peer", b"test", seq_id));
+        }
+        // an old id within the window distance is a duplicate
+        assert!(!store.register_delivery(b"peer", b"test", 1990));
+        // the sender started over
+        assert!(!store.is_delivered(b"peer", b"test", 1));
+        assert!(store.register_delivery(b"peer", b"test", 1));
+        assert!(store.register_delivery(b"peer", b"test", 2));
+        assert!(!store.register_delivery(b"peer", b"test", 1));
+    }
+
+    #[test]
+    fn test_check_seq_id() {
+        let mut store = SeqStore::new();
+        let check = |store: &mut SeqStore, seq_id: u64| {
+            let mut m = Message::user_data(UserData::new("test"));
+            m.meta.seq_id = seq_id;
+            store.check_seq_id(&m)
+        };
+        assert_eq!(check(&mut store, 1), SeqIdStatus::Valid);
+        assert_eq!(check(&mut store, 2), SeqIdStatus::Valid);
+        let gap = check(&mut store, 5);
+        assert_eq!(
+            gap,
+            SeqIdStatus::Gap {
+                expected: 3,
+                received: 5
+            }
+        );
+        assert_eq!(gap.lost(), 2);
+        assert_eq!(
+            check(&mut store, 4),
+            SeqIdStatus::Duplicate {
+                expected: 6,
+                received: 4
+            }
+        );
+        assert_eq!(check(&mut store, 6), SeqIdStatus::Valid);
+        for seq_id in 7..=100 {
+            assert_eq!(check(&mut store, seq_id), SeqIdStatus::Valid);
+        }
+        assert_eq!(
+            check(&mut store, 85),
+            SeqIdStatus::Duplicate {
+                expected: 101,
+                received: 85
+            }
+        );
+        assert_eq!(
+            check(&mut store, 50),
+            SeqIdStatus::Reset {
+                expected: 101,
+                received: 50
+            }
+        );
+        assert_eq!(check(&mut store, 51), SeqIdStatus::Valid);
+        assert_eq!(
+            check(&mut store, 1),
+            SeqIdStatus::Reset {
+                expected: 52,
+                received: 1
+            }
+        );
+        assert_eq!(
+            store.check_seq_id(&Message::end_of_stream(EndOfStream::new("test".into()))),
+            SeqIdStatus::Valid
+        );
+        assert_eq!(check(&mut store, 1), SeqIdStatus::Valid);
+    }
+
+    #[test]
+    fn test_check_seq_id_of_unknown_source() {
+        let mut store = SeqStore::new();
+        let check = |store: &mut SeqStore, seq_id: u64| {
+            let mut m = Message::user_data(UserData::new("joined"));
+            m.meta.seq_id = seq_id;
+            store.check_seq_id(&m)
+        };
+        assert_eq!(check(&mut store, 1000), SeqIdStatus::Valid);
+        assert_eq!(check(&mut store, 1001), SeqIdStatus::Valid);
+        assert_eq!(
+            check(&mut store, 1003),
+            SeqIdStatus::Gap {
+                expected: 1002,
+                received: 1003
+            }
+        );
+    }
+
     #[test]
     fn test_save_load_eos() {
         let eos = EndOfStream::new("test".to_string());
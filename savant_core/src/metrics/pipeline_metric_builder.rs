@@ -106,6 +106,18 @@ impl PipelineMetricBuilder {
                 &aspln_refs,
                 None,
             );
+            let stage_dropped_counter = get_or_create_counter_family(
+                "stage_dropped_counter",
+                Some("Number of frames or batches dropped because the stage queue was full"),
+                &aspln_refs,
+                None,
+            );
+            let stage_rejected_counter = get_or_create_counter_family(
+                "stage_rejected_counter",
+                Some("Number of frames or batches rejected because the stage queue was full"),
+                &aspln_refs,
+                None,
+            );
             let stage_min_latency = get_or_create_gauge_family(
                 "stage_min_latency",
                 Some("Minimum latency of the stage"),
@@ -167,6 +179,12 @@ impl PipelineMetricBuilder {
                 stage_batch_counter
                     .lock()
                     .set(sps.batch_counter as u64, &stage_performance_label_refs)?;
+                stage_dropped_counter
+                    .lock()
+                    .set(sps.dropped_counter as u64, &stage_performance_label_refs)?;
+                stage_rejected_counter
+                    .lock()
+                    .set(sps.rejected_counter as u64, &stage_performance_label_refs)?;
                 debug!(
                     "Building metrics for stage latencies: {}",
                     sls.latencies.len()
@@ -1,5 +1,5 @@
 use std::sync::Arc;
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 
 use anyhow::Result;
 use hashbrown::HashMap;
@@ -19,6 +19,7 @@ use crate::webserver::{register_pipeline, unregister_pipeline};
 
 const MAX_TRACKED_STREAMS: usize = 8192; // defines how many streams are tracked for the frame ordering
 
+pub mod spec;
 pub mod stage;
 pub mod stage_function_loader;
 pub mod stage_plugin_sample;
@@ -34,9 +35,52 @@ pub trait PipelineStageFunction: Send {
         order: PipelineStageFunctionOrder,
         payload: &mut PipelinePayload,
     ) -> Result<()>;
+
+    /// Parameters the function is created with, they are passed to [`Self::init`].
+    ///
+    fn get_params(&self) -> Option<&PluginParams> {
+        None
+    }
+
+    /// Called once when the stage is created with the parameters returned by
+    /// [`Self::get_params`] (empty when there are none). The function validates its
+    /// [`PluginParams`] here, an error aborts the pipeline creation.
+    ///
+    fn init(
+        &mut self,
+        _stage: &str,
+        _order: PipelineStageFunctionOrder,
+        _params: &PluginParams,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called when the source stops sending frames, see [`Pipeline::notify_source_eos`].
+    ///
+    fn on_source_eos(&self, _source_id: &str) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called periodically when [`PipelineConfiguration::tick_period_ms`] is set.
+    ///
+    fn on_tick(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called before the shutdown to emit the buffered results.
+    ///
+    fn flush(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called once when the pipeline is shut down or dropped.
+    ///
+    fn on_shutdown(&self) -> Result<()> {
+        Ok(())
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum PipelineStageFunctionOrder {
     Ingress,
     Egress,
@@ -51,12 +95,38 @@ pub struct PluginParams {
 pub type PipelineStageFunctionFactory =
     fn(name: &str, parameters: PluginParams) -> *mut (dyn PipelineStageFunction);
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum PipelineStagePayloadType {
     Frame,
     Batch,
 }
 
+/// Defines what happens when a payload is moved to a stage whose queue is full.
+///
+#[derive(Clone, Debug, PartialEq)]
+pub enum StageQueuePolicy {
+    /// The operation fails immediately.
+    Reject,
+    /// The operation waits for free space and fails when the timeout expires.
+    Block(Duration),
+    /// The oldest payloads holding frames of the same sources are dropped; when there are not
+    /// enough of them, the operation fails.
+    DropOldestOfSource,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct StageQueueLimit {
+    pub capacity: usize,
+    pub policy: StageQueuePolicy,
+}
+
+impl StageQueueLimit {
+    pub fn new(capacity: usize, policy: StageQueuePolicy) -> Self {
+        Self { capacity, policy }
+    }
+}
+
 #[derive(Debug)]
 pub enum PipelinePayload {
     Frame(
@@ -75,6 +145,17 @@ pub enum PipelinePayload {
     ),
 }
 
+impl PipelinePayload {
+    pub(crate) fn source_ids(&self) -> Vec<String> {
+        match self {
+            PipelinePayload::Frame(frame, _, _, _, _) => vec![frame.get_source_id()],
+            PipelinePayload::Batch(batch, _, _, _, _) => {
+                batch.frames.values().map(|f| f.get_source_id()).collect()
+            }
+        }
+    }
+}
+
 #[derive(Clone, Default, Debug)]
 pub struct Pipeline(pub(crate) Arc<implementation::Pipeline>);
 
@@ -90,6 +171,23 @@ impl Pipeline {
         configuration: PipelineConfiguration,
     ) -> Result<Self> {
         let pipeline = Arc::new(implementation::Pipeline::new(stages, configuration)?);
+        if let Some(period) = pipeline.get_tick_period() {
+            let pipeline = Arc::downgrade(&pipeline);
+            std::thread::Builder::new()
+                .name("pipeline-tick".to_string())
+                .spawn(move || loop {
+                    std::thread::sleep(period);
+                    let Some(pipeline) = pipeline.upgrade() else {
+                        break;
+                    };
+                    if pipeline.is_shut_down() {
+                        break;
+                    }
+                    if let Err(e) = pipeline.tick() {
+                        log::error!(target: "savant_rs::pipeline", "Stage function tick failed: {}", e);
+                    }
+                })?;
+        }
         let p = Self(pipeline);
         register_pipeline(p.0.clone());
         Ok(p)
@@ -123,10 +221,32 @@ impl Pipeline {
         self as *const Self as usize
     }
 
+    /// Notifies the stage functions about the end of the source and clears its ordering.
+    ///
     pub fn clear_source_ordering(&self, source_id: &str) -> Result<()> {
         self.0.clear_source_ordering(source_id)
     }
 
+    /// Notifies the stage functions about the end of the source, e.g. on EOS message.
+    ///
+    pub fn notify_source_eos(&self, source_id: &str) -> Result<()> {
+        self.0.notify_source_eos(source_id)
+    }
+
+    pub fn tick(&self) -> Result<()> {
+        self.0.tick()
+    }
+
+    pub fn flush(&self) -> Result<()> {
+        self.0.flush()
+    }
+
+    /// Flushes and shuts down the stage functions, subsequent calls do nothing.
+    ///
+    pub fn shutdown(&self) -> Result<()> {
+        self.0.shutdown()
+    }
+
     pub fn set_root_span_name(&self, name: String) -> Result<()> {
         self.0.set_root_span_name(name)
     }
@@ -244,9 +364,9 @@ impl Drop for Pipeline {
 pub(super) mod implementation {
     use std::collections::VecDeque;
     use std::num::NonZeroUsize;
-    use std::sync::atomic::{AtomicI64, Ordering};
+    use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
     use std::sync::OnceLock;
-    use std::time::SystemTime;
+    use std::time::{Duration, SystemTime};
 
     use anyhow::{anyhow, bail, Result};
     use derive_builder::Builder;
@@ -260,7 +380,8 @@ pub(super) mod implementation {
     use crate::pipeline::stage::PipelineStage;
     use crate::pipeline::stats::{FrameProcessingStatRecord, Stats};
     use crate::pipeline::{
-        PipelinePayload, PipelineStageFunction, PipelineStagePayloadType, MAX_TRACKED_STREAMS,
+        PipelinePayload, PipelineStageFunction, PipelineStageFunctionOrder,
+        PipelineStagePayloadType, StageQueueLimit, MAX_TRACKED_STREAMS,
     };
     use crate::primitives::frame::VideoFrameProxy;
     use crate::primitives::frame_batch::VideoFrameBatch;
@@ -270,18 +391,41 @@ pub(super) mod implementation {
 
     const DEFAULT_ROOT_SPAN_NAME: &str = "video_pipeline";
 
-    #[derive(Builder, Default, Debug, Clone)]
+    fn default_stat_period() -> Option<i64> {
+        Some(1000)
+    }
+
+    fn default_collection_history() -> usize {
+        10
+    }
+
+    fn default_keyframe_history() -> usize {
+        60
+    }
+
+    #[derive(Builder, Default, Debug, Clone, serde::Serialize, serde::Deserialize)]
     pub struct PipelineConfiguration {
         #[builder(default = "false")]
+        #[serde(default)]
         pub append_frame_meta_to_otlp_span: bool,
-        #[builder(default = "Some(1000)")]
+        #[builder(default = "default_stat_period()")]
+        #[serde(default = "default_stat_period")]
         pub timestamp_period: Option<i64>,
-        #[builder(default = "Some(1000)")]
+        #[builder(default = "default_stat_period()")]
+        #[serde(default = "default_stat_period")]
         pub frame_period: Option<i64>,
-        #[builder(default = "10")]
+        #[builder(default = "default_collection_history()")]
+        #[serde(default = "default_collection_history")]
         pub collection_history: usize,
-        #[builder(default = "60")]
+        #[builder(default = "default_keyframe_history()")]
+        #[serde(default = "default_keyframe_history")]
         pub keyframe_history: usize,
+        #[builder(default)]
+        #[serde(skip)]
+        pub stage_queue_limits: HashMap<String, StageQueueLimit>,
+        #[builder(default)]
+        #[serde(default)]
+        pub tick_period_ms: Option<u64>,
     }
 
     #[derive(Debug)]
@@ -299,6 +443,7 @@ pub(super) mod implementation {
         root_span_name: OnceLock<String>,
         configuration: PipelineConfiguration,
         stats: Stats,
+        shut_down: AtomicBool,
     }
 
     impl Default for Pipeline {
@@ -323,6 +468,15 @@ pub(super) mod implementation {
                 root_span_name: OnceLock::new(),
                 configuration: PipelineConfiguration::default(),
                 stats: Stats::default(),
+                shut_down: AtomicBool::new(false),
+            }
+        }
+    }
+
+    impl Drop for Pipeline {
+        fn drop(&mut self) {
+            if let Err(e) = self.shutdown() {
+                log::error!(target: "savant_rs::pipeline", "Pipeline shutdown failed: {}", e);
             }
         }
     }
@@ -353,19 +507,47 @@ pub(super) mod implementation {
             &mut self,
             name: String,
             stage_type: PipelineStagePayloadType,
-            ingress_function: Option<Box<dyn PipelineStageFunction>>,
-            egress_function: Option<Box<dyn PipelineStageFunction>>,
+            mut ingress_function: Option<Box<dyn PipelineStageFunction>>,
+            mut egress_function: Option<Box<dyn PipelineStageFunction>>,
         ) -> Result<()> {
             if self.find_stage(&name, 0).is_ok() {
                 bail!("Stage with name {} already exists", name)
             }
 
+            for (order, function) in [
+                (PipelineStageFunctionOrder::Ingress, &mut ingress_function),
+                (PipelineStageFunctionOrder::Egress, &mut egress_function),
+            ] {
+                if let Some(function) = function {
+                    let params = function.get_params().cloned().unwrap_or_default();
+                    function.init(&name, order, &params).map_err(|e| {
+                        anyhow!(
+                            "Failed to initialize {:?} function of the stage {}: {}",
+                            order,
+                            name,
+                            e
+                        )
+                    })?;
+                }
+            }
+
+            let queue_limit = self.configuration.stage_queue_limits.get(&name).cloned();
+            if let Some(limit) = &queue_limit {
+                if limit.capacity == 0 {
+                    bail!(
+                        "Queue capacity for stage {} must be greater than zero",
+                        name
+                    )
+                }
+            }
+
             let stage = PipelineStage::new(
                 self.stages.len(),
                 name,
                 stage_type,
                 ingress_function,
                 egress_function,
+                queue_limit,
             );
             let stat = stage.get_stat();
             self.stats.add_stage_stats(stat);
@@ -388,15 +570,19 @@ pub(super) mod implementation {
                 configuration.frame_period,
                 configuration.timestamp_period,
             );
-            let mut pipeline = Self {
-                configuration,
-                stats,
-                ..Default::default()
-            };
+            let mut pipeline = Self::default();
+            pipeline.configuration = configuration;
+            pipeline.stats = stats;
 
             for (name, stage_type, ingress_function, egress_function) in stages {
                 pipeline.add_stage(name, stage_type, ingress_function, egress_function)?;
             }
+
+            for name in pipeline.configuration.stage_queue_limits.keys() {
+                if pipeline.find_stage(name, 0).is_err() {
+                    bail!("Queue limit is defined for the unknown stage {}", name)
+                }
+            }
             Ok(pipeline)
         }
 
@@ -574,6 +760,10 @@ pub(super) mod implementation {
                 bail!("Stage does not accept batched frames")
             }
 
+            let (index, stage) = self.find_stage(stage_name, 0)?;
+            let (_reservation, dropped) = stage.reserve(1, &[frame.get_source_id()])?;
+            self.discard_dropped(stage, dropped);
+
             self.frame_counter.fetch_add(1, Ordering::SeqCst);
             let id_counter = self.id_counter.fetch_add(1, Ordering::SeqCst) + 1;
             let source_id = frame.get_source_id();
@@ -631,7 +821,6 @@ pub(super) mod implementation {
             let frame_payload =
                 PipelinePayload::Frame(frame, Vec::new(), ctx, None, SystemTime::now());
 
-            let (index, stage) = self.find_stage(stage_name, 0)?;
             stage.add_frame_payload(id_counter, frame_payload)?;
             self.frame_locations.write().insert(id_counter, index);
 
@@ -646,7 +835,93 @@ pub(super) mod implementation {
                 .map(|h| h.iter().cloned().collect())
         }
 
+        fn discard_dropped(&self, stage: &PipelineStage, dropped: Vec<(i64, PipelinePayload)>) {
+            if dropped.is_empty() {
+                return;
+            }
+            {
+                let mut locations = self.frame_locations.write();
+                for (id, _) in &dropped {
+                    locations.remove(id);
+                }
+            }
+            let mut root_spans = self.root_spans.write();
+            for (id, payload) in dropped {
+                let frame_ids = match payload {
+                    PipelinePayload::Frame(_, _, ctx, _, _) => {
+                        ctx.span().end();
+                        vec![id]
+                    }
+                    PipelinePayload::Batch(_, _, contexts, _, _) => contexts
+                        .into_iter()
+                        .map(|(frame_id, ctx)| {
+                            ctx.span().end();
+                            frame_id
+                        })
+                        .collect(),
+                };
+                for frame_id in frame_ids {
+                    if let Some(root_ctx) = root_spans.remove(&frame_id) {
+                        root_ctx.span().end();
+                    }
+                }
+                log::debug!(target: "savant_rs::pipeline", "Dropped payload {} from the stage {} to free queue space", id, stage.name);
+            }
+        }
+
+        pub fn get_tick_period(&self) -> Option<Duration> {
+            self.configuration.tick_period_ms.map(Duration::from_millis)
+        }
+
+        pub fn is_shut_down(&self) -> bool {
+            self.shut_down.load(Ordering::SeqCst)
+        }
+
+        fn call_stage_functions<F>(&self, hook: &str, f: F) -> Result<()>
+        where
+            F: Fn(&dyn PipelineStageFunction) -> Result<()>,
+        {
+            let mut first_error = None;
+            for stage in &self.stages {
+                if let Err(e) = stage.call_functions(&f) {
+                    log::error!(target: "savant_rs::pipeline", "Stage {} function failed in {}: {}", stage.name, hook, e);
+                    first_error.get_or_insert(anyhow!(
+                        "Stage {} function failed in {}: {}",
+                        stage.name,
+                        hook,
+                        e
+                    ));
+                }
+            }
+            match first_error {
+                Some(e) => Err(e),
+                None => Ok(()),
+            }
+        }
+
+        pub fn notify_source_eos(&self, source_id: &str) -> Result<()> {
+            self.call_stage_functions("on_source_eos", |f| f.on_source_eos(source_id))
+        }
+
+        pub fn tick(&self) -> Result<()> {
+            self.call_stage_functions("on_tick", |f| f.on_tick())
+        }
+
+        pub fn flush(&self) -> Result<()> {
+            self.call_stage_functions("flush", |f| f.flush())
+        }
+
+        pub fn shutdown(&self) -> Result<()> {
+            if self.shut_down.swap(true, Ordering::SeqCst) {
+                return Ok(());
+            }
+            let flushed = self.flush();
+            let shut_down = self.call_stage_functions("on_shutdown", |f| f.on_shutdown());
+            flushed.and(shut_down)
+        }
+
         pub fn clear_source_ordering(&self, source_id: &str) -> Result<()> {
+            let notified = self.notify_source_eos(source_id);
             let mut ordering = self.frame_ordering.write();
             ordering.pop(source_id).ok_or_else(|| {
                 anyhow!(
@@ -654,7 +929,7 @@ pub(super) mod implementation {
                     source_id
                 )
             })?;
-            Ok(())
+            notified
         }
 
         fn add_frame_json(&self, frame: &VideoFrameProxy, ctx: &Context) {
@@ -861,6 +1136,15 @@ pub(super) mod implementation {
                     source_stage.name, source_stage.stage_type, dest_stage.name, dest_stage.stage_type)
             }
 
+            let _reservation = if dest_index != source_index {
+                let source_ids = source_stage.get_source_ids(&object_ids);
+                let (reservation, dropped) = dest_stage.reserve(object_ids.len(), &source_ids)?;
+                self.discard_dropped(dest_stage, dropped);
+                Some(reservation)
+            } else {
+                None
+            };
+
             let removed_objects = source_stage_opt
                 .as_ref()
                 .expect("Stage must be defined according to the previous check")
@@ -926,6 +1210,10 @@ pub(super) mod implementation {
                 bail!("Source stage {} must contain independent frames and destination stage must contain batched frames", source_stage.name)
             }
 
+            let source_ids = source_stage.get_source_ids(&frame_ids);
+            let (_reservation, dropped) = dest_stage.reserve(1, &source_ids)?;
+            self.discard_dropped(dest_stage, dropped);
+
             let batch_id = self.id_counter.fetch_add(1, Ordering::SeqCst) + 1;
 
             self.update_frame_locations(&frame_ids, dest_index);
@@ -1009,6 +1297,10 @@ pub(super) mod implementation {
                 bail!("Source stage {} must contain batched frames and destination stage must contain independent frames", source_stage.name)
             }
 
+            let source_ids = source_stage.get_source_ids(&[batch_id]);
+            let (_reservation, dropped) = dest_stage.reserve(source_ids.len(), &source_ids)?;
+            self.discard_dropped(dest_stage, dropped);
+
             let (batch, updates, mut contexts, last_stage, last_times) = if let Some(payload) =
                 source_stage_opt
                     .as_ref()
@@ -1134,7 +1426,19 @@ pub(super) mod implementation {
 
         use opentelemetry::trace::TraceContextExt;
 
-        use crate::pipeline::implementation::{create_test_pipeline, PipelineStagePayloadType};
+        use std::sync::Arc;
+
+        use hashbrown::HashMap;
+
+        use crate::pipeline::implementation::{
+            create_test_pipeline, Pipeline, PipelineConfigurationBuilder, PipelineStagePayloadType,
+        };
+        use crate::pipeline::stage::PipelineStage;
+        use crate::pipeline::stats::StageProcessingStat;
+        use crate::pipeline::{
+            PipelinePayload, PipelineStageFunction, PipelineStageFunctionOrder, PluginParams,
+            StageQueueLimit, StageQueuePolicy,
+        };
         use crate::primitives::attribute_value::AttributeValue;
         use crate::primitives::frame_update::VideoFrameUpdate;
         use crate::primitives::{Attribute, WithAttributes};
@@ -1383,5 +1687,320 @@ pub(super) mod implementation {
             let _ = pipeline.get_stat_records(10);
             Ok(())
         }
+
+        fn create_limited_pipeline(policy: StageQueuePolicy) -> anyhow::Result<Pipeline> {
+            Pipeline::new(
+                vec![
+                    (
+                        "input".to_string(),
+                        PipelineStagePayloadType::Frame,
+                        None,
+                        None,
+                    ),
+                    (
+                        "proc".to_string(),
+                        PipelineStagePayloadType::Frame,
+                        None,
+                        None,
+                    ),
+                    (
+                        "batch".to_string(),
+                        PipelineStagePayloadType::Batch,
+                        None,
+                        None,
+                    ),
+                ],
+                PipelineConfigurationBuilder::default()
+                    .stage_queue_limits(HashMap::from([
+                        ("input".to_string(), StageQueueLimit::new(2, policy.clone())),
+                        ("proc".to_string(), StageQueueLimit::new(1, policy.clone())),
+                        ("batch".to_string(), StageQueueLimit::new(1, policy)),
+                    ]))
+                    .build()
+                    .unwrap(),
+            )
+        }
+
+        fn stage_stat(pipeline: &Pipeline, stage: &str) -> StageProcessingStat {
+            let (_, stage) = pipeline.find_stage(stage, 0).unwrap();
+            stage.get_stat().lock().0.clone()
+        }
+
+        #[test]
+        fn test_queue_limit_for_unknown_stage() {
+            let res = Pipeline::new(
+                vec![(
+                    "input".to_string(),
+                    PipelineStagePayloadType::Frame,
+                    None,
+                    None,
+                )],
+                PipelineConfigurationBuilder::default()
+                    .stage_queue_limits(HashMap::from([(
+                        "missing".to_string(),
+                        StageQueueLimit::new(1, StageQueuePolicy::Reject),
+                    )]))
+                    .build()
+                    .unwrap(),
+            );
+            assert!(res.is_err());
+        }
+
+        #[test]
+        fn test_queue_reject() -> anyhow::Result<()> {
+            let pipeline = create_limited_pipeline(StageQueuePolicy::Reject)?;
+            let id1 = pipeline.add_frame("input", gen_frame())?;
+            let id2 = pipeline.add_frame("input", gen_frame())?;
+            assert!(pipeline.add_frame("input", gen_frame()).is_err());
+            assert_eq!(pipeline.get_stage_queue_len("input")?, 2);
+
+            pipeline.move_as_is("proc", vec![id1])?;
+            assert!(pipeline.move_as_is("proc", vec![id2]).is_err());
+            // the rejected frame stays in the source stage
+            assert!(pipeline.get_independent_frame(id2).is_ok());
+            assert_eq!(pipeline.get_stage_queue_len("input")?, 1);
+
+            pipeline.move_and_pack_frames("batch", vec![id2])?;
+            let id3 = pipeline.add_frame("input", gen_frame())?;
+            assert!(pipeline.move_and_pack_frames("batch", vec![id3]).is_err());
+            assert!(pipeline.get_independent_frame(id3).is_ok());
+
+            assert_eq!(stage_stat(&pipeline, "input").rejected_counter, 1);
+            assert_eq!(stage_stat(&pipeline, "proc").rejected_counter, 1);
+            assert_eq!(stage_stat(&pipeline, "batch").rejected_counter, 1);
+            Ok(())
+        }
+
+        #[test]
+        fn test_queue_drop_oldest_of_source() -> anyhow::Result<()> {
+            let pipeline = create_limited_pipeline(StageQueuePolicy::DropOldestOfSource)?;
+            let id1 = pipeline.add_frame("input", gen_frame())?;
+            let id2 = pipeline.add_frame("input", gen_frame())?;
+            let id3 = pipeline.add_frame("input", gen_frame())?;
+            assert_eq!(pipeline.get_stage_queue_len("input")?, 2);
+            assert!(pipeline.get_independent_frame(id1).is_err());
+            assert!(pipeline.delete(id1).is_err());
+            assert!(pipeline.get_independent_frame(id2).is_ok());
+            assert!(pipeline.get_independent_frame(id3).is_ok());
+
+            let mut other = gen_frame();
+            other.set_source_id("other");
+            pipeline.move_as_is("proc", vec![id2])?;
+            let id4 = pipeline.add_frame("input", other)?;
+            // there is no frame of the source "other" in the stage to drop
+            assert!(pipeline.move_as_is("proc", vec![id4]).is_err());
+            pipeline.move_as_is("proc", vec![id3])?;
+            assert!(pipeline.get_independent_frame(id2).is_err());
+
+            let stat = stage_stat(&pipeline, "input");
+            assert_eq!((stat.dropped_counter, stat.rejected_counter), (1, 0));
+            let stat = stage_stat(&pipeline, "proc");
+            assert_eq!((stat.dropped_counter, stat.rejected_counter), (1, 1));
+            assert_eq!(pipeline.get_id_locations_len(), 2);
+            Ok(())
+        }
+
+        #[test]
+        fn test_queue_block() -> anyhow::Result<()> {
+            let pipeline = Arc::new(create_limited_pipeline(StageQueuePolicy::Block(
+                Duration::from_millis(50),
+            ))?);
+            let id1 = pipeline.add_frame("input", gen_frame())?;
+            pipeline.add_frame("input", gen_frame())?;
+            assert!(pipeline.add_frame("input", gen_frame()).is_err());
+            assert_eq!(stage_stat(&pipeline, "input").rejected_counter, 1);
+
+            let p = pipeline.clone();
+            let deleter = std::thread::spawn(move || {
+                sleep(Duration::from_millis(10));
+                p.delete(id1).unwrap();
+            });
+            pipeline.add_frame("input", gen_frame())?;
+            deleter.join().unwrap();
+            assert_eq!(pipeline.get_stage_queue_len("input")?, 2);
+            Ok(())
+        }
+
+        #[test]
+        fn test_queue_reject_concurrent() -> anyhow::Result<()> {
+            let pipeline = Arc::new(create_limited_pipeline(StageQueuePolicy::Reject)?);
+            let producers = (0..8)
+                .map(|_| {
+                    let p = pipeline.clone();
+                    std::thread::spawn(move || {
+                        (0..16)
+                            .filter(|_| p.add_frame("input", gen_frame()).is_ok())
+                            .count()
+                    })
+                })
+                .collect::<Vec<_>>();
+            let accepted = producers
+                .into_iter()
+                .map(|h| h.join().unwrap())
+                .sum::<usize>();
+            assert_eq!(accepted, 2);
+            assert_eq!(pipeline.get_stage_queue_len("input")?, 2);
+            assert_eq!(stage_stat(&pipeline, "input").rejected_counter, 126);
+            Ok(())
+        }
+
+        struct RecordingFunction {
+            events: Arc<parking_lot::Mutex<Vec<String>>>,
+            pipeline: Option<crate::pipeline::Pipeline>,
+            params: PluginParams,
+            fail_init: bool,
+        }
+
+        impl RecordingFunction {
+            fn boxed(
+                events: &Arc<parking_lot::Mutex<Vec<String>>>,
+                fail_init: bool,
+            ) -> Option<Box<dyn PipelineStageFunction>> {
+                let mut params = PluginParams::default();
+                params
+                    .params
+                    .insert("label".to_string(), AttributeValue::string("rec", None));
+                Some(Box::new(Self {
+                    events: events.clone(),
+                    pipeline: None,
+                    params,
+                    fail_init,
+                }))
+            }
+        }
+
+        impl PipelineStageFunction for RecordingFunction {
+            fn set_pipeline(&mut self, pipeline: crate::pipeline::Pipeline) {
+                self.pipeline = Some(pipeline);
+            }
+            fn get_pipeline(&self) -> &Option<crate::pipeline::Pipeline> {
+                &self.pipeline
+            }
+            fn call(
+                &self,
+                _: i64,
+                _: &PipelineStage,
+                _: PipelineStageFunctionOrder,
+                _: &mut PipelinePayload,
+            ) -> anyhow::Result<()> {
+                Ok(())
+            }
+            fn get_params(&self) -> Option<&PluginParams> {
+                Some(&self.params)
+            }
+            fn init(
+                &mut self,
+                stage: &str,
+                order: PipelineStageFunctionOrder,
+                params: &PluginParams,
+            ) -> anyhow::Result<()> {
+                if self.fail_init || !params.params.contains_key("label") {
+                    anyhow::bail!("invalid parameters")
+                }
+                self.events
+                    .lock()
+                    .push(format!("init {} {:?}", stage, order));
+                Ok(())
+            }
+            fn on_source_eos(&self, source_id: &str) -> anyhow::Result<()> {
+                self.events.lock().push(format!("eos {}", source_id));
+                Ok(())
+            }
+            fn on_tick(&self) -> anyhow::Result<()> {
+                self.events.lock().push("tick".to_string());
+                Ok(())
+            }
+            fn flush(&self) -> anyhow::Result<()> {
+                self.events.lock().push("flush".to_string());
+                Ok(())
+            }
+            fn on_shutdown(&self) -> anyhow::Result<()> {
+                self.events.lock().push("shutdown".to_string());
+                Ok(())
+            }
+        }
+
+        #[test]
+        fn test_stage_function_hooks() -> anyhow::Result<()> {
+            let events = Arc::new(parking_lot::Mutex::new(Vec::new()));
+            let pipeline = Pipeline::new(
+                vec![(
+                    "input".to_string(),
+                    PipelineStagePayloadType::Frame,
+                    RecordingFunction::boxed(&events, false),
+                    RecordingFunction::boxed(&events, false),
+                )],
+                PipelineConfigurationBuilder::default().build()?,
+            )?;
+            pipeline.add_frame("input", gen_frame())?;
+            pipeline.clear_source_ordering("test")?;
+            pipeline.notify_source_eos("other")?;
+            pipeline.tick()?;
+            pipeline.shutdown()?;
+            assert!(pipeline.is_shut_down());
+            drop(pipeline);
+            assert_eq!(
+                *events.lock(),
+                vec![
+                    "init input Ingress",
+                    "init input Egress",
+                    "eos test",
+                    "eos test",
+                    "eos other",
+                    "eos other",
+                    "tick",
+                    "tick",
+                    "flush",
+                    "flush",
+                    "shutdown",
+                    "shutdown",
+                ]
+            );
+
+            let res = Pipeline::new(
+                vec![(
+                    "input".to_string(),
+                    PipelineStagePayloadType::Frame,
+                    RecordingFunction::boxed(&events, true),
+                    None,
+                )],
+                PipelineConfigurationBuilder::default().build()?,
+            );
+            assert!(res.is_err());
+            Ok(())
+        }
+
+        #[test]
+        fn test_stage_function_tick() -> anyhow::Result<()> {
+            let events = Arc::new(parking_lot::Mutex::new(Vec::new()));
+            let pipeline = crate::pipeline::Pipeline::new(
+                vec![(
+                    "input".to_string(),
+                    PipelineStagePayloadType::Frame,
+                    RecordingFunction::boxed(&events, false),
+                    None,
+                )],
+                PipelineConfigurationBuilder::default()
+                    .tick_period_ms(Some(5))
+                    .build()?,
+            )?;
+            sleep(Duration::from_millis(50));
+            let ticks = events.lock().iter().filter(|e| *e == "tick").count();
+            assert!(ticks > 0);
+
+            // neither the tick thread nor the webserver registry keeps the pipeline alive
+            let weak = Arc::downgrade(&pipeline.0);
+            drop(pipeline);
+            // a tick in progress holds the pipeline until it returns
+            for _ in 0..10 {
+                if weak.strong_count() == 0 {
+                    break;
+                }
+                sleep(Duration::from_millis(5));
+            }
+            assert!(weak.upgrade().is_none());
+            assert_eq!(events.lock().last().map(String::as_str), Some("shutdown"));
+            Ok(())
+        }
     }
 }
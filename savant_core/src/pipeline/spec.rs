@@ -0,0 +1,362 @@
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context};
+use hashbrown::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+
+use crate::pipeline::stage_function_loader::load_stage_function_plugin;
+use crate::pipeline::{
+    Pipeline, PipelineConfiguration, PipelineConfigurationBuilder, PipelineStageFunction,
+    PipelineStagePayloadType, PluginParams, StageQueueLimit, StageQueuePolicy,
+};
+use crate::primitives::attribute_value::AttributeValue;
+
+/// A stage function loaded from a plugin library with
+/// [`load_stage_function_plugin`].
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StageFunctionSpec {
+    pub library: String,
+    pub init: String,
+    pub name: String,
+    #[serde(default)]
+    pub params: HashMap<String, AttributeValue>,
+}
+
+impl StageFunctionSpec {
+    pub fn load(&self) -> anyhow::Result<Box<dyn PipelineStageFunction>> {
+        load_stage_function_plugin(
+            &self.library,
+            &self.init,
+            &self.name,
+            PluginParams {
+                params: self.params.clone(),
+            },
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StageQueuePolicySpec {
+    Reject,
+    Block,
+    DropOldestOfSource,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StageQueueSpec {
+    pub capacity: usize,
+    pub policy: StageQueuePolicySpec,
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+}
+
+impl StageQueueSpec {
+    pub fn to_queue_limit(&self) -> anyhow::Result<StageQueueLimit> {
+        if self.capacity == 0 {
+            bail!("queue capacity must be greater than zero")
+        }
+        let policy = match (self.policy, self.timeout_ms) {
+            (StageQueuePolicySpec::Reject, _) => StageQueuePolicy::Reject,
+            (StageQueuePolicySpec::Block, Some(timeout_ms)) => {
+                StageQueuePolicy::Block(Duration::from_millis(timeout_ms))
+            }
+            (StageQueuePolicySpec::Block, None) => {
+                bail!("queue policy 'block' requires 'timeout_ms'")
+            }
+            (StageQueuePolicySpec::DropOldestOfSource, _) => StageQueuePolicy::DropOldestOfSource,
+        };
+        Ok(StageQueueLimit::new(self.capacity, policy))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StageSpec {
+    pub name: String,
+    pub payload: PipelineStagePayloadType,
+    #[serde(default)]
+    pub ingress: Option<StageFunctionSpec>,
+    #[serde(default)]
+    pub egress: Option<StageFunctionSpec>,
+    #[serde(default)]
+    pub queue: Option<StageQueueSpec>,
+}
+
+/// Declarative pipeline definition, the counterpart of [`Pipeline::new`].
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PipelineSpec {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub root_span_name: Option<String>,
+    #[serde(default)]
+    pub sampling_period: Option<i64>,
+    pub stages: Vec<StageSpec>,
+    #[serde(default = "default_configuration")]
+    pub configuration: PipelineConfiguration,
+}
+
+fn default_configuration() -> PipelineConfiguration {
+    PipelineConfigurationBuilder::default().build().unwrap()
+}
+
+impl PipelineSpec {
+    pub fn to_json_pretty(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    pub fn to_yaml(&self) -> String {
+        serde_yaml::to_string(&serde_json::to_value(self).unwrap()).unwrap()
+    }
+
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn from_yaml(yaml: &str) -> anyhow::Result<Self> {
+        Ok(serde_json::from_value(serde_yaml::from_str(yaml)?)?)
+    }
+
+    /// Loads the spec from a file, ``.json`` files are parsed as JSON, others as YAML.
+    ///
+    pub fn from_file<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read pipeline spec {}", path.display()))?;
+        let is_json = path
+            .extension()
+            .map(|e| e.eq_ignore_ascii_case("json"))
+            .unwrap_or(false);
+        if is_json {
+            Self::from_json(&text)
+        } else {
+            Self::from_yaml(&text)
+        }
+        .with_context(|| format!("Failed to parse pipeline spec {}", path.display()))
+    }
+
+    fn stage_error(index: usize, stage: &StageSpec, e: anyhow::Error) -> anyhow::Error {
+        anyhow!("Stage #{} ({}): {}", index, stage.name, e)
+    }
+
+    /// Checks the spec without loading plugins.
+    ///
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.stages.is_empty() {
+            bail!("Pipeline spec must define at least one stage")
+        }
+        let mut names = HashSet::with_capacity(self.stages.len());
+        for (index, stage) in self.stages.iter().enumerate() {
+            if stage.name.is_empty() {
+                bail!("Stage #{}: the name must not be empty", index)
+            }
+            if !names.insert(stage.name.as_str()) {
+                return Err(Self::stage_error(
+                    index,
+                    stage,
+                    anyhow!("the stage name is not unique"),
+                ));
+            }
+            for (kind, function) in [("ingress", &stage.ingress), ("egress", &stage.egress)] {
+                if let Some(function) = function {
+                    if function.library.is_empty() || function.init.is_empty() {
+                        return Err(Self::stage_error(
+                            index,
+                            stage,
+                            anyhow!("{} function must define 'library' and 'init'", kind),
+                        ));
+                    }
+                }
+            }
+            if let Some(queue) = &stage.queue {
+                queue
+                    .to_queue_limit()
+                    .map_err(|e| Self::stage_error(index, stage, e))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Validates the spec, loads the stage function plugins and creates the pipeline.
+    ///
+    pub fn build(&self) -> anyhow::Result<Pipeline> {
+        self.validate()?;
+        let mut configuration = self.configuration.clone();
+        let mut stages = Vec::with_capacity(self.stages.len());
+        for (index, stage) in self.stages.iter().enumerate() {
+            let load = |kind: &str, function: &Option<StageFunctionSpec>| {
+                function
+                    .as_ref()
+                    .map(|f| {
+                        f.load().map_err(|e| {
+                            Self::stage_error(
+                                index,
+                                stage,
+                                anyhow!(
+                                    "failed to load {} function {} from {}: {}",
+                                    kind,
+                                    f.init,
+                                    f.library,
+                                    e
+                                ),
+                            )
+                        })
+                    })
+                    .transpose()
+            };
+            let ingress = load("ingress", &stage.ingress)?;
+            let egress = load("egress", &stage.egress)?;
+            if let Some(queue) = &stage.queue {
+                configuration
+                    .stage_queue_limits
+                    .insert(stage.name.clone(), queue.to_queue_limit()?);
+            }
+            stages.push((stage.name.clone(), stage.payload.clone(), ingress, egress));
+        }
+        let pipeline = Pipeline::new(stages, configuration)?;
+        if let Some(name) = &self.name {
+            pipeline.set_name(name.clone())?;
+        }
+        if let Some(root_span_name) = &self.root_span_name {
+            pipeline.set_root_span_name(root_span_name.clone())?;
+        }
+        if let Some(sampling_period) = self.sampling_period {
+            pipeline.set_sampling_period(sampling_period)?;
+        }
+        Ok(pipeline)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pipeline::spec::{PipelineSpec, StageQueuePolicySpec};
+    use crate::pipeline::PipelineStagePayloadType;
+    use crate::primitives::attribute_value::AttributeValueVariant;
+    use crate::test::gen_frame;
+
+    const SPEC: &str = r#"
+name: spec-test
+sampling_period: 10
+stages:
+  - name: input
+    payload: frame
+    queue:
+      capacity: 2
+      policy: reject
+  - name: infer
+    payload: batch
+    queue:
+      capacity: 1
+      policy: block
+      timeout_ms: 100
+  - name: output
+    payload: frame
+configuration:
+  frame_period: null
+  keyframe_history: 5
+"#;
+
+    #[test]
+    fn test_load_and_build() -> anyhow::Result<()> {
+        let spec = PipelineSpec::from_yaml(SPEC)?;
+        assert_eq!(spec.stages.len(), 3);
+        assert_eq!(spec.stages[1].payload, PipelineStagePayloadType::Batch);
+        assert_eq!(
+            spec.stages[1].queue.as_ref().unwrap().policy,
+            StageQueuePolicySpec::Block
+        );
+        assert_eq!(spec.configuration.frame_period, None);
+        assert_eq!(spec.configuration.timestamp_period, Some(1000));
+        assert_eq!(spec.configuration.collection_history, 10);
+        assert_eq!(spec.configuration.keyframe_history, 5);
+
+        let spec = PipelineSpec::from_json(&spec.to_json())?;
+        let pipeline = spec.build()?;
+        assert_eq!(pipeline.get_name(), Some("spec-test".to_string()));
+        assert_eq!(pipeline.get_sampling_period(), 10);
+        assert_eq!(
+            pipeline.get_stage_type("infer")?,
+            PipelineStagePayloadType::Batch
+        );
+
+        pipeline.add_frame("input", gen_frame())?;
+        pipeline.add_frame("input", gen_frame())?;
+        assert!(pipeline.add_frame("input", gen_frame()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_plugin_params() -> anyhow::Result<()> {
+        let spec = PipelineSpec::from_yaml(
+            r#"
+stages:
+  - name: input
+    payload: frame
+    ingress:
+      library: libmissing_plugin.so
+      init: init_plugin
+      name: ingress
+      params:
+        threshold:
+          confidence: null
+          value:
+            Float: 0.5
+"#,
+        )?;
+        let ingress = spec.stages[0].ingress.as_ref().unwrap();
+        assert_eq!(
+            ingress.params.get("threshold").unwrap().value,
+            AttributeValueVariant::Float(0.5)
+        );
+        spec.validate()?;
+        let e = spec.build().unwrap_err().to_string();
+        assert!(e.starts_with("Stage #0 (input): failed to load ingress function"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_validation_names_stage() {
+        let e = PipelineSpec::from_yaml(
+            r#"
+stages:
+  - name: input
+    payload: frame
+  - name: infer
+    payload: frame
+    queue:
+      capacity: 1
+      policy: block
+"#,
+        )
+        .unwrap()
+        .validate()
+        .unwrap_err()
+        .to_string();
+        assert_eq!(
+            e,
+            "Stage #1 (infer): queue policy 'block' requires 'timeout_ms'"
+        );
+
+        let e = PipelineSpec::from_yaml(
+            r#"
+stages:
+  - name: input
+    payload: frame
+  - name: input
+    payload: batch
+"#,
+        )
+        .unwrap()
+        .validate()
+        .unwrap_err()
+        .to_string();
+        assert_eq!(e, "Stage #1 (input): the stage name is not unique");
+    }
+}
@@ -1,18 +1,20 @@
 use std::fmt::{Debug, Formatter};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
-use std::time::SystemTime;
+use std::time::{Instant, SystemTime};
 
 use anyhow::bail;
 use hashbrown::{HashMap, HashSet};
 use opentelemetry::trace::TraceContextExt;
 use opentelemetry::Context;
-use parking_lot::Mutex;
+use parking_lot::{Condvar, Mutex};
 
 use crate::match_query::MatchQuery;
 use crate::pipeline::implementation::Pipeline;
 use crate::pipeline::stats::{StageLatencyStat, StageProcessingStat, StageStats};
 use crate::pipeline::{
     PipelinePayload, PipelineStageFunction, PipelineStageFunctionOrder, PipelineStagePayloadType,
+    StageQueueLimit, StageQueuePolicy,
 };
 use crate::primitives::frame::VideoFrameProxy;
 use crate::primitives::frame_batch::VideoFrameBatch;
@@ -26,6 +28,10 @@ pub struct PipelineStage {
     pub stage_type: PipelineStagePayloadType,
     pub payload: SavantRwLock<HashMap<i64, PipelinePayload>>,
     pub stat: StageStats,
+    pub queue_limit: Option<StageQueueLimit>,
+    reserved: AtomicUsize,
+    space_lock: Mutex<()>,
+    space_available: Condvar,
     ingress_function: Option<Box<dyn PipelineStageFunction>>,
     egress_function: Option<Box<dyn PipelineStageFunction>>,
 }
@@ -38,12 +44,32 @@ impl Debug for PipelineStage {
             .field("stage_type", &self.stage_type)
             .field("payload", &self.payload)
             .field("stat", &self.stat)
+            .field("queue_limit", &self.queue_limit)
             .field("ingress_function", &self.ingress_function.is_some())
             .field("egress_function", &self.egress_function.is_some())
             .finish()
     }
 }
 
+/// Space reserved in a stage queue by [`PipelineStage::reserve`]. The space is counted
+/// against the stage capacity until the reservation is dropped, so the caller must keep it
+/// alive until the payloads are inserted.
+///
+#[must_use]
+pub struct StageReservation<'a> {
+    stage: &'a PipelineStage,
+    count: usize,
+}
+
+impl Drop for StageReservation<'_> {
+    fn drop(&mut self) {
+        if self.count > 0 {
+            self.stage.reserved.fetch_sub(self.count, Ordering::SeqCst);
+            self.stage.notify_space_available();
+        }
+    }
+}
+
 impl PipelineStage {
     pub fn new(
         id: usize,
@@ -51,6 +77,7 @@ impl PipelineStage {
         stage_type: PipelineStagePayloadType,
         ingress_function: Option<Box<dyn PipelineStageFunction>>,
         egress_function: Option<Box<dyn PipelineStageFunction>>,
+        queue_limit: Option<StageQueueLimit>,
     ) -> Self {
         Self {
             id,
@@ -61,6 +88,10 @@ impl PipelineStage {
                 StageProcessingStat::new(name.clone()),
                 StageLatencyStat::new(name),
             ))),
+            queue_limit,
+            reserved: AtomicUsize::new(0),
+            space_lock: Mutex::new(()),
+            space_available: Condvar::new(),
             ingress_function,
             egress_function,
         }
@@ -70,6 +101,18 @@ impl PipelineStage {
         self.stat.clone()
     }
 
+    /// Calls `f` for the ingress and egress functions, both are called even if the
+    /// first one fails.
+    ///
+    pub(crate) fn call_functions<F>(&self, f: F) -> anyhow::Result<()>
+    where
+        F: Fn(&dyn PipelineStageFunction) -> anyhow::Result<()>,
+    {
+        let ingress = self.ingress_function.as_deref().map(&f).transpose();
+        let egress = self.egress_function.as_deref().map(&f).transpose();
+        ingress.and(egress).map(|_| ())
+    }
+
     fn with_payload_item_mut<F, T>(&self, id: i64, f: F) -> anyhow::Result<T>
     where
         F: FnOnce(&mut PipelinePayload) -> T,
@@ -284,6 +327,11 @@ impl PipelineStage {
             }
             Ok(res)
         })
+        .inspect(|res| {
+            if res.is_some() {
+                self.notify_space_available();
+            }
+        })
     }
 
     pub fn delete_many(&self, ids: &[i64]) -> anyhow::Result<Vec<(i64, PipelinePayload)>> {
@@ -307,12 +355,138 @@ impl PipelineStage {
             stats_bind.0.queue_length = bind.len();
             Ok(removed)
         })
+        .inspect(|removed| {
+            if !removed.is_empty() {
+                self.notify_space_available();
+            }
+        })
+    }
+
+    fn notify_space_available(&self) {
+        if self.queue_limit.is_some() {
+            let _guard = self.space_lock.lock();
+            self.space_available.notify_all();
+        }
+    }
+
+    fn reject<T>(&self, count: usize, capacity: usize) -> anyhow::Result<T> {
+        self.stat.lock().0.rejected_counter += count;
+        bail!(
+            "Stage {} cannot accept {} payload(s), the queue capacity is {}",
+            self.name,
+            count,
+            capacity
+        )
+    }
+
+    fn reservation(&self, count: usize) -> StageReservation<'_> {
+        StageReservation { stage: self, count }
+    }
+
+    /// Reserves `count` slots if they fit into `capacity` together with the queued payloads and
+    /// the outstanding reservations. The check and the reservation happen under the payload
+    /// lock, so concurrent producers cannot overcommit the stage.
+    ///
+    fn try_reserve(&self, count: usize, capacity: usize) -> Option<StageReservation<'_>> {
+        self.with_payload_mut(|bind| {
+            let reserved = self.reserved.load(Ordering::SeqCst);
+            if bind.len() + reserved + count > capacity {
+                return None;
+            }
+            self.reserved.fetch_add(count, Ordering::SeqCst);
+            Some(self.reservation(count))
+        })
+    }
+
+    /// Makes room for `count` payloads with frames of `source_ids` according to the stage queue
+    /// limit. Returns the reservation, which must be held until the payloads are added, and the
+    /// payloads dropped to free the space; they are no longer in the stage.
+    ///
+    pub fn reserve(
+        &self,
+        count: usize,
+        source_ids: &[String],
+    ) -> anyhow::Result<(StageReservation<'_>, Vec<(i64, PipelinePayload)>)> {
+        let Some(limit) = &self.queue_limit else {
+            return Ok((self.reservation(0), Vec::new()));
+        };
+        let capacity = limit.capacity;
+        if count > capacity {
+            return self.reject(count, capacity);
+        }
+        match &limit.policy {
+            StageQueuePolicy::Reject => match self.try_reserve(count, capacity) {
+                Some(reservation) => Ok((reservation, Vec::new())),
+                None => self.reject(count, capacity),
+            },
+            StageQueuePolicy::Block(timeout) => {
+                let deadline = Instant::now() + *timeout;
+                let mut guard = self.space_lock.lock();
+                loop {
+                    if let Some(reservation) = self.try_reserve(count, capacity) {
+                        return Ok((reservation, Vec::new()));
+                    }
+                    if self
+                        .space_available
+                        .wait_until(&mut guard, deadline)
+                        .timed_out()
+                    {
+                        if let Some(reservation) = self.try_reserve(count, capacity) {
+                            return Ok((reservation, Vec::new()));
+                        }
+                        return self.reject(count, capacity);
+                    }
+                }
+            }
+            StageQueuePolicy::DropOldestOfSource => {
+                let reserved = self.with_payload_mut(|bind| {
+                    let excess = (bind.len() + self.reserved.load(Ordering::SeqCst) + count)
+                        .saturating_sub(capacity);
+                    let dropped = if excess == 0 {
+                        Vec::new()
+                    } else {
+                        let mut candidates = bind
+                            .iter()
+                            .filter(|(_, p)| p.source_ids().iter().any(|s| source_ids.contains(s)))
+                            .map(|(id, _)| *id)
+                            .collect::<Vec<_>>();
+                        if candidates.len() < excess {
+                            return None;
+                        }
+                        candidates.sort_unstable();
+                        let dropped = candidates[..excess]
+                            .iter()
+                            .map(|id| (*id, bind.remove(id).unwrap()))
+                            .collect::<Vec<_>>();
+                        let mut stats_bind = self.stat.lock();
+                        stats_bind.0.queue_length = bind.len();
+                        stats_bind.0.dropped_counter += dropped.len();
+                        dropped
+                    };
+                    self.reserved.fetch_add(count, Ordering::SeqCst);
+                    Some((self.reservation(count), dropped))
+                });
+                match reserved {
+                    Some(reserved) => Ok(reserved),
+                    None => self.reject(count, capacity),
+                }
+            }
+        }
     }
 
     pub fn len(&self) -> usize {
         self.with_payload(|bind| bind.len())
     }
 
+    pub fn get_source_ids(&self, ids: &[i64]) -> Vec<String> {
+        self.with_payload(|bind| {
+            ids.iter()
+                .filter_map(|id| bind.get(id))
+                .flat_map(|p| p.source_ids())
+                .collect()
+        })
+    }
+
     pub fn is_empty(&self) -> bool {
         self.with_payload(|bind| bind.is_empty())
     }
@@ -469,12 +643,12 @@ mod tests {
 
     fn get_frame_stage() -> PipelineStage {
         let name = "stage".to_string();
-        PipelineStage::new(0, name, PipelineStagePayloadType::Frame, None, None)
+        PipelineStage::new(0, name, PipelineStagePayloadType::Frame, None, None, None)
     }
 
     fn get_batch_stage() -> PipelineStage {
         let name = "stage".to_string();
-        PipelineStage::new(0, name, PipelineStagePayloadType::Batch, None, None)
+        PipelineStage::new(0, name, PipelineStagePayloadType::Batch, None, None, None)
     }
 
     #[test]
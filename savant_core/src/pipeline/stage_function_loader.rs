@@ -1,18 +1,227 @@
 use crate::pipeline::{PipelineStageFunction, PluginParams};
+use crate::primitives::attribute_value::AttributeValueVariant;
+use anyhow::{anyhow, bail};
 use hashbrown::HashMap;
 use lazy_static::lazy_static;
 use parking_lot::Mutex;
+use std::ffi::{c_char, CStr};
 
 lazy_static! {
     static ref LIBRARIES: Mutex<HashMap<String, libloading::Library>> = Mutex::new(HashMap::new());
 }
 
-pub fn load_stage_function_plugin(
+/// Version of the stage function plugin interface, incremented on every change of
+/// [`PipelineStageFunction`], [`PluginParams`] or [`StageFunctionPluginManifest`].
+///
+pub const STAGE_FUNCTION_PLUGIN_API_VERSION: u32 = 2;
+
+/// Name of the symbol exported by [`declare_stage_function_plugin!`].
+///
+pub const STAGE_FUNCTION_PLUGIN_MANIFEST_SYMBOL: &str = "savant_stage_function_plugin_manifest";
+
+pub const SAVANT_CORE_VERSION: &str = concat!(env!("CARGO_PKG_VERSION"), "\0");
+pub const RUSTC_VERSION: &str = concat!(env!("SAVANT_RUSTC_VERSION"), "\0");
+
+pub type StageFunctionPluginManifestFn =
+    unsafe extern "C" fn() -> *const StageFunctionPluginManifest;
+
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PluginParameterKind {
+    Any,
+    String,
+    Integer,
+    Float,
+    Boolean,
+}
+
+impl PluginParameterKind {
+    fn accepts(&self, value: &AttributeValueVariant) -> bool {
+        match self {
+            PluginParameterKind::Any => true,
+            PluginParameterKind::String => matches!(value, AttributeValueVariant::String(_)),
+            PluginParameterKind::Integer => matches!(value, AttributeValueVariant::Integer(_)),
+            PluginParameterKind::Float => matches!(value, AttributeValueVariant::Float(_)),
+            PluginParameterKind::Boolean => matches!(value, AttributeValueVariant::Boolean(_)),
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct PluginParameterSpec {
+    pub name: *const c_char,
+    pub kind: PluginParameterKind,
+    pub required: bool,
+}
+
+/// Plugin description checked by [`load_stage_function_plugin`] before the
+/// factory is called. The layout is C-compatible, so it can be read safely from a
+/// library built with another compiler or another version of savant_core.
+///
+#[repr(C)]
+#[derive(Debug)]
+pub struct StageFunctionPluginManifest {
+    pub api_version: u32,
+    pub savant_core_version: *const c_char,
+    pub rustc_version: *const c_char,
+    pub name: *const c_char,
+    pub parameters: *const PluginParameterSpec,
+    pub parameters_len: usize,
+}
+
+unsafe impl Sync for PluginParameterSpec {}
+unsafe impl Sync for StageFunctionPluginManifest {}
+
+/// Exports the manifest of the plugin library. Every library providing stage
+/// function factories must invoke the macro once.
+///
+/// ```ignore
+/// declare_stage_function_plugin!("sample", [("threshold", Float, true)]);
+/// ```
+///
+#[macro_export]
+macro_rules! declare_stage_function_plugin {
+    ($name:expr) => {
+        $crate::declare_stage_function_plugin!($name, []);
+    };
+    ($name:expr, [$(($param:expr, $kind:ident, $required:expr)),* $(,)?]) => {
+        #[no_mangle]
+        pub extern "C" fn savant_stage_function_plugin_manifest(
+        ) -> *const $crate::pipeline::stage_function_loader::StageFunctionPluginManifest {
+            use $crate::pipeline::stage_function_loader as loader;
+            static PARAMETERS: &[loader::PluginParameterSpec] = &[$(
+                loader::PluginParameterSpec {
+                    name: concat!($param, "\0").as_ptr() as *const ::std::ffi::c_char,
+                    kind: loader::PluginParameterKind::$kind,
+                    required: $required,
+                }
+            ),*];
+            static MANIFEST: loader::StageFunctionPluginManifest =
+                loader::StageFunctionPluginManifest {
+                    api_version: loader::STAGE_FUNCTION_PLUGIN_API_VERSION,
+                    savant_core_version: loader::SAVANT_CORE_VERSION.as_ptr()
+                        as *const ::std::ffi::c_char,
+                    rustc_version: loader::RUSTC_VERSION.as_ptr() as *const ::std::ffi::c_char,
+                    name: concat!($name, "\0").as_ptr() as *const ::std::ffi::c_char,
+                    parameters: PARAMETERS.as_ptr(),
+                    parameters_len: PARAMETERS.len(),
+                };
+            &MANIFEST
+        }
+    };
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PluginParameter {
+    pub name: String,
+    pub kind: PluginParameterKind,
+    pub required: bool,
+}
+
+/// Owned copy of a verified [`StageFunctionPluginManifest`].
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct PluginInfo {
+    pub name: String,
+    pub savant_core_version: String,
+    pub rustc_version: String,
+    pub parameters: Vec<PluginParameter>,
+}
+
+impl PluginInfo {
+    fn from_manifest(manifest: &StageFunctionPluginManifest) -> anyhow::Result<Self> {
+        if manifest.api_version != STAGE_FUNCTION_PLUGIN_API_VERSION {
+            bail!(
+                "Plugin API version {} is not supported, expected {}",
+                manifest.api_version,
+                STAGE_FUNCTION_PLUGIN_API_VERSION
+            );
+        }
+        let read = |field: &str, ptr: *const c_char| {
+            if ptr.is_null() {
+                bail!("Plugin manifest field '{}' is null", field);
+            }
+            Ok(unsafe { CStr::from_ptr(ptr) }.to_str()?.to_string())
+        };
+        let savant_core_version = read("savant_core_version", manifest.savant_core_version)?;
+        let expected = SAVANT_CORE_VERSION.trim_end_matches('\0');
+        if savant_core_version != expected {
+            bail!(
+                "Plugin is built with savant_core {}, but {} is loaded",
+                savant_core_version,
+                expected
+            );
+        }
+        let rustc_version = read("rustc_version", manifest.rustc_version)?;
+        let expected = RUSTC_VERSION.trim_end_matches('\0');
+        if rustc_version != expected {
+            bail!(
+                "Plugin is built with {}, but savant_core is built with {}",
+                rustc_version,
+                expected
+            );
+        }
+        let name = read("name", manifest.name)?;
+        let specs = if manifest.parameters_len == 0 {
+            &[][..]
+        } else if manifest.parameters.is_null() {
+            bail!("Plugin manifest field 'parameters' is null");
+        } else {
+            unsafe { std::slice::from_raw_parts(manifest.parameters, manifest.parameters_len) }
+        };
+        let parameters = specs
+            .iter()
+            .map(|p| {
+                Ok(PluginParameter {
+                    name: read("parameters.name", p.name)?,
+                    kind: p.kind,
+                    required: p.required,
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(Self {
+            name,
+            savant_core_version,
+            rustc_version,
+            parameters,
+        })
+    }
+
+    pub fn validate_params(&self, params: &PluginParams) -> anyhow::Result<()> {
+        for p in &self.parameters {
+            match params.params.get(&p.name) {
+                Some(v) if !p.kind.accepts(&v.value) => bail!(
+                    "Plugin '{}' parameter '{}' must be of kind {:?}",
+                    self.name,
+                    p.name,
+                    p.kind
+                ),
+                None if p.required => {
+                    bail!("Plugin '{}' requires parameter '{}'", self.name, p.name)
+                }
+                _ => {}
+            }
+        }
+        if let Some(unknown) = params
+            .params
+            .keys()
+            .find(|k| !self.parameters.iter().any(|p| &p.name == *k))
+        {
+            bail!(
+                "Plugin '{}' does not declare parameter '{}'",
+                self.name,
+                unknown
+            );
+        }
+        Ok(())
+    }
+}
+
+fn with_library<T>(
     libname: &str,
-    init_name: &str,
-    plugin_name: &str,
-    params: PluginParams,
-) -> anyhow::Result<Box<dyn PipelineStageFunction>> {
+    f: impl FnOnce(&libloading::Library) -> anyhow::Result<T>,
+) -> anyhow::Result<T> {
     let mut libs = LIBRARIES.lock();
     if !libs.contains_key(libname) {
         let lib = unsafe { libloading::Library::new(libname)? };
@@ -21,8 +230,129 @@ pub fn load_stage_function_plugin(
     let lib = libs
         .get(libname)
         .expect("Library must be available according to the code logic");
-    let init: libloading::Symbol<super::PipelineStageFunctionFactory> =
-        unsafe { lib.get(init_name.as_bytes())? };
-    let raw = init(plugin_name, params);
-    Ok(unsafe { Box::from_raw(raw) })
+    f(lib)
+}
+
+fn read_manifest(lib: &libloading::Library, libname: &str) -> anyhow::Result<PluginInfo> {
+    let manifest: libloading::Symbol<StageFunctionPluginManifestFn> =
+        unsafe { lib.get(STAGE_FUNCTION_PLUGIN_MANIFEST_SYMBOL.as_bytes()) }.map_err(|e| {
+            anyhow!(
+                "Library {} does not export the plugin manifest '{}', \
+                 it must be declared with declare_stage_function_plugin!: {}",
+                libname,
+                STAGE_FUNCTION_PLUGIN_MANIFEST_SYMBOL,
+                e
+            )
+        })?;
+    let manifest = unsafe { manifest() };
+    if manifest.is_null() {
+        bail!("Library {} returned a null plugin manifest", libname);
+    }
+    PluginInfo::from_manifest(unsafe { &*manifest })
+        .map_err(|e| anyhow!("Library {} is incompatible: {}", libname, e))
+}
+
+/// Reads and verifies the manifest of the plugin library without creating a stage function.
+///
+pub fn load_stage_function_plugin_info(libname: &str) -> anyhow::Result<PluginInfo> {
+    with_library(libname, |lib| read_manifest(lib, libname))
+}
+
+pub fn load_stage_function_plugin(
+    libname: &str,
+    init_name: &str,
+    plugin_name: &str,
+    params: PluginParams,
+) -> anyhow::Result<Box<dyn PipelineStageFunction>> {
+    with_library(libname, |lib| {
+        let info = read_manifest(lib, libname)?;
+        info.validate_params(&params)?;
+        let init: libloading::Symbol<super::PipelineStageFunctionFactory> =
+            unsafe { lib.get(init_name.as_bytes())? };
+        let raw = init(plugin_name, params);
+        Ok(unsafe { Box::from_raw(raw) })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        PluginInfo, PluginParameterKind, PluginParameterSpec, StageFunctionPluginManifest,
+        RUSTC_VERSION, SAVANT_CORE_VERSION, STAGE_FUNCTION_PLUGIN_API_VERSION,
+    };
+    use crate::pipeline::PluginParams;
+    use crate::primitives::attribute_value::AttributeValue;
+    use std::ffi::c_char;
+
+    fn manifest(
+        api_version: u32,
+        savant_core_version: &'static str,
+        parameters: &'static [PluginParameterSpec],
+    ) -> StageFunctionPluginManifest {
+        StageFunctionPluginManifest {
+            api_version,
+            savant_core_version: savant_core_version.as_ptr() as *const c_char,
+            rustc_version: RUSTC_VERSION.as_ptr() as *const c_char,
+            name: c"test".as_ptr(),
+            parameters: parameters.as_ptr(),
+            parameters_len: parameters.len(),
+        }
+    }
+
+    #[test]
+    fn test_version_mismatch() {
+        let e = PluginInfo::from_manifest(&manifest(
+            STAGE_FUNCTION_PLUGIN_API_VERSION + 1,
+            SAVANT_CORE_VERSION,
+            &[],
+        ))
+        .unwrap_err();
+        assert!(e.to_string().starts_with("Plugin API version"));
+
+        let e =
+            PluginInfo::from_manifest(&manifest(STAGE_FUNCTION_PLUGIN_API_VERSION, "0.0.0\0", &[]))
+                .unwrap_err();
+        assert!(e
+            .to_string()
+            .starts_with("Plugin is built with savant_core 0.0.0"));
+    }
+
+    #[test]
+    fn test_params_validation() -> anyhow::Result<()> {
+        static PARAMETERS: &[PluginParameterSpec] = &[
+            PluginParameterSpec {
+                name: c"threshold".as_ptr(),
+                kind: PluginParameterKind::Float,
+                required: true,
+            },
+            PluginParameterSpec {
+                name: c"label".as_ptr(),
+                kind: PluginParameterKind::Any,
+                required: false,
+            },
+        ];
+        let info = PluginInfo::from_manifest(&manifest(
+            STAGE_FUNCTION_PLUGIN_API_VERSION,
+            SAVANT_CORE_VERSION,
+            PARAMETERS,
+        ))?;
+        assert_eq!(info.name, "test");
+        assert_eq!(info.parameters.len(), 2);
+
+        let mut params = PluginParams::default();
+        assert!(info.validate_params(&params).is_err());
+        params
+            .params
+            .insert("threshold".to_string(), AttributeValue::integer(1, None));
+        assert!(info.validate_params(&params).is_err());
+        params
+            .params
+            .insert("threshold".to_string(), AttributeValue::float(0.5, None));
+        info.validate_params(&params)?;
+        params
+            .params
+            .insert("other".to_string(), AttributeValue::float(0.5, None));
+        assert!(info.validate_params(&params).is_err());
+        Ok(())
+    }
 }
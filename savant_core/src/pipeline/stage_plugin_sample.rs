@@ -3,6 +3,9 @@ use crate::pipeline::{
     Pipeline, PipelinePayload, PipelineStageFunction, PipelineStageFunctionOrder, PluginParams,
 };
 
+#[cfg(feature = "sample-plugin")]
+crate::declare_stage_function_plugin!("savant_core_test", [("label", Any, false)]);
+
 #[no_mangle]
 pub fn init_plugin_test(_: &str, params: PluginParams) -> *mut dyn PipelineStageFunction {
     let plugin = Plugin {
@@ -24,6 +27,9 @@ impl PipelineStageFunction for Plugin {
     fn get_pipeline(&self) -> &Option<Pipeline> {
         &self.pipeline
     }
+    fn get_params(&self) -> Option<&PluginParams> {
+        Some(&self.params)
+    }
     fn call(
         &self,
         id: i64,
@@ -51,6 +51,8 @@ pub struct StageProcessingStat {
     pub frame_counter: usize,
     pub object_counter: usize,
     pub batch_counter: usize,
+    pub dropped_counter: usize,
+    pub rejected_counter: usize,
 }
 
 #[derive(Debug, Clone, Default)]
@@ -122,12 +124,14 @@ impl StageProcessingStat {
 
     pub fn log_stats(&self) {
         info!(
-            "📊 {:<32} > queue {:>8}, frames {:>8}, objects {:>8}, batches {:>8}",
+            "📊 {:<32} > queue {:>8}, frames {:>8}, objects {:>8}, batches {:>8}, dropped {:>8}, rejected {:>8}",
             self.stage_name,
             self.queue_length,
             self.frame_counter,
             self.object_counter,
             self.batch_counter,
+            self.dropped_counter,
+            self.rejected_counter,
         );
     }
 }
@@ -1,4 +1,5 @@
 use crate::primitives::{BBoxMetricType, RBBox};
+use anyhow::bail;
 use geo::{Area, BooleanOps, MultiPolygon};
 use rayon::iter::ParallelIterator;
 use rayon::prelude::IntoParallelRefIterator;
@@ -75,12 +76,7 @@ pub fn associate_bboxes(
     for (ci, c) in candidates.iter().enumerate() {
         associations.insert(ci, Vec::new());
         for (co, o) in owners.iter().enumerate() {
-            let mv = match metric {
-                BBoxMetricType::IoU => c.iou(o),
-                BBoxMetricType::IoSelf => c.ios(o),
-                BBoxMetricType::IoOther => c.ioo(o),
-            };
-            if let Ok(mv) = mv {
+            if let Ok(mv) = calculate_metric(c, o, &metric) {
                 if mv > threshold {
                     associations.entry(ci).and_modify(|v| v.push((co, mv)));
                 }
@@ -94,6 +90,207 @@ pub fn associate_bboxes(
     associations
 }
 
+fn calculate_metric(a: &RBBox, b: &RBBox, metric: &BBoxMetricType) -> anyhow::Result<f32> {
+    match metric {
+        BBoxMetricType::IoU => a.iou(b),
+        BBoxMetricType::IoSelf => a.ios(b),
+        BBoxMetricType::IoOther => a.ioo(b),
+    }
+}
+
+fn check_lengths(bboxes: &[&RBBox], scores: &[f32], classes: Option<&[i64]>) -> anyhow::Result<()> {
+    if scores.len() != bboxes.len() {
+        bail!(
+            "The number of scores ({}) must match the number of boxes ({})",
+            scores.len(),
+            bboxes.len()
+        );
+    }
+    if let Some(classes) = classes {
+        if classes.len() != bboxes.len() {
+            bail!(
+                "The number of classes ({}) must match the number of boxes ({})",
+                classes.len(),
+                bboxes.len()
+            );
+        }
+    }
+    Ok(())
+}
+
+fn same_class(classes: Option<&[i64]>, a: usize, b: usize) -> bool {
+    classes.map(|c| c[a] == c[b]).unwrap_or(true)
+}
+
+fn order_by_score(scores: &[f32]) -> Vec<usize> {
+    let mut order = (0..scores.len()).collect::<Vec<_>>();
+    order.sort_by(|&a, &b| scores[b].total_cmp(&scores[a]));
+    order
+}
+
+/// Non-maximum suppression. When `classes` are set, only boxes of the same class
+/// suppress each other.
+///
+/// Returns the indices of the kept boxes ordered by descending score.
+///
+pub fn nms(
+    bboxes: &[&RBBox],
+    scores: &[f32],
+    classes: Option<&[i64]>,
+    metric: BBoxMetricType,
+    threshold: f32,
+) -> anyhow::Result<Vec<usize>> {
+    check_lengths(bboxes, scores, classes)?;
+    let mut kept: Vec<usize> = Vec::new();
+    for i in order_by_score(scores) {
+        let suppressed = kept.iter().any(|&k| {
+            same_class(classes, i, k)
+                && matches!(calculate_metric(bboxes[i], bboxes[k], &metric), Ok(m) if m > threshold)
+        });
+        if !suppressed {
+            kept.push(i);
+        }
+    }
+    Ok(kept)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SoftNmsDecay {
+    /// The score is multiplied by `1 - metric` when the metric exceeds the threshold.
+    Linear,
+    /// The score is multiplied by `exp(-metric^2 / sigma)`, the sigma must be positive.
+    Gaussian(f32),
+}
+
+/// Soft non-maximum suppression, overlapping boxes decay the scores of each other
+/// instead of being removed. Boxes with the decayed score below `score_threshold`
+/// are dropped.
+///
+/// Returns the indices of the kept boxes with the decayed scores ordered by
+/// descending score.
+///
+#[allow(clippy::too_many_arguments)]
+pub fn soft_nms(
+    bboxes: &[&RBBox],
+    scores: &[f32],
+    classes: Option<&[i64]>,
+    metric: BBoxMetricType,
+    decay: SoftNmsDecay,
+    threshold: f32,
+    score_threshold: f32,
+) -> anyhow::Result<Vec<(usize, f32)>> {
+    check_lengths(bboxes, scores, classes)?;
+    if let SoftNmsDecay::Gaussian(sigma) = decay {
+        if sigma.is_nan() || sigma <= 0.0 {
+            bail!("Gaussian decay sigma must be positive, got {}", sigma);
+        }
+    }
+    let mut pending = (0..bboxes.len())
+        .map(|i| (i, scores[i]))
+        .collect::<Vec<_>>();
+    let mut kept = Vec::new();
+    while !pending.is_empty() {
+        let best = pending
+            .iter()
+            .enumerate()
+            .max_by(|(_, (_, a)), (_, (_, b))| a.total_cmp(b))
+            .map(|(pos, _)| pos)
+            .expect("Pending boxes are not empty");
+        let (i, score) = pending.swap_remove(best);
+        if score < score_threshold {
+            break;
+        }
+        kept.push((i, score));
+        for (j, score) in pending.iter_mut() {
+            if !same_class(classes, i, *j) {
+                continue;
+            }
+            let m = calculate_metric(bboxes[i], bboxes[*j], &metric).unwrap_or(0.0);
+            *score *= match decay {
+                SoftNmsDecay::Linear if m > threshold => 1.0 - m,
+                SoftNmsDecay::Linear => 1.0,
+                SoftNmsDecay::Gaussian(sigma) => (-(m * m) / sigma).exp(),
+            };
+        }
+    }
+    Ok(kept)
+}
+
+#[derive(Debug, Clone)]
+pub struct FusedBox {
+    pub bbox: RBBox,
+    pub score: f32,
+    pub class: Option<i64>,
+    /// Indices of the fused source boxes.
+    pub members: Vec<usize>,
+}
+
+fn fuse(bboxes: &[&RBBox], scores: &[f32], members: &[usize]) -> RBBox {
+    let total = members.iter().map(|&i| scores[i]).sum::<f32>();
+    // the boxes are averaged with equal weights when their scores sum to zero
+    let uniform = total <= 0.0;
+    let weight = |i: usize| if uniform { 1.0 } else { scores[i] };
+    let total = if uniform { members.len() as f32 } else { total };
+    let weighted = |f: &dyn Fn(&RBBox) -> f32| {
+        members
+            .iter()
+            .map(|&i| f(bboxes[i]) * weight(i))
+            .sum::<f32>()
+            / total
+    };
+    let angle = if members.iter().all(|&i| bboxes[i].get_angle().is_some()) {
+        Some(weighted(&|b| b.get_angle().unwrap()))
+    } else {
+        None
+    };
+    RBBox::new(
+        weighted(&|b| b.get_xc()),
+        weighted(&|b| b.get_yc()),
+        weighted(&|b| b.get_width()),
+        weighted(&|b| b.get_height()),
+        angle,
+    )
+}
+
+/// Weighted box fusion: boxes of the same class overlapping the cluster box above
+/// the threshold are merged into the score-weighted average box, or the plain average
+/// box when the scores of the cluster sum to zero. The fused score is the average
+/// score of the cluster.
+///
+pub fn weighted_boxes_fusion(
+    bboxes: &[&RBBox],
+    scores: &[f32],
+    classes: Option<&[i64]>,
+    metric: BBoxMetricType,
+    threshold: f32,
+) -> anyhow::Result<Vec<FusedBox>> {
+    check_lengths(bboxes, scores, classes)?;
+    let mut clusters: Vec<FusedBox> = Vec::new();
+    for i in order_by_score(scores) {
+        let cluster = clusters.iter_mut().find(|c| {
+            same_class(classes, i, c.members[0])
+                && matches!(calculate_metric(bboxes[i], &c.bbox, &metric), Ok(m) if m > threshold)
+        });
+        match cluster {
+            Some(cluster) => {
+                cluster.members.push(i);
+                cluster.bbox = fuse(bboxes, scores, &cluster.members);
+            }
+            None => clusters.push(FusedBox {
+                bbox: bboxes[i].copy(),
+                score: scores[i],
+                class: classes.map(|c| c[i]),
+                members: vec![i],
+            }),
+        }
+    }
+    for cluster in clusters.iter_mut() {
+        cluster.score =
+            cluster.members.iter().map(|&i| scores[i]).sum::<f32>() / cluster.members.len() as f32;
+    }
+    Ok(clusters)
+}
+
 #[cfg(test)]
 mod tests {
     use crate::primitives::{BBoxMetricType, RBBox};
@@ -177,4 +374,113 @@ mod tests {
         assert!(matches!(lp2_associations.as_slice(), [(1, _), (0, _)]));
         assert!(lp3_associations.is_empty());
     }
+
+    #[test]
+    fn test_nms() -> anyhow::Result<()> {
+        let a = RBBox::ltwh(0.0, 0.0, 10.0, 10.0);
+        let b = RBBox::ltwh(1.0, 1.0, 10.0, 10.0);
+        let c = RBBox::ltwh(50.0, 50.0, 10.0, 10.0);
+        let boxes = [&a, &b, &c];
+        let kept = super::nms(&boxes, &[0.8, 0.9, 0.5], None, BBoxMetricType::IoU, 0.5)?;
+        assert_eq!(kept, vec![1, 2]);
+
+        let kept = super::nms(
+            &boxes,
+            &[0.8, 0.9, 0.5],
+            Some(&[1, 2, 1]),
+            BBoxMetricType::IoU,
+            0.5,
+        )?;
+        assert_eq!(kept, vec![1, 0, 2]);
+
+        assert!(super::nms(&boxes, &[0.8], None, BBoxMetricType::IoU, 0.5).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_nms_rotated() -> anyhow::Result<()> {
+        let a = RBBox::new(0.0, 0.0, 20.0, 4.0, Some(0.0));
+        let b = RBBox::new(0.0, 0.0, 20.0, 4.0, Some(90.0));
+        let c = RBBox::new(0.0, 0.0, 20.0, 4.0, Some(5.0));
+        let kept = super::nms(
+            &[&a, &b, &c],
+            &[0.9, 0.8, 0.7],
+            None,
+            BBoxMetricType::IoU,
+            0.5,
+        )?;
+        assert_eq!(kept, vec![0, 1]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_soft_nms() -> anyhow::Result<()> {
+        let a = RBBox::ltwh(0.0, 0.0, 10.0, 10.0);
+        let b = RBBox::ltwh(1.0, 1.0, 10.0, 10.0);
+        let c = RBBox::ltwh(50.0, 50.0, 10.0, 10.0);
+        let boxes = [&a, &b, &c];
+        let kept = super::soft_nms(
+            &boxes,
+            &[0.8, 0.9, 0.5],
+            None,
+            BBoxMetricType::IoU,
+            super::SoftNmsDecay::Linear,
+            0.3,
+            0.3,
+        )?;
+        assert_eq!(kept.iter().map(|(i, _)| *i).collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(kept[1].1, 0.5);
+
+        let kept = super::soft_nms(
+            &boxes,
+            &[0.8, 0.9, 0.5],
+            None,
+            BBoxMetricType::IoU,
+            super::SoftNmsDecay::Gaussian(0.5),
+            0.3,
+            0.01,
+        )?;
+        assert_eq!(kept.len(), 3);
+        assert!(kept.iter().find(|(i, _)| *i == 0).unwrap().1 < 0.8);
+
+        for sigma in [0.0, -1.0, f32::NAN] {
+            assert!(super::soft_nms(
+                &boxes,
+                &[0.8, 0.9, 0.5],
+                None,
+                BBoxMetricType::IoU,
+                super::SoftNmsDecay::Gaussian(sigma),
+                0.3,
+                0.01,
+            )
+            .is_err());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_weighted_boxes_fusion() -> anyhow::Result<()> {
+        let a = RBBox::ltwh(0.0, 0.0, 10.0, 10.0);
+        let b = RBBox::ltwh(2.0, 0.0, 10.0, 10.0);
+        let c = RBBox::ltwh(50.0, 50.0, 10.0, 10.0);
+        let fused = super::weighted_boxes_fusion(
+            &[&a, &b, &c],
+            &[0.5, 0.5, 0.7],
+            None,
+            BBoxMetricType::IoU,
+            0.5,
+        )?;
+        assert_eq!(fused.len(), 2);
+        assert_eq!(fused[0].members, vec![2]);
+        assert_eq!(fused[1].members.len(), 2);
+        assert_eq!(fused[1].bbox.get_xc(), 6.0);
+        assert_eq!(fused[1].score, 0.5);
+
+        let fused =
+            super::weighted_boxes_fusion(&[&a, &b], &[0.0, 0.0], None, BBoxMetricType::IoU, 0.5)?;
+        assert_eq!(fused.len(), 1);
+        assert_eq!(fused[0].bbox.get_xc(), 6.0);
+        assert_eq!(fused[0].score, 0.0);
+        Ok(())
+    }
 }
@@ -1,7 +1,11 @@
 use crate::draw::DrawLabelKind;
-use crate::json_api::ToSerdeJsonValue;
+use crate::json_api::{
+    from_versioned_json, from_versioned_yaml, to_versioned_json, to_versioned_yaml,
+    ToSerdeJsonValue,
+};
 use crate::match_query::{and, IntExpression, MatchQuery, StringExpression};
 use crate::message::Message;
+use crate::primitives::bbox::utils::nms;
 use crate::primitives::frame_update::VideoFrameUpdate;
 use crate::primitives::object::private::{
     SealedObjectOperations, SealedWithFrame, SealedWithParent,
@@ -10,7 +14,7 @@ use crate::primitives::object::{
     BorrowedVideoObject, IdCollisionResolutionPolicy, ObjectAccess, ObjectOperations, VideoObject,
     VideoObjectBBoxTransformation, VideoObjectBuilder,
 };
-use crate::primitives::{Attribute, RBBox, WithAttributes};
+use crate::primitives::{Attribute, BBoxMetricType, RBBox, WithAttributes};
 use crate::rwlock::{SavantArcRwLock, SavantRwLock};
 use crate::trace;
 use crate::utils::iter::fiter_map_with_control_flow;
@@ -46,7 +50,7 @@ impl<'a> StreamCompatibilityInformation<'a> {
     }
 }
 
-#[derive(Debug, PartialEq, Clone, serde::Serialize)]
+#[derive(Debug, PartialEq, Clone, serde::Serialize, serde::Deserialize)]
 pub struct ExternalFrame {
     pub method: String,
     pub location: Option<String>,
@@ -67,7 +71,8 @@ impl ExternalFrame {
     }
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum VideoFrameContent {
     External(ExternalFrame),
     Internal(Vec<u8>),
@@ -88,7 +93,7 @@ impl ToSerdeJsonValue for VideoFrameContent {
     }
 }
 
-#[derive(Debug, PartialEq, Clone, serde::Serialize)]
+#[derive(Debug, PartialEq, Clone, serde::Serialize, serde::Deserialize)]
 pub enum VideoFrameTranscodingMethod {
     Copy,
     Encoded,
@@ -100,7 +105,7 @@ impl ToSerdeJsonValue for VideoFrameTranscodingMethod {
     }
 }
 
-#[derive(Debug, PartialEq, Clone, serde::Serialize)]
+#[derive(Debug, PartialEq, Clone, serde::Serialize, serde::Deserialize)]
 pub enum VideoFrameTransformation {
     InitialSize(u64, u64),
     Scale(u64, u64),
@@ -114,13 +119,88 @@ impl ToSerdeJsonValue for VideoFrameTransformation {
     }
 }
 
-#[derive(Debug, Clone, Builder)]
+mod uuid_string {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+    use uuid::Uuid;
+
+    pub fn serialize<S: Serializer>(uuid: &u128, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&Uuid::from_u128(*uuid).to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u128, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Uuid::parse_str(&s)
+            .map(|u| u.as_u128())
+            .map_err(D::Error::custom)
+    }
+}
+
+mod optional_uuid_string {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+    use uuid::Uuid;
+
+    pub fn serialize<S: Serializer>(uuid: &Option<u128>, serializer: S) -> Result<S::Ok, S::Error> {
+        match uuid {
+            Some(uuid) => serializer.serialize_some(&Uuid::from_u128(*uuid).to_string()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<u128>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|s| {
+                Uuid::parse_str(&s)
+                    .map(|u| u.as_u128())
+                    .map_err(D::Error::custom)
+            })
+            .transpose()
+    }
+}
+
+mod objects_by_id {
+    use crate::primitives::object::VideoObject;
+    use hashbrown::HashMap;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        objects: &HashMap<i64, VideoObject>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut objects = objects.values().collect::<Vec<_>>();
+        objects.sort_by_key(|o| o.id);
+        serializer.collect_seq(objects)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<HashMap<i64, VideoObject>, D::Error> {
+        let objects = Vec::<VideoObject>::deserialize(deserializer)?;
+        let mut map = HashMap::with_capacity(objects.len());
+        for o in objects {
+            let id = o.id;
+            if map.insert(id, o).is_some() {
+                return Err(D::Error::custom(format!("Duplicate object id {}", id)));
+            }
+        }
+        Ok(map)
+    }
+}
+
+#[derive(Debug, Clone, Builder, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 pub struct VideoFrame {
     #[builder(setter(skip))]
     pub previous_frame_seq_id: Option<i64>,
     #[builder(setter(skip))]
+    #[serde(with = "optional_uuid_string")]
     pub previous_keyframe: Option<u128>,
     pub source_id: String,
+    #[serde(with = "uuid_string")]
     pub uuid: u128,
     #[builder(setter(skip))]
     pub creation_timestamp_ns: u128,
@@ -143,8 +223,10 @@ pub struct VideoFrame {
     #[builder(setter(skip))]
     pub attributes: Vec<Attribute>,
     #[builder(setter(skip))]
+    #[serde(with = "objects_by_id")]
     pub(crate) objects: HashMap<i64, VideoObject>,
     #[builder(setter(skip))]
+    #[serde(skip)]
     pub(crate) max_object_id: i64,
 }
 
@@ -451,6 +533,47 @@ impl VideoFrameProxy {
         serde_json::to_string_pretty(&self.to_serde_json_value()).unwrap()
     }
 
+    fn persistent_copy(&self) -> VideoFrame {
+        let mut frame = trace!(self.inner.read_recursive()).smart_copy();
+        frame.exclude_all_temporary_attributes();
+        frame
+    }
+
+    fn from_document(mut frame: VideoFrame) -> anyhow::Result<Self> {
+        for o in frame.objects.values() {
+            if let Some(parent_id) = o.parent_id {
+                if !frame.objects.contains_key(&parent_id) {
+                    bail!(
+                        "Object {} refers to the parent object {} which is not in the frame",
+                        o.id,
+                        parent_id
+                    );
+                }
+            }
+        }
+        frame.max_object_id = frame.objects.keys().max().copied().unwrap_or_default();
+        Ok(Self::from_inner(frame))
+    }
+
+    /// Serializes the frame with its objects and persistent attributes to versioned JSON
+    /// which can be loaded back with [`VideoFrameProxy::from_json`].
+    ///
+    pub fn to_json(&self, pretty: bool) -> anyhow::Result<String> {
+        to_versioned_json(&self.persistent_copy(), pretty)
+    }
+
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        Self::from_document(from_versioned_json(json)?)
+    }
+
+    pub fn to_yaml(&self) -> anyhow::Result<String> {
+        to_versioned_yaml(&self.persistent_copy())
+    }
+
+    pub fn from_yaml(yaml: &str) -> anyhow::Result<Self> {
+        Self::from_document(from_versioned_yaml(yaml)?)
+    }
+
     pub fn access_objects_with_id(&self, ids: &[i64]) -> Vec<BorrowedVideoObject> {
         let inner = trace!(self.inner.read_recursive());
         let resident_objects = inner.objects.clone();
@@ -504,6 +627,55 @@ impl VideoFrameProxy {
         self.delete_objects_with_ids(&ids)
     }
 
+    /// Applies non-maximum suppression to the objects selected by the query using
+    /// the detection boxes and confidences, the suppressed objects are deleted and
+    /// returned. With `class_aware` only objects with the same namespace and label
+    /// suppress each other.
+    ///
+    pub fn apply_nms(
+        &self,
+        q: &MatchQuery,
+        metric: BBoxMetricType,
+        threshold: f32,
+        class_aware: bool,
+    ) -> anyhow::Result<Vec<VideoObject>> {
+        let objects = self.access_objects(q);
+        let bboxes = objects
+            .iter()
+            .map(|o| o.get_detection_box())
+            .collect::<Vec<_>>();
+        let scores = objects
+            .iter()
+            .map(|o| o.get_confidence().unwrap_or(0.0))
+            .collect::<Vec<_>>();
+        let classes = class_aware.then(|| {
+            let mut known = HashMap::new();
+            objects
+                .iter()
+                .map(|o| {
+                    let next = known.len() as i64;
+                    *known
+                        .entry((o.get_namespace(), o.get_label()))
+                        .or_insert(next)
+                })
+                .collect::<Vec<_>>()
+        });
+        let kept = nms(
+            &bboxes.iter().collect::<Vec<_>>(),
+            &scores,
+            classes.as_deref(),
+            metric,
+            threshold,
+        )?;
+        let suppressed = objects
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !kept.contains(i))
+            .map(|(_, o)| o.get_id())
+            .collect::<Vec<_>>();
+        Ok(self.delete_objects_with_ids(&suppressed))
+    }
+
     pub fn get_object(&self, id: i64) -> Option<BorrowedVideoObject> {
         let inner = trace!(self.inner.read_recursive());
         let obj = inner.objects.get(&id);
@@ -1062,12 +1234,18 @@ impl VideoFrameProxy {
 #[cfg(test)]
 mod tests {
     use crate::draw::DrawLabelKind;
+    use crate::json_api::JSON_SCHEMA_VERSION;
     use crate::match_query::{eq, one_of, MatchQuery};
+    use crate::primitives::attribute_value::AttributeValue;
+    use crate::primitives::frame::{VideoFrameContent, VideoFrameProxy, VideoFrameTransformation};
     use crate::primitives::object::private::{SealedWithFrame, SealedWithParent};
     use crate::primitives::object::{
         IdCollisionResolutionPolicy, ObjectOperations, VideoObjectBuilder,
     };
-    use crate::primitives::{RBBox, WithAttributes};
+    use crate::primitives::{
+        BBoxMetricType, Intersection, IntersectionKind, Point, PolygonalArea, RBBox, RBBoxData,
+        WithAttributes,
+    };
     use crate::test::{gen_empty_frame, gen_frame, gen_object, s};
     use std::sync::Arc;
 
@@ -1105,6 +1283,36 @@ mod tests {
         assert_eq!(objects[0].get_id(), 2);
     }
 
+    #[test]
+    fn test_apply_nms() -> anyhow::Result<()> {
+        let f = gen_empty_frame();
+        for (id, (label, left, confidence)) in
+            [("car", 0.0, 0.9), ("car", 1.0, 0.8), ("bus", 1.0, 0.7)]
+                .into_iter()
+                .enumerate()
+        {
+            f.add_object(
+                VideoObjectBuilder::default()
+                    .id(id as i64)
+                    .namespace(s("detector"))
+                    .label(s(label))
+                    .confidence(Some(confidence))
+                    .detection_box(RBBox::ltwh(left, 0.0, 10.0, 10.0))
+                    .build()?,
+                IdCollisionResolutionPolicy::Error,
+            )?;
+        }
+        let removed = f.apply_nms(&MatchQuery::Idle, BBoxMetricType::IoU, 0.5, true)?;
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].get_id(), 1);
+
+        let removed = f.apply_nms(&MatchQuery::Idle, BBoxMetricType::IoU, 0.5, false)?;
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].get_id(), 2);
+        assert_eq!(f.get_all_objects().len(), 1);
+        Ok(())
+    }
+
     #[test]
     fn test_parent_cleared_when_delete_objects_by_ids() {
         let f = gen_frame();
@@ -1366,4 +1574,129 @@ mod tests {
         let objs = frame.get_all_objects();
         assert_eq!(objs.len(), 1);
     }
+
+    fn values_of_every_kind() -> Vec<AttributeValue> {
+        let area = PolygonalArea::new(
+            vec![
+                Point::new(0.0, 0.0),
+                Point::new(10.0, 0.0),
+                Point::new(10.0, 10.0),
+            ],
+            Some(vec![Some(s("a")), None, Some(s("c"))]),
+        );
+        vec![
+            AttributeValue::bytes(&[2, 2], &[1, 2, 3, 4], Some(0.5)),
+            AttributeValue::string("s", None),
+            AttributeValue::string_vector(vec![s("a"), s("b")], None),
+            AttributeValue::integer(-1, None),
+            AttributeValue::integer_vector(vec![1, 2], None),
+            AttributeValue::float(1.5, None),
+            AttributeValue::float_vector(vec![0.25, 0.5], None),
+            AttributeValue::boolean(true, None),
+            AttributeValue::boolean_vector(vec![true, false], None),
+            AttributeValue::bbox(RBBoxData::new(1.0, 2.0, 3.0, 4.0, Some(30.0)), None),
+            AttributeValue::bbox_vector(vec![RBBoxData::new(1.0, 2.0, 3.0, 4.0, None)], None),
+            AttributeValue::point(Point::new(1.0, 2.0), None),
+            AttributeValue::point_vector(vec![Point::new(1.0, 2.0)], None),
+            AttributeValue::polygon(area.clone(), None),
+            AttributeValue::polygon_vector(vec![area], None),
+            AttributeValue::intersection(
+                Intersection::new(IntersectionKind::Cross, vec![(0, Some(s("a")))]),
+                None,
+            ),
+            AttributeValue::none(),
+        ]
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let mut frame = gen_frame();
+        frame.set_previous_keyframe(Some(frame.get_uuid_u128()));
+        frame.set_content(VideoFrameContent::Internal(vec![0, 1, 2, 255]));
+        frame.add_transformation(VideoFrameTransformation::InitialSize(1920, 1080));
+        frame.add_transformation(VideoFrameTransformation::Padding(0, 10, 0, 10));
+        frame.set_persistent_attribute("json", "values", &None, true, values_of_every_kind());
+        frame.set_temporary_attribute("json", "temporary", &None, false, vec![]);
+        let mut o = frame.get_object(1).unwrap();
+        o.set_persistent_attribute(
+            "json",
+            "values",
+            &Some("hint"),
+            false,
+            values_of_every_kind(),
+        );
+
+        let json = frame.to_json(false).unwrap();
+        let restored = VideoFrameProxy::from_json(&json).unwrap();
+        assert_eq!(restored.to_json(false).unwrap(), json);
+
+        assert_eq!(restored.get_uuid_u128(), frame.get_uuid_u128());
+        assert_eq!(
+            restored.get_previous_keyframe(),
+            Some(frame.get_uuid_u128())
+        );
+        assert_eq!(
+            restored.get_creation_timestamp_ns(),
+            frame.get_creation_timestamp_ns()
+        );
+        assert_eq!(*restored.get_content(), *frame.get_content());
+        assert_eq!(restored.get_transformations(), frame.get_transformations());
+        assert_eq!(
+            restored
+                .get_attribute("json", "values")
+                .unwrap()
+                .to_json()
+                .unwrap(),
+            frame
+                .get_attribute("json", "values")
+                .unwrap()
+                .to_json()
+                .unwrap()
+        );
+        assert!(restored.get_attribute("json", "temporary").is_none());
+        assert_eq!(restored.get_max_object_id(), frame.get_max_object_id());
+
+        let o = restored.get_object(1).unwrap();
+        assert_eq!(o.get_parent().unwrap().get_id(), 0);
+        assert_eq!(
+            o.get_attribute("json", "values")
+                .unwrap()
+                .to_json()
+                .unwrap(),
+            frame
+                .get_object(1)
+                .unwrap()
+                .get_attribute("json", "values")
+                .unwrap()
+                .to_json()
+                .unwrap()
+        );
+
+        let yaml = frame.to_yaml().unwrap();
+        let restored = VideoFrameProxy::from_yaml(&yaml).unwrap();
+        assert_eq!(restored.to_json(false).unwrap(), json);
+    }
+
+    #[test]
+    fn test_json_schema_version() {
+        let frame = gen_frame();
+        let mut value: serde_json::Value =
+            serde_json::from_str(&frame.to_json(false).unwrap()).unwrap();
+        assert_eq!(value["schema_version"], JSON_SCHEMA_VERSION);
+
+        value["schema_version"] = serde_json::json!(JSON_SCHEMA_VERSION + 1);
+        assert!(VideoFrameProxy::from_json(&value.to_string()).is_err());
+
+        value.as_object_mut().unwrap().remove("schema_version");
+        assert!(VideoFrameProxy::from_json(&value.to_string()).is_err());
+    }
+
+    #[test]
+    fn test_json_missing_parent() {
+        let frame = gen_frame();
+        let mut value: serde_json::Value =
+            serde_json::from_str(&frame.to_json(false).unwrap()).unwrap();
+        value["objects"].as_array_mut().unwrap().remove(0);
+        assert!(VideoFrameProxy::from_json(&value.to_string()).is_err());
+    }
 }
@@ -1,3 +1,6 @@
+use crate::json_api::{
+    from_versioned_json, from_versioned_yaml, to_versioned_json, to_versioned_yaml,
+};
 use crate::primitives::object::VideoObject;
 use crate::primitives::Attribute;
 
@@ -24,11 +27,9 @@ pub enum AttributeUpdatePolicy {
 pub struct VideoFrameUpdate {
     pub(crate) frame_attributes: Vec<Attribute>,
     pub(crate) object_attributes: Vec<(i64, Attribute)>,
-    #[serde(skip)]
     pub(crate) objects: Vec<(VideoObject, Option<i64>)>,
     pub(crate) frame_attribute_policy: AttributeUpdatePolicy,
     pub(crate) object_attribute_policy: AttributeUpdatePolicy,
-    #[serde(skip)]
     pub(crate) object_policy: ObjectUpdatePolicy,
 }
 
@@ -95,16 +96,20 @@ impl VideoFrameUpdate {
     }
 
     pub fn to_json(&self, pretty: bool) -> anyhow::Result<String> {
-        Ok(if pretty {
-            serde_json::to_string_pretty(self)?
-        } else {
-            serde_json::to_string(self)?
-        })
+        to_versioned_json(self, pretty)
     }
 
-    //pub fn from_json(json: &str) -> anyhow::Result<Self> {
-    //    Ok(serde_json::from_str(json)?)
-    //}
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        from_versioned_json(json)
+    }
+
+    pub fn to_yaml(&self) -> anyhow::Result<String> {
+        to_versioned_yaml(self)
+    }
+
+    pub fn from_yaml(yaml: &str) -> anyhow::Result<Self> {
+        from_versioned_yaml(yaml)
+    }
 }
 
 #[cfg(test)]
@@ -336,4 +341,35 @@ mod tests {
         let o = f.access_objects(&MatchQuery::ParentId(IntExpression::EQ(1)));
         assert_eq!(o[0].get_parent().unwrap().get_id(), 1);
     }
+
+    #[test]
+    fn update_json_round_trip() {
+        let mut upd = VideoFrameUpdate::default();
+        let (a1, _) = get_attributes();
+        upd.add_frame_attribute(a1);
+        for (id, attr) in get_object_attributes() {
+            upd.add_object_attribute(id, attr);
+        }
+        upd.add_object(gen_object(1), Some(0));
+        upd.add_object(gen_object(2), None);
+        upd.set_object_policy(ObjectUpdatePolicy::ReplaceSameLabelObjects);
+        upd.set_frame_attribute_policy(AttributeUpdatePolicy::KeepOwn);
+
+        let json = upd.to_json(true).unwrap();
+        let restored = VideoFrameUpdate::from_json(&json).unwrap();
+        assert_eq!(restored.to_json(true).unwrap(), json);
+        assert_eq!(
+            restored.get_object_policy(),
+            ObjectUpdatePolicy::ReplaceSameLabelObjects
+        );
+        assert_eq!(restored.get_objects().len(), 2);
+        assert_eq!(restored.get_objects()[0].1, Some(0));
+        assert_eq!(
+            restored.get_object_attributes(),
+            upd.get_object_attributes()
+        );
+
+        let restored = VideoFrameUpdate::from_yaml(&upd.to_yaml().unwrap()).unwrap();
+        assert_eq!(restored.to_json(true).unwrap(), json);
+    }
 }
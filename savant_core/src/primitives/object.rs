@@ -2,7 +2,10 @@ use anyhow::bail;
 use serde_json::Value;
 use std::fmt::Debug;
 
-use crate::json_api::ToSerdeJsonValue;
+use crate::json_api::{
+    from_versioned_json, from_versioned_yaml, to_versioned_json, to_versioned_yaml,
+    ToSerdeJsonValue,
+};
 use crate::primitives::frame::{BelongingVideoFrame, VideoFrameProxy};
 use crate::primitives::object::private::{
     SealedObjectOperations, SealedWithFrame, SealedWithParent,
@@ -108,6 +111,22 @@ impl VideoObject {
         self.with_object_mut(|o| o.id = id);
         Ok(())
     }
+
+    pub fn to_json(&self, pretty: bool) -> anyhow::Result<String> {
+        to_versioned_json(self, pretty)
+    }
+
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        from_versioned_json(json)
+    }
+
+    pub fn to_yaml(&self) -> anyhow::Result<String> {
+        to_versioned_yaml(self)
+    }
+
+    pub fn from_yaml(yaml: &str) -> anyhow::Result<Self> {
+        from_versioned_yaml(yaml)
+    }
 }
 
 impl ToSerdeJsonValue for VideoObject {
@@ -1,5 +1,6 @@
 use crate::message::{Message, MessageEnvelope, MessageMeta};
 use crate::otlp::PropagatedContext;
+use crate::transport::zeromq::COMPRESSION_MARKER;
 use savant_protobuf::generated;
 
 mod serialize;
@@ -59,6 +60,9 @@ pub fn serialize(m: &Message) -> Result<Vec<u8>, Error> {
 
 pub fn deserialize(bytes: &[u8]) -> Result<Message, Error> {
     use prost::Message as ProstMessage;
+    if let [COMPRESSION_MARKER, codec, ..] = bytes {
+        return Err(Error::CompressedMessage(*codec));
+    }
     let message = generated::Message::decode(bytes)?;
     let m = Message::try_from(&message)?;
     Ok(m)
@@ -67,7 +71,15 @@ pub fn deserialize(bytes: &[u8]) -> Result<Message, Error> {
 #[cfg(test)]
 mod tests {
     use crate::primitives::eos::EndOfStream;
-    use crate::protobuf::{deserialize, serialize};
+    use crate::protobuf::{deserialize, serialize, Error};
+
+    #[test]
+    fn test_compressed_message() {
+        assert!(matches!(
+            deserialize(&[0xFF, 1, 1, 0x28, 0xb5]),
+            Err(Error::CompressedMessage(1))
+        ));
+    }
 
     #[test]
     fn test_eos_message() {
@@ -35,6 +35,8 @@ pub enum Error {
     InvalidVideoFrameParentObject(i64),
     #[error("Failed to convert protobuf enum balue to Rust enum value: {0}")]
     EnumConversionError(i32),
+    #[error("The message is compressed with codec {0}, it must be received by a reader with compression support")]
+    CompressedMessage(u8),
 }
 
 impl From<uuid::Error> for Error {
@@ -0,0 +1,3 @@
+pub mod hungarian;
+pub mod kalman;
+pub mod sort;
@@ -0,0 +1,104 @@
+/// Solves the rectangular assignment problem minimizing the total cost.
+///
+/// Returns the assigned column for every row of `costs`, when there are more rows
+/// than columns some rows stay unassigned.
+///
+pub fn assign(costs: &[Vec<f64>]) -> Vec<Option<usize>> {
+    let rows = costs.len();
+    let cols = costs.first().map(|r| r.len()).unwrap_or(0);
+    if rows == 0 || cols == 0 {
+        return vec![None; rows];
+    }
+    if rows > cols {
+        let transposed = (0..cols)
+            .map(|c| (0..rows).map(|r| costs[r][c]).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let mut assignment = vec![None; rows];
+        for (c, r) in assign(&transposed).into_iter().enumerate() {
+            if let Some(r) = r {
+                assignment[r] = Some(c);
+            }
+        }
+        return assignment;
+    }
+
+    // potentials method with 1-based indices, rows <= cols
+    let mut u = vec![0.0; rows + 1];
+    let mut v = vec![0.0; cols + 1];
+    let mut col_row = vec![0usize; cols + 1];
+    let mut way = vec![0usize; cols + 1];
+    for row in 1..=rows {
+        col_row[0] = row;
+        let mut col0 = 0;
+        let mut min_v = vec![f64::INFINITY; cols + 1];
+        let mut used = vec![false; cols + 1];
+        loop {
+            used[col0] = true;
+            let row0 = col_row[col0];
+            let mut delta = f64::INFINITY;
+            let mut col1 = 0;
+            for col in 1..=cols {
+                if !used[col] {
+                    let cur = costs[row0 - 1][col - 1] - u[row0] - v[col];
+                    if cur < min_v[col] {
+                        min_v[col] = cur;
+                        way[col] = col0;
+                    }
+                    if min_v[col] < delta {
+                        delta = min_v[col];
+                        col1 = col;
+                    }
+                }
+            }
+            for col in 0..=cols {
+                if used[col] {
+                    u[col_row[col]] += delta;
+                    v[col] -= delta;
+                } else {
+                    min_v[col] -= delta;
+                }
+            }
+            col0 = col1;
+            if col_row[col0] == 0 {
+                break;
+            }
+        }
+        loop {
+            let col1 = way[col0];
+            col_row[col0] = col_row[col1];
+            col0 = col1;
+            if col0 == 0 {
+                break;
+            }
+        }
+    }
+
+    let mut assignment = vec![None; rows];
+    for col in 1..=cols {
+        if col_row[col] != 0 {
+            assignment[col_row[col] - 1] = Some(col - 1);
+        }
+    }
+    assignment
+}
+
+#[cfg(test)]
+mod tests {
+    use super::assign;
+
+    #[test]
+    fn test_assign() {
+        let costs = vec![
+            vec![4.0, 1.0, 3.0],
+            vec![2.0, 0.0, 5.0],
+            vec![3.0, 2.0, 2.0],
+        ];
+        assert_eq!(assign(&costs), vec![Some(1), Some(0), Some(2)]);
+
+        let costs = vec![vec![1.0, 0.0], vec![0.0, 1.0], vec![0.5, 0.5]];
+        assert_eq!(assign(&costs), vec![Some(1), Some(0), None]);
+
+        assert_eq!(assign(&[]), Vec::<Option<usize>>::new());
+        assert_eq!(assign(&[vec![]]), vec![None]);
+    }
+}
@@ -0,0 +1,175 @@
+use crate::primitives::RBBox;
+
+const DIM_X: usize = 7;
+const DIM_Z: usize = 4;
+
+type StateMatrix = [[f64; DIM_X]; DIM_X];
+
+/// Constant velocity Kalman filter over the box state `[xc, yc, area, ratio, vxc, vyc, varea]`,
+/// the aspect ratio is considered constant.
+///
+#[derive(Debug, Clone)]
+pub struct KalmanBoxFilter {
+    x: [f64; DIM_X],
+    p: StateMatrix,
+}
+
+fn measurement(bbox: &RBBox) -> [f64; DIM_Z] {
+    let width = bbox.get_width() as f64;
+    let height = bbox.get_height() as f64;
+    [
+        bbox.get_xc() as f64,
+        bbox.get_yc() as f64,
+        width * height,
+        width / height,
+    ]
+}
+
+fn diagonal(values: [f64; DIM_X]) -> StateMatrix {
+    let mut m = [[0.0; DIM_X]; DIM_X];
+    for (i, v) in values.into_iter().enumerate() {
+        m[i][i] = v;
+    }
+    m
+}
+
+fn invert_4x4(m: [[f64; DIM_Z]; DIM_Z]) -> Option<[[f64; DIM_Z]; DIM_Z]> {
+    let mut a = m;
+    let mut inv = [[0.0; DIM_Z]; DIM_Z];
+    for (i, row) in inv.iter_mut().enumerate() {
+        row[i] = 1.0;
+    }
+    for col in 0..DIM_Z {
+        let pivot = (col..DIM_Z).max_by(|&l, &r| a[l][col].abs().total_cmp(&a[r][col].abs()))?;
+        if a[pivot][col].abs() < f64::EPSILON {
+            return None;
+        }
+        a.swap(col, pivot);
+        inv.swap(col, pivot);
+        let d = a[col][col];
+        for j in 0..DIM_Z {
+            a[col][j] /= d;
+            inv[col][j] /= d;
+        }
+        for row in 0..DIM_Z {
+            if row != col {
+                let f = a[row][col];
+                for j in 0..DIM_Z {
+                    a[row][j] -= f * a[col][j];
+                    inv[row][j] -= f * inv[col][j];
+                }
+            }
+        }
+    }
+    Some(inv)
+}
+
+#[allow(clippy::needless_range_loop)]
+impl KalmanBoxFilter {
+    const MEASUREMENT_NOISE: [f64; DIM_Z] = [1.0, 1.0, 10.0, 10.0];
+    const PROCESS_NOISE: [f64; DIM_X] = [1.0, 1.0, 1.0, 1.0, 0.01, 0.01, 0.0001];
+    const INITIAL_COVARIANCE: [f64; DIM_X] = [10.0, 10.0, 10.0, 10.0, 10000.0, 10000.0, 10000.0];
+
+    pub fn new(bbox: &RBBox) -> Self {
+        let z = measurement(bbox);
+        Self {
+            x: [z[0], z[1], z[2], z[3], 0.0, 0.0, 0.0],
+            p: diagonal(Self::INITIAL_COVARIANCE),
+        }
+    }
+
+    pub fn predict(&mut self) {
+        if self.x[2] + self.x[6] <= 0.0 {
+            self.x[6] = 0.0;
+        }
+        for i in 0..3 {
+            self.x[i] += self.x[i + 4];
+        }
+        // P = F * P * F^T + Q, F adds the velocity row/column to the position ones
+        let mut fp = self.p;
+        for i in 0..3 {
+            for j in 0..DIM_X {
+                fp[i][j] += self.p[i + 4][j];
+            }
+        }
+        let mut p = fp;
+        for row in p.iter_mut() {
+            for j in 0..3 {
+                row[j] += row[j + 4];
+            }
+        }
+        for (i, q) in Self::PROCESS_NOISE.iter().enumerate() {
+            p[i][i] += q;
+        }
+        self.p = p;
+    }
+
+    pub fn update(&mut self, bbox: &RBBox) {
+        let z = measurement(bbox);
+        // H selects the first four state components
+        let mut s = [[0.0; DIM_Z]; DIM_Z];
+        for i in 0..DIM_Z {
+            for j in 0..DIM_Z {
+                s[i][j] = self.p[i][j];
+            }
+            s[i][i] += Self::MEASUREMENT_NOISE[i];
+        }
+        let Some(s_inv) = invert_4x4(s) else {
+            return;
+        };
+        let mut k = [[0.0; DIM_Z]; DIM_X];
+        for i in 0..DIM_X {
+            for j in 0..DIM_Z {
+                k[i][j] = (0..DIM_Z).map(|l| self.p[i][l] * s_inv[l][j]).sum();
+            }
+        }
+        let y: Vec<f64> = (0..DIM_Z).map(|i| z[i] - self.x[i]).collect();
+        for i in 0..DIM_X {
+            self.x[i] += (0..DIM_Z).map(|j| k[i][j] * y[j]).sum::<f64>();
+        }
+        let mut p = self.p;
+        for i in 0..DIM_X {
+            for j in 0..DIM_X {
+                p[i][j] -= (0..DIM_Z).map(|l| k[i][l] * self.p[l][j]).sum::<f64>();
+            }
+        }
+        self.p = p;
+    }
+
+    pub fn get_bbox(&self, angle: Option<f32>) -> Option<RBBox> {
+        let [xc, yc, area, ratio, ..] = self.x;
+        if !(area > 0.0 && ratio > 0.0) {
+            return None;
+        }
+        let width = (area * ratio).sqrt();
+        let height = area / width;
+        Some(RBBox::new(
+            xc as f32,
+            yc as f32,
+            width as f32,
+            height as f32,
+            angle,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::KalmanBoxFilter;
+    use crate::primitives::RBBox;
+
+    #[test]
+    fn test_constant_velocity() {
+        let mut filter = KalmanBoxFilter::new(&RBBox::new(10.0, 10.0, 4.0, 8.0, None));
+        for step in 1..=20 {
+            filter.predict();
+            filter.update(&RBBox::new(10.0 + 2.0 * step as f32, 10.0, 4.0, 8.0, None));
+        }
+        filter.predict();
+        let bbox = filter.get_bbox(None).unwrap();
+        assert!((bbox.get_xc() - 52.0).abs() < 0.5);
+        assert!((bbox.get_yc() - 10.0).abs() < 0.5);
+        assert!((bbox.get_width() - 4.0).abs() < 0.1);
+        assert!((bbox.get_height() - 8.0).abs() < 0.1);
+    }
+}
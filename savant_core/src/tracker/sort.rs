@@ -0,0 +1,355 @@
+use std::sync::Arc;
+
+use hashbrown::HashMap;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::match_query::MatchQuery;
+use crate::pipeline::stage::PipelineStage;
+use crate::pipeline::{
+    Pipeline, PipelinePayload, PipelineStageFunction, PipelineStageFunctionOrder,
+};
+use crate::primitives::frame::VideoFrameProxy;
+use crate::primitives::object::ObjectOperations;
+use crate::tracker::hungarian::assign;
+use crate::tracker::kalman::KalmanBoxFilter;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SortTrackerConfig {
+    /// The number of frames the track is kept without matching detections.
+    pub max_age: usize,
+    /// The number of consecutive matches before the track id is assigned to objects.
+    pub min_hits: usize,
+    /// The minimal IoU between the detection and the predicted track box.
+    pub iou_threshold: f32,
+}
+
+impl Default for SortTrackerConfig {
+    fn default() -> Self {
+        Self {
+            max_age: 1,
+            min_hits: 3,
+            iou_threshold: 0.3,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Track {
+    id: i64,
+    filter: KalmanBoxFilter,
+    angle: Option<f32>,
+    time_since_update: usize,
+    hit_streak: usize,
+}
+
+#[derive(Debug, Default)]
+struct SourceTracks {
+    tracks: Vec<Track>,
+    frame_count: usize,
+}
+
+/// SORT tracker: Kalman motion model with Hungarian assignment on IoU. Tracks
+/// are kept per source and assigned to the objects selected by the query.
+///
+#[derive(Debug)]
+pub struct SortTracker {
+    config: SortTrackerConfig,
+    query: MatchQuery,
+    sources: HashMap<String, SourceTracks>,
+    next_track_id: i64,
+}
+
+impl SortTracker {
+    pub fn new(config: SortTrackerConfig, query: MatchQuery) -> Self {
+        Self {
+            config,
+            query,
+            sources: HashMap::new(),
+            next_track_id: 1,
+        }
+    }
+
+    pub fn get_config(&self) -> &SortTrackerConfig {
+        &self.config
+    }
+
+    pub fn get_track_count(&self, source_id: &str) -> usize {
+        self.sources
+            .get(source_id)
+            .map(|s| s.tracks.len())
+            .unwrap_or(0)
+    }
+
+    pub fn clear_source(&mut self, source_id: &str) {
+        self.sources.remove(source_id);
+    }
+
+    /// Updates the tracks of the frame source and sets `track_id` and `track_box` of
+    /// the selected objects, the track info of objects without a confirmed track is
+    /// cleared.
+    ///
+    /// Returns pairs of the object id and the assigned track id sorted by the object id.
+    ///
+    pub fn track(&mut self, frame: &VideoFrameProxy) -> Vec<(i64, i64)> {
+        let mut objects = frame.access_objects(&self.query);
+        let detections = objects
+            .iter()
+            .map(|o| o.get_detection_box())
+            .collect::<Vec<_>>();
+        let config = &self.config;
+        let source = self.sources.entry(frame.get_source_id()).or_default();
+        source.frame_count += 1;
+
+        let mut predictions = Vec::with_capacity(source.tracks.len());
+        source.tracks.retain_mut(|t| {
+            t.filter.predict();
+            match t.filter.get_bbox(t.angle) {
+                Some(bbox) => {
+                    predictions.push(bbox);
+                    true
+                }
+                None => false,
+            }
+        });
+
+        let costs = detections
+            .iter()
+            .map(|d| {
+                predictions
+                    .iter()
+                    .map(|p| 1.0 - d.iou(p).unwrap_or(0.0) as f64)
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let threshold = 1.0 - config.iou_threshold as f64;
+        let matches = assign(&costs)
+            .into_iter()
+            .enumerate()
+            .map(|(d, t)| t.filter(|&t| costs[d][t] <= threshold))
+            .collect::<Vec<_>>();
+
+        for track in source.tracks.iter_mut() {
+            track.time_since_update += 1;
+        }
+        let mut detection_tracks = Vec::with_capacity(detections.len());
+        for (detection, matched) in detections.iter().zip(matches) {
+            let index = match matched {
+                Some(index) => {
+                    let track = &mut source.tracks[index];
+                    track.filter.update(detection);
+                    track.angle = detection.get_angle();
+                    track.hit_streak = if track.time_since_update == 1 {
+                        track.hit_streak + 1
+                    } else {
+                        1
+                    };
+                    track.time_since_update = 0;
+                    index
+                }
+                None => {
+                    source.tracks.push(Track {
+                        id: self.next_track_id,
+                        filter: KalmanBoxFilter::new(detection),
+                        angle: detection.get_angle(),
+                        time_since_update: 0,
+                        hit_streak: 1,
+                    });
+                    self.next_track_id += 1;
+                    source.tracks.len() - 1
+                }
+            };
+            detection_tracks.push(index);
+        }
+
+        let mut assigned = Vec::new();
+        for (object, index) in objects.iter_mut().zip(detection_tracks) {
+            let track = &source.tracks[index];
+            let confirmed =
+                track.hit_streak >= config.min_hits || source.frame_count <= config.min_hits;
+            match (confirmed, track.filter.get_bbox(track.angle)) {
+                (true, Some(bbox)) => {
+                    object.set_track_info(track.id, bbox);
+                    assigned.push((object.get_id(), track.id));
+                }
+                _ => object.clear_track_info(),
+            }
+        }
+
+        source
+            .tracks
+            .retain(|t| t.time_since_update <= config.max_age);
+        assigned.sort_unstable();
+        assigned
+    }
+}
+
+/// [`SortTracker`] as a stage function, tracks frames and batches passing the stage
+/// and drops the tracks of ended sources.
+///
+pub struct SortTrackerFunction {
+    tracker: Arc<Mutex<SortTracker>>,
+    pipeline: Option<Pipeline>,
+}
+
+impl SortTrackerFunction {
+    pub fn new(tracker: SortTracker) -> Self {
+        Self::with_shared_tracker(Arc::new(Mutex::new(tracker)))
+    }
+
+    /// Creates the function over the tracker which is also used outside the pipeline.
+    ///
+    pub fn with_shared_tracker(tracker: Arc<Mutex<SortTracker>>) -> Self {
+        Self {
+            tracker,
+            pipeline: None,
+        }
+    }
+}
+
+impl PipelineStageFunction for SortTrackerFunction {
+    fn set_pipeline(&mut self, pipeline: Pipeline) {
+        self.pipeline = Some(pipeline);
+    }
+
+    fn get_pipeline(&self) -> &Option<Pipeline> {
+        &self.pipeline
+    }
+
+    fn call(
+        &self,
+        _: i64,
+        _: &PipelineStage,
+        _: PipelineStageFunctionOrder,
+        payload: &mut PipelinePayload,
+    ) -> anyhow::Result<()> {
+        let mut tracker = self.tracker.lock();
+        match payload {
+            PipelinePayload::Frame(frame, _, _, _, _) => {
+                tracker.track(frame);
+            }
+            PipelinePayload::Batch(batch, _, _, _, _) => {
+                let mut ids = batch.frames.keys().copied().collect::<Vec<_>>();
+                ids.sort();
+                for id in ids {
+                    tracker.track(&batch.frames[&id]);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn on_source_eos(&self, source_id: &str) -> anyhow::Result<()> {
+        self.tracker.lock().clear_source(source_id);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SortTracker, SortTrackerConfig, SortTrackerFunction};
+    use crate::match_query::MatchQuery;
+    use crate::pipeline::{Pipeline, PipelineConfigurationBuilder, PipelineStagePayloadType};
+    use crate::primitives::frame::VideoFrameProxy;
+    use crate::primitives::object::{
+        IdCollisionResolutionPolicy, ObjectOperations, VideoObjectBuilder,
+    };
+    use crate::primitives::RBBox;
+    use crate::test::gen_empty_frame;
+
+    fn frame_with_boxes(source_id: &str, boxes: &[RBBox]) -> VideoFrameProxy {
+        let mut frame = gen_empty_frame();
+        frame.set_source_id(source_id);
+        for (id, bbox) in boxes.iter().enumerate() {
+            frame
+                .add_object(
+                    VideoObjectBuilder::default()
+                        .id(id as i64)
+                        .namespace("detector".to_string())
+                        .label("person".to_string())
+                        .detection_box(bbox.clone())
+                        .build()
+                        .unwrap(),
+                    IdCollisionResolutionPolicy::Error,
+                )
+                .unwrap();
+        }
+        frame
+    }
+
+    #[test]
+    fn test_tracks_moving_objects() {
+        let mut tracker = SortTracker::new(
+            SortTrackerConfig {
+                max_age: 1,
+                min_hits: 2,
+                iou_threshold: 0.3,
+            },
+            MatchQuery::Idle,
+        );
+        let mut track_ids = Vec::new();
+        for step in 0..10 {
+            let dx = step as f32 * 2.0;
+            let frame = frame_with_boxes(
+                "cam",
+                &[
+                    RBBox::ltwh(10.0 + dx, 10.0, 20.0, 40.0),
+                    RBBox::ltwh(200.0 - dx, 100.0, 30.0, 30.0),
+                ],
+            );
+            let assigned = tracker.track(&frame);
+            assert_eq!(assigned.len(), 2);
+            track_ids.push(assigned);
+        }
+        assert!(track_ids.windows(2).all(|w| w[0] == w[1]));
+        assert_ne!(track_ids[0][0].1, track_ids[0][1].1);
+
+        let frame = frame_with_boxes("cam", &[RBBox::ltwh(40.0, 10.0, 20.0, 40.0)]);
+        tracker.track(&frame);
+        let object = frame.get_object(0).unwrap();
+        assert_eq!(object.get_track_id(), Some(track_ids[0][0].1));
+        assert!(object.get_track_box().is_some());
+        assert_eq!(tracker.get_track_count("cam"), 2);
+
+        // the second track is not matched for longer than max_age
+        tracker.track(&frame);
+        assert_eq!(tracker.get_track_count("cam"), 1);
+        assert_eq!(tracker.get_track_count("other"), 0);
+    }
+
+    #[test]
+    fn test_min_hits() {
+        let mut tracker = SortTracker::new(SortTrackerConfig::default(), MatchQuery::Idle);
+        let bbox = RBBox::ltwh(10.0, 10.0, 20.0, 20.0);
+        for _ in 0..4 {
+            tracker.track(&frame_with_boxes("cam", std::slice::from_ref(&bbox)));
+        }
+        // a new object does not get the track id until it is matched min_hits times
+        let frame = frame_with_boxes(
+            "cam",
+            &[bbox.clone(), RBBox::ltwh(100.0, 100.0, 20.0, 20.0)],
+        );
+        assert_eq!(tracker.track(&frame).len(), 1);
+        assert_eq!(frame.get_object(1).unwrap().get_track_id(), None);
+    }
+
+    #[test]
+    fn test_stage_function() -> anyhow::Result<()> {
+        let tracker = SortTracker::new(SortTrackerConfig::default(), MatchQuery::Idle);
+        let pipeline = Pipeline::new(
+            vec![(
+                "tracker".to_string(),
+                PipelineStagePayloadType::Frame,
+                Some(Box::new(SortTrackerFunction::new(tracker))),
+                None,
+            )],
+            PipelineConfigurationBuilder::default().build()?,
+        )?;
+        let bbox = RBBox::ltwh(10.0, 10.0, 20.0, 20.0);
+        let frame = frame_with_boxes("cam", &[bbox]);
+        pipeline.add_frame("tracker", frame.clone())?;
+        assert!(frame.get_object(0).unwrap().get_track_id().is_some());
+        pipeline.notify_source_eos("cam")?;
+        Ok(())
+    }
+}
@@ -1 +1,29 @@
+pub mod inproc;
+pub mod router;
+pub mod tape;
 pub mod zeromq;
+
+use crate::message::Message;
+use crate::transport::zeromq::{ReaderResult, WriterResult};
+
+/// Transport-agnostic source of messages. Implemented by ZeroMQ readers and
+/// by the in-process channel reader.
+pub trait MessageSource: Send {
+    fn receive(&self) -> anyhow::Result<ReaderResult>;
+    fn is_started(&self) -> bool;
+    fn shutdown(&mut self) -> anyhow::Result<()>;
+}
+
+/// Transport-agnostic sink of messages. Implemented by ZeroMQ writers and
+/// by the in-process channel writer.
+pub trait MessageSink: Send {
+    fn send_message(
+        &self,
+        topic: &str,
+        message: &Message,
+        data: &[&[u8]],
+    ) -> anyhow::Result<WriterResult>;
+    fn send_eos(&self, topic: &str) -> anyhow::Result<WriterResult>;
+    fn is_started(&self) -> bool;
+    fn shutdown(&mut self) -> anyhow::Result<()>;
+}
@@ -0,0 +1,445 @@
+use crate::message::Message;
+use crate::primitives::eos::EndOfStream;
+use crate::transport::zeromq::{ReaderResult, TopicPrefixSpec, WriterResult};
+use crate::transport::{MessageSink, MessageSource};
+use crate::utils::bytes_to_hex_string;
+use anyhow::bail;
+use crossbeam::channel::{Receiver, RecvTimeoutError, SendTimeoutError, Sender};
+use hashbrown::HashMap;
+use lazy_static::lazy_static;
+use log::{debug, info};
+use parking_lot::Mutex;
+use std::str::from_utf8;
+use std::time::{Duration, Instant};
+
+pub const INPROC_CHANNEL_SCHEME: &str = "inproc+channel:";
+pub const INPROC_CHANNEL_CAPACITY: usize = 100;
+pub const INPROC_RECEIVE_TIMEOUT: Duration = Duration::from_millis(1000);
+pub const INPROC_SEND_TIMEOUT: Duration = Duration::from_millis(5000);
+
+struct InprocEnvelope {
+    topic: Vec<u8>,
+    message: Message,
+    data: Vec<Vec<u8>>,
+}
+
+#[derive(Clone)]
+struct InprocChannel {
+    sender: Sender<InprocEnvelope>,
+    receiver: Receiver<InprocEnvelope>,
+}
+
+impl InprocChannel {
+    fn new(capacity: usize) -> Self {
+        let (sender, receiver) = crossbeam::channel::bounded(capacity);
+        Self { sender, receiver }
+    }
+}
+
+lazy_static! {
+    static ref INPROC_CHANNELS: Mutex<HashMap<String, InprocChannel>> = Mutex::new(HashMap::new());
+}
+
+/// Parses a URI like `inproc+channel:name` and returns the channel name.
+pub fn parse_inproc_uri(uri: &str) -> anyhow::Result<String> {
+    match uri.strip_prefix(INPROC_CHANNEL_SCHEME) {
+        Some(name) if !name.is_empty() => Ok(name.to_string()),
+        Some(_) => bail!("In-process channel name is empty in URI {}", uri),
+        None => bail!(
+            "Invalid in-process channel URI {}, expected {}<name>",
+            uri,
+            INPROC_CHANNEL_SCHEME
+        ),
+    }
+}
+
+/// Creates a channel with a specific capacity. Fails if the channel already exists.
+/// Readers and writers create missing channels with [`INPROC_CHANNEL_CAPACITY`].
+pub fn create_channel(uri: &str, capacity: usize) -> anyhow::Result<()> {
+    let name = parse_inproc_uri(uri)?;
+    if capacity == 0 {
+        bail!("In-process channel capacity must be greater than 0");
+    }
+    let mut channels = INPROC_CHANNELS.lock();
+    if channels.contains_key(&name) {
+        bail!("In-process channel {} already exists", name);
+    }
+    channels.insert(name, InprocChannel::new(capacity));
+    Ok(())
+}
+
+/// Unregisters a channel. Readers and writers which are already attached to
+/// the channel keep working, new ones get a fresh channel.
+pub fn remove_channel(uri: &str) -> anyhow::Result<bool> {
+    let name = parse_inproc_uri(uri)?;
+    Ok(INPROC_CHANNELS.lock().remove(&name).is_some())
+}
+
+fn get_or_create_channel(name: &str) -> InprocChannel {
+    INPROC_CHANNELS
+        .lock()
+        .entry(name.to_string())
+        .or_insert_with(|| InprocChannel::new(INPROC_CHANNEL_CAPACITY))
+        .clone()
+}
+
+/// Receives messages from an in-process channel. Messages are passed by
+/// reference, so the frames received are the same objects the writer sent.
+/// When several readers are attached to one channel, each message is
+/// delivered to exactly one of them.
+pub struct InprocReader {
+    name: String,
+    topic_prefix_spec: TopicPrefixSpec,
+    receive_timeout: Duration,
+    receiver: Option<Receiver<InprocEnvelope>>,
+}
+
+impl InprocReader {
+    pub fn new(
+        uri: &str,
+        topic_prefix_spec: TopicPrefixSpec,
+        receive_timeout: Duration,
+    ) -> anyhow::Result<Self> {
+        let name = parse_inproc_uri(uri)?;
+        let channel = get_or_create_channel(&name);
+        Ok(Self {
+            name,
+            topic_prefix_spec,
+            receive_timeout,
+            receiver: Some(channel.receiver),
+        })
+    }
+
+    pub fn receive(&self) -> anyhow::Result<ReaderResult> {
+        let receiver = match &self.receiver {
+            Some(receiver) => receiver,
+            None => bail!(
+                "In-process channel {} is no longer available, because the reader was shut down.",
+                self.name
+            ),
+        };
+        let envelope = match receiver.recv_timeout(self.receive_timeout) {
+            Ok(envelope) => envelope,
+            Err(RecvTimeoutError::Timeout) => return Ok(ReaderResult::Timeout),
+            Err(RecvTimeoutError::Disconnected) => {
+                bail!("In-process channel {} is disconnected", self.name)
+            }
+        };
+        let topic = envelope.topic;
+
+        if !envelope.message.is_end_of_stream() && !self.topic_prefix_spec.matches(&topic) {
+            debug!(
+                target: "savant_rs::inproc::reader",
+                "Received message with invalid topic from in-process channel {}. Expected topic to match spec {:?}, but got {}",
+                self.name,
+                self.topic_prefix_spec,
+                from_utf8(&topic).unwrap_or(&bytes_to_hex_string(&topic))
+            );
+            return Ok(ReaderResult::PrefixMismatch {
+                topic,
+                routing_id: None,
+            });
+        }
+
+        Ok(ReaderResult::Message {
+            message: Box::new(envelope.message),
+            topic,
+            routing_id: None,
+            data: envelope.data,
+            seq_id_status: None,
+        })
+    }
+
+    pub fn is_started(&self) -> bool {
+        self.receiver.is_some()
+    }
+
+    pub fn shutdown(&mut self) -> anyhow::Result<()> {
+        info!(
+            target: "savant_rs::inproc::reader",
+            "Shutting down reader for in-process channel {}", self.name
+        );
+        if self.receiver.take().is_none() {
+            bail!("Reader is shutdown.");
+        }
+        Ok(())
+    }
+}
+
+impl MessageSource for InprocReader {
+    fn receive(&self) -> anyhow::Result<ReaderResult> {
+        InprocReader::receive(self)
+    }
+
+    fn is_started(&self) -> bool {
+        InprocReader::is_started(self)
+    }
+
+    fn shutdown(&mut self) -> anyhow::Result<()> {
+        InprocReader::shutdown(self)
+    }
+}
+
+/// Sends messages to an in-process channel without serializing them.
+pub struct InprocWriter {
+    name: String,
+    send_timeout: Duration,
+    sender: Option<Sender<InprocEnvelope>>,
+}
+
+impl InprocWriter {
+    pub fn new(uri: &str, send_timeout: Duration) -> anyhow::Result<Self> {
+        let name = parse_inproc_uri(uri)?;
+        let channel = get_or_create_channel(&name);
+        Ok(Self {
+            name,
+            send_timeout,
+            sender: Some(channel.sender),
+        })
+    }
+
+    pub fn send_eos(&self, topic: &str) -> anyhow::Result<WriterResult> {
+        let m = Message::end_of_stream(EndOfStream::new(topic.to_string()));
+        self.send(topic, m, &[])
+    }
+
+    pub fn send_message(
+        &self,
+        topic: &str,
+        message: &Message,
+        data: &[&[u8]],
+    ) -> anyhow::Result<WriterResult> {
+        self.send(topic, message.clone(), data)
+    }
+
+    fn send(&self, topic: &str, message: Message, data: &[&[u8]]) -> anyhow::Result<WriterResult> {
+        let sender = match &self.sender {
+            Some(sender) => sender,
+            None => bail!(
+                "In-process channel {} is no longer available, because the writer was shut down.",
+                self.name
+            ),
+        };
+        let start = Instant::now();
+        let envelope = InprocEnvelope {
+            topic: topic.as_bytes().to_vec(),
+            message,
+            data: data.iter().map(|d| d.to_vec()).collect(),
+        };
+        match sender.send_timeout(envelope, self.send_timeout) {
+            Ok(_) => Ok(WriterResult::Success {
+                retries_spent: 0,
+                time_spent: start.elapsed().as_millis(),
+            }),
+            Err(SendTimeoutError::Timeout(_)) => Ok(WriterResult::SendTimeout),
+            Err(SendTimeoutError::Disconnected(_)) => {
+                bail!("In-process channel {} is disconnected", self.name)
+            }
+        }
+    }
+
+    pub fn is_started(&self) -> bool {
+        self.sender.is_some()
+    }
+
+    pub fn shutdown(&mut self) -> anyhow::Result<()> {
+        info!(
+            target: "savant_rs::inproc::writer",
+            "Shutting down writer for in-process channel {}", self.name
+        );
+        if self.sender.take().is_none() {
+            bail!("Writer is shutdown.");
+        }
+        Ok(())
+    }
+}
+
+impl MessageSink for InprocWriter {
+    fn send_message(
+        &self,
+        topic: &str,
+        message: &Message,
+        data: &[&[u8]],
+    ) -> anyhow::Result<WriterResult> {
+        InprocWriter::send_message(self, topic, message, data)
+    }
+
+    fn send_eos(&self, topic: &str) -> anyhow::Result<WriterResult> {
+        InprocWriter::send_eos(self, topic)
+    }
+
+    fn is_started(&self) -> bool {
+        InprocWriter::is_started(self)
+    }
+
+    fn shutdown(&mut self) -> anyhow::Result<()> {
+        InprocWriter::shutdown(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{create_channel, parse_inproc_uri, remove_channel, InprocReader, InprocWriter};
+    use crate::message::Message;
+    use crate::test::gen_frame;
+    use crate::transport::zeromq::{
+        ReaderConfig, ReaderResult, SyncReader, SyncWriter, TopicPrefixSpec, WriterConfig,
+        WriterResult,
+    };
+    use crate::transport::{MessageSink, MessageSource};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_parse_uri() {
+        assert_eq!(parse_inproc_uri("inproc+channel:abc").unwrap(), "abc");
+        assert!(parse_inproc_uri("inproc+channel:").is_err());
+        assert!(parse_inproc_uri("ipc:///tmp/abc").is_err());
+    }
+
+    #[test]
+    fn test_send_receive_shares_frame() -> anyhow::Result<()> {
+        let uri = "inproc+channel:test-send-receive";
+        let reader = InprocReader::new(uri, TopicPrefixSpec::none(), Duration::from_millis(100))?;
+        let writer = InprocWriter::new(uri, Duration::from_millis(100))?;
+        let frame = gen_frame();
+        let m = Message::video_frame(&frame);
+        let res = writer.send_message("test", &m, &[b"abc"])?;
+        assert!(matches!(res, WriterResult::Success { .. }));
+        let res = reader.receive()?;
+        match res {
+            ReaderResult::Message {
+                message,
+                topic,
+                routing_id,
+                data,
+                ..
+            } => {
+                assert_eq!(topic, b"test");
+                assert!(routing_id.is_none());
+                assert_eq!(data, vec![b"abc".to_vec()]);
+                let received = message.as_video_frame().unwrap();
+                assert!(Arc::ptr_eq(&received.inner.0, &frame.inner.0));
+            }
+            _ => panic!("Unexpected result {:?}", res),
+        }
+        remove_channel(uri)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_timeouts() -> anyhow::Result<()> {
+        let uri = "inproc+channel:test-timeouts";
+        create_channel(uri, 1)?;
+        assert!(create_channel(uri, 1).is_err());
+        let reader = InprocReader::new(uri, TopicPrefixSpec::none(), Duration::from_millis(10))?;
+        let writer = InprocWriter::new(uri, Duration::from_millis(10))?;
+        assert!(matches!(reader.receive()?, ReaderResult::Timeout));
+        assert!(matches!(
+            writer.send_eos("test")?,
+            WriterResult::Success { .. }
+        ));
+        assert!(matches!(
+            writer.send_eos("test")?,
+            WriterResult::SendTimeout
+        ));
+        remove_channel(uri)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_prefix_mismatch() -> anyhow::Result<()> {
+        let uri = "inproc+channel:test-prefix-mismatch";
+        let reader = InprocReader::new(
+            uri,
+            TopicPrefixSpec::source_id("test"),
+            Duration::from_millis(100),
+        )?;
+        let writer = InprocWriter::new(uri, Duration::from_millis(100))?;
+        writer.send_message("other", &Message::video_frame(&gen_frame()), &[])?;
+        assert!(matches!(
+            reader.receive()?,
+            ReaderResult::PrefixMismatch { topic, routing_id } if topic == b"other" && routing_id.is_none()
+        ));
+        writer.send_eos("other")?;
+        assert!(matches!(
+            reader.receive()?,
+            ReaderResult::Message { message, .. } if message.is_end_of_stream()
+        ));
+        remove_channel(uri)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_shutdown() -> anyhow::Result<()> {
+        let uri = "inproc+channel:test-shutdown";
+        let mut reader =
+            InprocReader::new(uri, TopicPrefixSpec::none(), Duration::from_millis(10))?;
+        let mut writer = InprocWriter::new(uri, Duration::from_millis(10))?;
+        reader.shutdown()?;
+        writer.shutdown()?;
+        assert!(!reader.is_started());
+        assert!(!writer.is_started());
+        assert!(reader.receive().is_err());
+        assert!(writer.send_eos("test").is_err());
+        assert!(reader.shutdown().is_err());
+        remove_channel(uri)?;
+        Ok(())
+    }
+
+    fn exchange(source: Box<dyn MessageSource>, sink: Box<dyn MessageSink>) -> anyhow::Result<()> {
+        let reader_thread = thread::spawn(move || {
+            let first = source.receive();
+            let second = source.receive();
+            (first, second)
+        });
+        let m = Message::video_frame(&gen_frame());
+        assert!(matches!(
+            sink.send_message("test", &m, &[])?,
+            WriterResult::Ack { .. } | WriterResult::Success { .. }
+        ));
+        assert!(matches!(
+            sink.send_eos("test")?,
+            WriterResult::Ack { .. } | WriterResult::Success { .. }
+        ));
+        let (first, second) = reader_thread.join().unwrap();
+        assert!(matches!(
+            first?,
+            ReaderResult::Message { message, topic, .. } if message.meta().seq_id == m.meta().seq_id && topic == b"test"
+        ));
+        assert!(matches!(
+            second?,
+            ReaderResult::Message { message, .. } if message.is_end_of_stream()
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_trait_objects() -> anyhow::Result<()> {
+        let uri = "inproc+channel:test-trait-objects";
+        exchange(
+            Box::new(InprocReader::new(
+                uri,
+                TopicPrefixSpec::none(),
+                Duration::from_millis(1000),
+            )?),
+            Box::new(InprocWriter::new(uri, Duration::from_millis(1000))?),
+        )?;
+        remove_channel(uri)?;
+
+        let path = "/tmp/test/inproc-trait-objects";
+        std::fs::remove_dir_all(path).unwrap_or_default();
+        let reader = SyncReader::new(
+            &ReaderConfig::new()
+                .url(&format!("rep+bind:ipc://{}", path))?
+                .build()?,
+        )?;
+        let writer = SyncWriter::new(
+            &WriterConfig::new()
+                .url(&format!("req+connect:ipc://{}", path))?
+                .build()?,
+        )?;
+        exchange(Box::new(reader), Box::new(writer))
+    }
+}
@@ -0,0 +1,511 @@
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use anyhow::{anyhow, bail, Context};
+use log::{debug, error, info, warn};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::match_query::MatchQuery;
+use crate::message::label_filter::LabelFilterRule;
+use crate::message::Message;
+use crate::primitives::eos::EndOfStream;
+use crate::transport::inproc::{
+    InprocReader, InprocWriter, INPROC_CHANNEL_SCHEME, INPROC_RECEIVE_TIMEOUT, INPROC_SEND_TIMEOUT,
+};
+use crate::transport::zeromq::{
+    ReaderConfig, ReaderResult, SyncReader, SyncWriter, TopicPrefixSpec, WriterConfig, WriterResult,
+};
+use crate::transport::{MessageSink, MessageSource};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TopicFilterSpec {
+    SourceId(String),
+    Prefix(String),
+}
+
+impl From<&TopicFilterSpec> for TopicPrefixSpec {
+    fn from(spec: &TopicFilterSpec) -> Self {
+        match spec {
+            TopicFilterSpec::SourceId(source_id) => TopicPrefixSpec::source_id(source_id),
+            TopicFilterSpec::Prefix(prefix) => TopicPrefixSpec::prefix(prefix),
+        }
+    }
+}
+
+/// The reader the router receives messages from, ZeroMQ reader or in-process channel URI.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RouterInputSpec {
+    pub url: String,
+    #[serde(default)]
+    pub topic: Option<TopicFilterSpec>,
+}
+
+/// The writer the router forwards messages to, a message is forwarded to every output
+/// whose filters it passes.
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouterOutputSpec {
+    pub url: String,
+    #[serde(default)]
+    pub topic: Option<TopicFilterSpec>,
+    /// Matched against the routing labels, end-of-stream messages are not filtered.
+    #[serde(default)]
+    pub labels: Option<LabelFilterRule>,
+    /// Video frames without objects matching the query are not forwarded.
+    #[serde(default)]
+    pub frame_query: Option<MatchQuery>,
+    /// Replaces the source id and the topic of the forwarded messages.
+    #[serde(default)]
+    pub source_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouterSpec {
+    pub inputs: Vec<RouterInputSpec>,
+    pub outputs: Vec<RouterOutputSpec>,
+}
+
+impl RouterSpec {
+    pub fn to_yaml(&self) -> String {
+        serde_yaml::to_string(&serde_json::to_value(self).unwrap()).unwrap()
+    }
+
+    pub fn from_yaml(yaml: &str) -> anyhow::Result<Self> {
+        Ok(serde_json::from_value(serde_yaml::from_str(yaml)?)?)
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read router spec {}", path.display()))?;
+        Self::from_yaml(&text)
+            .with_context(|| format!("Failed to parse router spec {}", path.display()))
+    }
+}
+
+fn open_source(spec: &RouterInputSpec) -> anyhow::Result<Box<dyn MessageSource>> {
+    let topic = spec
+        .topic
+        .as_ref()
+        .map(TopicPrefixSpec::from)
+        .unwrap_or(TopicPrefixSpec::none());
+    if spec.url.starts_with(INPROC_CHANNEL_SCHEME) {
+        Ok(Box::new(InprocReader::new(
+            &spec.url,
+            topic,
+            INPROC_RECEIVE_TIMEOUT,
+        )?))
+    } else {
+        let config = ReaderConfig::new()
+            .url(&spec.url)?
+            .with_topic_prefix_spec(topic)?
+            .build()?;
+        Ok(Box::new(SyncReader::new(&config)?))
+    }
+}
+
+fn open_sink(spec: &RouterOutputSpec) -> anyhow::Result<Box<dyn MessageSink>> {
+    if spec.url.starts_with(INPROC_CHANNEL_SCHEME) {
+        Ok(Box::new(InprocWriter::new(&spec.url, INPROC_SEND_TIMEOUT)?))
+    } else {
+        let config = WriterConfig::new().url(&spec.url)?.build()?;
+        Ok(Box::new(SyncWriter::new(&config)?))
+    }
+}
+
+/// Copies the message for another source, the routing labels and the span context are
+/// preserved.
+///
+fn rewrite_source_id(m: &Message, source_id: &str) -> Message {
+    let mut copy = if let Some(frame) = m.as_video_frame() {
+        let mut frame = frame.smart_copy();
+        frame.set_source_id(source_id);
+        Message::video_frame(&frame)
+    } else if let Some(user_data) = m.as_user_data() {
+        let mut user_data = user_data.clone();
+        user_data.source_id = source_id.to_string();
+        Message::user_data(user_data)
+    } else if m.is_end_of_stream() {
+        Message::end_of_stream(EndOfStream::new(source_id.to_string()))
+    } else {
+        return m.clone();
+    };
+    copy.meta_mut().routing_labels = m.meta().routing_labels.clone();
+    copy.meta_mut().span_context = m.meta().span_context.clone();
+    copy
+}
+
+struct RouterOutput {
+    spec: RouterOutputSpec,
+    topic: Option<TopicPrefixSpec>,
+    sink: Mutex<Box<dyn MessageSink>>,
+}
+
+impl RouterOutput {
+    fn accepts(&self, topic: &[u8], m: &Message) -> bool {
+        if let Some(spec) = &self.topic {
+            if !spec.matches(topic) {
+                return false;
+            }
+        }
+        if m.is_end_of_stream() {
+            return true;
+        }
+        if let Some(rule) = &self.spec.labels {
+            if !rule.matches(&m.get_labels()) {
+                return false;
+            }
+        }
+        if let (Some(query), Some(frame)) = (&self.spec.frame_query, m.as_video_frame()) {
+            if frame.access_objects(query).is_empty() {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn forward(&self, topic: &[u8], m: &Message, data: &[&[u8]]) -> anyhow::Result<WriterResult> {
+        let sink = self.sink.lock();
+        let res = match &self.spec.source_id {
+            Some(source_id) if m.is_end_of_stream() => sink.send_eos(source_id)?,
+            Some(source_id) => {
+                sink.send_message(source_id, &rewrite_source_id(m, source_id), data)?
+            }
+            None => {
+                let topic = std::str::from_utf8(topic)
+                    .map_err(|e| anyhow!("Topic is not a valid UTF-8 string: {}", e))?;
+                if m.is_end_of_stream() {
+                    sink.send_eos(topic)?
+                } else {
+                    sink.send_message(topic, m, data)?
+                }
+            }
+        };
+        debug!(
+            target: "savant_rs::router",
+            "Message forwarded to {}, result: {:?}", self.spec.url, res);
+        Ok(res)
+    }
+}
+
+/// The outcome of forwarding a message to the matching outputs, a failed output does not
+/// prevent the others from receiving the message.
+///
+#[derive(Debug, Default, PartialEq)]
+pub struct ForwardResult {
+    /// The number of the outputs the message is sent to.
+    pub forwarded: usize,
+    /// The URLs of the outputs which failed to send the message.
+    pub failed: Vec<String>,
+}
+
+/// Forwards messages from the inputs to the outputs, each input is served by its own
+/// thread.
+///
+pub struct Router {
+    sources: Vec<(String, Box<dyn MessageSource>)>,
+    outputs: Arc<Vec<RouterOutput>>,
+}
+
+fn forward(outputs: &[RouterOutput], topic: &[u8], m: &Message, data: &[Vec<u8>]) -> ForwardResult {
+    let data = data.iter().map(|d| d.as_slice()).collect::<Vec<_>>();
+    let mut result = ForwardResult::default();
+    for output in outputs.iter().filter(|o| o.accepts(topic, m)) {
+        match output.forward(topic, m, &data) {
+            Ok(res @ (WriterResult::SendTimeout | WriterResult::AckTimeout(_))) => {
+                warn!(
+                    target: "savant_rs::router",
+                    "Message is not delivered to {}: {:?}", output.spec.url, res);
+                result.failed.push(output.spec.url.clone());
+            }
+            Ok(_) => result.forwarded += 1,
+            Err(e) => {
+                error!(
+                    target: "savant_rs::router",
+                    "Failed to forward message to {}: {:?}", output.spec.url, e);
+                result.failed.push(output.spec.url.clone());
+            }
+        }
+    }
+    result
+}
+
+impl Router {
+    pub fn new(spec: &RouterSpec) -> anyhow::Result<Self> {
+        if spec.inputs.is_empty() {
+            bail!("Router requires at least one input");
+        }
+        if spec.outputs.is_empty() {
+            bail!("Router requires at least one output");
+        }
+        let sources = spec
+            .inputs
+            .iter()
+            .map(|s| {
+                open_source(s)
+                    .map(|source| (s.url.clone(), source))
+                    .with_context(|| format!("Failed to open input {}", s.url))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let outputs = spec
+            .outputs
+            .iter()
+            .map(|s| {
+                Ok(RouterOutput {
+                    spec: s.clone(),
+                    topic: s.topic.as_ref().map(TopicPrefixSpec::from),
+                    sink: Mutex::new(
+                        open_sink(s).with_context(|| format!("Failed to open output {}", s.url))?,
+                    ),
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(Self {
+            sources,
+            outputs: Arc::new(outputs),
+        })
+    }
+
+    /// Forwards the message to every matching output, the failures are logged and reported
+    /// in the result.
+    ///
+    pub fn forward(&self, topic: &[u8], m: &Message, data: &[Vec<u8>]) -> ForwardResult {
+        forward(&self.outputs, topic, m, data)
+    }
+
+    /// Serves the inputs until `stop` is set, then shuts the readers and the writers down.
+    ///
+    pub fn run(self, stop: Arc<AtomicBool>) -> anyhow::Result<()> {
+        let threads = self
+            .sources
+            .into_iter()
+            .map(|(url, mut source)| {
+                let outputs = self.outputs.clone();
+                let stop = stop.clone();
+                thread::spawn(move || {
+                    info!(target: "savant_rs::router", "Serving input {}", url);
+                    while !stop.load(Ordering::Relaxed) {
+                        match source.receive() {
+                            Ok(ReaderResult::Message {
+                                message,
+                                topic,
+                                data,
+                                ..
+                            }) => {
+                                forward(&outputs, &topic, &message, &data);
+                            }
+                            Ok(ReaderResult::Timeout) => {}
+                            Ok(res) => {
+                                debug!(
+                                    target: "savant_rs::router",
+                                    "Message from {} is not forwarded: {:?}", url, res);
+                            }
+                            Err(e) => {
+                                error!(
+                                    target: "savant_rs::router",
+                                    "Input {} failed: {:?}", url, e);
+                                break;
+                            }
+                        }
+                    }
+                    if let Err(e) = source.shutdown() {
+                        warn!(
+                            target: "savant_rs::router",
+                            "Failed to shut down input {}: {:?}", url, e);
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for t in threads {
+            t.join()
+                .map_err(|_| anyhow!("Router input thread panicked"))?;
+        }
+        for output in self.outputs.iter() {
+            output.sink.lock().shutdown()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ForwardResult, Router, RouterSpec};
+    use crate::message::Message;
+    use crate::primitives::eos::EndOfStream;
+    use crate::test::gen_frame;
+    use crate::transport::inproc::{InprocReader, INPROC_RECEIVE_TIMEOUT};
+    use crate::transport::zeromq::{
+        ReaderConfig, ReaderResult, SyncReader, SyncWriter, TopicPrefixSpec, WriterConfig,
+    };
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+
+    const SPEC: &str = r#"
+inputs:
+  - url: "inproc+channel:router-test-in"
+outputs:
+  - url: "inproc+channel:router-test-all"
+  - url: "inproc+channel:router-test-labeled"
+    labels:
+      set: "hd"
+  - url: "inproc+channel:router-test-renamed"
+    topic:
+      prefix: "test"
+    source_id: "renamed"
+"#;
+
+    fn receive(reader: &InprocReader) -> anyhow::Result<Option<(Vec<u8>, Box<Message>)>> {
+        Ok(match reader.receive()? {
+            ReaderResult::Message { topic, message, .. } => Some((topic, message)),
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn test_forward() -> anyhow::Result<()> {
+        let spec = RouterSpec::from_yaml(SPEC)?;
+        let router = Router::new(&spec)?;
+        let readers = ["all", "labeled", "renamed"]
+            .map(|name| {
+                InprocReader::new(
+                    &format!("inproc+channel:router-test-{}", name),
+                    TopicPrefixSpec::none(),
+                    INPROC_RECEIVE_TIMEOUT,
+                )
+            })
+            .into_iter()
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let frame = gen_frame();
+        let mut m = Message::video_frame(&frame);
+        m.set_labels(vec!["hd".to_string()]);
+        assert_eq!(router.forward(b"test", &m, &[]).forwarded, 3);
+        let m = Message::video_frame(&frame);
+        assert_eq!(router.forward(b"other", &m, &[]).forwarded, 1);
+        let eos = Message::end_of_stream(EndOfStream::new("test".to_string()));
+        assert_eq!(router.forward(b"test", &eos, &[]).forwarded, 3);
+
+        let (topic, _) = receive(&readers[0])?.unwrap();
+        assert_eq!(topic, b"test");
+        let (topic, _) = receive(&readers[1])?.unwrap();
+        assert_eq!(topic, b"test");
+        let (topic, message) = receive(&readers[2])?.unwrap();
+        assert_eq!(topic, b"renamed");
+        assert_eq!(message.as_video_frame().unwrap().get_source_id(), "renamed");
+        assert_eq!(message.get_labels(), vec!["hd".to_string()]);
+        assert_eq!(frame.get_source_id(), "test");
+
+        let (topic, _) = receive(&readers[0])?.unwrap();
+        assert_eq!(topic, b"other");
+        let (_, message) = receive(&readers[2])?.unwrap();
+        assert_eq!(
+            message.as_end_of_stream().unwrap().source_id,
+            "renamed".to_string()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_forward_past_failed_output() -> anyhow::Result<()> {
+        let spec = RouterSpec::from_yaml(
+            r#"
+inputs:
+  - url: "inproc+channel:router-test-failed-in"
+outputs:
+  - url: "inproc+channel:router-test-failed"
+  - url: "inproc+channel:router-test-alive"
+"#,
+        )?;
+        let router = Router::new(&spec)?;
+        let reader = InprocReader::new(
+            "inproc+channel:router-test-alive",
+            TopicPrefixSpec::none(),
+            INPROC_RECEIVE_TIMEOUT,
+        )?;
+        router.outputs[0].sink.lock().shutdown()?;
+
+        let m = Message::video_frame(&gen_frame());
+        assert_eq!(
+            router.forward(b"test", &m, &[]),
+            ForwardResult {
+                forwarded: 1,
+                failed: vec!["inproc+channel:router-test-failed".to_string()],
+            }
+        );
+        assert!(receive(&reader)?.is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_spec() -> anyhow::Result<()> {
+        let spec = RouterSpec::from_yaml("inputs: []\noutputs: []")?;
+        assert!(Router::new(&spec).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_zeromq_legs() -> anyhow::Result<()> {
+        let zmq_out = SyncReader::new(
+            &ReaderConfig::new()
+                .url("router+bind:ipc:///tmp/router-test-zmq-out")?
+                .build()?,
+        )?;
+        let spec = RouterSpec::from_yaml(
+            r#"
+inputs:
+  - url: "router+bind:ipc:///tmp/router-test-zmq-in"
+outputs:
+  - url: "dealer+connect:ipc:///tmp/router-test-zmq-out"
+  - url: "inproc+channel:router-test-zmq-inproc"
+"#,
+        )?;
+        let router = Router::new(&spec)?;
+        let inproc_out = InprocReader::new(
+            "inproc+channel:router-test-zmq-inproc",
+            TopicPrefixSpec::none(),
+            INPROC_RECEIVE_TIMEOUT,
+        )?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let router_thread = thread::spawn({
+            let stop = stop.clone();
+            move || router.run(stop)
+        });
+
+        let writer = SyncWriter::new(
+            &WriterConfig::new()
+                .url("dealer+connect:ipc:///tmp/router-test-zmq-in")?
+                .build()?,
+        )?;
+        let frame = gen_frame();
+        writer.send_message("test", &Message::video_frame(&frame), &[b"data"])?;
+
+        let (topic, message) = receive(&inproc_out)?.unwrap();
+        assert_eq!(topic, b"test");
+        assert!(message.is_video_frame());
+        match zmq_out.receive()? {
+            ReaderResult::Message {
+                topic,
+                message,
+                data,
+                ..
+            } => {
+                assert_eq!(topic, b"test");
+                assert!(message.is_video_frame());
+                assert_eq!(data, vec![b"data".to_vec()]);
+            }
+            res => panic!("Unexpected result: {:?}", res),
+        }
+
+        stop.store(true, Ordering::Relaxed);
+        router_thread.join().unwrap()?;
+        writer.shutdown()?;
+        zmq_out.shutdown()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,1186 @@
+use crate::message::{save_message, Message, MessageEnvelope};
+use crate::protobuf::deserialize;
+use crate::transport::zeromq::{ReaderResult, WriterResult};
+use crate::transport::MessageSink;
+use anyhow::{bail, Context};
+use hashbrown::HashMap;
+use log::{debug, info, warn};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+// A tape is a directory with numbered segment files. Every segment keeps the
+// records and has a companion index file which allows seeking by source id or
+// frame UUID without decoding the messages.
+//
+// Record layout (little endian):
+// magic: u32 | body length: u32 | crc32(body): u32 | body, where body is
+// timestamp_ns: u64 | topic | labels | data parts | serialized message.
+const SEGMENT_FILE_PREFIX: &str = "segment-";
+const SEGMENT_FILE_EXTENSION: &str = "tape";
+const INDEX_FILE_EXTENSION: &str = "index";
+const RECORD_MAGIC: u32 = 0x5456_4153; // "SAVT"
+const RECORD_HEADER_LEN: u64 = 12;
+const MAX_RECORD_LEN: u64 = 1024 * 1024 * 1024;
+pub const TAPE_SEGMENT_SIZE: u64 = 256 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TapeRecordKind {
+    VideoFrame,
+    VideoFrameBatch,
+    VideoFrameUpdate,
+    EndOfStream,
+    UserData,
+    Shutdown,
+    Unknown,
+}
+
+impl TapeRecordKind {
+    fn to_byte(self) -> u8 {
+        match self {
+            TapeRecordKind::VideoFrame => 0,
+            TapeRecordKind::VideoFrameBatch => 1,
+            TapeRecordKind::VideoFrameUpdate => 2,
+            TapeRecordKind::EndOfStream => 3,
+            TapeRecordKind::UserData => 4,
+            TapeRecordKind::Shutdown => 5,
+            TapeRecordKind::Unknown => 6,
+        }
+    }
+
+    fn from_byte(b: u8) -> anyhow::Result<Self> {
+        Ok(match b {
+            0 => TapeRecordKind::VideoFrame,
+            1 => TapeRecordKind::VideoFrameBatch,
+            2 => TapeRecordKind::VideoFrameUpdate,
+            3 => TapeRecordKind::EndOfStream,
+            4 => TapeRecordKind::UserData,
+            5 => TapeRecordKind::Shutdown,
+            6 => TapeRecordKind::Unknown,
+            _ => bail!("Unknown tape record kind {}", b),
+        })
+    }
+}
+
+/// Index entry describing a single record of the tape.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TapeIndexEntry {
+    pub segment: u64,
+    pub offset: u64,
+    pub timestamp_ns: u64,
+    pub kind: TapeRecordKind,
+    /// Source id of the message payload; the topic is used when the payload
+    /// does not carry a source id (e.g. [`crate::primitives::frame_update::VideoFrameUpdate`]).
+    pub source_id: String,
+    pub frame_uuid: Option<u128>,
+    pub keyframe: Option<bool>,
+}
+
+impl TapeIndexEntry {
+    fn new(segment: u64, offset: u64, timestamp_ns: u64, topic: &[u8], message: &Message) -> Self {
+        let topic_source = || String::from_utf8_lossy(topic).to_string();
+        let (kind, source_id, frame_uuid, keyframe) = match message.payload() {
+            MessageEnvelope::VideoFrame(f) => (
+                TapeRecordKind::VideoFrame,
+                f.get_source_id(),
+                Some(f.get_uuid_u128()),
+                f.get_keyframe(),
+            ),
+            MessageEnvelope::VideoFrameBatch(_) => {
+                (TapeRecordKind::VideoFrameBatch, topic_source(), None, None)
+            }
+            MessageEnvelope::VideoFrameUpdate(_) => {
+                (TapeRecordKind::VideoFrameUpdate, topic_source(), None, None)
+            }
+            MessageEnvelope::EndOfStream(eos) => (
+                TapeRecordKind::EndOfStream,
+                eos.source_id.clone(),
+                None,
+                None,
+            ),
+            MessageEnvelope::UserData(ud) => (
+                TapeRecordKind::UserData,
+                ud.get_source_id().to_string(),
+                None,
+                None,
+            ),
+            MessageEnvelope::Shutdown(_) => (TapeRecordKind::Shutdown, topic_source(), None, None),
+            MessageEnvelope::Unknown(_) => (TapeRecordKind::Unknown, topic_source(), None, None),
+        };
+        Self {
+            segment,
+            offset,
+            timestamp_ns,
+            kind,
+            source_id,
+            frame_uuid,
+            keyframe,
+        }
+    }
+
+    fn encode(&self, buf: &mut Vec<u8>) {
+        put_u64(buf, self.offset);
+        put_u64(buf, self.timestamp_ns);
+        buf.push(self.kind.to_byte());
+        buf.push(match self.keyframe {
+            None => 0,
+            Some(false) => 1,
+            Some(true) => 2,
+        });
+        match self.frame_uuid {
+            Some(uuid) => {
+                buf.push(1);
+                buf.extend_from_slice(&uuid.to_le_bytes());
+            }
+            None => buf.push(0),
+        }
+        put_bytes(buf, self.source_id.as_bytes());
+    }
+
+    fn decode(segment: u64, d: &mut Decoder) -> anyhow::Result<Self> {
+        let offset = d.u64()?;
+        let timestamp_ns = d.u64()?;
+        let kind = TapeRecordKind::from_byte(d.u8()?)?;
+        let keyframe = match d.u8()? {
+            0 => None,
+            1 => Some(false),
+            2 => Some(true),
+            v => bail!("Invalid keyframe flag {}", v),
+        };
+        let frame_uuid = match d.u8()? {
+            0 => None,
+            _ => Some(u128::from_le_bytes(d.take(16)?.try_into()?)),
+        };
+        let source_id = String::from_utf8(d.bytes()?.to_vec())?;
+        Ok(Self {
+            segment,
+            offset,
+            timestamp_ns,
+            kind,
+            source_id,
+            frame_uuid,
+            keyframe,
+        })
+    }
+}
+
+/// A record read from the tape.
+#[derive(Debug, Clone)]
+pub struct TapeRecord {
+    pub timestamp_ns: u64,
+    pub topic: Vec<u8>,
+    pub labels: Vec<String>,
+    pub message: Message,
+    pub data: Vec<Vec<u8>>,
+}
+
+fn put_u32(buf: &mut Vec<u8>, v: u32) {
+    buf.extend_from_slice(&v.to_le_bytes());
+}
+
+fn put_u64(buf: &mut Vec<u8>, v: u64) {
+    buf.extend_from_slice(&v.to_le_bytes());
+}
+
+fn put_bytes(buf: &mut Vec<u8>, v: &[u8]) {
+    put_u32(buf, v.len() as u32);
+    buf.extend_from_slice(v);
+}
+
+struct Decoder<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn new(buf: &'a [u8]) -> Self {
+        Self { buf, pos: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos >= self.buf.len()
+    }
+
+    fn take(&mut self, n: usize) -> anyhow::Result<&'a [u8]> {
+        if self.buf.len() - self.pos < n {
+            bail!(
+                "Unexpected end of data: {} bytes requested, {} available",
+                n,
+                self.buf.len() - self.pos
+            );
+        }
+        let res = &self.buf[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(res)
+    }
+
+    fn u8(&mut self) -> anyhow::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> anyhow::Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into()?))
+    }
+
+    fn u64(&mut self) -> anyhow::Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into()?))
+    }
+
+    fn bytes(&mut self) -> anyhow::Result<&'a [u8]> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+}
+
+fn segment_path(path: &Path, segment: u64) -> PathBuf {
+    path.join(format!(
+        "{}{:010}.{}",
+        SEGMENT_FILE_PREFIX, segment, SEGMENT_FILE_EXTENSION
+    ))
+}
+
+fn index_path(path: &Path, segment: u64) -> PathBuf {
+    path.join(format!(
+        "{}{:010}.{}",
+        SEGMENT_FILE_PREFIX, segment, INDEX_FILE_EXTENSION
+    ))
+}
+
+fn list_segments(path: &Path) -> anyhow::Result<Vec<u64>> {
+    let mut segments = Vec::new();
+    for entry in fs::read_dir(path)? {
+        let name = entry?.file_name();
+        let name = name.to_string_lossy();
+        if let Some(n) = name
+            .strip_prefix(SEGMENT_FILE_PREFIX)
+            .and_then(|n| n.strip_suffix(&format!(".{}", SEGMENT_FILE_EXTENSION)))
+        {
+            if let Ok(n) = n.parse::<u64>() {
+                segments.push(n);
+            }
+        }
+    }
+    segments.sort();
+    Ok(segments)
+}
+
+fn now_ns() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos() as u64
+}
+
+/// Reads the record at the current file position, `available` is the number of the segment
+/// bytes starting from the position. Returns `None` when the end of the segment or a torn
+/// record header is reached.
+fn read_record_body<R: Read>(reader: &mut R, available: u64) -> anyhow::Result<Option<Vec<u8>>> {
+    let mut header = [0u8; RECORD_HEADER_LEN as usize];
+    match reader.read_exact(&mut header) {
+        Ok(_) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    let mut d = Decoder::new(&header);
+    let magic = d.u32()?;
+    if magic != RECORD_MAGIC {
+        bail!("Invalid tape record magic {:#x}", magic);
+    }
+    let len = d.u32()? as u64;
+    let crc = d.u32()?;
+    if len > MAX_RECORD_LEN {
+        bail!(
+            "Tape record length {} exceeds the maximum record length {}",
+            len,
+            MAX_RECORD_LEN
+        );
+    }
+    if RECORD_HEADER_LEN + len > available {
+        bail!(
+            "Tape record length {} exceeds the {} bytes left in the segment",
+            len,
+            available.saturating_sub(RECORD_HEADER_LEN)
+        );
+    }
+    let mut body = vec![0u8; len as usize];
+    match reader.read_exact(&mut body) {
+        Ok(_) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    if crate::fast_hash(&body) != crc {
+        bail!("Tape record checksum mismatch");
+    }
+    Ok(Some(body))
+}
+
+fn decode_record(body: &[u8]) -> anyhow::Result<TapeRecord> {
+    let mut d = Decoder::new(body);
+    let timestamp_ns = d.u64()?;
+    let topic = d.bytes()?.to_vec();
+    let labels_count = d.u32()?;
+    let labels = (0..labels_count)
+        .map(|_| Ok(String::from_utf8(d.bytes()?.to_vec())?))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let data_count = d.u32()?;
+    let data = (0..data_count)
+        .map(|_| Ok(d.bytes()?.to_vec()))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let message = deserialize(d.bytes()?)?;
+    if !d.is_empty() {
+        bail!("Unexpected trailing bytes in tape record");
+    }
+    Ok(TapeRecord {
+        timestamp_ns,
+        topic,
+        labels,
+        message,
+        data,
+    })
+}
+
+/// Appends messages to a tape. A new segment is always started when the
+/// writer is created, so records of a previous (possibly crashed) session are
+/// never modified.
+pub struct TapeWriter {
+    path: PathBuf,
+    max_segment_size: u64,
+    segment: u64,
+    segment_size: u64,
+    segment_file: BufWriter<File>,
+    index_file: BufWriter<File>,
+}
+
+impl TapeWriter {
+    pub fn new(path: &Path, max_segment_size: u64) -> anyhow::Result<Self> {
+        if max_segment_size == 0 {
+            bail!("Tape segment size must be greater than 0");
+        }
+        fs::create_dir_all(path)?;
+        let segment = list_segments(path)?.last().map(|s| s + 1).unwrap_or(0);
+        let (segment_file, index_file) = Self::open_segment(path, segment)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            max_segment_size,
+            segment,
+            segment_size: 0,
+            segment_file,
+            index_file,
+        })
+    }
+
+    fn open_segment(
+        path: &Path,
+        segment: u64,
+    ) -> anyhow::Result<(BufWriter<File>, BufWriter<File>)> {
+        info!(
+            target: "savant_rs::tape::writer",
+            "Opening tape segment {} in {}", segment, path.display()
+        );
+        let open = |p: PathBuf| {
+            OpenOptions::new()
+                .create_new(true)
+                .append(true)
+                .open(&p)
+                .with_context(|| format!("Failed to create tape file {}", p.display()))
+        };
+        Ok((
+            BufWriter::new(open(segment_path(path, segment))?),
+            BufWriter::new(open(index_path(path, segment))?),
+        ))
+    }
+
+    fn rotate(&mut self) -> anyhow::Result<()> {
+        self.flush()?;
+        self.segment += 1;
+        let (segment_file, index_file) = Self::open_segment(&self.path, self.segment)?;
+        self.segment_file = segment_file;
+        self.index_file = index_file;
+        self.segment_size = 0;
+        Ok(())
+    }
+
+    pub fn append(
+        &mut self,
+        topic: &[u8],
+        message: &Message,
+        data: &[&[u8]],
+    ) -> anyhow::Result<TapeIndexEntry> {
+        self.append_with_timestamp(now_ns(), topic, message, data)
+    }
+
+    pub fn append_with_timestamp(
+        &mut self,
+        timestamp_ns: u64,
+        topic: &[u8],
+        message: &Message,
+        data: &[&[u8]],
+    ) -> anyhow::Result<TapeIndexEntry> {
+        let mut body = Vec::new();
+        put_u64(&mut body, timestamp_ns);
+        put_bytes(&mut body, topic);
+        let labels = &message.meta().routing_labels;
+        put_u32(&mut body, labels.len() as u32);
+        for l in labels {
+            put_bytes(&mut body, l.as_bytes());
+        }
+        put_u32(&mut body, data.len() as u32);
+        for part in data {
+            put_bytes(&mut body, part);
+        }
+        put_bytes(&mut body, &save_message(message)?);
+        if body.len() as u64 > MAX_RECORD_LEN {
+            bail!(
+                "Tape record length {} exceeds the maximum record length {}",
+                body.len(),
+                MAX_RECORD_LEN
+            );
+        }
+
+        let record_size = RECORD_HEADER_LEN + body.len() as u64;
+        if self.segment_size > 0 && self.segment_size + record_size > self.max_segment_size {
+            self.rotate()?;
+        }
+
+        let entry = TapeIndexEntry::new(
+            self.segment,
+            self.segment_size,
+            timestamp_ns,
+            topic,
+            message,
+        );
+
+        let mut header = Vec::with_capacity(RECORD_HEADER_LEN as usize);
+        put_u32(&mut header, RECORD_MAGIC);
+        put_u32(&mut header, body.len() as u32);
+        put_u32(&mut header, crate::fast_hash(&body));
+        self.segment_file.write_all(&header)?;
+        self.segment_file.write_all(&body)?;
+        self.segment_size += record_size;
+
+        let mut index_record = Vec::new();
+        entry.encode(&mut index_record);
+        self.index_file.write_all(&index_record)?;
+        debug!(
+            target: "savant_rs::tape::writer",
+            "Appended record {:?}", entry
+        );
+        Ok(entry)
+    }
+
+    /// Records a message received by a reader. Results other than
+    /// [`ReaderResult::Message`] are ignored.
+    pub fn record(&mut self, result: &ReaderResult) -> anyhow::Result<Option<TapeIndexEntry>> {
+        match result {
+            ReaderResult::Message {
+                message,
+                topic,
+                data,
+                ..
+            } => {
+                let data = data.iter().map(|d| d.as_slice()).collect::<Vec<_>>();
+                Ok(Some(self.append(topic, message, &data)?))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    pub fn segment(&self) -> u64 {
+        self.segment
+    }
+
+    pub fn flush(&mut self) -> anyhow::Result<()> {
+        self.segment_file.flush()?;
+        self.index_file.flush()?;
+        Ok(())
+    }
+
+    /// Flushes buffers and waits until the data reaches the disk.
+    pub fn sync(&mut self) -> anyhow::Result<()> {
+        self.flush()?;
+        self.segment_file.get_ref().sync_data()?;
+        self.index_file.get_ref().sync_data()?;
+        Ok(())
+    }
+}
+
+impl Drop for TapeWriter {
+    fn drop(&mut self) {
+        if let Err(e) = self.flush() {
+            warn!(
+                target: "savant_rs::tape::writer",
+                "Failed to flush tape {}: {:?}", self.path.display(), e
+            );
+        }
+    }
+}
+
+/// Reads a tape written by [`TapeWriter`]. The index of every segment is
+/// loaded when the reader is created; segments with a missing or damaged
+/// index are rescanned.
+pub struct TapeReader {
+    path: PathBuf,
+    index: Vec<TapeIndexEntry>,
+    sources: HashMap<String, Vec<usize>>,
+    frames: HashMap<u128, usize>,
+    position: usize,
+    /// The open segment, its length and the reader.
+    current: Option<(u64, u64, BufReader<File>)>,
+    corrupt_records: usize,
+}
+
+impl TapeReader {
+    pub fn new(path: &Path) -> anyhow::Result<Self> {
+        if !path.is_dir() {
+            bail!("Tape directory {} does not exist", path.display());
+        }
+        let mut index = Vec::new();
+        let mut corrupt_records = 0;
+        for segment in list_segments(path)? {
+            let (entries, corrupt) = Self::load_segment_index(path, segment)?;
+            index.extend(entries);
+            corrupt_records += corrupt;
+        }
+        let mut sources: HashMap<String, Vec<usize>> = HashMap::new();
+        let mut frames = HashMap::new();
+        for (i, e) in index.iter().enumerate() {
+            sources.entry(e.source_id.clone()).or_default().push(i);
+            if let Some(uuid) = e.frame_uuid {
+                frames.insert(uuid, i);
+            }
+        }
+        Ok(Self {
+            path: path.to_path_buf(),
+            index,
+            sources,
+            frames,
+            position: 0,
+            current: None,
+            corrupt_records,
+        })
+    }
+
+    /// Loads the index of the segment, returns the entries and the number of the corrupt
+    /// records skipped.
+    fn load_segment_index(
+        path: &Path,
+        segment: u64,
+    ) -> anyhow::Result<(Vec<TapeIndexEntry>, usize)> {
+        let segment_len = fs::metadata(segment_path(path, segment))?.len();
+        let index = fs::read(index_path(path, segment))
+            .map_err(anyhow::Error::from)
+            .and_then(|buf| {
+                let mut d = Decoder::new(&buf);
+                let mut entries = Vec::new();
+                while !d.is_empty() {
+                    entries.push(TapeIndexEntry::decode(segment, &mut d)?);
+                }
+                Ok(entries)
+            });
+        let index = index.and_then(|entries| {
+            // the index is valid only when it covers the whole segment
+            let mut f = File::open(segment_path(path, segment))?;
+            let end = match entries.last() {
+                Some(e) => {
+                    f.seek(SeekFrom::Start(e.offset))?;
+                    let body = read_record_body(&mut f, segment_len.saturating_sub(e.offset))?
+                        .ok_or_else(|| anyhow::anyhow!("Indexed record is truncated"))?;
+                    e.offset + RECORD_HEADER_LEN + body.len() as u64
+                }
+                None => 0,
+            };
+            if end != segment_len {
+                bail!("Index covers {} bytes of {}", end, segment_len);
+            }
+            Ok(entries)
+        });
+        match index {
+            Ok(entries) => Ok((entries, 0)),
+            Err(e) => {
+                warn!(
+                    target: "savant_rs::tape::reader",
+                    "Index of tape segment {} in {} is not usable ({:?}), rebuilding it from the segment",
+                    segment, path.display(), e
+                );
+                Self::scan_segment(path, segment)
+            }
+        }
+    }
+
+    fn scan_segment(path: &Path, segment: u64) -> anyhow::Result<(Vec<TapeIndexEntry>, usize)> {
+        let f = File::open(segment_path(path, segment))?;
+        let segment_len = f.metadata()?.len();
+        let mut f = BufReader::new(f);
+        let mut entries = Vec::new();
+        let mut corrupt = 0;
+        let mut offset = 0;
+        loop {
+            let body = match read_record_body(&mut f, segment_len - offset) {
+                Ok(Some(body)) => body,
+                Ok(None) => break,
+                Err(e) => {
+                    warn!(
+                        target: "savant_rs::tape::reader",
+                        "Tape segment {} in {} is damaged at offset {}: {:?}",
+                        segment, path.display(), offset, e
+                    );
+                    break;
+                }
+            };
+            match decode_record(&body) {
+                Ok(record) => entries.push(TapeIndexEntry::new(
+                    segment,
+                    offset,
+                    record.timestamp_ns,
+                    &record.topic,
+                    &record.message,
+                )),
+                Err(e) => {
+                    warn!(
+                        target: "savant_rs::tape::reader",
+                        "Skipping corrupt record of tape segment {} in {} at offset {}: {:?}",
+                        segment, path.display(), offset, e
+                    );
+                    corrupt += 1;
+                }
+            }
+            offset += RECORD_HEADER_LEN + body.len() as u64;
+        }
+        Ok((entries, corrupt))
+    }
+
+    pub fn index(&self) -> &[TapeIndexEntry] {
+        &self.index
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// The number of the records skipped because they could not be decoded when the index
+    /// was rebuilt from the segments.
+    pub fn corrupt_records(&self) -> usize {
+        self.corrupt_records
+    }
+
+    pub fn seek(&mut self, position: usize) -> anyhow::Result<()> {
+        if position > self.index.len() {
+            bail!(
+                "Tape position {} is out of range, the tape has {} records",
+                position,
+                self.index.len()
+            );
+        }
+        self.position = position;
+        Ok(())
+    }
+
+    pub fn source_ids(&self) -> Vec<String> {
+        self.sources.keys().cloned().collect()
+    }
+
+    /// Positions of all records belonging to the source, in tape order.
+    pub fn source_positions(&self, source_id: &str) -> &[usize] {
+        self.sources
+            .get(source_id)
+            .map(|v| v.as_slice())
+            .unwrap_or(&[])
+    }
+
+    pub fn find_frame(&self, uuid: u128) -> Option<usize> {
+        self.frames.get(&uuid).copied()
+    }
+
+    /// Finds the frame with the UUID and returns the position of the closest
+    /// keyframe of the same source at or before it.
+    pub fn find_keyframe(&self, uuid: u128) -> Option<usize> {
+        let position = self.find_frame(uuid)?;
+        let source_id = &self.index[position].source_id;
+        self.source_positions(source_id)
+            .iter()
+            .rev()
+            .filter(|p| **p <= position)
+            .find(|p| self.index[**p].keyframe == Some(true))
+            .copied()
+    }
+
+    pub fn seek_to_keyframe(&mut self, uuid: u128) -> anyhow::Result<bool> {
+        match self.find_keyframe(uuid) {
+            Some(p) => {
+                self.seek(p)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Reads the record at the current position and advances the position.
+    pub fn read(&mut self) -> anyhow::Result<Option<TapeRecord>> {
+        let entry = match self.index.get(self.position) {
+            Some(e) => e,
+            None => return Ok(None),
+        };
+        if !matches!(&self.current, Some((s, _, _)) if *s == entry.segment) {
+            let f = File::open(segment_path(&self.path, entry.segment))?;
+            let segment_len = f.metadata()?.len();
+            self.current = Some((entry.segment, segment_len, BufReader::new(f)));
+        }
+        let (_, segment_len, f) = self.current.as_mut().unwrap();
+        f.seek(SeekFrom::Start(entry.offset))?;
+        let available = segment_len.saturating_sub(entry.offset);
+        let body = read_record_body(f, available)?.ok_or_else(|| {
+            anyhow::anyhow!(
+                "Tape record at segment {} offset {} is truncated",
+                entry.segment,
+                entry.offset
+            )
+        })?;
+        let record = decode_record(&body)?;
+        self.position += 1;
+        Ok(Some(record))
+    }
+}
+
+impl Iterator for TapeReader {
+    type Item = anyhow::Result<TapeRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read().transpose()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TapePacing {
+    /// Records are returned without delays.
+    AsFastAsPossible,
+    /// Records are delayed to reproduce the intervals between receive timestamps.
+    Timestamp,
+    /// Video frames are delayed according to their PTS and time base,
+    /// independently for every source. Other messages are not delayed.
+    FramePts,
+}
+
+/// The outcome of [`TapePlayer::replay`].
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct TapeReplayResult {
+    /// The messages the sink sent successfully.
+    pub sent: usize,
+    /// The messages the sink failed to send, e.g. because of a send timeout.
+    pub failed: usize,
+}
+
+/// Replays a tape with the selected pacing.
+pub struct TapePlayer {
+    reader: TapeReader,
+    pacing: TapePacing,
+    timestamp_anchor: Option<(Instant, u64)>,
+    pts_anchors: HashMap<String, (Instant, i64)>,
+}
+
+impl TapePlayer {
+    pub fn new(reader: TapeReader, pacing: TapePacing) -> Self {
+        Self {
+            reader,
+            pacing,
+            timestamp_anchor: None,
+            pts_anchors: HashMap::new(),
+        }
+    }
+
+    pub fn reader(&self) -> &TapeReader {
+        &self.reader
+    }
+
+    pub fn seek(&mut self, position: usize) -> anyhow::Result<()> {
+        self.reader.seek(position)?;
+        self.reset_pacing();
+        Ok(())
+    }
+
+    pub fn seek_to_keyframe(&mut self, uuid: u128) -> anyhow::Result<bool> {
+        let found = self.reader.seek_to_keyframe(uuid)?;
+        self.reset_pacing();
+        Ok(found)
+    }
+
+    fn reset_pacing(&mut self) {
+        self.timestamp_anchor = None;
+        self.pts_anchors.clear();
+    }
+
+    fn delay(&mut self, record: &TapeRecord) -> Option<Instant> {
+        let now = Instant::now();
+        match self.pacing {
+            TapePacing::AsFastAsPossible => None,
+            TapePacing::Timestamp => match self.timestamp_anchor {
+                Some((start, ts)) if record.timestamp_ns >= ts => {
+                    Some(start + Duration::from_nanos(record.timestamp_ns - ts))
+                }
+                _ => {
+                    self.timestamp_anchor = Some((now, record.timestamp_ns));
+                    None
+                }
+            },
+            TapePacing::FramePts => {
+                let frame = record.message.as_video_frame()?;
+                let (num, den) = frame.get_time_base();
+                let pts = frame.get_pts();
+                let anchor = self.pts_anchors.get(&frame.get_source_id());
+                match anchor {
+                    Some((start, anchor_pts)) if pts >= *anchor_pts && num > 0 && den > 0 => {
+                        let ns =
+                            (pts - anchor_pts) as i128 * num as i128 * 1_000_000_000 / den as i128;
+                        Some(*start + Duration::from_nanos(ns as u64))
+                    }
+                    _ => {
+                        self.pts_anchors.insert(frame.get_source_id(), (now, pts));
+                        None
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the next record when it is due according to the pacing.
+    pub fn next_record(&mut self) -> anyhow::Result<Option<TapeRecord>> {
+        let record = match self.reader.read()? {
+            Some(r) => r,
+            None => return Ok(None),
+        };
+        if let Some(due) = self.delay(&record) {
+            let now = Instant::now();
+            if due > now {
+                std::thread::sleep(due - now);
+            }
+        }
+        Ok(Some(record))
+    }
+
+    /// Sends the rest of the tape to the sink, returns the number of messages sent and
+    /// the number of messages the sink failed to send.
+    pub fn replay(&mut self, sink: &dyn MessageSink) -> anyhow::Result<TapeReplayResult> {
+        let mut result = TapeReplayResult::default();
+        while let Some(record) = self.next_record()? {
+            let topic = String::from_utf8_lossy(&record.topic);
+            let data = record.data.iter().map(|d| d.as_slice()).collect::<Vec<_>>();
+            match sink.send_message(&topic, &record.message, &data)? {
+                res @ (WriterResult::Success { .. } | WriterResult::Ack { .. }) => {
+                    debug!(
+                        target: "savant_rs::tape::player",
+                        "Replayed record to topic {}: {:?}", topic, res
+                    );
+                    result.sent += 1;
+                }
+                res => {
+                    warn!(
+                        target: "savant_rs::tape::player",
+                        "Failed to replay record to topic {}: {:?}", topic, res
+                    );
+                    result.failed += 1;
+                }
+            }
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        index_path, segment_path, TapePacing, TapePlayer, TapeReader, TapeRecordKind,
+        TapeReplayResult, TapeWriter, RECORD_MAGIC,
+    };
+    use crate::message::Message;
+    use crate::primitives::eos::EndOfStream;
+    use crate::primitives::frame_update::VideoFrameUpdate;
+    use crate::test::gen_frame;
+    use crate::transport::inproc::{create_channel, remove_channel, InprocReader, InprocWriter};
+    use crate::transport::zeromq::{ReaderResult, TopicPrefixSpec};
+    use std::fs::OpenOptions;
+    use std::io::{Seek, SeekFrom, Write};
+    use std::path::Path;
+    use std::time::{Duration, Instant};
+
+    fn prepare(path: &str) -> &Path {
+        std::fs::remove_dir_all(path).unwrap_or_default();
+        Path::new(path)
+    }
+
+    fn frame(source_id: &str, pts: i64, keyframe: bool) -> Message {
+        let mut f = gen_frame();
+        f.set_source_id(source_id);
+        f.set_pts(pts);
+        f.set_time_base((1, 100));
+        f.set_keyframe(Some(keyframe));
+        Message::video_frame(&f)
+    }
+
+    #[test]
+    fn test_write_read() -> anyhow::Result<()> {
+        let path = prepare("/tmp/test/tape-write-read");
+        let mut m = frame("cam-1", 0, true);
+        m.set_labels(vec!["label".to_string()]);
+        {
+            let mut writer = TapeWriter::new(path, 1024 * 1024)?;
+            writer.append(b"cam-1", &m, &[b"extra"])?;
+            writer.append(
+                b"cam-1",
+                &Message::video_frame_update(VideoFrameUpdate::default()),
+                &[],
+            )?;
+            writer.append(
+                b"cam-1",
+                &Message::end_of_stream(EndOfStream::new("cam-1".to_string())),
+                &[],
+            )?;
+        }
+        let mut reader = TapeReader::new(path)?;
+        assert_eq!(reader.len(), 3);
+        let kinds = reader.index().iter().map(|e| e.kind).collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            vec![
+                TapeRecordKind::VideoFrame,
+                TapeRecordKind::VideoFrameUpdate,
+                TapeRecordKind::EndOfStream
+            ]
+        );
+        assert_eq!(reader.source_positions("cam-1"), &[0, 1, 2]);
+        let record = reader.read()?.unwrap();
+        assert_eq!(record.topic, b"cam-1");
+        assert_eq!(record.labels, vec!["label".to_string()]);
+        assert_eq!(record.data, vec![b"extra".to_vec()]);
+        assert_eq!(
+            record.message.as_video_frame().unwrap().get_uuid_u128(),
+            m.as_video_frame().unwrap().get_uuid_u128()
+        );
+        assert!(reader.read()?.unwrap().message.is_video_frame_update());
+        assert!(reader.read()?.unwrap().message.is_end_of_stream());
+        assert!(reader.read()?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_segments_and_keyframe_seek() -> anyhow::Result<()> {
+        let path = prepare("/tmp/test/tape-segments");
+        let messages = (0..10)
+            .map(|i| frame("cam-1", i * 10, i % 4 == 0))
+            .collect::<Vec<_>>();
+        {
+            let mut writer = TapeWriter::new(path, 2048)?;
+            for m in &messages {
+                writer.append(b"cam-1", m, &[])?;
+            }
+            assert!(writer.segment() > 0);
+        }
+        let mut reader = TapeReader::new(path)?;
+        assert_eq!(reader.len(), 10);
+        let uuid = messages[6].as_video_frame().unwrap().get_uuid_u128();
+        assert_eq!(reader.find_frame(uuid), Some(6));
+        assert!(reader.seek_to_keyframe(uuid)?);
+        assert_eq!(reader.position(), 4);
+        let records = reader.by_ref().collect::<anyhow::Result<Vec<_>>>()?;
+        assert_eq!(records.len(), 6);
+        assert_eq!(records[0].message.as_video_frame().unwrap().get_pts(), 40);
+        Ok(())
+    }
+
+    #[test]
+    fn test_reopen_starts_new_segment() -> anyhow::Result<()> {
+        let path = prepare("/tmp/test/tape-reopen");
+        {
+            let mut writer = TapeWriter::new(path, 1024 * 1024)?;
+            writer.append(b"cam-1", &frame("cam-1", 0, true), &[])?;
+        }
+        {
+            let mut writer = TapeWriter::new(path, 1024 * 1024)?;
+            assert_eq!(writer.segment(), 1);
+            writer.append(b"cam-1", &frame("cam-1", 1, false), &[])?;
+        }
+        let reader = TapeReader::new(path)?;
+        assert_eq!(reader.len(), 2);
+        assert_eq!(reader.index()[1].segment, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_index_rebuild_and_torn_tail() -> anyhow::Result<()> {
+        let path = prepare("/tmp/test/tape-rebuild");
+        {
+            let mut writer = TapeWriter::new(path, 1024 * 1024)?;
+            writer.append(b"cam-1", &frame("cam-1", 0, true), &[])?;
+            writer.append(b"cam-1", &frame("cam-1", 1, false), &[])?;
+        }
+        std::fs::remove_file(index_path(path, 0))?;
+        let mut f = OpenOptions::new()
+            .append(true)
+            .open(segment_path(path, 0))?;
+        f.write_all(&[0x53, 0x41, 0x56])?;
+        drop(f);
+        let reader = TapeReader::new(path)?;
+        assert_eq!(reader.len(), 2);
+        assert_eq!(reader.index()[0].keyframe, Some(true));
+        Ok(())
+    }
+
+    #[test]
+    fn test_corrupt_record_length() -> anyhow::Result<()> {
+        let path = prepare("/tmp/test/tape-corrupt-length");
+        {
+            let mut writer = TapeWriter::new(path, 1024 * 1024)?;
+            writer.append(b"cam-1", &frame("cam-1", 0, true), &[])?;
+            writer.append(b"cam-1", &frame("cam-1", 1, false), &[])?;
+        }
+        let mut f = OpenOptions::new().write(true).open(segment_path(path, 0))?;
+        f.seek(SeekFrom::Start(4))?;
+        f.write_all(&0xFFFF_FFF0u32.to_le_bytes())?;
+        drop(f);
+
+        let mut reader = TapeReader::new(path)?;
+        assert_eq!(reader.len(), 2);
+        let e = reader.read().unwrap_err();
+        assert!(e.to_string().contains("exceeds"), "{}", e);
+
+        std::fs::remove_file(index_path(path, 0))?;
+        let reader = TapeReader::new(path)?;
+        assert!(reader.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_skip_undecodable_record() -> anyhow::Result<()> {
+        let path = prepare("/tmp/test/tape-undecodable");
+        let second = {
+            let mut writer = TapeWriter::new(path, 1024 * 1024)?;
+            writer.append(b"cam-1", &frame("cam-1", 0, true), &[])?;
+            writer.append(b"cam-1", &frame("cam-1", 1, false), &[])?
+        };
+        // a record with a valid checksum which is not a valid body
+        let body = [1u8, 2, 3, 4];
+        let mut garbage = Vec::new();
+        garbage.extend_from_slice(&RECORD_MAGIC.to_le_bytes());
+        garbage.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        garbage.extend_from_slice(&crate::fast_hash(&body).to_le_bytes());
+        garbage.extend_from_slice(&body);
+        let mut segment = std::fs::read(segment_path(path, 0))?;
+        let offset = second.offset as usize;
+        segment.splice(offset..offset, garbage);
+        std::fs::write(segment_path(path, 0), segment)?;
+        std::fs::remove_file(index_path(path, 0))?;
+
+        let mut reader = TapeReader::new(path)?;
+        assert_eq!(reader.len(), 2);
+        assert_eq!(reader.corrupt_records(), 1);
+        assert_eq!(
+            reader
+                .read()?
+                .unwrap()
+                .message
+                .as_video_frame()
+                .unwrap()
+                .get_pts(),
+            0
+        );
+        assert_eq!(
+            reader
+                .read()?
+                .unwrap()
+                .message
+                .as_video_frame()
+                .unwrap()
+                .get_pts(),
+            1
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_replay_reports_failures() -> anyhow::Result<()> {
+        let path = prepare("/tmp/test/tape-replay-failures");
+        {
+            let mut writer = TapeWriter::new(path, 1024 * 1024)?;
+            for pts in 0..3 {
+                writer.append(b"cam-1", &frame("cam-1", pts, pts == 0), &[])?;
+            }
+        }
+        let uri = "inproc+channel:tape-replay-failures";
+        create_channel(uri, 1)?;
+        let writer = InprocWriter::new(uri, Duration::from_millis(10))?;
+        let mut player = TapePlayer::new(TapeReader::new(path)?, TapePacing::AsFastAsPossible);
+        assert_eq!(
+            player.replay(&writer)?,
+            TapeReplayResult { sent: 1, failed: 2 }
+        );
+        remove_channel(uri)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_timestamp_pacing() -> anyhow::Result<()> {
+        let path = prepare("/tmp/test/tape-timestamp-pacing");
+        {
+            let mut writer = TapeWriter::new(path, 1024 * 1024)?;
+            writer.append_with_timestamp(1_000_000_000, b"cam-1", &frame("cam-1", 0, true), &[])?;
+            writer.append_with_timestamp(
+                1_100_000_000,
+                b"cam-1",
+                &frame("cam-1", 0, false),
+                &[],
+            )?;
+        }
+        let mut player = TapePlayer::new(TapeReader::new(path)?, TapePacing::Timestamp);
+        let start = Instant::now();
+        player.next_record()?.unwrap();
+        player.next_record()?.unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(100));
+        assert!(player.next_record()?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_pts_pacing_and_replay() -> anyhow::Result<()> {
+        let path = prepare("/tmp/test/tape-pts-pacing");
+        {
+            let mut writer = TapeWriter::new(path, 1024 * 1024)?;
+            // 10 ticks of 1/100 s between frames
+            writer.append(b"cam-1", &frame("cam-1", 0, true), &[])?;
+            writer.append(b"cam-1", &frame("cam-1", 10, false), &[])?;
+            writer.append(
+                b"cam-1",
+                &Message::end_of_stream(EndOfStream::new("cam-1".to_string())),
+                &[],
+            )?;
+        }
+        let uri = "inproc+channel:tape-replay";
+        let reader = InprocReader::new(uri, TopicPrefixSpec::none(), Duration::from_millis(10))?;
+        let writer = InprocWriter::new(uri, Duration::from_millis(1000))?;
+        let mut player = TapePlayer::new(TapeReader::new(path)?, TapePacing::FramePts);
+        let start = Instant::now();
+        assert_eq!(
+            player.replay(&writer)?,
+            TapeReplayResult { sent: 3, failed: 0 }
+        );
+        assert!(start.elapsed() >= Duration::from_millis(100));
+        for _ in 0..2 {
+            assert!(matches!(
+                reader.receive()?,
+                ReaderResult::Message { message, topic, .. } if message.is_video_frame() && topic == b"cam-1"
+            ));
+        }
+        assert!(matches!(
+            reader.receive()?,
+            ReaderResult::Message { message, .. } if message.is_end_of_stream()
+        ));
+        remove_channel(uri)?;
+        Ok(())
+    }
+}
@@ -3,24 +3,51 @@ use lazy_static::lazy_static;
 use log::debug;
 use lru::LruCache;
 use std::num::NonZeroUsize;
-
+use std::os::fd::{AsRawFd, RawFd};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::unix::AsyncFd;
+use tokio::io::Interest;
+
+mod async_reader;
+mod async_writer;
+mod compression;
+mod curve;
+mod monitor;
+mod multi_writer;
 mod nonblocking_reader;
 mod nonblocking_writer;
+mod rate_limit;
 pub mod reader;
 mod reader_config;
+mod reliable;
+mod send_operation;
 mod sync_reader;
 mod sync_writer;
+mod topic_filter;
 mod writer;
 mod writer_config;
 
+pub use async_reader::AsyncReader;
+pub use async_writer::AsyncWriter;
+pub(crate) use compression::COMPRESSION_MARKER;
+pub use compression::{Compression, CompressionCodec, ZSTD_DEFAULT_LEVEL};
+use curve::ZapHandler;
+pub use curve::{generate_curve_keypair, load_curve_allowed_clients, load_curve_key, CurveConfig};
+use monitor::SocketMonitor;
+pub use monitor::{get_socket_statuses, SocketEvent, SocketEventKind, SocketHealth, SocketStatus};
+pub use multi_writer::{MultiWriter, MultiWriterConfig, MultiWriterEndpoint, MultiWriterRouting};
 pub use nonblocking_reader::NonBlockingReader;
 pub use nonblocking_writer::{NonBlockingWriter, WriteOperationResult};
+pub use rate_limit::{RateLimit, RateLimitPolicy};
 pub use reader::{Reader, ReaderResult};
 pub use reader_config::{ReaderConfig, ReaderConfigBuilder};
+pub use reliable::ReliableDelivery;
 use std::mem;
 use std::os::unix::fs::PermissionsExt;
 pub use sync_reader::SyncReader;
 pub use sync_writer::SyncWriter;
+pub use topic_filter::TopicFilter;
 pub use writer::{Writer, WriterResult};
 pub use writer_config::{WriterConfig, WriterConfigBuilder};
 use zmq::Context;
@@ -35,6 +62,7 @@ const SEND_TIMEOUT: i32 = 5000;
 const ROUTING_ID_CACHE_SIZE: usize = 512;
 const SOURCE_BLACKLIST_CACHE_SIZE: u64 = 1024;
 const SOURCE_BLACKLIST_CACHE_EXPIRATION: u64 = 10;
+const MAX_DECOMPRESSED_SIZE: usize = 256 * 1024 * 1024;
 
 const CONFIRMATION_MESSAGE: &[u8] = b"OK";
 const IPC_PERMISSIONS: u32 = 0o777;
@@ -83,9 +111,31 @@ pub struct ZmqSocketUri {
     pub source: Option<String>,
     pub bind: Option<bool>,
     pub socket_type: Option<SocketType>,
+    pub curve: Option<CurveConfig>,
+}
+
+fn parse_uri_params(query: &str) -> anyhow::Result<Vec<(String, String)>> {
+    query
+        .split('&')
+        .filter(|p| !p.is_empty())
+        .map(|p| match p.split_once('=') {
+            Some((name, value)) => Ok((name.to_string(), value.to_string())),
+            None => bail!("Invalid socket URI parameter {}", p),
+        })
+        .collect()
 }
 
+/// Parses the socket URI `[<type>+<bind|connect>:]<endpoint>[:<source>][?<params>]`, the
+/// parameters configure CURVE security (see [`CurveConfig::from_uri_params`]).
+///
 pub fn parse_zmq_socket_uri(uri: String) -> anyhow::Result<ZmqSocketUri> {
+    let (uri, curve) = match uri.split_once('?') {
+        Some((uri, query)) => (
+            uri.to_string(),
+            CurveConfig::from_uri_params(&parse_uri_params(query)?)?,
+        ),
+        None => (uri, None),
+    };
     let source;
     let mut socket_type = None;
     let mut bind = None;
@@ -147,6 +197,7 @@ pub fn parse_zmq_socket_uri(uri: String) -> anyhow::Result<ZmqSocketUri> {
             bind,
             socket_type,
             source,
+            curve,
         })
     } else {
         bail!("Invalid ZeroMQ socket URI {}", uri);
@@ -358,6 +409,43 @@ impl<C: MockSocketResponder> Socket<C> {
         }
     }
 
+    fn set_curve(&self, curve: &CurveConfig) -> anyhow::Result<()> {
+        match self {
+            Socket::ZmqSocket(socket) => curve.apply(socket),
+            Socket::MockSocket(_, _) => Ok(()),
+        }
+    }
+
+    /// Starts the ZAP handler on the context when the server allows only listed clients.
+    ///
+    fn start_zap_handler(
+        &self,
+        context: &Context,
+        curve: &CurveConfig,
+    ) -> anyhow::Result<Option<ZapHandler>> {
+        match (self, curve.allowed_clients()) {
+            (Socket::ZmqSocket(_), Some(allowed_clients)) => {
+                Ok(Some(ZapHandler::start(context, allowed_clients)?))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Starts reporting the socket events to the health, the mock socket has no events.
+    ///
+    fn start_monitor(
+        &self,
+        context: &Context,
+        health: &Arc<SocketHealth>,
+    ) -> anyhow::Result<Option<SocketMonitor>> {
+        match self {
+            Socket::ZmqSocket(socket) => {
+                Ok(Some(SocketMonitor::start(context, socket, health.clone())?))
+            }
+            Socket::MockSocket(_, _) => Ok(None),
+        }
+    }
+
     fn bind(&self, endpoint: &str) -> anyhow::Result<()> {
         match self {
             Socket::ZmqSocket(socket) => socket.bind(endpoint).map_err(|e| e.into()),
@@ -378,6 +466,86 @@ impl<C: MockSocketResponder> Socket<C> {
             Socket::MockSocket(data, _) => mem::take(data),
         }
     }
+
+    fn get_fd(&self) -> anyhow::Result<RawFd> {
+        match self {
+            Socket::ZmqSocket(socket) => Ok(socket.get_fd()?),
+            Socket::MockSocket(_, _) => bail!("Mock socket has no file descriptor"),
+        }
+    }
+
+    /// Blocks until the socket has any of the `events`, returns `false` on timeout. The mock
+    /// socket is always writable and readable while it holds data.
+    ///
+    fn poll(&self, events: zmq::PollEvents, timeout: Duration) -> anyhow::Result<bool> {
+        match self {
+            Socket::ZmqSocket(socket) => Ok(socket.poll(events, timeout.as_millis() as i64)? > 0),
+            Socket::MockSocket(data, _) => Ok(events.contains(zmq::POLLOUT) || !data.is_empty()),
+        }
+    }
+
+    fn get_events(&self) -> anyhow::Result<zmq::PollEvents> {
+        match self {
+            Socket::ZmqSocket(socket) => Ok(socket.get_events()?),
+            Socket::MockSocket(_, _) => Ok(zmq::POLLIN | zmq::POLLOUT),
+        }
+    }
+}
+
+struct SocketFd(RawFd);
+
+impl AsRawFd for SocketFd {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0
+    }
+}
+
+/// The registration of the ZeroMQ descriptor of a socket in the shared runtime, created once
+/// per socket and shared by its async waits. The descriptor only signals that the socket state
+/// may have changed and reading the events resets it, so the waits are serialized: concurrent
+/// waiters could consume each other's notifications.
+pub(super) struct SocketReadiness {
+    fd: AsyncFd<SocketFd>,
+    wait_lock: tokio::sync::Mutex<()>,
+}
+
+impl SocketReadiness {
+    fn new(fd: RawFd) -> anyhow::Result<Self> {
+        let _guard = crate::get_or_init_async_runtime().enter();
+        Ok(Self {
+            fd: AsyncFd::with_interest(SocketFd(fd), Interest::READABLE)?,
+            wait_lock: tokio::sync::Mutex::new(()),
+        })
+    }
+
+    /// Waits until the socket has any of the `events`, returns `false` when the timeout
+    /// expires. The events are checked before every wait. The timer is registered in the
+    /// shared runtime, which lets the future be awaited from any executor.
+    pub async fn wait<F>(
+        &self,
+        events: zmq::PollEvents,
+        timeout: Duration,
+        get_events: F,
+    ) -> anyhow::Result<bool>
+    where
+        F: Fn() -> anyhow::Result<zmq::PollEvents>,
+    {
+        let wait = async {
+            let _wait_lock = self.wait_lock.lock().await;
+            loop {
+                if get_events()?.intersects(events) {
+                    return Ok(true);
+                }
+                let mut guard = self.fd.readable().await?;
+                guard.clear_ready();
+            }
+        };
+        let wait = {
+            let _guard = crate::get_or_init_async_runtime().enter();
+            tokio::time::timeout(timeout, wait)
+        };
+        wait.await.unwrap_or(Ok(false))
+    }
 }
 
 fn create_ipc_dirs(endpoint: &str) -> anyhow::Result<()> {
@@ -544,9 +712,14 @@ mod integration_tests {
     use crate::transport::zeromq::reader_config::ReaderConfig;
     use crate::transport::zeromq::writer_config::WriterConfig;
     use crate::transport::zeromq::{
-        NoopResponder, TopicPrefixSpec, WriterResult, ZmqSocketProvider,
+        generate_curve_keypair, Compression, CompressionCodec, CurveConfig, NoopResponder,
+        RateLimit, RateLimitPolicy, ReliableDelivery, TopicPrefixSpec, WriterResult,
+        ZmqSocketProvider,
     };
-    use crate::transport::zeromq::{Reader, Writer};
+    use crate::transport::zeromq::{
+        get_socket_statuses, AsyncReader, AsyncWriter, Reader, SocketEventKind, Writer,
+    };
+    use std::num::NonZeroU32;
     use std::thread;
     use std::time::Duration;
 
@@ -583,7 +756,7 @@ mod integration_tests {
         );
         let res = rx.recv().unwrap()?;
         assert!(
-            matches!(res, ReaderResult::Message {message,topic,routing_id,data}
+            matches!(res, ReaderResult::Message {message,topic,routing_id,data,..}
                 if message.meta.seq_id == m.meta.seq_id && topic == b"test" && routing_id.is_none() && data.is_empty())
         );
         let res = writer.send_eos("test")?;
@@ -592,7 +765,7 @@ mod integration_tests {
         );
         let res = rx.recv().unwrap()?;
         assert!(
-            matches!(res, ReaderResult::Message {message,topic,routing_id,data}
+            matches!(res, ReaderResult::Message {message,topic,routing_id,data,..}
                 if message.is_end_of_stream() && topic == b"test" && routing_id.is_none() && data.is_empty())
         );
         reader_thread.join().unwrap();
@@ -636,7 +809,7 @@ mod integration_tests {
         ));
         let res = rx.recv().unwrap()?;
         assert!(
-            matches!(res, ReaderResult::Message {message,topic,routing_id,data}
+            matches!(res, ReaderResult::Message {message,topic,routing_id,data,..}
                 if message.meta.seq_id == m.meta.seq_id && topic == b"test" && routing_id.is_some() && data.is_empty())
         );
         let res = writer.send_eos("test")?;
@@ -645,7 +818,7 @@ mod integration_tests {
         );
         let res = rx.recv().unwrap()?;
         assert!(
-            matches!(res, ReaderResult::Message {message,topic,routing_id,data} if message.is_end_of_stream() && topic == b"test" && routing_id.is_some() && data.is_empty())
+            matches!(res, ReaderResult::Message {message,topic,routing_id,data,..} if message.is_end_of_stream() && topic == b"test" && routing_id.is_some() && data.is_empty())
         );
         reader_thread.join().unwrap();
         Ok(())
@@ -761,14 +934,14 @@ mod integration_tests {
         assert!(matches!(res, WriterResult::Success { .. }));
         let res = rx.recv().unwrap()?;
         assert!(
-            matches!(res, ReaderResult::Message {message,topic,routing_id,data}
+            matches!(res, ReaderResult::Message {message,topic,routing_id,data,..}
                 if message.meta.seq_id == m.meta.seq_id && topic == b"test" && routing_id.is_none() && data.is_empty())
         );
         let res = writer.send_eos("test")?;
         assert!(matches!(res, WriterResult::Success { .. }));
         let res = rx.recv().unwrap()?;
         assert!(
-            matches!(res, ReaderResult::Message {message,topic,routing_id,data} if message.is_end_of_stream() && topic == b"test" && routing_id.is_none() && data.is_empty())
+            matches!(res, ReaderResult::Message {message,topic,routing_id,data,..} if message.is_end_of_stream() && topic == b"test" && routing_id.is_none() && data.is_empty())
         );
         reader_thread.join().unwrap();
         Ok(())
@@ -792,4 +965,402 @@ mod integration_tests {
         assert!(matches!(res, WriterResult::SendTimeout));
         Ok(())
     }
+
+    fn wait_for(f: impl Fn() -> bool) -> bool {
+        (0..50).any(|_| {
+            thread::sleep(Duration::from_millis(50));
+            f()
+        })
+    }
+
+    #[test]
+    fn test_socket_monitor() -> anyhow::Result<()> {
+        let path = "/tmp/test/socket-monitor";
+        std::fs::remove_dir_all(path).unwrap_or_default();
+
+        let reader = Reader::<NoopResponder, ZmqSocketProvider>::new(
+            &ReaderConfig::new()
+                .url(&format!("router+bind:ipc://{}", path))?
+                .build()?,
+        )?;
+        let writer = Writer::<NoopResponder, ZmqSocketProvider>::new(
+            &WriterConfig::new()
+                .url(&format!("dealer+connect:ipc://{}", path))?
+                .build()?,
+        )?;
+        assert!(wait_for(|| writer.is_connected() && reader.is_connected()));
+        assert!(writer
+            .take_socket_events()
+            .iter()
+            .any(|e| e.kind == SocketEventKind::Connected));
+        assert!(get_socket_statuses()
+            .iter()
+            .any(|s| s.endpoint == format!("ipc://{}", path) && s.role == "writer" && s.connected));
+
+        reader.destroy()?;
+        assert!(wait_for(|| !writer.is_connected()));
+        assert!(writer
+            .take_socket_events()
+            .iter()
+            .any(|e| e.kind == SocketEventKind::Disconnected));
+        Ok(())
+    }
+
+    #[test]
+    fn test_reader_reconnect() -> anyhow::Result<()> {
+        let path = "/tmp/test/reader-reconnect";
+        std::fs::remove_dir_all(path).unwrap_or_default();
+
+        let endpoint = format!("ipc://{}", path);
+        let reader = Reader::<NoopResponder, ZmqSocketProvider>::new(
+            &ReaderConfig::new()
+                .url(&format!("sub+connect:{}", endpoint))?
+                .with_receive_timeout(100)?
+                .with_reconnect_after(NonZeroU32::new(2).unwrap())?
+                .build()?,
+        )?;
+        for _ in 0..2 {
+            assert!(matches!(reader.receive()?, ReaderResult::Timeout));
+        }
+        assert!(reader
+            .take_socket_events()
+            .iter()
+            .any(|e| e.kind == SocketEventKind::Reconnected));
+        let status = get_socket_statuses()
+            .into_iter()
+            .find(|s| s.endpoint == endpoint && s.role == "reader")
+            .unwrap();
+        assert_eq!(status.reconnects, 1);
+        assert!(reader.is_alive());
+        Ok(())
+    }
+
+    #[test]
+    fn test_writer_reconnect() -> anyhow::Result<()> {
+        let path = "/tmp/test/writer-reconnect";
+        std::fs::remove_dir_all(path).unwrap_or_default();
+
+        let mut writer = Writer::<NoopResponder, ZmqSocketProvider>::new(
+            &WriterConfig::new()
+                .url(&format!("dealer+connect:ipc://{}", path))?
+                .with_send_timeout(100)?
+                .with_send_retries(0)?
+                .with_send_hwm(1)?
+                .with_reconnect_after(NonZeroU32::new(1).unwrap())?
+                .build()?,
+        )?;
+        let m = Message::video_frame(&gen_frame());
+        // the messages are queued until the high-water mark is reached
+        let timed_out = (0..10).any(|_| {
+            matches!(
+                writer.send_message("test", &m, &[]),
+                Ok(WriterResult::SendTimeout)
+            )
+        });
+        assert!(timed_out);
+        assert!(writer
+            .take_socket_events()
+            .iter()
+            .any(|e| e.kind == SocketEventKind::Reconnected));
+        assert!(writer.is_started());
+        Ok(())
+    }
+
+    #[test]
+    fn test_curve_dealer_router() -> anyhow::Result<()> {
+        if zmq::has("curve") != Some(true) {
+            return Ok(());
+        }
+        let endpoint = "tcp://127.0.0.1:6011";
+        let (server_public, server_secret) = generate_curve_keypair()?;
+        let (client_public, client_secret) = generate_curve_keypair()?;
+        let (other_public, other_secret) = generate_curve_keypair()?;
+
+        let reader = Reader::<NoopResponder, ZmqSocketProvider>::new(
+            &ReaderConfig::new()
+                .url(&format!("router+bind:{}", endpoint))?
+                .with_receive_timeout(500)?
+                .with_curve(CurveConfig::server(
+                    &server_secret,
+                    Some(vec![client_public.clone()]),
+                )?)?
+                .build()?,
+        )?;
+        let writer = |public: &str, secret: &str| {
+            Writer::<NoopResponder, ZmqSocketProvider>::new(
+                &WriterConfig::new()
+                    .url(&format!("dealer+connect:{}", endpoint))?
+                    .with_send_timeout(500)?
+                    .with_send_retries(1)?
+                    .with_curve(CurveConfig::client(public, secret, &server_public)?)?
+                    .build()?,
+            )
+        };
+        let m = Message::video_frame(&gen_frame());
+
+        // the client key is not in the allowed list
+        let mut rejected = writer(&other_public, &other_secret)?;
+        let _ = rejected.send_message("test", &m, &[])?;
+        assert!(matches!(reader.receive()?, ReaderResult::Timeout));
+        rejected.destroy()?;
+
+        let mut accepted = writer(&client_public, &client_secret)?;
+        let res = accepted.send_message("test", &m, &[])?;
+        assert!(matches!(res, WriterResult::Success { .. }));
+        assert!(
+            matches!(reader.receive()?, ReaderResult::Message { message, topic, .. }
+                if message.meta.seq_id == m.meta.seq_id && topic == b"test")
+        );
+        reader.destroy()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_async_dealer_router() -> anyhow::Result<()> {
+        let path = "/tmp/test/async-dealer-router";
+        std::fs::remove_dir_all(path).unwrap_or_default();
+
+        let reader = AsyncReader::new(
+            &ReaderConfig::new()
+                .url(&format!("router+bind:ipc://{}", path))?
+                .build()?,
+        )?;
+        let writer = AsyncWriter::new(
+            &WriterConfig::new()
+                .url(&format!("dealer+connect:ipc://{}", path))?
+                .build()?,
+        )?;
+
+        crate::get_or_init_async_runtime().block_on(async {
+            let m = Message::video_frame(&gen_frame());
+            let res = writer.send_message("test", &m, &[b"abc"]).await?;
+            assert!(matches!(res, WriterResult::Success { .. }));
+            let res = reader.receive().await?;
+            assert!(
+                matches!(res, ReaderResult::Message { message, topic, data, .. }
+                    if message.meta.seq_id == m.meta.seq_id && topic == b"test" && data == vec![b"abc".to_vec()])
+            );
+
+            let (eos, res) = tokio::join!(writer.send_eos("test"), reader.receive());
+            assert!(matches!(eos?, WriterResult::Ack { .. }));
+            assert!(
+                matches!(res?, ReaderResult::Message { message, .. } if message.is_end_of_stream())
+            );
+            writer.shutdown().await?;
+            reader.shutdown().await
+        })
+    }
+
+    #[test]
+    fn test_async_concurrent_waits() -> anyhow::Result<()> {
+        let path = "/tmp/test/async-concurrent-waits";
+        std::fs::remove_dir_all(path).unwrap_or_default();
+
+        let reader = AsyncReader::new(
+            &ReaderConfig::new()
+                .url(&format!("router+bind:ipc://{}", path))?
+                .build()?,
+        )?;
+        let writer = AsyncWriter::new(
+            &WriterConfig::new()
+                .url(&format!("dealer+connect:ipc://{}", path))?
+                .with_reliable_delivery(ReliableDelivery {
+                    window_size: 4,
+                    retransmit_timeout: Duration::from_millis(200),
+                    max_retransmits: 3,
+                })?
+                .build()?,
+        )?;
+
+        crate::get_or_init_async_runtime().block_on(async {
+            // the clones and the flush wait on the same sockets at the same time
+            let other = reader.clone();
+            let (m1, m2) = (
+                Message::video_frame(&gen_frame()),
+                Message::video_frame(&gen_frame()),
+            );
+            let (first, second, sent, flushed) = tokio::join!(
+                reader.receive(),
+                other.receive(),
+                async {
+                    writer.send_message("test", &m1, &[]).await?;
+                    writer.send_message("test", &m2, &[]).await
+                },
+                async {
+                    tokio::time::sleep(Duration::from_millis(5)).await;
+                    writer.flush().await
+                }
+            );
+            flushed?;
+            assert!(matches!(sent?, WriterResult::Success { .. }));
+            let mut seq_ids = [first?, second?].map(|res| match res {
+                ReaderResult::Message { message, .. } => message.meta.seq_id,
+                _ => panic!("Message is expected"),
+            });
+            seq_ids.sort();
+            assert_eq!(seq_ids, [m1.meta.seq_id, m2.meta.seq_id]);
+            writer.shutdown().await?;
+            reader.shutdown().await
+        })
+    }
+
+    #[test]
+    fn test_async_rate_limit_does_not_block_runtime() -> anyhow::Result<()> {
+        let path = "/tmp/test/async-rate-limit";
+        std::fs::remove_dir_all(path).unwrap_or_default();
+
+        let writer = AsyncWriter::new(
+            &WriterConfig::new()
+                .url(&format!("pub+bind:ipc://{}", path))?
+                .with_rate_limit(RateLimit {
+                    frames_per_second: Some(2.0),
+                    bytes_per_second: None,
+                    policy: RateLimitPolicy::Block,
+                })?
+                .build()?,
+        )?;
+
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()?;
+        rt.block_on(async {
+            let start = std::time::Instant::now();
+            let ticker = async {
+                let mut ticks = 0;
+                while start.elapsed() < Duration::from_millis(400) {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                    ticks += 1;
+                }
+                ticks
+            };
+            let sender = async {
+                for _ in 0..3 {
+                    let m = Message::video_frame(&gen_frame());
+                    let res = writer.send_message("test", &m, &[]).await?;
+                    assert!(matches!(res, WriterResult::Success { .. }));
+                }
+                Ok::<_, anyhow::Error>(())
+            };
+            let (ticks, sent) = tokio::join!(ticker, sender);
+            sent?;
+            assert!(start.elapsed() >= Duration::from_millis(400));
+            assert!(ticks > 10);
+            writer.shutdown().await
+        })
+    }
+
+    #[test]
+    fn test_async_req_rep_cancel() -> anyhow::Result<()> {
+        let path = "/tmp/test/async-req-rep";
+        std::fs::remove_dir_all(path).unwrap_or_default();
+
+        let reader = AsyncReader::new(
+            &ReaderConfig::new()
+                .url(&format!("rep+bind:ipc://{}", path))?
+                .with_receive_timeout(100)?
+                .build()?,
+        )?;
+        let writer = AsyncWriter::new(
+            &WriterConfig::new()
+                .url(&format!("req+connect:ipc://{}", path))?
+                .build()?,
+        )?;
+
+        // the runtime has neither IO nor threads of its own, the sockets are driven by the
+        // shared one
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()?;
+        runtime.block_on(async {
+            assert!(matches!(reader.receive().await?, ReaderResult::Timeout));
+            let cancelled = tokio::time::timeout(Duration::from_millis(10), reader.receive()).await;
+            assert!(cancelled.is_err());
+
+            let m = Message::video_frame(&gen_frame());
+            let (sent, received) =
+                tokio::join!(writer.send_message("test", &m, &[]), reader.receive());
+            assert!(matches!(sent?, WriterResult::Ack { .. }));
+            assert!(
+                matches!(received?, ReaderResult::Message { message, .. } if message.meta.seq_id == m.meta.seq_id)
+            );
+            writer.shutdown().await?;
+            reader.shutdown().await
+        })
+    }
+
+    #[test]
+    fn test_compressed_dealer_router() -> anyhow::Result<()> {
+        let path = "/tmp/test/compressed-dealer-router";
+        std::fs::remove_dir_all(path).unwrap_or_default();
+
+        let reader = Reader::<NoopResponder, ZmqSocketProvider>::new(
+            &ReaderConfig::new()
+                .url(&format!("router+bind:ipc://{}", path))?
+                .build()?,
+        )?;
+        let mut writer = Writer::<NoopResponder, ZmqSocketProvider>::new(
+            &WriterConfig::new()
+                .url(&format!("dealer+connect:ipc://{}", path))?
+                .with_compression(Compression::new(CompressionCodec::Lz4))?
+                .build()?,
+        )?;
+
+        let m = Message::video_frame(&gen_frame());
+        let data = vec![7u8; 65536];
+        let res = writer.send_message("test", &m, &[&data])?;
+        assert!(matches!(res, WriterResult::Success { .. }));
+        let res = reader.receive()?;
+        assert!(
+            matches!(res, ReaderResult::Message { message, topic, data: parts, .. }
+                if message.meta.seq_id == m.meta.seq_id && topic == b"test" && parts == vec![data])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_reliable_dealer_router() -> anyhow::Result<()> {
+        let path = "/tmp/test/reliable-dealer-router";
+        std::fs::remove_dir_all(path).unwrap_or_default();
+
+        let reader = Reader::<NoopResponder, ZmqSocketProvider>::new(
+            &ReaderConfig::new()
+                .url(&format!("router+bind:ipc://{}", path))?
+                .build()?,
+        )?;
+        let mut writer = Writer::<NoopResponder, ZmqSocketProvider>::new(
+            &WriterConfig::new()
+                .url(&format!("dealer+connect:ipc://{}", path))?
+                .with_reliable_delivery(ReliableDelivery {
+                    window_size: 4,
+                    retransmit_timeout: Duration::from_millis(200),
+                    max_retransmits: 3,
+                })?
+                .build()?,
+        )?;
+
+        let m1 = Message::video_frame(&gen_frame());
+        let res = writer.send_message("test", &m1, &[])?;
+        assert!(matches!(res, WriterResult::Success { .. }));
+        // the first message is not acknowledged in time and is sent again
+        thread::sleep(Duration::from_millis(300));
+        let m2 = Message::video_frame(&gen_frame());
+        let res = writer.send_message("test", &m2, &[])?;
+        assert!(matches!(res, WriterResult::Success { .. }));
+
+        let res = reader.receive()?;
+        assert!(
+            matches!(res, ReaderResult::Message { message, .. } if message.meta.seq_id == m1.meta.seq_id)
+        );
+        let res = reader.receive()?;
+        assert!(matches!(res, ReaderResult::Duplicate { seq_id, .. } if seq_id == m1.meta.seq_id));
+        let res = reader.receive()?;
+        assert!(
+            matches!(res, ReaderResult::Message { message, .. } if message.meta.seq_id == m2.meta.seq_id)
+        );
+
+        writer.flush()?;
+        assert_eq!(writer.get_unacknowledged_count(), 0);
+        assert_eq!(writer.get_lost_count(), 0);
+        Ok(())
+    }
 }
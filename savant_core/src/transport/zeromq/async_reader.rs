@@ -0,0 +1,57 @@
+use crate::transport::zeromq::reader::ReaderResult;
+use crate::transport::zeromq::{ReaderConfig, SyncReader};
+use std::time::Duration;
+
+/// Reader for async code. The socket is polled without blocking and the readiness is awaited
+/// on its ZeroMQ descriptor, so the future can be awaited from any executor and no thread is
+/// occupied while waiting.
+///
+/// The future is cancel safe: a message is taken from the socket only when it is returned.
+#[derive(Clone)]
+pub struct AsyncReader {
+    reader: SyncReader,
+    receive_timeout: Duration,
+}
+
+impl AsyncReader {
+    pub fn new(config: &ReaderConfig) -> anyhow::Result<Self> {
+        Ok(Self {
+            reader: SyncReader::new(config)?,
+            receive_timeout: Duration::from_millis(*config.receive_timeout() as u64),
+        })
+    }
+
+    /// Waits for a message up to the receive timeout of the configuration.
+    ///
+    pub async fn receive(&self) -> anyhow::Result<ReaderResult> {
+        let reader = &self.reader.0;
+        loop {
+            if let Some(res) = reader.try_receive()? {
+                return Ok(res);
+            }
+            let ready = reader
+                .readiness()?
+                .wait(zmq::POLLIN, self.receive_timeout, || reader.get_events())
+                .await?;
+            if !ready {
+                return reader.on_receive_timeout();
+            }
+        }
+    }
+
+    pub fn is_started(&self) -> bool {
+        self.reader.is_started()
+    }
+
+    pub async fn shutdown(&self) -> anyhow::Result<()> {
+        self.reader.shutdown()
+    }
+
+    pub fn blacklist_source(&self, source_id: &[u8]) {
+        self.reader.blacklist_source(source_id);
+    }
+
+    pub fn is_blacklisted(&self, source_id: &[u8]) -> bool {
+        self.reader.is_blacklisted(source_id)
+    }
+}
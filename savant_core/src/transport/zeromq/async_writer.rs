@@ -0,0 +1,142 @@
+use crate::message::Message;
+use crate::primitives::eos::EndOfStream;
+use crate::protobuf::serialize;
+use crate::transport::zeromq::rate_limit::Admission;
+use crate::transport::zeromq::send_operation::{SendOperation, SendStep};
+use crate::transport::zeromq::{
+    NoopResponder, SyncWriter, Writer, WriterConfig, WriterResult, ZmqSocketProvider,
+};
+use parking_lot::MutexGuard;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Writer for async code. The socket is used without blocking and the readiness is awaited
+/// on its ZeroMQ descriptor, so the futures can be awaited from any executor and no thread
+/// is occupied while waiting. Concurrent sends are serialized.
+///
+/// The send is abandoned when the future is dropped, for Req sockets a reply left unread
+/// breaks the next send.
+#[derive(Clone)]
+pub struct AsyncWriter {
+    writer: SyncWriter,
+    config: WriterConfig,
+    send_lock: Arc<tokio::sync::Mutex<()>>,
+}
+
+fn timeout(millis: i32) -> Duration {
+    Duration::from_millis(millis.max(0) as u64)
+}
+
+impl AsyncWriter {
+    pub fn new(config: &WriterConfig) -> anyhow::Result<Self> {
+        Ok(Self {
+            writer: SyncWriter::new(config)?,
+            config: config.clone(),
+            send_lock: Arc::new(tokio::sync::Mutex::new(())),
+        })
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Writer<NoopResponder, ZmqSocketProvider>> {
+        self.writer.0.lock()
+    }
+
+    /// Waits until the socket has any of the `events`, returns `false` on timeout.
+    ///
+    async fn wait(&self, events: zmq::PollEvents, timeout: Duration) -> anyhow::Result<bool> {
+        let readiness = self.lock().readiness()?;
+        readiness
+            .wait(events, timeout, || self.lock().get_events())
+            .await
+    }
+
+    pub async fn send_eos(&self, topic: &str) -> anyhow::Result<WriterResult> {
+        let m = Message::end_of_stream(EndOfStream::new(topic.to_string()));
+        self.send(topic.as_bytes(), &m, &[]).await
+    }
+
+    pub async fn send_message(
+        &self,
+        topic: &str,
+        message: &Message,
+        data: &[&[u8]],
+    ) -> anyhow::Result<WriterResult> {
+        self.send(topic.as_bytes(), message, data).await
+    }
+
+    async fn send(
+        &self,
+        topic: &[u8],
+        m: &Message,
+        extra_parts: &[&[u8]],
+    ) -> anyhow::Result<WriterResult> {
+        let _send_lock = self.send_lock.lock().await;
+        let serialized_message = serialize(m)?;
+        let admission = self
+            .lock()
+            .apply_rate_limit(m, &serialized_message, extra_parts);
+        match admission {
+            Admission::Send => {}
+            Admission::Drop(dropped) => return Ok(WriterResult::RateLimited { dropped }),
+            Admission::Wait(wait) => {
+                let sleep = {
+                    let _guard = crate::get_or_init_async_runtime().enter();
+                    tokio::time::sleep(wait)
+                };
+                sleep.await;
+            }
+        }
+        let res = self
+            .send_once(topic, m, serialized_message, extra_parts)
+            .await?;
+        self.lock().register_result(&res)?;
+        Ok(res)
+    }
+
+    async fn send_once(
+        &self,
+        topic: &[u8],
+        m: &Message,
+        serialized_message: Vec<u8>,
+        extra_parts: &[&[u8]],
+    ) -> anyhow::Result<WriterResult> {
+        let mut operation =
+            SendOperation::new(&self.lock(), topic, m, serialized_message, extra_parts)?;
+        loop {
+            let step = operation.step(&mut self.lock())?;
+            match step {
+                SendStep::Wait(events, timeout) => {
+                    if !self.wait(events, timeout).await? {
+                        operation.timed_out();
+                    }
+                }
+                SendStep::Done(res) => return Ok(res),
+            }
+        }
+    }
+
+    /// Waits until the messages sent in the reliable mode are acknowledged or lost.
+    ///
+    pub async fn flush(&self) -> anyhow::Result<()> {
+        let Some(deadline) = self.lock().flush_deadline() else {
+            return Ok(());
+        };
+        let receive_timeout = timeout(*self.config.receive_timeout());
+        while !self.lock().try_flush(deadline)? {
+            let wait = receive_timeout.min(deadline.saturating_duration_since(Instant::now()));
+            self.wait(zmq::POLLIN, wait).await?;
+        }
+        Ok(())
+    }
+
+    pub fn is_started(&self) -> bool {
+        self.writer.is_started()
+    }
+
+    pub async fn shutdown(&self) -> anyhow::Result<()> {
+        let _send_lock = self.send_lock.lock().await;
+        if self.is_started() {
+            self.flush().await?;
+        }
+        self.writer.shutdown()
+    }
+}
@@ -0,0 +1,241 @@
+use anyhow::{anyhow, bail};
+use std::borrow::Cow;
+use std::io::Read;
+
+/// The first byte of a compressed message part. A protobuf message never starts with it
+/// (wire type 7 is invalid), so the peers without compression support fail to decode
+/// the message instead of misinterpreting it, [`crate::protobuf::deserialize`] reports
+/// such a message with [`crate::protobuf::Error::CompressedMessage`].
+///
+pub(crate) const COMPRESSION_MARKER: u8 = 0xFF;
+const COMPRESSION_HEADER_SIZE: usize = 3;
+const FLAG_MESSAGE: u8 = 0x01;
+const FLAG_EXTRA_PARTS: u8 = 0x02;
+
+const CODEC_ZSTD: u8 = 1;
+const CODEC_LZ4: u8 = 2;
+const LZ4_SIZE_PREFIX: usize = 4;
+
+pub const ZSTD_DEFAULT_LEVEL: i32 = 3;
+
+pub(super) type EncodedParts<'a> = (Vec<u8>, Vec<Cow<'a, [u8]>>);
+type DecodedParts<'a> = (Cow<'a, [u8]>, Cow<'a, [Vec<u8>]>);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CompressionCodec {
+    /// Zstandard with the compression level.
+    Zstd(i32),
+    Lz4,
+}
+
+impl CompressionCodec {
+    fn id(&self) -> u8 {
+        match self {
+            CompressionCodec::Zstd(_) => CODEC_ZSTD,
+            CompressionCodec::Lz4 => CODEC_LZ4,
+        }
+    }
+
+    fn compress(&self, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+        Ok(match self {
+            CompressionCodec::Zstd(level) => zstd::encode_all(data, *level)?,
+            CompressionCodec::Lz4 => lz4_flex::compress_prepend_size(data),
+        })
+    }
+}
+
+/// Decompresses the part, failing before the allocation when the result exceeds `limit`
+/// bytes.
+///
+fn decompress(codec: u8, data: &[u8], limit: usize) -> anyhow::Result<Vec<u8>> {
+    let decompressed = match codec {
+        CODEC_ZSTD => {
+            let mut decompressed = Vec::new();
+            zstd::stream::read::Decoder::with_buffer(data)?
+                .take(limit as u64 + 1)
+                .read_to_end(&mut decompressed)?;
+            decompressed
+        }
+        CODEC_LZ4 => {
+            let Some(size) = data.first_chunk::<LZ4_SIZE_PREFIX>() else {
+                bail!("Compressed part is truncated");
+            };
+            let size = u32::from_le_bytes(*size) as usize;
+            if size > limit {
+                bail!(
+                    "Decompressed size {} exceeds the limit of {} bytes",
+                    size,
+                    limit
+                );
+            }
+            lz4_flex::decompress_size_prepended(data)?
+        }
+        _ => bail!("Unsupported compression codec {}", codec),
+    };
+    if decompressed.len() > limit {
+        bail!("Decompressed size exceeds the limit of {} bytes", limit);
+    }
+    Ok(decompressed)
+}
+
+/// Message compression of the writer, the reader decompresses messages automatically.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Compression {
+    pub codec: CompressionCodec,
+    /// Compress the serialized message.
+    pub message: bool,
+    /// Compress the extra data parts.
+    pub extra_parts: bool,
+}
+
+impl Compression {
+    pub fn new(codec: CompressionCodec) -> Self {
+        Self {
+            codec,
+            message: true,
+            extra_parts: true,
+        }
+    }
+
+    /// Encodes the serialized message and the extra parts, the message part gets the
+    /// header with the codec and the compressed parts.
+    ///
+    pub(super) fn compress<'a>(
+        &self,
+        message: &[u8],
+        extra_parts: &[&'a [u8]],
+    ) -> anyhow::Result<EncodedParts<'a>> {
+        let mut flags = 0;
+        if self.message {
+            flags |= FLAG_MESSAGE;
+        }
+        if self.extra_parts {
+            flags |= FLAG_EXTRA_PARTS;
+        }
+        let mut encoded = vec![COMPRESSION_MARKER, self.codec.id(), flags];
+        if self.message {
+            encoded.extend(self.codec.compress(message)?);
+        } else {
+            encoded.extend_from_slice(message);
+        }
+        let extra_parts = extra_parts
+            .iter()
+            .map(|p| {
+                if self.extra_parts {
+                    self.codec.compress(p).map(Cow::Owned)
+                } else {
+                    Ok(Cow::Borrowed(*p))
+                }
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok((encoded, extra_parts))
+    }
+}
+
+/// Decodes the message part and the extra parts, the parts of uncompressed messages are
+/// returned as is. The total size of the decompressed parts is limited by `max_size`.
+///
+pub(super) fn decompress_parts<'a>(
+    message: &'a [u8],
+    extra_parts: &'a [Vec<u8>],
+    max_size: usize,
+) -> anyhow::Result<DecodedParts<'a>> {
+    if message.first() != Some(&COMPRESSION_MARKER) {
+        return Ok((Cow::Borrowed(message), Cow::Borrowed(extra_parts)));
+    }
+    if message.len() < COMPRESSION_HEADER_SIZE {
+        bail!("Compressed message header is truncated");
+    }
+    let (codec, flags) = (message[1], message[2]);
+    let payload = &message[COMPRESSION_HEADER_SIZE..];
+    let message = if flags & FLAG_MESSAGE != 0 {
+        Cow::Owned(decompress(codec, payload, max_size)?)
+    } else {
+        Cow::Borrowed(payload)
+    };
+    let extra_parts = if flags & FLAG_EXTRA_PARTS != 0 {
+        let mut remaining = max_size.saturating_sub(message.len());
+        Cow::Owned(
+            extra_parts
+                .iter()
+                .map(|p| {
+                    let part = decompress(codec, p, remaining)?;
+                    remaining -= part.len();
+                    Ok(part)
+                })
+                .collect::<anyhow::Result<Vec<_>>>()
+                .map_err(|e| anyhow!("Failed to decompress extra part: {}", e))?,
+        )
+    } else {
+        Cow::Borrowed(extra_parts)
+    };
+    Ok((message, extra_parts))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decompress_parts, Compression, CompressionCodec, ZSTD_DEFAULT_LEVEL};
+
+    const LIMIT: usize = 1024 * 1024;
+
+    #[test]
+    fn test_roundtrip() -> anyhow::Result<()> {
+        let message = vec![0x0a; 4096];
+        let extra = vec![0x1; 10000];
+        for codec in [
+            CompressionCodec::Zstd(ZSTD_DEFAULT_LEVEL),
+            CompressionCodec::Lz4,
+        ] {
+            for (compress_message, compress_extra) in [(true, true), (false, true), (true, false)] {
+                let compression = Compression {
+                    codec,
+                    message: compress_message,
+                    extra_parts: compress_extra,
+                };
+                let (encoded, parts) = compression.compress(&message, &[&extra])?;
+                let parts = parts.into_iter().map(|p| p.to_vec()).collect::<Vec<_>>();
+                let (decoded, decoded_parts) = decompress_parts(&encoded, &parts, LIMIT)?;
+                assert_eq!(decoded.as_ref(), message.as_slice());
+                assert_eq!(decoded_parts.as_ref(), std::slice::from_ref(&extra));
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_uncompressed_passthrough() -> anyhow::Result<()> {
+        let (decoded, parts) = decompress_parts(b"\x0a\x01", &[], LIMIT)?;
+        assert_eq!(decoded.as_ref(), b"\x0a\x01");
+        assert!(parts.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_codec() {
+        assert!(decompress_parts(&[0xFF, 42, 1, 0, 0], &[], LIMIT).is_err());
+        assert!(decompress_parts(&[0xFF, 1], &[], LIMIT).is_err());
+    }
+
+    #[test]
+    fn test_size_limit() -> anyhow::Result<()> {
+        let message = vec![0x0a; 4096];
+        let extra = vec![0x1; 10000];
+        for codec in [
+            CompressionCodec::Zstd(ZSTD_DEFAULT_LEVEL),
+            CompressionCodec::Lz4,
+        ] {
+            let (encoded, parts) = Compression::new(codec).compress(&message, &[&extra])?;
+            let parts = parts.into_iter().map(|p| p.to_vec()).collect::<Vec<_>>();
+            assert!(decompress_parts(&encoded, &parts, 4096 + 10000).is_ok());
+            assert!(decompress_parts(&encoded, &[], 4095).is_err());
+            assert!(decompress_parts(&encoded, &parts, 4096 + 9999).is_err());
+        }
+
+        // the forged lz4 size prefix is rejected before the allocation
+        let forged = [0xFF, 2, 1, 0xFF, 0xFF, 0xFF, 0xFF, 0];
+        let e = decompress_parts(&forged, &[], LIMIT).unwrap_err();
+        assert!(e.to_string().contains("exceeds the limit"));
+        Ok(())
+    }
+}
@@ -0,0 +1,423 @@
+use anyhow::{anyhow, bail};
+use hashbrown::{HashMap, HashSet};
+use log::{debug, warn};
+use std::fmt;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use zmq::Context;
+
+const ZAP_ENDPOINT: &str = "inproc://zeromq.zap.01";
+const ZAP_VERSION: &[u8] = b"1.0";
+const ZAP_POLL_TIMEOUT: i32 = 100;
+const CURVE_KEY_LENGTH: usize = 32;
+
+/// CURVE security of the socket, the keys are Z85-encoded. The secret key is redacted from
+/// the debug output, which ends up in the logs and the reprs of the configs.
+///
+#[derive(Clone, PartialEq)]
+pub enum CurveConfig {
+    /// The socket authenticates clients, when `allowed_clients` is set only the clients
+    /// with the listed public keys are accepted.
+    Server {
+        secret_key: String,
+        allowed_clients: Option<Vec<String>>,
+    },
+    Client {
+        public_key: String,
+        secret_key: String,
+        server_key: String,
+    },
+}
+
+const REDACTED: &str = "<redacted>";
+
+impl fmt::Debug for CurveConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CurveConfig::Server {
+                allowed_clients, ..
+            } => f
+                .debug_struct("Server")
+                .field("secret_key", &REDACTED)
+                .field("allowed_clients", allowed_clients)
+                .finish(),
+            CurveConfig::Client {
+                public_key,
+                server_key,
+                ..
+            } => f
+                .debug_struct("Client")
+                .field("public_key", public_key)
+                .field("secret_key", &REDACTED)
+                .field("server_key", server_key)
+                .finish(),
+        }
+    }
+}
+
+fn check_key(key: &str) -> anyhow::Result<Vec<u8>> {
+    let decoded = zmq::z85_decode(key).map_err(|e| anyhow!("Invalid CURVE key: {:?}", e))?;
+    if decoded.len() != CURVE_KEY_LENGTH {
+        bail!(
+            "Invalid CURVE key: {} bytes decoded, {} expected",
+            decoded.len(),
+            CURVE_KEY_LENGTH
+        );
+    }
+    Ok(decoded)
+}
+
+/// Loads the Z85-encoded key from the file, which contains either the key alone or is a
+/// ZeroMQ certificate with `public-key = "..."` and `secret-key = "..."` entries.
+///
+pub fn load_curve_key(path: &Path, secret: bool) -> anyhow::Result<String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| anyhow!("Failed to read CURVE key file {}: {}", path.display(), e))?;
+    let entry = if secret { "secret-key" } else { "public-key" };
+    let key = if content.contains("public-key") || content.contains("secret-key") {
+        content
+            .lines()
+            .filter_map(|l| l.trim().split_once('='))
+            .find(|(name, _)| name.trim() == entry)
+            .map(|(_, value)| value.trim().trim_matches('"').to_string())
+            .ok_or_else(|| anyhow!("No {} in CURVE certificate {}", entry, path.display()))?
+    } else {
+        content.trim().to_string()
+    };
+    check_key(&key)?;
+    Ok(key)
+}
+
+/// Loads the public keys of the allowed clients, one key per line, empty lines and lines
+/// starting with `#` are skipped.
+///
+pub fn load_curve_allowed_clients(path: &Path) -> anyhow::Result<Vec<String>> {
+    let content = std::fs::read_to_string(path).map_err(|e| {
+        anyhow!(
+            "Failed to read CURVE allowed clients file {}: {}",
+            path.display(),
+            e
+        )
+    })?;
+    let keys = content
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(String::from)
+        .collect::<Vec<_>>();
+    for key in &keys {
+        check_key(key)?;
+    }
+    Ok(keys)
+}
+
+/// Generates a new CURVE keypair.
+///
+/// Returns the Z85-encoded public and secret keys.
+///
+pub fn generate_curve_keypair() -> anyhow::Result<(String, String)> {
+    let pair = zmq::CurveKeyPair::new()?;
+    Ok((
+        zmq::z85_encode(&pair.public_key).map_err(|e| anyhow!("{:?}", e))?,
+        zmq::z85_encode(&pair.secret_key).map_err(|e| anyhow!("{:?}", e))?,
+    ))
+}
+
+impl CurveConfig {
+    pub fn server(secret_key: &str, allowed_clients: Option<Vec<String>>) -> anyhow::Result<Self> {
+        check_key(secret_key)?;
+        for key in allowed_clients.iter().flatten() {
+            check_key(key)?;
+        }
+        Ok(Self::Server {
+            secret_key: secret_key.to_string(),
+            allowed_clients,
+        })
+    }
+
+    pub fn client(public_key: &str, secret_key: &str, server_key: &str) -> anyhow::Result<Self> {
+        check_key(public_key)?;
+        check_key(secret_key)?;
+        check_key(server_key)?;
+        Ok(Self::Client {
+            public_key: public_key.to_string(),
+            secret_key: secret_key.to_string(),
+            server_key: server_key.to_string(),
+        })
+    }
+
+    pub fn server_from_files(
+        secret_key_file: &Path,
+        allowed_clients_file: Option<&Path>,
+    ) -> anyhow::Result<Self> {
+        Ok(Self::Server {
+            secret_key: load_curve_key(secret_key_file, true)?,
+            allowed_clients: allowed_clients_file
+                .map(load_curve_allowed_clients)
+                .transpose()?,
+        })
+    }
+
+    /// Creates the client configuration, the public and the secret keys may be loaded
+    /// from the same certificate file.
+    ///
+    pub fn client_from_files(
+        public_key_file: &Path,
+        secret_key_file: &Path,
+        server_key_file: &Path,
+    ) -> anyhow::Result<Self> {
+        Ok(Self::Client {
+            public_key: load_curve_key(public_key_file, false)?,
+            secret_key: load_curve_key(secret_key_file, true)?,
+            server_key: load_curve_key(server_key_file, false)?,
+        })
+    }
+
+    /// Parses the socket URI query parameters:
+    ///
+    /// * `curve=server&secret_key_file=...[&allowed_clients_file=...]`;
+    /// * `curve=client&public_key_file=...&secret_key_file=...&server_key_file=...`.
+    ///
+    pub fn from_uri_params(params: &[(String, String)]) -> anyhow::Result<Option<Self>> {
+        let mut role = None;
+        let mut files = HashMap::new();
+        for (name, value) in params {
+            match name.as_str() {
+                "curve" => role = Some(value.as_str()),
+                "public_key_file"
+                | "secret_key_file"
+                | "server_key_file"
+                | "allowed_clients_file" => {
+                    files.insert(name.as_str(), Path::new(value.as_str()));
+                }
+                _ => bail!("Unknown socket URI parameter {}", name),
+            }
+        }
+        let file = |name: &str| {
+            files
+                .get(name)
+                .copied()
+                .ok_or_else(|| anyhow!("Socket URI parameter {} is required", name))
+        };
+        let config = match role {
+            None if files.is_empty() => return Ok(None),
+            None => bail!("Socket URI parameter curve is required for key files"),
+            Some("server") => Self::server_from_files(
+                file("secret_key_file")?,
+                files.get("allowed_clients_file").copied(),
+            )?,
+            Some("client") => Self::client_from_files(
+                file("public_key_file")?,
+                file("secret_key_file")?,
+                file("server_key_file")?,
+            )?,
+            Some(role) => bail!("Unknown CURVE role {}", role),
+        };
+        Ok(Some(config))
+    }
+
+    pub(super) fn allowed_clients(&self) -> Option<&Vec<String>> {
+        match self {
+            Self::Server {
+                allowed_clients, ..
+            } => allowed_clients.as_ref(),
+            Self::Client { .. } => None,
+        }
+    }
+
+    pub(super) fn apply(&self, socket: &zmq::Socket) -> anyhow::Result<()> {
+        if zmq::has("curve") != Some(true) {
+            bail!("ZeroMQ library is built without CURVE support");
+        }
+        match self {
+            Self::Server { secret_key, .. } => {
+                socket.set_curve_server(true)?;
+                socket.set_curve_secretkey(secret_key.as_bytes())?;
+            }
+            Self::Client {
+                public_key,
+                secret_key,
+                server_key,
+            } => {
+                socket.set_curve_publickey(public_key.as_bytes())?;
+                socket.set_curve_secretkey(secret_key.as_bytes())?;
+                socket.set_curve_serverkey(server_key.as_bytes())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// ZAP handler accepting the CURVE clients with the allowed public keys, serves the
+/// sockets of the context until dropped.
+///
+pub(super) struct ZapHandler {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ZapHandler {
+    pub fn start(context: &Context, allowed_clients: &[String]) -> anyhow::Result<Self> {
+        let allowed = allowed_clients
+            .iter()
+            .map(|k| check_key(k))
+            .collect::<anyhow::Result<HashSet<_>>>()?;
+        let socket = context.socket(zmq::REP)?;
+        socket.set_linger(0)?;
+        socket.set_rcvtimeo(ZAP_POLL_TIMEOUT)?;
+        socket.bind(ZAP_ENDPOINT)?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        let thread = std::thread::Builder::new()
+            .name("zmq-zap-handler".to_string())
+            .spawn(move || Self::serve(socket, allowed, thread_stop))?;
+        Ok(Self {
+            stop,
+            thread: Some(thread),
+        })
+    }
+
+    fn serve(socket: zmq::Socket, allowed: HashSet<Vec<u8>>, stop: Arc<AtomicBool>) {
+        while !stop.load(Ordering::Relaxed) {
+            let request = match socket.recv_multipart(0) {
+                Ok(request) => request,
+                Err(zmq::Error::EAGAIN) => continue,
+                Err(e) => {
+                    warn!(target: "savant_rs::zeromq::zap", "ZAP handler failed to receive: {:?}", e);
+                    break;
+                }
+            };
+            // version, request id, domain, address, identity, mechanism, credentials
+            if request.len() < 6 || request[0] != ZAP_VERSION {
+                warn!(target: "savant_rs::zeromq::zap", "Malformed ZAP request: {:?}", request);
+                continue;
+            }
+            let key = request.get(6);
+            let accepted = request[5] == b"CURVE" && key.is_some_and(|k| allowed.contains(k));
+            let user_id = key
+                .and_then(|k| zmq::z85_encode(k).ok())
+                .unwrap_or_default();
+            debug!(
+                target: "savant_rs::zeromq::zap",
+                "ZAP request from client {} is {}",
+                user_id,
+                if accepted { "accepted" } else { "rejected" }
+            );
+            let (status, text, user_id) = if accepted {
+                ("200", "OK", user_id.as_str())
+            } else {
+                ("400", "Client key is not allowed", "")
+            };
+            let reply: [&[u8]; 6] = [
+                ZAP_VERSION,
+                &request[1],
+                status.as_bytes(),
+                text.as_bytes(),
+                user_id.as_bytes(),
+                b"",
+            ];
+            if let Err(e) = socket.send_multipart(reply, 0) {
+                warn!(target: "savant_rs::zeromq::zap", "ZAP handler failed to reply: {:?}", e);
+            }
+        }
+    }
+}
+
+impl Drop for ZapHandler {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{generate_curve_keypair, load_curve_key, CurveConfig};
+    use crate::transport::zeromq::parse_zmq_socket_uri;
+    use std::io::Write;
+
+    // the keys of the zmq_curve(7) example, used when the library cannot generate keys
+    const PUBLIC_KEY: &str = "Yne@$w-vo<fVvi]a<NY6T1ed:M$fCG*[IaLV{hID";
+    const SECRET_KEY: &str = "D:)Q[IlAW!ahhC2ac:9*A}h:p?([4%wOTJ%JR%cs";
+
+    fn keypair() -> (String, String) {
+        generate_curve_keypair()
+            .unwrap_or_else(|_| (PUBLIC_KEY.to_string(), SECRET_KEY.to_string()))
+    }
+
+    #[test]
+    fn test_key_validation() {
+        let (public, secret) = keypair();
+        assert!(CurveConfig::server(&secret, Some(vec![public.clone()])).is_ok());
+        assert!(CurveConfig::server("short", None).is_err());
+        assert!(CurveConfig::client(&public, &secret, "bad key").is_err());
+    }
+
+    #[test]
+    fn test_secret_key_is_redacted() -> anyhow::Result<()> {
+        let (public, secret) = keypair();
+        for config in [
+            CurveConfig::server(&secret, Some(vec![public.clone()]))?,
+            CurveConfig::client(&public, &secret, &public)?,
+        ] {
+            let debug = format!("{:?}", config);
+            assert!(!debug.contains(&secret));
+            assert!(debug.contains(&public));
+            assert!(debug.contains("secret_key: \"<redacted>\""));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_keys() -> anyhow::Result<()> {
+        let (public, secret) = keypair();
+        let dir = std::path::Path::new("/tmp/test/zmq-curve-keys");
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+        std::fs::create_dir_all(dir)?;
+
+        let key_path = dir.join("server.key");
+        std::fs::write(&key_path, format!("{}\n", public))?;
+        assert_eq!(load_curve_key(&key_path, false)?, public);
+
+        let cert_path = dir.join("client.key_secret");
+        let mut cert = std::fs::File::create(&cert_path)?;
+        writeln!(cert, "metadata\ncurve")?;
+        writeln!(cert, "    public-key = \"{}\"", public)?;
+        writeln!(cert, "    secret-key = \"{}\"", secret)?;
+        assert_eq!(load_curve_key(&cert_path, false)?, public);
+        assert_eq!(load_curve_key(&cert_path, true)?, secret);
+
+        let clients_path = dir.join("clients");
+        std::fs::write(&clients_path, format!("# edge boxes\n{}\n\n", public))?;
+        let params = [
+            ("curve", "server"),
+            ("secret_key_file", cert_path.to_str().unwrap()),
+            ("allowed_clients_file", clients_path.to_str().unwrap()),
+        ]
+        .map(|(n, v)| (n.to_string(), v.to_string()));
+        assert_eq!(
+            CurveConfig::from_uri_params(&params)?,
+            Some(CurveConfig::Server {
+                secret_key: secret,
+                allowed_clients: Some(vec![public]),
+            })
+        );
+        assert!(CurveConfig::from_uri_params(&params[1..]).is_err());
+
+        let uri = parse_zmq_socket_uri(format!(
+            "dealer+connect:tcp://127.0.0.1:5555:source?curve=client&public_key_file={0}&secret_key_file={0}&server_key_file={1}",
+            cert_path.display(),
+            key_path.display()
+        ))?;
+        assert_eq!(uri.endpoint, "tcp://127.0.0.1:5555");
+        assert_eq!(uri.source, Some("source".to_string()));
+        assert!(matches!(uri.curve, Some(CurveConfig::Client { .. })));
+        assert!(parse_zmq_socket_uri("tcp://127.0.0.1:5555?curve=peer".to_string()).is_err());
+        assert!(parse_zmq_socket_uri("tcp://127.0.0.1:5555?unknown=1".to_string()).is_err());
+        Ok(())
+    }
+}
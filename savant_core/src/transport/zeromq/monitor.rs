@@ -0,0 +1,320 @@
+use lazy_static::lazy_static;
+use log::{debug, info, warn};
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Weak};
+use std::thread::JoinHandle;
+use zmq::Context;
+
+const MONITOR_POLL_TIMEOUT: i32 = 100;
+const MAX_SOCKET_EVENTS: usize = 256;
+
+lazy_static! {
+    static ref SOCKET_REGISTRY: Mutex<Vec<Weak<SocketHealth>>> = Mutex::new(Vec::new());
+}
+
+static MONITOR_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SocketEventKind {
+    Connected,
+    ConnectDelayed,
+    ConnectRetried,
+    Listening,
+    BindFailed,
+    Accepted,
+    AcceptFailed,
+    Closed,
+    CloseFailed,
+    Disconnected,
+    HandshakeSucceeded,
+    /// The security handshake failed, e.g. the CURVE keys do not match.
+    HandshakeFailed,
+    MonitorStopped,
+    /// The socket was recreated after persistent failures.
+    Reconnected,
+    Unknown(u16),
+}
+
+impl From<u16> for SocketEventKind {
+    fn from(raw: u16) -> Self {
+        use zmq::SocketEvent as E;
+        match raw {
+            r if r == E::CONNECTED.to_raw() => Self::Connected,
+            r if r == E::CONNECT_DELAYED.to_raw() => Self::ConnectDelayed,
+            r if r == E::CONNECT_RETRIED.to_raw() => Self::ConnectRetried,
+            r if r == E::LISTENING.to_raw() => Self::Listening,
+            r if r == E::BIND_FAILED.to_raw() => Self::BindFailed,
+            r if r == E::ACCEPTED.to_raw() => Self::Accepted,
+            r if r == E::ACCEPT_FAILED.to_raw() => Self::AcceptFailed,
+            r if r == E::CLOSED.to_raw() => Self::Closed,
+            r if r == E::CLOSE_FAILED.to_raw() => Self::CloseFailed,
+            r if r == E::DISCONNECTED.to_raw() => Self::Disconnected,
+            r if r == E::MONITOR_STOPPED.to_raw() => Self::MonitorStopped,
+            r if r == E::HANDSHAKE_SUCCEEDED.to_raw() => Self::HandshakeSucceeded,
+            r if r == E::HANDSHAKE_FAILED_NO_DETAIL.to_raw()
+                || r == E::HANDSHAKE_FAILED_PROTOCOL.to_raw()
+                || r == E::HANDSHAKE_FAILED_AUTH.to_raw() =>
+            {
+                Self::HandshakeFailed
+            }
+            r => Self::Unknown(r),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SocketEvent {
+    pub kind: SocketEventKind,
+    /// The peer or the local endpoint the event relates to.
+    pub address: String,
+    /// The event value, e.g. the error code or the reconnect interval.
+    pub value: u32,
+}
+
+/// The connection state of a reader or a writer socket, updated by the socket monitor.
+///
+#[derive(Debug)]
+pub struct SocketHealth {
+    endpoint: String,
+    role: &'static str,
+    peers: AtomicU64,
+    handshake_failures: AtomicU64,
+    reconnects: AtomicU64,
+    events: Mutex<VecDeque<SocketEvent>>,
+}
+
+/// The snapshot of the socket state reported by the webserver.
+///
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SocketStatus {
+    pub endpoint: String,
+    pub role: String,
+    pub connected: bool,
+    pub peers: u64,
+    pub handshake_failures: u64,
+    pub reconnects: u64,
+    pub last_event: Option<SocketEvent>,
+}
+
+impl SocketHealth {
+    pub(super) fn register(endpoint: &str, role: &'static str) -> Arc<Self> {
+        let health = Arc::new(Self {
+            endpoint: endpoint.to_string(),
+            role,
+            peers: AtomicU64::new(0),
+            handshake_failures: AtomicU64::new(0),
+            reconnects: AtomicU64::new(0),
+            events: Mutex::new(VecDeque::new()),
+        });
+        let mut registry = SOCKET_REGISTRY.lock();
+        registry.retain(|h| h.strong_count() > 0);
+        registry.push(Arc::downgrade(&health));
+        health
+    }
+
+    /// The socket has at least one connected peer.
+    ///
+    pub fn is_connected(&self) -> bool {
+        self.peers.load(Ordering::Relaxed) > 0
+    }
+
+    /// Returns the events received since the previous call, at most the last 256 are kept.
+    ///
+    pub fn take_events(&self) -> Vec<SocketEvent> {
+        self.events.lock().drain(..).collect()
+    }
+
+    pub fn status(&self) -> SocketStatus {
+        SocketStatus {
+            endpoint: self.endpoint.clone(),
+            role: self.role.to_string(),
+            connected: self.is_connected(),
+            peers: self.peers.load(Ordering::Relaxed),
+            handshake_failures: self.handshake_failures.load(Ordering::Relaxed),
+            reconnects: self.reconnects.load(Ordering::Relaxed),
+            last_event: self.events.lock().back().cloned(),
+        }
+    }
+
+    pub(super) fn push_event(&self, event: SocketEvent) {
+        match event.kind {
+            SocketEventKind::Connected | SocketEventKind::Accepted => {
+                self.peers.fetch_add(1, Ordering::Relaxed);
+            }
+            SocketEventKind::Disconnected => {
+                let _ = self
+                    .peers
+                    .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |p| p.checked_sub(1));
+            }
+            SocketEventKind::HandshakeFailed => {
+                self.handshake_failures.fetch_add(1, Ordering::Relaxed);
+            }
+            SocketEventKind::Reconnected => {
+                self.peers.store(0, Ordering::Relaxed);
+                self.reconnects.fetch_add(1, Ordering::Relaxed);
+            }
+            _ => {}
+        }
+        let mut events = self.events.lock();
+        if events.len() >= MAX_SOCKET_EVENTS {
+            events.pop_front();
+        }
+        events.push_back(event);
+    }
+}
+
+/// Returns the state of the alive reader and writer sockets.
+///
+pub fn get_socket_statuses() -> Vec<SocketStatus> {
+    let mut registry = SOCKET_REGISTRY.lock();
+    registry.retain(|h| h.strong_count() > 0);
+    registry
+        .iter()
+        .filter_map(|h| h.upgrade())
+        .map(|h| h.status())
+        .collect()
+}
+
+fn parse_event(parts: &[Vec<u8>]) -> Option<SocketEvent> {
+    match parts {
+        [header, address, ..] if header.len() == 6 => Some(SocketEvent {
+            kind: u16::from_le_bytes([header[0], header[1]]).into(),
+            address: String::from_utf8_lossy(address).to_string(),
+            value: u32::from_le_bytes([header[2], header[3], header[4], header[5]]),
+        }),
+        _ => None,
+    }
+}
+
+/// Receives the events of the monitored socket in the background thread. The monitor
+/// must be dropped before the context.
+///
+pub(super) struct SocketMonitor {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl SocketMonitor {
+    pub fn start(
+        context: &Context,
+        socket: &zmq::Socket,
+        health: Arc<SocketHealth>,
+    ) -> anyhow::Result<Self> {
+        let endpoint = format!(
+            "inproc://savant-rs.monitor.{}",
+            MONITOR_COUNTER.fetch_add(1, Ordering::Relaxed)
+        );
+        socket.monitor(&endpoint, zmq::SocketEvent::ALL as i32)?;
+        let monitor = context.socket(zmq::PAIR)?;
+        monitor.set_linger(0)?;
+        monitor.set_rcvtimeo(MONITOR_POLL_TIMEOUT)?;
+        monitor.connect(&endpoint)?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        let thread = std::thread::spawn(move || {
+            while !thread_stop.load(Ordering::Relaxed) {
+                match monitor.recv_multipart(0) {
+                    Ok(parts) => {
+                        let Some(event) = parse_event(&parts) else {
+                            warn!(
+                                target: "savant_rs::zeromq::monitor",
+                                "Unexpected socket monitor message: {:?}", parts);
+                            continue;
+                        };
+                        if event.kind == SocketEventKind::MonitorStopped {
+                            break;
+                        }
+                        match event.kind {
+                            SocketEventKind::Connected
+                            | SocketEventKind::Accepted
+                            | SocketEventKind::Disconnected => info!(
+                                target: "savant_rs::zeromq::monitor",
+                                "Socket {} event: {:?}", health.endpoint, event),
+                            SocketEventKind::HandshakeFailed => warn!(
+                                target: "savant_rs::zeromq::monitor",
+                                "Socket {} event: {:?}", health.endpoint, event),
+                            _ => debug!(
+                                target: "savant_rs::zeromq::monitor",
+                                "Socket {} event: {:?}", health.endpoint, event),
+                        }
+                        health.push_event(event);
+                    }
+                    Err(zmq::Error::EAGAIN) => {}
+                    Err(e) => {
+                        warn!(
+                            target: "savant_rs::zeromq::monitor",
+                            "Socket monitor of {} failed: {:?}", health.endpoint, e);
+                        break;
+                    }
+                }
+            }
+        });
+        Ok(Self {
+            stop,
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Drop for SocketMonitor {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{get_socket_statuses, parse_event, SocketEvent, SocketEventKind, SocketHealth};
+
+    #[test]
+    fn test_health() {
+        let health = SocketHealth::register("ipc:///tmp/test-health", "writer");
+        let event = |kind| SocketEvent {
+            kind,
+            address: "ipc:///tmp/test-health".into(),
+            value: 0,
+        };
+        assert!(!health.is_connected());
+        health.push_event(event(SocketEventKind::Connected));
+        assert!(health.is_connected());
+        health.push_event(event(SocketEventKind::Disconnected));
+        health.push_event(event(SocketEventKind::Disconnected));
+        assert!(!health.is_connected());
+        health.push_event(event(SocketEventKind::HandshakeFailed));
+        assert_eq!(health.take_events().len(), 4);
+        assert!(health.take_events().is_empty());
+
+        let status = get_socket_statuses()
+            .into_iter()
+            .find(|s| s.endpoint == "ipc:///tmp/test-health")
+            .unwrap();
+        assert_eq!(status.handshake_failures, 1);
+        assert!(!status.connected);
+        drop(health);
+        assert!(get_socket_statuses()
+            .iter()
+            .all(|s| s.endpoint != "ipc:///tmp/test-health"));
+    }
+
+    #[test]
+    fn test_parse_event() {
+        let mut header = zmq::SocketEvent::CONNECTED.to_raw().to_le_bytes().to_vec();
+        header.extend_from_slice(&7u32.to_le_bytes());
+        assert_eq!(
+            parse_event(&[header, b"tcp://127.0.0.1:5555".to_vec()]),
+            Some(SocketEvent {
+                kind: SocketEventKind::Connected,
+                address: "tcp://127.0.0.1:5555".into(),
+                value: 7,
+            })
+        );
+        assert_eq!(parse_event(&[b"short".to_vec()]), None);
+    }
+}
@@ -0,0 +1,569 @@
+use crate::message::label_filter::LabelFilterRule;
+use crate::message::Message;
+use crate::primitives::eos::EndOfStream;
+use crate::transport::zeromq::{SyncWriter, WriterConfig, WriterResult};
+use crate::transport::MessageSink;
+use anyhow::bail;
+use hashbrown::HashMap;
+use log::{info, warn};
+use parking_lot::Mutex;
+use std::collections::BTreeSet;
+use std::time::{Duration, Instant};
+
+const DEFAULT_MAX_FAILURES: u32 = 3;
+const DEFAULT_RECOVERY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How the multiplexing writer selects the endpoint for a message.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MultiWriterRouting {
+    /// The endpoint is selected by the hash of the message source id, so a stream always
+    /// goes to the same endpoint while it is healthy.
+    SourceHash,
+    /// The first endpoint whose label rule matches the routing labels of the message.
+    Labels,
+    /// The endpoints are used in turn.
+    RoundRobin,
+}
+
+#[derive(Clone, Debug)]
+pub struct MultiWriterEndpoint {
+    pub config: WriterConfig,
+    /// The rule matched against the message routing labels in the [`MultiWriterRouting::Labels`]
+    /// mode, the endpoint without the rule accepts any message.
+    pub labels: Option<LabelFilterRule>,
+}
+
+impl MultiWriterEndpoint {
+    pub fn new(config: WriterConfig) -> Self {
+        Self {
+            config,
+            labels: None,
+        }
+    }
+
+    pub fn with_labels(config: WriterConfig, labels: LabelFilterRule) -> Self {
+        Self {
+            config,
+            labels: Some(labels),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MultiWriterConfig {
+    pub endpoints: Vec<MultiWriterEndpoint>,
+    pub routing: MultiWriterRouting,
+    /// The number of consecutive failed sends after which the endpoint is excluded.
+    pub max_failures: u32,
+    /// The time after which the excluded endpoint is tried again.
+    pub recovery_interval: Duration,
+}
+
+impl MultiWriterConfig {
+    pub fn new(endpoints: Vec<MultiWriterEndpoint>, routing: MultiWriterRouting) -> Self {
+        Self {
+            endpoints,
+            routing,
+            max_failures: DEFAULT_MAX_FAILURES,
+            recovery_interval: DEFAULT_RECOVERY_INTERVAL,
+        }
+    }
+}
+
+struct EndpointHealth {
+    failures: u32,
+    excluded_until: Option<Instant>,
+}
+
+impl EndpointHealth {
+    fn is_healthy(&self, now: Instant) -> bool {
+        self.excluded_until.is_none_or(|until| now >= until)
+    }
+}
+
+/// The sink is locked only for the send, so the endpoints are written concurrently.
+///
+struct Endpoint {
+    sink: Mutex<Box<dyn MessageSink>>,
+    labels: Option<LabelFilterRule>,
+    health: Mutex<EndpointHealth>,
+}
+
+impl Endpoint {
+    fn is_healthy(&self, now: Instant) -> bool {
+        self.health.lock().is_healthy(now)
+    }
+}
+
+struct State {
+    next: usize,
+    assignments: HashMap<String, BTreeSet<usize>>,
+}
+
+/// Writer multiplexing messages over several endpoints. A message goes to the endpoint
+/// selected by the routing rule, when the send fails the message is sent to the next
+/// candidate endpoint. Endpoints failing repeatedly are excluded for the recovery interval.
+///
+/// The end-of-stream is sent to the endpoints which received the messages of the source.
+///
+pub struct MultiWriter {
+    routing: MultiWriterRouting,
+    max_failures: u32,
+    recovery_interval: Duration,
+    endpoints: Vec<Endpoint>,
+    state: Mutex<State>,
+}
+
+fn source_hash(source_id: &str) -> u64 {
+    // FNV-1a, stable across processes, so that independent writers shard alike
+    source_id.bytes().fold(0xcbf29ce484222325, |hash, b| {
+        (hash ^ b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn message_source_id(topic: &str, m: &Message) -> String {
+    if let Some(frame) = m.as_video_frame() {
+        frame.get_source_id()
+    } else if let Some(user_data) = m.as_user_data() {
+        user_data.get_source_id().to_string()
+    } else if let Some(eos) = m.as_end_of_stream() {
+        eos.source_id.clone()
+    } else {
+        topic.to_string()
+    }
+}
+
+fn is_failure(res: &anyhow::Result<WriterResult>) -> bool {
+    matches!(
+        res,
+        Err(_) | Ok(WriterResult::SendTimeout) | Ok(WriterResult::AckTimeout(_))
+    )
+}
+
+impl MultiWriter {
+    pub fn new(config: &MultiWriterConfig) -> anyhow::Result<Self> {
+        let sinks = config
+            .endpoints
+            .iter()
+            .map(|e| {
+                Ok((
+                    Box::new(SyncWriter::new(&e.config)?) as Box<dyn MessageSink>,
+                    e.labels.clone(),
+                ))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Self::with_sinks(config, sinks)
+    }
+
+    /// Creates the writer over arbitrary sinks, the endpoint configurations are ignored.
+    ///
+    pub fn with_sinks(
+        config: &MultiWriterConfig,
+        sinks: Vec<(Box<dyn MessageSink>, Option<LabelFilterRule>)>,
+    ) -> anyhow::Result<Self> {
+        if sinks.is_empty() {
+            bail!("Multiplexing writer requires at least one endpoint");
+        }
+        if config.max_failures == 0 {
+            bail!("The number of failures must be positive");
+        }
+        Ok(Self {
+            routing: config.routing,
+            max_failures: config.max_failures,
+            recovery_interval: config.recovery_interval,
+            endpoints: sinks
+                .into_iter()
+                .map(|(sink, labels)| Endpoint {
+                    sink: Mutex::new(sink),
+                    labels,
+                    health: Mutex::new(EndpointHealth {
+                        failures: 0,
+                        excluded_until: None,
+                    }),
+                })
+                .collect(),
+            state: Mutex::new(State {
+                next: 0,
+                assignments: HashMap::new(),
+            }),
+        })
+    }
+
+    /// Returns the health of the endpoints in the order of the configuration.
+    ///
+    pub fn get_endpoint_health(&self) -> Vec<bool> {
+        let now = Instant::now();
+        self.endpoints.iter().map(|e| e.is_healthy(now)).collect()
+    }
+
+    /// The candidate endpoints in the order of preference, healthy ones go first.
+    ///
+    fn candidates(&self, topic: &str, m: &Message) -> Vec<usize> {
+        let n = self.endpoints.len();
+        let mut candidates = match self.routing {
+            MultiWriterRouting::SourceHash => {
+                let first = (source_hash(&message_source_id(topic, m)) % n as u64) as usize;
+                (0..n).map(|i| (first + i) % n).collect::<Vec<_>>()
+            }
+            MultiWriterRouting::RoundRobin => {
+                let mut state = self.state.lock();
+                let first = state.next % n;
+                state.next = (first + 1) % n;
+                (0..n).map(|i| (first + i) % n).collect()
+            }
+            MultiWriterRouting::Labels => {
+                let labels = m.get_labels();
+                (0..n)
+                    .filter(|i| {
+                        self.endpoints[*i]
+                            .labels
+                            .as_ref()
+                            .is_none_or(|rule| rule.matches(&labels))
+                    })
+                    .collect()
+            }
+        };
+        let now = Instant::now();
+        candidates.sort_by_key(|i| !self.endpoints[*i].is_healthy(now));
+        candidates
+    }
+
+    fn send_to(
+        &self,
+        index: usize,
+        send: impl Fn(&dyn MessageSink) -> anyhow::Result<WriterResult>,
+    ) -> anyhow::Result<WriterResult> {
+        let endpoint = &self.endpoints[index];
+        let res = send(endpoint.sink.lock().as_ref());
+        let mut health = endpoint.health.lock();
+        if is_failure(&res) {
+            health.failures += 1;
+            warn!(
+                target: "savant_rs::zeromq::multi_writer",
+                "Failed to send message to endpoint {}, consecutive failures: {}, result: {:?}",
+                index, health.failures, res);
+            if health.failures >= self.max_failures {
+                warn!(
+                    target: "savant_rs::zeromq::multi_writer",
+                    "Endpoint {} is excluded for {:?}", index, self.recovery_interval);
+                health.excluded_until = Some(Instant::now() + self.recovery_interval);
+            }
+        } else {
+            if health.excluded_until.take().is_some() {
+                info!(
+                    target: "savant_rs::zeromq::multi_writer",
+                    "Endpoint {} is recovered", index);
+            }
+            health.failures = 0;
+        }
+        res
+    }
+
+    pub fn send_message(
+        &self,
+        topic: &str,
+        m: &Message,
+        data: &[&[u8]],
+    ) -> anyhow::Result<WriterResult> {
+        if m.is_end_of_stream() {
+            return self.send_end_of_stream(topic, m, |sink| sink.send_message(topic, m, data));
+        }
+        let candidates = self.candidates(topic, m);
+        if candidates.is_empty() {
+            bail!(
+                "No endpoint matches the routing labels {:?} of the message",
+                m.get_labels()
+            );
+        }
+        let mut res = Ok(WriterResult::SendTimeout);
+        for index in candidates {
+            res = self.send_to(index, |sink| sink.send_message(topic, m, data));
+            if !is_failure(&res) {
+                self.state
+                    .lock()
+                    .assignments
+                    .entry(message_source_id(topic, m))
+                    .or_default()
+                    .insert(index);
+                break;
+            }
+        }
+        res
+    }
+
+    /// Sends the end-of-stream to every endpoint which received the messages of the source,
+    /// or to the endpoint selected by the routing rule when there were none.
+    ///
+    pub fn send_eos(&self, topic: &str) -> anyhow::Result<WriterResult> {
+        let m = Message::end_of_stream(EndOfStream::new(topic.to_string()));
+        self.send_end_of_stream(topic, &m, |sink| sink.send_eos(topic))
+    }
+
+    /// The endpoints are selected by the source id of the end-of-stream, which may differ
+    /// from the topic, the assignments of the source are forgotten.
+    ///
+    fn send_end_of_stream(
+        &self,
+        topic: &str,
+        m: &Message,
+        send: impl Fn(&dyn MessageSink) -> anyhow::Result<WriterResult>,
+    ) -> anyhow::Result<WriterResult> {
+        let assigned = self
+            .state
+            .lock()
+            .assignments
+            .remove(&message_source_id(topic, m));
+        let targets = match assigned {
+            Some(targets) => targets.into_iter().collect::<Vec<_>>(),
+            None => self.candidates(topic, m).into_iter().take(1).collect(),
+        };
+        let mut res = Ok(WriterResult::SendTimeout);
+        for index in targets {
+            let endpoint_res = self.send_to(index, &send);
+            if !is_failure(&endpoint_res) || is_failure(&res) {
+                res = endpoint_res;
+            }
+        }
+        res
+    }
+
+    pub fn is_started(&self) -> bool {
+        self.endpoints.iter().any(|e| e.sink.lock().is_started())
+    }
+
+    pub fn shutdown(&self) -> anyhow::Result<()> {
+        for endpoint in &self.endpoints {
+            endpoint.sink.lock().shutdown()?;
+        }
+        Ok(())
+    }
+}
+
+impl MessageSink for MultiWriter {
+    fn send_message(
+        &self,
+        topic: &str,
+        message: &Message,
+        data: &[&[u8]],
+    ) -> anyhow::Result<WriterResult> {
+        MultiWriter::send_message(self, topic, message, data)
+    }
+
+    fn send_eos(&self, topic: &str) -> anyhow::Result<WriterResult> {
+        MultiWriter::send_eos(self, topic)
+    }
+
+    fn is_started(&self) -> bool {
+        MultiWriter::is_started(self)
+    }
+
+    fn shutdown(&mut self) -> anyhow::Result<()> {
+        MultiWriter::shutdown(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MultiWriter, MultiWriterConfig, MultiWriterRouting};
+    use crate::message::label_filter::LabelFilterRule;
+    use crate::message::Message;
+    use crate::primitives::eos::EndOfStream;
+    use crate::primitives::userdata::UserData;
+    use crate::transport::zeromq::WriterResult;
+    use crate::transport::MessageSink;
+    use parking_lot::Mutex;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    #[derive(Clone, Default)]
+    struct TestSink {
+        sent: Arc<Mutex<Vec<String>>>,
+        failing: Arc<AtomicBool>,
+        gate: Arc<Mutex<()>>,
+    }
+
+    impl MessageSink for TestSink {
+        fn send_message(
+            &self,
+            topic: &str,
+            m: &Message,
+            _: &[&[u8]],
+        ) -> anyhow::Result<WriterResult> {
+            let _gate = self.gate.lock();
+            if self.failing.load(Ordering::SeqCst) {
+                return Ok(WriterResult::SendTimeout);
+            }
+            match m.as_end_of_stream() {
+                Some(eos) => self.sent.lock().push(format!("eos:{}", eos.source_id)),
+                None => self.sent.lock().push(topic.to_string()),
+            }
+            Ok(WriterResult::Success {
+                retries_spent: 0,
+                time_spent: 0,
+            })
+        }
+
+        fn send_eos(&self, topic: &str) -> anyhow::Result<WriterResult> {
+            self.sent.lock().push(format!("eos:{}", topic));
+            Ok(WriterResult::Success {
+                retries_spent: 0,
+                time_spent: 0,
+            })
+        }
+
+        fn is_started(&self) -> bool {
+            true
+        }
+
+        fn shutdown(&mut self) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn writer(
+        routing: MultiWriterRouting,
+        labels: Vec<Option<LabelFilterRule>>,
+    ) -> anyhow::Result<(MultiWriter, Vec<TestSink>)> {
+        let sinks = labels
+            .iter()
+            .map(|_| TestSink::default())
+            .collect::<Vec<_>>();
+        let mut config = MultiWriterConfig::new(vec![], routing);
+        config.max_failures = 1;
+        config.recovery_interval = Duration::from_secs(60);
+        let writer = MultiWriter::with_sinks(
+            &config,
+            sinks
+                .iter()
+                .zip(labels)
+                .map(|(s, l)| (Box::new(s.clone()) as Box<dyn MessageSink>, l))
+                .collect(),
+        )?;
+        Ok((writer, sinks))
+    }
+
+    fn message(source_id: &str, labels: &[&str]) -> Message {
+        let mut m = Message::user_data(UserData::new(source_id));
+        m.set_labels(labels.iter().map(|l| l.to_string()).collect());
+        m
+    }
+
+    #[test]
+    fn test_source_hash_is_sticky() -> anyhow::Result<()> {
+        let (writer, sinks) = writer(MultiWriterRouting::SourceHash, vec![None; 3])?;
+        for _ in 0..3 {
+            for source in ["a", "b", "c", "d"] {
+                writer.send_message(source, &message(source, &[]), &[])?;
+            }
+        }
+        for source in ["a", "b", "c", "d"] {
+            let holders = sinks
+                .iter()
+                .filter(|s| s.sent.lock().iter().any(|t| t == source))
+                .count();
+            assert_eq!(holders, 1);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_round_robin_eos() -> anyhow::Result<()> {
+        let (writer, sinks) = writer(MultiWriterRouting::RoundRobin, vec![None; 2])?;
+        writer.send_message("a", &message("a", &[]), &[])?;
+        writer.send_message("a", &message("a", &[]), &[])?;
+        writer.send_eos("a")?;
+        for sink in &sinks {
+            assert_eq!(
+                *sink.sent.lock(),
+                vec!["a".to_string(), "eos:a".to_string()]
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_labels() -> anyhow::Result<()> {
+        let (writer, sinks) = writer(
+            MultiWriterRouting::Labels,
+            vec![
+                Some(LabelFilterRule::Set("gpu".into())),
+                Some(LabelFilterRule::Set("cpu".into())),
+            ],
+        )?;
+        writer.send_message("a", &message("a", &["cpu"]), &[])?;
+        writer.send_message("b", &message("b", &["gpu"]), &[])?;
+        assert!(writer
+            .send_message("c", &message("c", &["tpu"]), &[])
+            .is_err());
+        assert_eq!(*sinks[0].sent.lock(), vec!["b".to_string()]);
+        assert_eq!(*sinks[1].sent.lock(), vec!["a".to_string()]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_failover() -> anyhow::Result<()> {
+        let (writer, sinks) = writer(MultiWriterRouting::RoundRobin, vec![None; 2])?;
+        sinks[0].failing.store(true, Ordering::SeqCst);
+        for _ in 0..4 {
+            let res = writer.send_message("a", &message("a", &[]), &[])?;
+            assert!(matches!(res, WriterResult::Success { .. }));
+        }
+        assert_eq!(writer.get_endpoint_health(), vec![false, true]);
+        assert_eq!(sinks[1].sent.lock().len(), 4);
+        Ok(())
+    }
+
+    #[test]
+    fn test_eos_source_affinity() -> anyhow::Result<()> {
+        let (writer, sinks) = writer(MultiWriterRouting::RoundRobin, vec![None; 3])?;
+        // the messages of the source go with another topic
+        writer.send_message("topic", &message("a", &[]), &[])?;
+        writer.send_message("topic", &message("a", &[]), &[])?;
+        writer.send_message(
+            "topic",
+            &Message::end_of_stream(EndOfStream::new("a".into())),
+            &[],
+        )?;
+        assert_eq!(
+            *sinks[0].sent.lock(),
+            vec!["topic".to_string(), "eos:a".to_string()]
+        );
+        assert_eq!(
+            *sinks[1].sent.lock(),
+            vec!["topic".to_string(), "eos:a".to_string()]
+        );
+        assert!(sinks[2].sent.lock().is_empty());
+        assert!(writer.state.lock().assignments.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_endpoints_are_written_concurrently() -> anyhow::Result<()> {
+        let (writer, sinks) = writer(
+            MultiWriterRouting::Labels,
+            vec![
+                Some(LabelFilterRule::Set("slow".into())),
+                Some(LabelFilterRule::Set("fast".into())),
+            ],
+        )?;
+        let writer = Arc::new(writer);
+        let gate = sinks[0].gate.lock();
+        let slow = thread::spawn({
+            let writer = writer.clone();
+            move || writer.send_message("a", &message("a", &["slow"]), &[])
+        });
+        // the slow endpoint blocks in the send
+        while !writer.endpoints[0].sink.is_locked() {
+            thread::yield_now();
+        }
+        writer.send_message("b", &message("b", &["fast"]), &[])?;
+        assert_eq!(*sinks[1].sent.lock(), vec!["b".to_string()]);
+        drop(gate);
+        slow.join().unwrap()?;
+        assert_eq!(*sinks[0].sent.lock(), vec!["a".to_string()]);
+        Ok(())
+    }
+}
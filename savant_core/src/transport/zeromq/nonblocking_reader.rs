@@ -1,5 +1,6 @@
 use crate::transport::zeromq::reader::ReaderResult;
 use crate::transport::zeromq::{ReaderConfig, SyncReader};
+use crate::transport::MessageSource;
 use crossbeam::channel::Receiver;
 use std::sync::{Arc, OnceLock};
 
@@ -138,6 +139,20 @@ impl NonBlockingReader {
     }
 }
 
+impl MessageSource for NonBlockingReader {
+    fn receive(&self) -> anyhow::Result<ReaderResult> {
+        NonBlockingReader::receive(self)
+    }
+
+    fn is_started(&self) -> bool {
+        NonBlockingReader::is_started(self)
+    }
+
+    fn shutdown(&mut self) -> anyhow::Result<()> {
+        NonBlockingReader::shutdown(self)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::transport::zeromq::reader::ReaderResult;
@@ -1,6 +1,7 @@
 use crate::message::Message;
 use crate::primitives::eos::EndOfStream;
 use crate::transport::zeromq::{SyncWriter, WriterConfig, WriterResult};
+use crate::transport::MessageSink;
 use crossbeam::channel::{Receiver, Sender, TryRecvError};
 use std::cell::OnceCell;
 use std::sync::{Arc, OnceLock};
@@ -174,6 +175,31 @@ impl NonBlockingWriter {
     }
 }
 
+/// The sink interface waits for the operation result, so it behaves
+/// like [`SyncWriter`] while the actual sending happens in the writer thread.
+impl MessageSink for NonBlockingWriter {
+    fn send_message(
+        &self,
+        topic: &str,
+        message: &Message,
+        data: &[&[u8]],
+    ) -> anyhow::Result<WriterResult> {
+        NonBlockingWriter::send_message(self, topic, message, data)?.get()
+    }
+
+    fn send_eos(&self, topic: &str) -> anyhow::Result<WriterResult> {
+        NonBlockingWriter::send_eos(self, topic)?.get()
+    }
+
+    fn is_started(&self) -> bool {
+        NonBlockingWriter::is_started(self)
+    }
+
+    fn shutdown(&mut self) -> anyhow::Result<()> {
+        NonBlockingWriter::shutdown(self)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::message::Message;
@@ -215,7 +241,7 @@ mod tests {
         );
         let reader_result = reader.receive()?;
         assert!(
-            matches!(reader_result, ReaderResult::Message {message,topic,routing_id,data}
+            matches!(reader_result, ReaderResult::Message {message,topic,routing_id,data,..}
                 if message.is_user_data() && topic == b"test" && routing_id.is_none() && data == vec![b"test".to_vec()]
             )
         );
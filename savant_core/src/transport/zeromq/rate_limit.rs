@@ -0,0 +1,344 @@
+use hashbrown::HashMap;
+use lru::LruCache;
+use std::num::{NonZeroU32, NonZeroUsize};
+use std::time::{Duration, Instant};
+
+/// The sources which vanished without the end of stream are evicted when the number of
+/// the tracked sources exceeds the size.
+const SOURCE_STATES_CACHE_SIZE: usize = 1024;
+
+/// The way the frames exceeding the rate limit are handled.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RateLimitPolicy {
+    /// Drops the non-keyframes exceeding the limit, the keyframes may borrow one more
+    /// second of the budget before they are dropped too.
+    DropNonKeyframes,
+    /// Drops the frames exceeding the limit except every Nth of them, the kept frames are
+    /// charged to the budget in advance.
+    KeepEveryNth(NonZeroU32),
+    /// Delays the frames exceeding the limit until they fit it.
+    Block,
+}
+
+/// The token-bucket limit applied to the video frames of a source, the bucket holds one
+/// second of the budget.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RateLimit {
+    pub frames_per_second: Option<f64>,
+    /// The size of the serialized message and the extra parts before compression.
+    pub bytes_per_second: Option<f64>,
+    pub policy: RateLimitPolicy,
+}
+
+impl RateLimit {
+    pub(super) fn validate(&self) -> anyhow::Result<()> {
+        if self.frames_per_second.is_none() && self.bytes_per_second.is_none() {
+            anyhow::bail!("Rate limit must define frames per second or bytes per second");
+        }
+        for rate in [self.frames_per_second, self.bytes_per_second]
+            .into_iter()
+            .flatten()
+        {
+            if !rate.is_finite() || rate <= 0.0 {
+                anyhow::bail!("Rate limit must be positive, got {}", rate);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    rate: f64,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: f64, now: Instant) -> Self {
+        Self {
+            rate,
+            tokens: rate,
+            updated: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.rate);
+        self.updated = now;
+    }
+
+    /// The items larger than the bucket cost the whole bucket, so they are not stuck
+    /// forever.
+    ///
+    fn cost(&self, amount: f64) -> f64 {
+        amount.min(self.rate)
+    }
+
+    fn fits(&self, amount: f64, borrow: bool) -> bool {
+        let credit = if borrow { self.rate } else { 0.0 };
+        self.tokens + credit >= self.cost(amount)
+    }
+
+    /// The debt is limited to one second of the budget, so a source recovers within two
+    /// seconds after an overload of any length.
+    ///
+    fn consume(&mut self, amount: f64) {
+        self.tokens = (self.tokens - self.cost(amount)).max(-self.rate);
+    }
+
+    fn wait_time(&self, amount: f64) -> Duration {
+        let deficit = self.cost(amount) - self.tokens;
+        if deficit <= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(deficit / self.rate)
+        }
+    }
+}
+
+#[derive(Debug)]
+struct SourceState {
+    frames: Option<TokenBucket>,
+    bytes: Option<TokenBucket>,
+    over_limit: u32,
+    dropped: u64,
+}
+
+impl SourceState {
+    fn new(limit: &RateLimit, now: Instant) -> Self {
+        Self {
+            frames: limit.frames_per_second.map(|r| TokenBucket::new(r, now)),
+            bytes: limit.bytes_per_second.map(|r| TokenBucket::new(r, now)),
+            over_limit: 0,
+            dropped: 0,
+        }
+    }
+
+    fn buckets(&mut self, bytes: usize) -> impl Iterator<Item = (&mut TokenBucket, f64)> {
+        self.frames
+            .as_mut()
+            .map(|b| (b, 1.0))
+            .into_iter()
+            .chain(self.bytes.as_mut().map(|b| (b, bytes as f64)))
+    }
+
+    fn refill(&mut self, now: Instant) {
+        self.buckets(0).for_each(|(b, _)| b.refill(now));
+    }
+
+    fn fits(&mut self, bytes: usize, borrow: bool) -> bool {
+        self.buckets(bytes)
+            .all(|(b, amount)| b.fits(amount, borrow))
+    }
+
+    fn consume(&mut self, bytes: usize) {
+        self.buckets(bytes)
+            .for_each(|(b, amount)| b.consume(amount));
+    }
+
+    fn wait_time(&mut self, bytes: usize) -> Duration {
+        self.buckets(bytes)
+            .map(|(b, amount)| b.wait_time(amount))
+            .max()
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub(super) enum Admission {
+    Send,
+    /// The frame is dropped, the total number of the dropped frames of the source.
+    Drop(u64),
+    /// The frame is charged to the budget and must be sent after the delay.
+    Wait(Duration),
+}
+
+/// The per-source token buckets of the writer.
+///
+#[derive(Debug)]
+pub(super) struct RateLimiter {
+    default: Option<RateLimit>,
+    sources: HashMap<String, RateLimit>,
+    states: LruCache<String, SourceState>,
+}
+
+impl RateLimiter {
+    pub fn new(default: Option<RateLimit>, sources: HashMap<String, RateLimit>) -> Option<Self> {
+        if default.is_none() && sources.is_empty() {
+            return None;
+        }
+        Some(Self {
+            default,
+            sources,
+            states: LruCache::new(NonZeroUsize::new(SOURCE_STATES_CACHE_SIZE).unwrap()),
+        })
+    }
+
+    pub fn admit(&mut self, source_id: &str, keyframe: bool, bytes: usize) -> Admission {
+        let Some(limit) = self.sources.get(source_id).or(self.default.as_ref()) else {
+            return Admission::Send;
+        };
+        let now = Instant::now();
+        if !self.states.contains(source_id) {
+            self.states
+                .put(source_id.to_string(), SourceState::new(limit, now));
+        }
+        let state = self.states.get_mut(source_id).unwrap();
+        state.refill(now);
+        let admitted = match limit.policy {
+            RateLimitPolicy::Block => {
+                let wait = state.wait_time(bytes);
+                state.consume(bytes);
+                return if wait.is_zero() {
+                    Admission::Send
+                } else {
+                    Admission::Wait(wait)
+                };
+            }
+            RateLimitPolicy::DropNonKeyframes => state.fits(bytes, keyframe),
+            RateLimitPolicy::KeepEveryNth(n) => {
+                if state.fits(bytes, false) {
+                    state.over_limit = 0;
+                    true
+                } else {
+                    state.over_limit += 1;
+                    if state.over_limit >= n.get() {
+                        state.over_limit = 0;
+                        true
+                    } else {
+                        false
+                    }
+                }
+            }
+        };
+        if admitted {
+            state.consume(bytes);
+            Admission::Send
+        } else {
+            state.dropped += 1;
+            Admission::Drop(state.dropped)
+        }
+    }
+
+    /// Forgets the state of the source, e.g. after the end of stream.
+    ///
+    pub fn reset(&mut self, source_id: &str) {
+        self.states.pop(source_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Admission, RateLimit, RateLimitPolicy, RateLimiter, SOURCE_STATES_CACHE_SIZE};
+    use hashbrown::HashMap;
+    use std::num::NonZeroU32;
+    use std::time::{Duration, Instant};
+
+    fn limiter(policy: RateLimitPolicy) -> RateLimiter {
+        let limit = RateLimit {
+            frames_per_second: Some(2.0),
+            bytes_per_second: None,
+            policy,
+        };
+        RateLimiter::new(None, HashMap::from([("cam".to_string(), limit)])).unwrap()
+    }
+
+    #[test]
+    fn test_drop_non_keyframes() {
+        let mut limiter = limiter(RateLimitPolicy::DropNonKeyframes);
+        assert_eq!(limiter.admit("other", false, 0), Admission::Send);
+        assert_eq!(limiter.admit("cam", false, 0), Admission::Send);
+        assert_eq!(limiter.admit("cam", false, 0), Admission::Send);
+        assert_eq!(limiter.admit("cam", false, 0), Admission::Drop(1));
+        assert_eq!(limiter.admit("cam", true, 0), Admission::Send);
+        assert_eq!(limiter.admit("cam", true, 0), Admission::Send);
+        assert_eq!(limiter.admit("cam", true, 0), Admission::Drop(2));
+        limiter.reset("cam");
+        assert_eq!(limiter.admit("cam", false, 0), Admission::Send);
+    }
+
+    #[test]
+    fn test_keep_every_nth() {
+        let mut limiter = limiter(RateLimitPolicy::KeepEveryNth(NonZeroU32::new(3).unwrap()));
+        let sent = (0..11)
+            .filter(|_| limiter.admit("cam", false, 0) == Admission::Send)
+            .count();
+        assert_eq!(sent, 5);
+        let tokens = limiter
+            .states
+            .peek("cam")
+            .unwrap()
+            .frames
+            .as_ref()
+            .unwrap()
+            .tokens;
+        assert!((-2.0..-1.9).contains(&tokens));
+    }
+
+    #[test]
+    fn test_recovery_after_overload() {
+        let mut limiter = limiter(RateLimitPolicy::KeepEveryNth(NonZeroU32::new(3).unwrap()));
+        for _ in 0..10_000 {
+            limiter.admit("cam", false, 0);
+        }
+        let bucket = limiter.states.peek("cam").unwrap().frames.as_ref().unwrap();
+        assert!((-2.0..-1.9).contains(&bucket.tokens));
+        std::thread::sleep(Duration::from_millis(1600));
+        assert_eq!(limiter.admit("cam", false, 0), Admission::Send);
+    }
+
+    #[test]
+    fn test_states_are_evicted() {
+        let limit = RateLimit {
+            frames_per_second: Some(2.0),
+            bytes_per_second: None,
+            policy: RateLimitPolicy::DropNonKeyframes,
+        };
+        let mut limiter = RateLimiter::new(Some(limit), HashMap::new()).unwrap();
+        for i in 0..2 * SOURCE_STATES_CACHE_SIZE {
+            limiter.admit(&format!("cam-{}", i), false, 0);
+        }
+        assert_eq!(limiter.states.len(), SOURCE_STATES_CACHE_SIZE);
+    }
+
+    #[test]
+    fn test_block() {
+        let mut limiter = limiter(RateLimitPolicy::Block);
+        let start = Instant::now();
+        assert_eq!(limiter.admit("cam", false, 0), Admission::Send);
+        assert_eq!(limiter.admit("cam", false, 0), Admission::Send);
+        let Admission::Wait(wait) = limiter.admit("cam", false, 0) else {
+            panic!("Frame exceeding the limit must wait");
+        };
+        assert!(wait > Duration::from_millis(400) && wait <= Duration::from_millis(500));
+        let Admission::Wait(wait) = limiter.admit("cam", false, 0) else {
+            panic!("Frame exceeding the limit must wait");
+        };
+        assert!(wait > Duration::from_millis(900) && wait <= Duration::from_secs(1));
+        assert!(start.elapsed() < Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_bytes_limit() {
+        let limit = RateLimit {
+            frames_per_second: None,
+            bytes_per_second: Some(1000.0),
+            policy: RateLimitPolicy::DropNonKeyframes,
+        };
+        assert!(limit.validate().is_ok());
+        let mut limiter = RateLimiter::new(Some(limit), HashMap::new()).unwrap();
+        assert_eq!(limiter.admit("cam", false, 600), Admission::Send);
+        assert_eq!(limiter.admit("cam", false, 600), Admission::Drop(1));
+        assert_eq!(limiter.admit("cam", false, 300), Admission::Send);
+        assert!(RateLimit {
+            bytes_per_second: Some(0.0),
+            ..limit
+        }
+        .validate()
+        .is_err());
+    }
+}
@@ -4,21 +4,35 @@ use lru::LruCache;
 use parking_lot::Mutex;
 use std::num::NonZeroUsize;
 use std::str::from_utf8;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
 use zmq::Context;
 
-use crate::message::Message;
+use crate::message::{Message, SeqIdStatus, SeqStore};
+use crate::metrics::get_or_create_counter_family;
+use crate::transport::zeromq::compression::decompress_parts;
+use crate::transport::zeromq::reliable::{ack_parts, strip_reliable_marker};
 use crate::transport::zeromq::{
     create_ipc_dirs, set_ipc_permissions, MockSocketResponder, ReaderConfig, ReaderSocketType,
-    RoutingIdFilter, Socket, SocketProvider, CONFIRMATION_MESSAGE, ZMQ_LINGER,
+    RoutingIdFilter, Socket, SocketEvent, SocketEventKind, SocketHealth, SocketMonitor,
+    SocketProvider, SocketReadiness, ZapHandler, CONFIRMATION_MESSAGE, ZMQ_LINGER,
 };
 use crate::utils::bytes_to_hex_string;
 
+const EOS_PEEK_MAX_SIZE: usize = 4096;
+
 pub struct Reader<R: MockSocketResponder, P: SocketProvider<R>> {
     context: Mutex<Option<Context>>,
     config: ReaderConfig,
     socket: Mutex<Option<Socket<R>>>,
+    readiness: Mutex<Option<Arc<SocketReadiness>>>,
+    zap_handler: Mutex<Option<ZapHandler>>,
     routing_id_filter: Mutex<RoutingIdFilter>,
     source_blacklist_cache: Mutex<LruCache<Vec<u8>, u64>>,
+    seq_store: Mutex<SeqStore>,
+    health: Arc<SocketHealth>,
+    monitor: Mutex<Option<SocketMonitor>>,
+    timeouts: AtomicU32,
     phony: std::marker::PhantomData<P>,
 }
 
@@ -39,6 +53,8 @@ pub enum ReaderResult {
         topic: Vec<u8>,
         routing_id: Option<Vec<u8>>,
         data: Vec<Vec<u8>>,
+        /// The result of the `seq_id` check, `None` when the reader does not track sequences.
+        seq_id_status: Option<SeqIdStatus>,
     },
     Timeout,
     PrefixMismatch {
@@ -51,6 +67,18 @@ pub enum ReaderResult {
     },
     TooShort(Vec<Vec<u8>>),
     Blacklisted(Vec<u8>),
+    /// The message is compressed with an unsupported codec or cannot be decompressed.
+    DecompressionFailed {
+        topic: Vec<u8>,
+        routing_id: Option<Vec<u8>>,
+        error: String,
+    },
+    /// The message sent in the reliable mode is already delivered, it is acknowledged again.
+    Duplicate {
+        topic: Vec<u8>,
+        routing_id: Option<Vec<u8>>,
+        seq_id: u64,
+    },
 }
 
 impl ReaderResult {
@@ -59,12 +87,14 @@ impl ReaderResult {
         topic: &[u8],
         routing_id: &Option<&Vec<u8>>,
         data: &[Vec<u8>],
+        seq_id_status: Option<SeqIdStatus>,
     ) -> Self {
         Self::Message {
             message: Box::new(message),
             topic: topic.to_vec(),
             routing_id: routing_id.cloned(),
             data: data.to_vec(),
+            seq_id_status,
         }
     }
 
@@ -89,10 +119,53 @@ impl<R: MockSocketResponder, P: SocketProvider<R> + Default> Reader<R, P> {
         let socket_provider = P::default();
         let socket = socket_provider.new_socket(&context, config.socket_type().into())?;
 
+        let mut zap_handler = None;
+        if let Some(curve) = config.curve() {
+            zap_handler = socket.start_zap_handler(&context, curve)?;
+        }
+
+        let health = SocketHealth::register(config.endpoint(), "reader");
+        let monitor = Self::setup_socket(&socket, &context, config, &health)?;
+
+        Ok(Self {
+            context: Mutex::new(Some(context)),
+            config: config.clone(),
+            socket: Mutex::new(Some(socket)),
+            readiness: Mutex::new(None),
+            zap_handler: Mutex::new(zap_handler),
+            routing_id_filter: Mutex::new(RoutingIdFilter::new(*config.routing_cache_size())?),
+            source_blacklist_cache: Mutex::new(LruCache::new(
+                NonZeroUsize::new(*config.source_blacklist_size() as usize).ok_or(
+                    anyhow::anyhow!("Source blacklist cache size must be greater than 0"),
+                )?,
+            )),
+            seq_store: Mutex::new(SeqStore::with_capacity(
+                NonZeroUsize::new(*config.seq_store_size())
+                    .ok_or(anyhow::anyhow!("Seq store size must be greater than 0"))?,
+            )),
+            health,
+            monitor: Mutex::new(monitor),
+            timeouts: AtomicU32::new(0),
+            phony: std::marker::PhantomData,
+        })
+    }
+
+    fn setup_socket(
+        socket: &Socket<R>,
+        context: &Context,
+        config: &ReaderConfig,
+        health: &Arc<SocketHealth>,
+    ) -> anyhow::Result<Option<SocketMonitor>> {
+        let monitor = socket.start_monitor(context, health)?;
+
         socket.set_rcvhwm(*config.receive_hwm())?;
         socket.set_rcvtimeo(*config.receive_timeout())?;
         socket.set_linger(ZMQ_LINGER)?;
 
+        if let Some(curve) = config.curve() {
+            socket.set_curve(curve)?;
+        }
+
         if config.socket_type() == &ReaderSocketType::Sub {
             socket.set_subscribe(config.topic_prefix_spec().get().as_bytes())?;
         }
@@ -112,19 +185,88 @@ impl<R: MockSocketResponder, P: SocketProvider<R> + Default> Reader<R, P> {
         } else {
             socket.connect(config.endpoint())?;
         }
+        Ok(monitor)
+    }
 
-        Ok(Self {
-            context: Mutex::new(Some(context)),
-            config: config.clone(),
-            socket: Mutex::new(Some(socket)),
-            routing_id_filter: Mutex::new(RoutingIdFilter::new(*config.routing_cache_size())?),
-            source_blacklist_cache: Mutex::new(LruCache::new(
-                NonZeroUsize::new(*config.source_blacklist_size() as usize).ok_or(
-                    anyhow::anyhow!("Source blacklist cache size must be greater than 0"),
-                )?,
-            )),
-            phony: std::marker::PhantomData,
-        })
+    /// Recreates the socket, the messages queued in the old socket are lost.
+    ///
+    fn reconnect(&self) -> anyhow::Result<()> {
+        warn!(
+            target: "savant_rs::zeromq::reader",
+            "Recreating ZeroMQ socket for endpoint {} after {} receive timeouts",
+            self.config.endpoint(),
+            self.timeouts.load(Ordering::Relaxed)
+        );
+        let context_bind = self.context.lock();
+        let Some(context) = context_bind.as_ref() else {
+            bail!("ZeroMQ context is no longer available");
+        };
+        let mut socket_bind = self.socket.lock();
+        let mut monitor_bind = self.monitor.lock();
+        self.readiness.lock().take();
+        socket_bind.take();
+        monitor_bind.take();
+        self.timeouts.store(0, Ordering::Relaxed);
+        self.health.push_event(SocketEvent {
+            kind: SocketEventKind::Reconnected,
+            address: self.config.endpoint().clone(),
+            value: 0,
+        });
+        let socket = P::default().new_socket(context, self.config.socket_type().into())?;
+        *monitor_bind = Self::setup_socket(&socket, context, &self.config, &self.health)?;
+        *socket_bind = Some(socket);
+        Ok(())
+    }
+
+    /// The socket has at least one connected peer.
+    ///
+    pub fn is_connected(&self) -> bool {
+        self.health.is_connected()
+    }
+
+    /// Returns the socket events received since the previous call.
+    ///
+    pub fn take_socket_events(&self) -> Vec<SocketEvent> {
+        self.health.take_events()
+    }
+
+    fn report_seq_id_status(&self, message: &Message, status: &SeqIdStatus) {
+        let (name, description, increment) = match status {
+            SeqIdStatus::Valid => return,
+            SeqIdStatus::Gap { .. } => (
+                "reader_lost_message_counter",
+                "Number of messages lost according to the gaps in seq_id",
+                status.lost(),
+            ),
+            SeqIdStatus::Duplicate { .. } => (
+                "reader_duplicate_message_counter",
+                "Number of duplicate or reordered messages",
+                1,
+            ),
+            SeqIdStatus::Reset { .. } => (
+                "reader_seq_id_reset_counter",
+                "Number of seq_id resets without end-of-stream",
+                1,
+            ),
+        };
+        let source_id = if let Some(frame) = message.as_video_frame() {
+            frame.get_source_id()
+        } else if let Some(user_data) = message.as_user_data() {
+            user_data.get_source_id().to_string()
+        } else {
+            return;
+        };
+        let counter =
+            get_or_create_counter_family(name, Some(description), &["endpoint", "source_id"], None);
+        let res = counter.lock().inc(
+            increment,
+            &[self.config.endpoint().as_str(), source_id.as_str()],
+        );
+        if let Err(e) = res {
+            warn!(
+                target: "savant_rs::zeromq::reader",
+                "Failed to update counter {}: {:?}", name, e);
+        }
     }
 
     pub fn destroy(&self) -> anyhow::Result<()> {
@@ -133,7 +275,12 @@ impl<R: MockSocketResponder, P: SocketProvider<R> + Default> Reader<R, P> {
             "Destroying ZeroMQ socket for endpoint {}",
             self.config.endpoint()
         );
-        self.socket.lock().take();
+        let mut socket_bind = self.socket.lock();
+        self.readiness.lock().take();
+        socket_bind.take();
+        drop(socket_bind);
+        self.monitor.lock().take();
+        self.zap_handler.lock().take();
         self.context.lock().take();
         info!(
             target: "savant_rs::zeromq::reader",
@@ -195,6 +342,68 @@ impl<R: MockSocketResponder, P: SocketProvider<R> + Default> Reader<R, P> {
     }
 
     pub fn receive(&self) -> anyhow::Result<ReaderResult> {
+        match self.receive_parts(0)? {
+            Some(parts) => self.process_parts(parts),
+            None => self.on_receive_timeout(),
+        }
+    }
+
+    /// Receives the message if it is already queued in the socket, returns `None` otherwise.
+    ///
+    pub(super) fn try_receive(&self) -> anyhow::Result<Option<ReaderResult>> {
+        match self.receive_parts(zmq::DONTWAIT)? {
+            Some(parts) => Ok(Some(self.process_parts(parts)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// The registration of the current socket used by the async waits, the same one until the
+    /// socket is recreated.
+    ///
+    pub(super) fn readiness(&self) -> anyhow::Result<Arc<SocketReadiness>> {
+        let socket_bind = self.socket.lock();
+        let Some(socket) = socket_bind.as_ref() else {
+            bail!(
+                "ZeroMQ socket for endpoint {} is no longer available, because it was destroyed.",
+                self.config.endpoint()
+            );
+        };
+        let mut readiness = self.readiness.lock();
+        if let Some(readiness) = readiness.as_ref() {
+            return Ok(readiness.clone());
+        }
+        let created = Arc::new(SocketReadiness::new(socket.get_fd()?)?);
+        *readiness = Some(created.clone());
+        Ok(created)
+    }
+
+    pub(super) fn get_events(&self) -> anyhow::Result<zmq::PollEvents> {
+        match self.socket.lock().as_ref() {
+            Some(socket) => socket.get_events(),
+            None => bail!(
+                "ZeroMQ socket for endpoint {} is no longer available, because it was destroyed.",
+                self.config.endpoint()
+            ),
+        }
+    }
+
+    /// Counts the receive timeout and recreates the socket when the peers are gone for too long.
+    ///
+    pub(super) fn on_receive_timeout(&self) -> anyhow::Result<ReaderResult> {
+        debug!(
+            target: "savant_rs::zeromq::reader",
+            "Failed to receive message from ZeroMQ socket due to timeout (EAGAIN)"
+        );
+        let timeouts = self.timeouts.fetch_add(1, Ordering::Relaxed) + 1;
+        if let Some(reconnect_after) = self.config.reconnect_after() {
+            if timeouts >= *reconnect_after && !self.health.is_connected() {
+                self.reconnect()?;
+            }
+        }
+        Ok(ReaderResult::Timeout)
+    }
+
+    fn receive_parts(&self, flags: i32) -> anyhow::Result<Option<Vec<Vec<u8>>>> {
         if self.socket.lock().is_none() {
             bail!(
                 "ZeroMQ socket for endpoint {} is no longer available, because it was destroyed.",
@@ -209,21 +418,20 @@ impl<R: MockSocketResponder, P: SocketProvider<R> + Default> Reader<R, P> {
         let parts = {
             let mut bind = self.socket.lock();
             let socket = bind.as_mut().unwrap();
-            socket.recv_multipart(0)
+            socket.recv_multipart(flags)
         };
 
-        debug!(
-            target: "savant_rs::zeromq::reader",
-            "Received message from ZeroMQ socket for endpoint {}",
-            self.config.endpoint());
-        if let Err(e) = parts {
-            if let zmq::Error::EAGAIN = e {
+        match parts {
+            Ok(parts) => {
                 debug!(
                     target: "savant_rs::zeromq::reader",
-                    "Failed to receive message from ZeroMQ socket due to timeout (EAGAIN)"
-                );
-                return Ok(ReaderResult::Timeout);
-            } else {
+                    "Received message from ZeroMQ socket for endpoint {}",
+                    self.config.endpoint());
+                self.timeouts.store(0, Ordering::Relaxed);
+                Ok(Some(parts))
+            }
+            Err(zmq::Error::EAGAIN) => Ok(None),
+            Err(e) => {
                 error!(
                     target: "savant_rs::zeromq::reader",
                     "Failed to receive message from ZeroMQ socket. Error is [{}] {:?}",
@@ -236,9 +444,9 @@ impl<R: MockSocketResponder, P: SocketProvider<R> + Default> Reader<R, P> {
                 );
             }
         }
+    }
 
-        let parts = parts.unwrap();
-
+    fn process_parts(&self, parts: Vec<Vec<u8>>) -> anyhow::Result<ReaderResult> {
         let min_required_parts = match self.config.socket_type() {
             ReaderSocketType::Sub => 2,
             ReaderSocketType::Router => 3,
@@ -266,6 +474,78 @@ impl<R: MockSocketResponder, P: SocketProvider<R> + Default> Reader<R, P> {
             } else {
                 (None, &parts[0], &parts[1], &parts[2..])
             };
+
+        let peer = routing_id.map(|r| r.as_slice()).unwrap_or_default();
+        let (reliable_seq_id, command) = match strip_reliable_marker(command) {
+            Some((seq_id, command)) => {
+                if self.seq_store.lock().is_delivered(peer, topic, seq_id) {
+                    debug!(
+                        target: "savant_rs::zeromq::reader",
+                        "Received duplicate message {} with topic {} from ZeroMQ socket for endpoint {}",
+                        seq_id,
+                        from_utf8(topic).unwrap_or(&bytes_to_hex_string(topic)),
+                        self.config.endpoint()
+                    );
+                    self.acknowledge(routing_id, topic, seq_id)?;
+                    return Ok(ReaderResult::Duplicate {
+                        topic: topic.clone(),
+                        routing_id: routing_id.cloned(),
+                        seq_id,
+                    });
+                }
+                (Some(seq_id), command)
+            }
+            None => (None, command.as_slice()),
+        };
+
+        let result =
+            self.handle_message(routing_id, topic, command, extra, reliable_seq_id.is_some());
+        // the message which failed to decompress is not acknowledged to get it retransmitted,
+        // the one which failed to decode is, because the retransmission fails the same way
+        if let Some(seq_id) = reliable_seq_id {
+            if !matches!(result, Ok(ReaderResult::DecompressionFailed { .. })) {
+                self.seq_store.lock().register_delivery(peer, topic, seq_id);
+                self.acknowledge(routing_id, topic, seq_id)?;
+            }
+        }
+        result
+    }
+
+    fn acknowledge(
+        &self,
+        routing_id: Option<&Vec<u8>>,
+        topic: &[u8],
+        seq_id: u64,
+    ) -> anyhow::Result<()> {
+        if let Some(routing_id) = routing_id {
+            let [ack, ack_topic, ack_seq_id] = ack_parts(topic, seq_id);
+            let mut bind = self.socket.lock();
+            let socket = bind.as_mut().unwrap();
+            socket.send_multipart(&[routing_id, &ack, &ack_topic, &ack_seq_id], 0)?;
+        }
+        Ok(())
+    }
+
+    /// Checks if a message which is not going to be decoded is the end-of-stream. Only the
+    /// messages short enough to be one are decoded.
+    fn is_end_of_stream(command: &[u8], extra: &[Vec<u8>]) -> bool {
+        if !extra.is_empty() || command.len() > EOS_PEEK_MAX_SIZE {
+            return false;
+        }
+        decompress_parts(command, extra, EOS_PEEK_MAX_SIZE)
+            .ok()
+            .and_then(|(command, _)| crate::protobuf::deserialize(&command).ok())
+            .is_some_and(|m: Message| m.is_end_of_stream())
+    }
+
+    fn handle_message(
+        &self,
+        routing_id: Option<&Vec<u8>>,
+        topic: &[u8],
+        command: &[u8],
+        extra: &[Vec<u8>],
+        reliable: bool,
+    ) -> anyhow::Result<ReaderResult> {
         if self.is_blacklisted(topic) {
             debug!(
                 target: "savant_rs::zeromq::reader",
@@ -279,12 +559,70 @@ impl<R: MockSocketResponder, P: SocketProvider<R> + Default> Reader<R, P> {
                 socket.send(CONFIRMATION_MESSAGE, 0)?;
             }
 
-            return Ok(ReaderResult::Blacklisted(topic.clone()));
+            return Ok(ReaderResult::Blacklisted(topic.to_vec()));
+        }
+
+        if !self.config.topic_filter().matches(topic) {
+            debug!(
+                target: "savant_rs::zeromq::reader",
+                "Received message with filtered out topic {} from ZeroMQ socket for endpoint {}",
+                from_utf8(topic).unwrap_or(&bytes_to_hex_string(topic)),
+                self.config.endpoint()
+            );
+            let mut bind = self.socket.lock();
+            let socket = bind.as_mut().unwrap();
+            if self.config.socket_type() == &ReaderSocketType::Rep {
+                socket.send(CONFIRMATION_MESSAGE, 0)?;
+            } else if let Some(routing_id) = routing_id {
+                // the writer waits for the end-of-stream to be confirmed
+                if Self::is_end_of_stream(command, extra) {
+                    socket.send_multipart(&[routing_id, CONFIRMATION_MESSAGE], 0)?;
+                }
+            }
+
+            return Ok(ReaderResult::PrefixMismatch {
+                topic: topic.to_vec(),
+                routing_id: routing_id.cloned(),
+            });
         }
 
-        let message = Box::new(crate::protobuf::deserialize(command)?);
+        let (command, extra) = match decompress_parts(
+            command,
+            extra,
+            *self.config.max_decompressed_size(),
+        ) {
+            Ok(parts) => parts,
+            Err(e) => {
+                warn!(
+                    target: "savant_rs::zeromq::reader",
+                    "Failed to decompress message from ZeroMQ socket for endpoint {}. Topic is {}, error is {:?}",
+                    self.config.endpoint(),
+                    from_utf8(topic).unwrap_or(&bytes_to_hex_string(topic)),
+                    e
+                );
+                if self.config.socket_type() == &ReaderSocketType::Rep {
+                    let mut bind = self.socket.lock();
+                    let socket = bind.as_mut().unwrap();
+                    socket.send(CONFIRMATION_MESSAGE, 0)?;
+                }
+                return Ok(ReaderResult::DecompressionFailed {
+                    topic: topic.to_vec(),
+                    routing_id: routing_id.cloned(),
+                    error: e.to_string(),
+                });
+            }
+        };
+
+        let message = Box::new(crate::protobuf::deserialize(&command)?);
 
         if message.is_end_of_stream() {
+            let seq_id_status = {
+                let mut seq_store = self.seq_store.lock();
+                // the sequence of the source is reset by the check like the writer resets it
+                // by the source of the end of stream, the deliveries are tracked by the topic
+                seq_store.reset_deliveries(topic);
+                seq_store.check_seq_id(&message)
+            };
             if self.config.socket_type() != &ReaderSocketType::Sub {
                 debug!(
                     target: "savant_rs::zeromq::reader",
@@ -302,9 +640,10 @@ impl<R: MockSocketResponder, P: SocketProvider<R> + Default> Reader<R, P> {
 
             return Ok(ReaderResult::Message {
                 message,
-                topic: topic.clone(),
+                topic: topic.to_vec(),
                 routing_id: routing_id.cloned(),
                 data: vec![],
+                seq_id_status: Some(seq_id_status),
             });
         }
 
@@ -323,7 +662,7 @@ impl<R: MockSocketResponder, P: SocketProvider<R> + Default> Reader<R, P> {
             }
 
             return Ok(ReaderResult::PrefixMismatch {
-                topic: topic.clone(),
+                topic: topic.to_vec(),
                 routing_id: routing_id.cloned(),
             });
         }
@@ -335,11 +674,30 @@ impl<R: MockSocketResponder, P: SocketProvider<R> + Default> Reader<R, P> {
         }
 
         if self.routing_id_filter.lock().allow(topic, &routing_id) {
+            let mut seq_id_status = self.seq_store.lock().check_seq_id(&message);
+            // the reliable window retransmits the lost messages, so they arrive out of order
+            // instead of being lost
+            if reliable
+                && matches!(
+                    seq_id_status,
+                    SeqIdStatus::Gap { .. } | SeqIdStatus::Duplicate { .. }
+                )
+            {
+                debug!(
+                    target: "savant_rs::zeromq::reader",
+                    "Reliable message from ZeroMQ socket for endpoint {} is out of order: {:?}",
+                    self.config.endpoint(),
+                    seq_id_status
+                );
+                seq_id_status = SeqIdStatus::Valid;
+            }
+            self.report_seq_id_status(&message, &seq_id_status);
             Ok(ReaderResult::Message {
                 message,
-                topic: topic.clone(),
+                topic: topic.to_vec(),
                 routing_id: routing_id.cloned(),
                 data: extra.iter().map(|e| e.to_vec()).collect(),
+                seq_id_status: Some(seq_id_status),
             })
         } else {
             debug!(
@@ -358,13 +716,15 @@ impl<R: MockSocketResponder, P: SocketProvider<R> + Default> Reader<R, P> {
 #[cfg(test)]
 mod tests {
     mod router_tests {
-        use crate::message::Message;
+        use crate::message::{Message, SeqIdStatus};
+        use crate::metrics::get_counter_family;
         use crate::primitives::eos::EndOfStream;
         use crate::primitives::userdata::UserData;
         use crate::protobuf::serialize;
         use crate::transport::zeromq::reader::ReaderResult;
+        use crate::transport::zeromq::reliable::{ack_parts, mark_reliable};
         use crate::transport::zeromq::{
-            MockSocketProvider, NoopResponder, Reader, ReaderConfig, TopicPrefixSpec,
+            MockSocketProvider, NoopResponder, Reader, ReaderConfig, TopicFilter, TopicPrefixSpec,
             CONFIRMATION_MESSAGE,
         };
 
@@ -393,7 +753,8 @@ mod tests {
                     message,
                     topic,
                     routing_id,
-                    data
+                    data,
+                    ..
                 } if message.is_user_data() && topic == b"topic" && routing_id == &Some(b"routing-id".to_vec()) && data == &vec![vec![0x0, 0x1, 0x2]]
             ));
             assert_eq!(
@@ -402,6 +763,241 @@ mod tests {
             );
             Ok(())
         }
+        #[test]
+        fn test_unsupported_compression() -> anyhow::Result<()> {
+            let conf = ReaderConfig::new()
+                .url("router+bind:ipc:///tmp/test")?
+                .build()?;
+
+            let reader = Reader::<NoopResponder, MockSocketProvider>::new(&conf)?;
+            reader
+                .socket
+                .lock()
+                .as_mut()
+                .unwrap()
+                .send_multipart(&[b"routing-id", b"topic", &[0xFF, 42, 1, 0x0, 0x1]], 0)?;
+
+            let m = reader.receive()?;
+            assert!(matches!(
+                &m,
+                ReaderResult::DecompressionFailed { topic, routing_id, error }
+                    if topic == b"topic" && routing_id == &Some(b"routing-id".to_vec()) && error.contains("42")
+            ));
+            Ok(())
+        }
+
+        #[test]
+        fn test_reliable_duplicate() -> anyhow::Result<()> {
+            let conf = ReaderConfig::new()
+                .url("router+bind:ipc:///tmp/test")?
+                .build()?;
+
+            let reader = Reader::<NoopResponder, MockSocketProvider>::new(&conf)?;
+            let mut binary =
+                crate::message::save_message(&Message::user_data(UserData::new("topic")))?;
+            mark_reliable(&mut binary, 7);
+            let ack = ack_parts(b"topic", 7);
+            let expected_ack = vec![
+                b"routing-id".to_vec(),
+                ack[0].clone(),
+                ack[1].clone(),
+                ack[2].clone(),
+            ];
+            for duplicate in [false, true] {
+                reader
+                    .socket
+                    .lock()
+                    .as_mut()
+                    .unwrap()
+                    .send_multipart(&[b"routing-id", b"topic", &binary], 0)?;
+                let m = reader.receive()?;
+                if duplicate {
+                    assert!(matches!(m, ReaderResult::Duplicate { seq_id, .. } if seq_id == 7));
+                } else {
+                    assert!(
+                        matches!(m, ReaderResult::Message { message, .. } if message.is_user_data())
+                    );
+                }
+                assert_eq!(
+                    reader.socket.lock().as_mut().unwrap().take_buffer(),
+                    expected_ack
+                );
+            }
+            Ok(())
+        }
+
+        #[test]
+        fn test_reliable_ack_after_accept() -> anyhow::Result<()> {
+            let conf = ReaderConfig::new()
+                .url("router+bind:ipc:///tmp/test")?
+                .build()?;
+
+            let reader = Reader::<NoopResponder, MockSocketProvider>::new(&conf)?;
+            let mut broken = vec![0xFF, 42, 1, 0x0, 0x1];
+            mark_reliable(&mut broken, 7);
+            reader
+                .socket
+                .lock()
+                .as_mut()
+                .unwrap()
+                .send_multipart(&[b"routing-id", b"topic", &broken], 0)?;
+            let m = reader.receive()?;
+            assert!(matches!(m, ReaderResult::DecompressionFailed { .. }));
+            assert!(reader
+                .socket
+                .lock()
+                .as_mut()
+                .unwrap()
+                .take_buffer()
+                .is_empty());
+
+            // the retransmitted message is not a duplicate
+            let mut binary =
+                crate::message::save_message(&Message::user_data(UserData::new("topic")))?;
+            mark_reliable(&mut binary, 7);
+            for routing_id in [b"routing-id", b"other-peer"] {
+                reader
+                    .socket
+                    .lock()
+                    .as_mut()
+                    .unwrap()
+                    .send_multipart(&[routing_id, b"topic", &binary], 0)?;
+                let m = reader.receive()?;
+                assert!(
+                    matches!(m, ReaderResult::Message { message, .. } if message.is_user_data())
+                );
+                let ack = ack_parts(b"topic", 7);
+                assert_eq!(
+                    reader.socket.lock().as_mut().unwrap().take_buffer(),
+                    vec![
+                        routing_id.to_vec(),
+                        ack[0].clone(),
+                        ack[1].clone(),
+                        ack[2].clone()
+                    ]
+                );
+            }
+            Ok(())
+        }
+
+        #[test]
+        fn test_seq_id_gap() -> anyhow::Result<()> {
+            let conf = ReaderConfig::new()
+                .url("router+bind:ipc:///tmp/test-seq-id-gap")?
+                .build()?;
+
+            let reader = Reader::<NoopResponder, MockSocketProvider>::new(&conf)?;
+            let messages = (0..4)
+                .map(|_| Message::user_data(UserData::new("seq-id-gap")))
+                .collect::<Vec<_>>();
+            let mut statuses = vec![];
+            for m in [&messages[0], &messages[2], &messages[1], &messages[3]] {
+                let binary = crate::message::save_message(m)?;
+                reader
+                    .socket
+                    .lock()
+                    .as_mut()
+                    .unwrap()
+                    .send_multipart(&[b"routing-id", b"topic", &binary], 0)?;
+                match reader.receive()? {
+                    ReaderResult::Message { seq_id_status, .. } => statuses.push(seq_id_status),
+                    res => panic!("Unexpected result: {:?}", res),
+                }
+            }
+            let first = messages[0].meta.seq_id;
+            assert_eq!(
+                statuses,
+                vec![
+                    Some(SeqIdStatus::Valid),
+                    Some(SeqIdStatus::Gap {
+                        expected: first + 1,
+                        received: first + 2
+                    }),
+                    Some(SeqIdStatus::Duplicate {
+                        expected: first + 3,
+                        received: first + 1
+                    }),
+                    Some(SeqIdStatus::Valid),
+                ]
+            );
+            let lost = get_counter_family("reader_lost_message_counter")
+                .unwrap()
+                .lock()
+                .get(&[conf.endpoint().as_str(), "seq-id-gap"])?;
+            assert_eq!(lost, Some(1));
+            Ok(())
+        }
+
+        #[test]
+        fn test_reliable_out_of_order() -> anyhow::Result<()> {
+            let conf = ReaderConfig::new()
+                .url("router+bind:ipc:///tmp/test-reliable-out-of-order")?
+                .build()?;
+
+            let reader = Reader::<NoopResponder, MockSocketProvider>::new(&conf)?;
+            let messages = (0..4)
+                .map(|_| Message::user_data(UserData::new("reliable-out-of-order")))
+                .collect::<Vec<_>>();
+            for i in [0, 2, 1, 3] {
+                let mut binary = crate::message::save_message(&messages[i])?;
+                mark_reliable(&mut binary, i as u64 + 1);
+                reader
+                    .socket
+                    .lock()
+                    .as_mut()
+                    .unwrap()
+                    .send_multipart(&[b"routing-id", b"topic", &binary], 0)?;
+                match reader.receive()? {
+                    ReaderResult::Message { seq_id_status, .. } => {
+                        assert_eq!(seq_id_status, Some(SeqIdStatus::Valid))
+                    }
+                    res => panic!("Unexpected result: {:?}", res),
+                }
+            }
+            for counter in [
+                "reader_lost_message_counter",
+                "reader_duplicate_message_counter",
+            ] {
+                let value = get_counter_family(counter).and_then(|c| {
+                    c.lock()
+                        .get(&[conf.endpoint().as_str(), "reliable-out-of-order"])
+                        .unwrap()
+                });
+                assert_eq!(value, None);
+            }
+            Ok(())
+        }
+
+        #[test]
+        fn test_reliable_ack_of_undecodable() -> anyhow::Result<()> {
+            let conf = ReaderConfig::new()
+                .url("router+bind:ipc:///tmp/test")?
+                .build()?;
+
+            let reader = Reader::<NoopResponder, MockSocketProvider>::new(&conf)?;
+            // a truncated protobuf field
+            let mut broken = vec![0x0a, 0xff];
+            mark_reliable(&mut broken, 7);
+            reader
+                .socket
+                .lock()
+                .as_mut()
+                .unwrap()
+                .send_multipart(&[b"routing-id", b"topic", &broken], 0)?;
+            assert!(reader.receive().is_err());
+            let ack = ack_parts(b"topic", 7);
+            assert_eq!(
+                reader.socket.lock().as_mut().unwrap().take_buffer(),
+                vec![
+                    b"routing-id".to_vec(),
+                    ack[0].clone(),
+                    ack[1].clone(),
+                    ack[2].clone()
+                ]
+            );
+            Ok(())
+        }
+
         #[test]
         fn test_empty_multipart() -> anyhow::Result<()> {
             let conf = ReaderConfig::new()
@@ -466,7 +1062,7 @@ mod tests {
             )?;
             let m = reader.receive()?;
             assert!(
-                matches!(m, ReaderResult::Message {message,topic,routing_id,data }
+                matches!(m, ReaderResult::Message {message,topic,routing_id,data,..}
                     if message.is_end_of_stream() && topic == b"topic" && routing_id == Some(b"routing-id".to_vec()) && data.is_empty())
             );
             assert_eq!(
@@ -476,6 +1072,59 @@ mod tests {
             Ok(())
         }
 
+        #[test]
+        fn test_eos_resets_source() -> anyhow::Result<()> {
+            let conf = ReaderConfig::new()
+                .url("router+bind:ipc:///tmp/test")?
+                .build()?;
+
+            let reader = Reader::<NoopResponder, MockSocketProvider>::new(&conf)?;
+            let receive = |parts: &[&[u8]]| -> anyhow::Result<ReaderResult> {
+                reader
+                    .socket
+                    .lock()
+                    .as_mut()
+                    .unwrap()
+                    .send_multipart(parts, 0)?;
+                let res = reader.receive()?;
+                reader.socket.lock().as_mut().unwrap().take_buffer();
+                Ok(res)
+            };
+            // the source named like the topic is not reset by the EOS of another source
+            let others = (0..3)
+                .map(|_| Message::user_data(UserData::new("topic")))
+                .collect::<Vec<_>>();
+            receive(&[b"routing-id", b"topic", &serialize(&others[0])?])?;
+            // the topic differs from the source, the sequence starts over after the EOS
+            for _ in 0..2 {
+                let m = Message::user_data(UserData::new("eos-resets-source"));
+                let mut binary = crate::message::save_message(&m)?;
+                mark_reliable(&mut binary, 1);
+                let res = receive(&[b"routing-id", b"topic", &binary])?;
+                assert!(matches!(
+                    res,
+                    ReaderResult::Message {
+                        seq_id_status: Some(SeqIdStatus::Valid),
+                        ..
+                    }
+                ));
+                let eos = Message::end_of_stream(EndOfStream::new("eos-resets-source".into()));
+                let res = receive(&[b"routing-id", b"topic", &serialize(&eos)?])?;
+                assert!(
+                    matches!(res, ReaderResult::Message { message, .. } if message.is_end_of_stream())
+                );
+            }
+            let res = receive(&[b"routing-id", b"topic", &serialize(&others[2])?])?;
+            assert!(matches!(
+                res,
+                ReaderResult::Message {
+                    seq_id_status: Some(SeqIdStatus::Gap { .. }),
+                    ..
+                }
+            ));
+            Ok(())
+        }
+
         #[test]
         fn test_too_short_routing_and_topic() -> anyhow::Result<()> {
             let conf = ReaderConfig::new()
@@ -600,6 +1249,86 @@ mod tests {
             Ok(())
         }
 
+        #[test]
+        fn test_topic_filter() -> anyhow::Result<()> {
+            let conf = ReaderConfig::new()
+                .url("router+bind:ipc:///tmp/test")?
+                .with_topic_filter(
+                    TopicFilter::new()
+                        .with_source_ids(["cam-1"])
+                        .with_glob("lobby/*")?
+                        .exclude_regex(r"lobby/\d+-test")?,
+                )?
+                .build()?;
+
+            let message = Message::user_data(UserData::new("test"));
+            let binary = crate::message::save_message(&message)?;
+
+            let reader = Reader::<NoopResponder, MockSocketProvider>::new(&conf)?;
+            for (topic, payload, passes) in [
+                (&b"cam-1"[..], binary.as_slice(), true),
+                (b"lobby/1", binary.as_slice(), true),
+                (b"lobby/1-test", binary.as_slice(), false),
+                // not decoded when filtered out
+                (b"cam-2", b"garbage".as_slice(), false),
+            ] {
+                reader
+                    .socket
+                    .lock()
+                    .as_mut()
+                    .unwrap()
+                    .send_multipart(&[b"routing-id", topic, payload], 0)?;
+                let m = reader.receive()?;
+                if passes {
+                    assert!(matches!(m, ReaderResult::Message { .. }));
+                } else {
+                    assert!(matches!(
+                        m,
+                        ReaderResult::PrefixMismatch { topic: t, .. } if t == topic
+                    ));
+                }
+            }
+            Ok(())
+        }
+
+        #[test]
+        fn test_topic_filter_confirms_eos() -> anyhow::Result<()> {
+            let conf = ReaderConfig::new()
+                .url("router+bind:ipc:///tmp/test")?
+                .with_topic_filter(TopicFilter::new().exclude_source_ids(["topic"]))?
+                .build()?;
+
+            let reader = Reader::<NoopResponder, MockSocketProvider>::new(&conf)?;
+            let eos = crate::message::save_message(&Message::end_of_stream(EndOfStream::new(
+                "topic".to_string(),
+            )))?;
+            let user_data =
+                crate::message::save_message(&Message::user_data(UserData::new("test")))?;
+            for (payload, confirmed) in [(eos, true), (user_data, false)] {
+                reader
+                    .socket
+                    .lock()
+                    .as_mut()
+                    .unwrap()
+                    .send_multipart(&[b"routing-id", b"topic", &payload], 0)?;
+                let m = reader.receive()?;
+                assert!(matches!(
+                    m,
+                    ReaderResult::PrefixMismatch { topic, routing_id } if topic == b"topic" && routing_id == Some(b"routing-id".to_vec())
+                ));
+                let expected = if confirmed {
+                    vec![b"routing-id".to_vec(), CONFIRMATION_MESSAGE.to_vec()]
+                } else {
+                    vec![]
+                };
+                assert_eq!(
+                    reader.socket.lock().as_mut().unwrap().take_buffer(),
+                    expected
+                );
+            }
+            Ok(())
+        }
+
         #[test]
         fn test_message_and_extra_parts() -> anyhow::Result<()> {
             let conf = ReaderConfig::new()
@@ -627,7 +1356,8 @@ mod tests {
                     message,
                     topic,
                     routing_id,
-                    data
+                    data,
+                    ..
                 } if message.is_user_data() && topic == b"topic2" && routing_id == Some(b"routing-id".to_vec()) && data == vec![b"extra"]
             ));
             Ok(())
@@ -668,7 +1398,8 @@ mod tests {
                     message,
                     topic,
                     routing_id,
-                    data
+                    data,
+                    ..
                 } if message.is_user_data() && topic == b"topic" && routing_id == &None && data == &vec![vec![0x0, 0x1, 0x2]]
             ));
             assert_eq!(
@@ -752,7 +1483,8 @@ mod tests {
                     message,
                     topic,
                     routing_id,
-                    data
+                    data,
+                    ..
                 } if message.is_user_data() && topic == b"topic" && routing_id == &None && data == &vec![vec![0x0, 0x1, 0x2]]
             ));
             assert_eq!(
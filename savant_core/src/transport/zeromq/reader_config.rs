@@ -1,11 +1,12 @@
 use super::{
-    parse_zmq_socket_uri, ReaderSocketType, SocketType, TopicPrefixSpec, IPC_PERMISSIONS,
-    RECEIVE_HWM, RECEIVE_TIMEOUT, ROUTING_ID_CACHE_SIZE, SOURCE_BLACKLIST_CACHE_EXPIRATION,
-    SOURCE_BLACKLIST_CACHE_SIZE,
+    parse_zmq_socket_uri, CurveConfig, ReaderSocketType, SocketType, TopicFilter, TopicPrefixSpec,
+    IPC_PERMISSIONS, MAX_DECOMPRESSED_SIZE, RECEIVE_HWM, RECEIVE_TIMEOUT, ROUTING_ID_CACHE_SIZE,
+    SOURCE_BLACKLIST_CACHE_EXPIRATION, SOURCE_BLACKLIST_CACHE_SIZE,
 };
+use crate::message::DEFAULT_SEQ_STORE_SIZE;
 use crate::utils::default_once::DefaultOnceCell;
 use anyhow::bail;
-use std::num::NonZeroU64;
+use std::num::{NonZeroU32, NonZeroU64, NonZeroUsize};
 
 #[derive(Clone, Debug, Default)]
 pub struct ReaderConfig(ReaderConfigBuilder);
@@ -40,6 +41,10 @@ impl ReaderConfig {
         self.0.topic_prefix_spec.get_or_init()
     }
 
+    pub fn topic_filter(&self) -> &TopicFilter {
+        self.0.topic_filter.get_or_init()
+    }
+
     pub fn routing_cache_size(&self) -> &usize {
         self.0.routing_ids_cache_size.get_or_init()
     }
@@ -55,6 +60,22 @@ impl ReaderConfig {
     pub fn source_blacklist_ttl(&self) -> &u64 {
         self.0.source_blacklist_ttl.get_or_init()
     }
+
+    pub fn curve(&self) -> &Option<CurveConfig> {
+        self.0.curve.get_or_init()
+    }
+
+    pub fn seq_store_size(&self) -> &usize {
+        self.0.seq_store_size.get_or_init()
+    }
+
+    pub fn reconnect_after(&self) -> &Option<u32> {
+        self.0.reconnect_after.get_or_init()
+    }
+
+    pub fn max_decompressed_size(&self) -> &usize {
+        self.0.max_decompressed_size.get_or_init()
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -65,10 +86,15 @@ pub struct ReaderConfigBuilder {
     receive_timeout: DefaultOnceCell<i32>,
     receive_hwm: DefaultOnceCell<i32>,
     topic_prefix_spec: DefaultOnceCell<TopicPrefixSpec>,
+    topic_filter: DefaultOnceCell<TopicFilter>,
     routing_ids_cache_size: DefaultOnceCell<usize>,
     fix_ipc_permissions: DefaultOnceCell<Option<u32>>,
     source_blacklist_size: DefaultOnceCell<u64>,
     source_blacklist_ttl: DefaultOnceCell<u64>,
+    curve: DefaultOnceCell<Option<CurveConfig>>,
+    seq_store_size: DefaultOnceCell<usize>,
+    reconnect_after: DefaultOnceCell<Option<u32>>,
+    max_decompressed_size: DefaultOnceCell<usize>,
 }
 
 impl Default for ReaderConfigBuilder {
@@ -80,10 +106,15 @@ impl Default for ReaderConfigBuilder {
             receive_timeout: DefaultOnceCell::new(RECEIVE_TIMEOUT),
             receive_hwm: DefaultOnceCell::new(RECEIVE_HWM),
             topic_prefix_spec: DefaultOnceCell::new(TopicPrefixSpec::None),
+            topic_filter: DefaultOnceCell::new(TopicFilter::default()),
             routing_ids_cache_size: DefaultOnceCell::new(ROUTING_ID_CACHE_SIZE),
             fix_ipc_permissions: DefaultOnceCell::new(Some(IPC_PERMISSIONS)),
             source_blacklist_size: DefaultOnceCell::new(SOURCE_BLACKLIST_CACHE_SIZE),
             source_blacklist_ttl: DefaultOnceCell::new(SOURCE_BLACKLIST_CACHE_EXPIRATION),
+            curve: DefaultOnceCell::new(None),
+            seq_store_size: DefaultOnceCell::new(DEFAULT_SEQ_STORE_SIZE),
+            reconnect_after: DefaultOnceCell::new(None),
+            max_decompressed_size: DefaultOnceCell::new(MAX_DECOMPRESSED_SIZE),
         }
     }
 }
@@ -93,6 +124,9 @@ impl ReaderConfigBuilder {
         if self.endpoint.get_or_init().is_empty() {
             bail!("ZeroMQ endpoint is not set");
         }
+        if self.reconnect_after.get_or_init().is_some() && *self.bind.get_or_init() {
+            bail!("Reconnect is only supported for connect sockets");
+        }
         Ok(ReaderConfig(self))
     }
     pub fn url(self, url: &str) -> anyhow::Result<Self> {
@@ -107,6 +141,9 @@ impl ReaderConfigBuilder {
                 _ => bail!("Invalid socket type for reader: {:?}", socket_type),
             })?;
         }
+        if let Some(curve) = uri.curve {
+            self.curve.set(Some(curve))?;
+        }
         Ok(self)
     }
 
@@ -136,6 +173,16 @@ impl ReaderConfigBuilder {
         Ok(self)
     }
 
+    /// The filter applied to the topics before the messages are decoded, in addition to the
+    /// topic prefix spec. The filtered out messages are reported as
+    /// `ReaderResult::PrefixMismatch`, the filtered out end-of-stream ones are still confirmed
+    /// to the writer.
+    ///
+    pub fn with_topic_filter(self, filter: TopicFilter) -> anyhow::Result<Self> {
+        self.topic_filter.set(filter)?;
+        Ok(self)
+    }
+
     pub fn with_routing_cache_size(self, size: usize) -> anyhow::Result<Self> {
         self.routing_ids_cache_size.set(size)?;
         Ok(self)
@@ -163,6 +210,34 @@ impl ReaderConfigBuilder {
         self.source_blacklist_ttl.set(ttl.get())?;
         Ok(self)
     }
+
+    pub fn with_curve(self, curve: CurveConfig) -> anyhow::Result<Self> {
+        self.curve.set(Some(curve))?;
+        Ok(self)
+    }
+
+    /// The number of sources whose `seq_id` is tracked to detect gaps and duplicates.
+    ///
+    pub fn with_seq_store_size(self, size: NonZeroUsize) -> anyhow::Result<Self> {
+        self.seq_store_size.set(size.get())?;
+        Ok(self)
+    }
+
+    /// Recreates the socket after the number of consecutive receive timeouts when there is
+    /// no connected peer.
+    ///
+    pub fn with_reconnect_after(self, timeouts: NonZeroU32) -> anyhow::Result<Self> {
+        self.reconnect_after.set(Some(timeouts.get()))?;
+        Ok(self)
+    }
+
+    /// The maximum total size of the decompressed message parts, larger compressed messages
+    /// are reported as `ReaderResult::DecompressionFailed` without being decompressed.
+    ///
+    pub fn with_max_decompressed_size(self, size: NonZeroUsize) -> anyhow::Result<Self> {
+        self.max_decompressed_size.set(size.get())?;
+        Ok(self)
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,359 @@
+use anyhow::bail;
+use log::{debug, error, warn};
+use std::collections::VecDeque;
+use std::str::from_utf8;
+use std::time::{Duration, Instant};
+
+use crate::transport::zeromq::{MockSocketResponder, Socket};
+use crate::utils::bytes_to_hex_string;
+
+/// The first byte of a message part sent in the reliable mode, followed by the big-endian
+/// `seq_id`. A protobuf message never starts with it (wire type 6 is invalid).
+///
+const RELIABLE_MARKER: u8 = 0xFE;
+const RELIABLE_HEADER_SIZE: usize = 9;
+const ACK_MESSAGE: &[u8] = b"ACK";
+
+const DEFAULT_WINDOW_SIZE: usize = 64;
+const DEFAULT_RETRANSMIT_TIMEOUT: Duration = Duration::from_millis(1000);
+const DEFAULT_MAX_RETRANSMITS: u32 = 5;
+
+/// Pipelined delivery for Dealer/Router: the writer keeps the window of unacknowledged
+/// messages and retransmits them after the timeout, the reader acknowledges messages
+/// asynchronously and drops duplicates.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ReliableDelivery {
+    /// The maximum number of unacknowledged messages, sending blocks when it is reached.
+    pub window_size: usize,
+    /// The time after which an unacknowledged message is sent again.
+    pub retransmit_timeout: Duration,
+    /// The number of retransmits after which the message is considered lost.
+    pub max_retransmits: u32,
+}
+
+impl Default for ReliableDelivery {
+    fn default() -> Self {
+        Self {
+            window_size: DEFAULT_WINDOW_SIZE,
+            retransmit_timeout: DEFAULT_RETRANSMIT_TIMEOUT,
+            max_retransmits: DEFAULT_MAX_RETRANSMITS,
+        }
+    }
+}
+
+pub(super) fn mark_reliable(message: &mut Vec<u8>, seq_id: u64) {
+    let mut header = Vec::with_capacity(RELIABLE_HEADER_SIZE + message.len());
+    header.push(RELIABLE_MARKER);
+    header.extend_from_slice(&seq_id.to_be_bytes());
+    header.append(message);
+    *message = header;
+}
+
+/// Returns the `seq_id` and the message part without the header for the messages sent in
+/// the reliable mode.
+///
+pub(super) fn strip_reliable_marker(message: &[u8]) -> Option<(u64, &[u8])> {
+    if message.len() < RELIABLE_HEADER_SIZE || message[0] != RELIABLE_MARKER {
+        return None;
+    }
+    let seq_id = u64::from_be_bytes(message[1..RELIABLE_HEADER_SIZE].try_into().unwrap());
+    Some((seq_id, &message[RELIABLE_HEADER_SIZE..]))
+}
+
+pub(super) fn ack_parts(topic: &[u8], seq_id: u64) -> [Vec<u8>; 3] {
+    [
+        ACK_MESSAGE.to_vec(),
+        topic.to_vec(),
+        seq_id.to_be_bytes().to_vec(),
+    ]
+}
+
+pub(super) fn parse_ack(parts: &[Vec<u8>]) -> Option<(&[u8], u64)> {
+    match parts {
+        [ack, topic, seq_id] if ack == ACK_MESSAGE => {
+            let seq_id = u64::from_be_bytes(seq_id.as_slice().try_into().ok()?);
+            Some((topic, seq_id))
+        }
+        _ => None,
+    }
+}
+
+struct PendingMessage {
+    topic: Vec<u8>,
+    seq_id: u64,
+    parts: Vec<Vec<u8>>,
+    sent_at: Instant,
+    retransmits: u32,
+}
+
+/// The unacknowledged messages of the writer.
+///
+pub(super) struct DeliveryWindow {
+    config: ReliableDelivery,
+    pending: VecDeque<PendingMessage>,
+    lost: u64,
+}
+
+impl DeliveryWindow {
+    pub fn new(config: ReliableDelivery) -> Self {
+        Self {
+            config,
+            pending: VecDeque::with_capacity(config.window_size),
+            lost: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.pending.len() >= self.config.window_size
+    }
+
+    pub fn get_lost(&self) -> u64 {
+        self.lost
+    }
+
+    pub fn push(&mut self, topic: &[u8], seq_id: u64, parts: &[&[u8]]) {
+        self.pending.push_back(PendingMessage {
+            topic: topic.to_vec(),
+            seq_id,
+            parts: parts.iter().map(|p| p.to_vec()).collect(),
+            sent_at: Instant::now(),
+            retransmits: 0,
+        });
+    }
+
+    pub fn acknowledge(&mut self, topic: &[u8], seq_id: u64) -> bool {
+        match self
+            .pending
+            .iter()
+            .position(|p| p.seq_id == seq_id && p.topic == topic)
+        {
+            Some(pos) => {
+                self.pending.remove(pos);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Processes the received acknowledgements, when `wait` is set waits for the first one
+    /// up to the socket receive timeout.
+    ///
+    /// Returns the number of acknowledged messages.
+    ///
+    pub fn receive_acks<C: MockSocketResponder>(
+        &mut self,
+        socket: &mut Socket<C>,
+        wait: bool,
+    ) -> anyhow::Result<usize> {
+        let mut acknowledged = 0;
+        let mut flags = if wait { 0 } else { zmq::DONTWAIT };
+        loop {
+            match socket.recv_multipart(flags) {
+                Ok(parts) if parts.is_empty() => return Ok(acknowledged),
+                Ok(parts) => {
+                    match parse_ack(&parts) {
+                        Some((topic, seq_id)) => {
+                            if self.acknowledge(topic, seq_id) {
+                                acknowledged += 1;
+                            }
+                        }
+                        None => warn!(
+                            target: "savant_rs::zeromq::writer::reliable",
+                            "Unexpected message received instead of acknowledgement: {:?}", parts),
+                    }
+                    flags = zmq::DONTWAIT;
+                }
+                Err(zmq::Error::EAGAIN) => return Ok(acknowledged),
+                Err(e) => bail!(
+                    "Failed to receive acknowledgement from ZeroMQ socket. Error is [{}] {:?}",
+                    e.to_raw(),
+                    e
+                ),
+            }
+        }
+    }
+
+    /// The time until which a flush waits for the acknowledgements:
+    /// `retransmit_timeout * (max_retransmits + 1)` from now.
+    ///
+    pub fn flush_deadline(&self) -> Instant {
+        Instant::now()
+            + self
+                .config
+                .retransmit_timeout
+                .saturating_mul(self.config.max_retransmits.saturating_add(1))
+    }
+
+    /// Drops the pending messages as lost.
+    ///
+    pub fn expire(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+        error!(
+            target: "savant_rs::zeromq::writer::reliable",
+            "{} messages are not acknowledged before the flush deadline, they are lost",
+            self.pending.len()
+        );
+        self.lost += self.pending.len() as u64;
+        self.pending.clear();
+    }
+
+    /// Waits until all the messages are acknowledged or lost. The wait is bounded by
+    /// [`DeliveryWindow::flush_deadline`], the messages still pending after it are
+    /// considered lost.
+    ///
+    pub fn flush<C: MockSocketResponder>(&mut self, socket: &mut Socket<C>) -> anyhow::Result<()> {
+        let deadline = self.flush_deadline();
+        while !self.is_empty() {
+            if Instant::now() >= deadline {
+                self.expire();
+                break;
+            }
+            self.receive_acks(socket, true)?;
+            self.retransmit(socket)?;
+        }
+        Ok(())
+    }
+
+    /// Sends again the messages not acknowledged within the retransmit timeout, the
+    /// messages exceeding the retransmit limit are dropped as lost. An attempt failed because
+    /// the socket is not ready counts toward the limit.
+    ///
+    pub fn retransmit<C: MockSocketResponder>(
+        &mut self,
+        socket: &mut Socket<C>,
+    ) -> anyhow::Result<()> {
+        let now = Instant::now();
+        let config = self.config;
+        let mut lost = 0;
+        let mut result = Ok(());
+        self.pending.retain_mut(|p| {
+            if result.is_err() || now.duration_since(p.sent_at) < config.retransmit_timeout {
+                return true;
+            }
+            if p.retransmits >= config.max_retransmits {
+                error!(
+                    target: "savant_rs::zeromq::writer::reliable",
+                    "Message {} with topic {} is not acknowledged after {} retransmits, it is lost",
+                    p.seq_id,
+                    from_utf8(&p.topic).unwrap_or(&bytes_to_hex_string(&p.topic)),
+                    p.retransmits
+                );
+                lost += 1;
+                return false;
+            }
+            let parts = p.parts.iter().map(|p| p.as_slice()).collect::<Vec<_>>();
+            match socket.send_multipart(&parts, zmq::DONTWAIT) {
+                Ok(()) => {
+                    debug!(
+                        target: "savant_rs::zeromq::writer::reliable",
+                        "Message {} is retransmitted, attempt {}", p.seq_id, p.retransmits + 1);
+                    p.sent_at = now;
+                    p.retransmits += 1;
+                }
+                Err(zmq::Error::EAGAIN) => {
+                    debug!(
+                        target: "savant_rs::zeromq::writer::reliable",
+                        "Message {} cannot be retransmitted, the socket is not ready, attempt {}",
+                        p.seq_id,
+                        p.retransmits + 1
+                    );
+                    p.sent_at = now;
+                    p.retransmits += 1;
+                }
+                Err(e) => {
+                    result = Err(anyhow::anyhow!(
+                        "Failed to retransmit message to ZeroMQ socket. Error is [{}] {:?}",
+                        e.to_raw(),
+                        e
+                    ))
+                }
+            }
+            true
+        });
+        self.lost += lost;
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        ack_parts, mark_reliable, parse_ack, strip_reliable_marker, DeliveryWindow,
+        ReliableDelivery,
+    };
+    use crate::transport::zeromq::{MockSocketProvider, NoopResponder, Socket, SocketProvider};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_framing() {
+        let mut message = vec![0x0a, 0x01];
+        mark_reliable(&mut message, 42);
+        assert_eq!(
+            strip_reliable_marker(&message),
+            Some((42, &[0x0a, 0x01][..]))
+        );
+        assert_eq!(strip_reliable_marker(&[0x0a, 0x01]), None);
+        assert_eq!(
+            parse_ack(&ack_parts(b"topic", 42)),
+            Some((&b"topic"[..], 42))
+        );
+        assert_eq!(parse_ack(&[b"OK".to_vec()]), None);
+    }
+
+    #[test]
+    fn test_window() -> anyhow::Result<()> {
+        let mut socket: Socket<NoopResponder> =
+            MockSocketProvider.new_socket(&zmq::Context::new(), zmq::DEALER)?;
+        let mut window = DeliveryWindow::new(ReliableDelivery {
+            window_size: 2,
+            retransmit_timeout: Duration::ZERO,
+            max_retransmits: 1,
+        });
+        window.push(b"topic", 1, &[b"topic", b"m1"]);
+        window.push(b"topic", 2, &[b"topic", b"m2"]);
+        assert!(window.is_full());
+        assert!(!window.acknowledge(b"other", 1));
+        assert!(window.acknowledge(b"topic", 1));
+        assert_eq!(window.len(), 1);
+
+        window.retransmit(&mut socket)?;
+        assert_eq!(
+            socket.take_buffer(),
+            vec![b"topic".to_vec(), b"m2".to_vec()]
+        );
+        window.retransmit(&mut socket)?;
+        assert!(window.is_empty());
+        assert_eq!(window.get_lost(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_flush_deadline() -> anyhow::Result<()> {
+        let mut socket: Socket<NoopResponder> =
+            MockSocketProvider.new_socket(&zmq::Context::new(), zmq::DEALER)?;
+        let mut window = DeliveryWindow::new(ReliableDelivery {
+            window_size: 2,
+            retransmit_timeout: Duration::from_millis(10),
+            max_retransmits: 2,
+        });
+        window.push(b"topic", 1, &[b"topic", b"m1"]);
+        window.push(b"topic", 2, &[b"topic", b"m2"]);
+        let started = Instant::now();
+        window.flush(&mut socket)?;
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert!(window.is_empty());
+        assert_eq!(window.get_lost(), 2);
+        Ok(())
+    }
+}
@@ -0,0 +1,222 @@
+use crate::message::Message;
+use crate::transport::zeromq::compression::EncodedParts;
+use crate::transport::zeromq::writer::{assemble_parts, Reply};
+use crate::transport::zeromq::{MockSocketResponder, SocketProvider, Writer, WriterResult};
+use crate::utils::bytes_to_hex_string;
+use log::{debug, warn};
+use std::str::from_utf8;
+use std::time::{Duration, Instant};
+
+/// What the writer driving a [`SendOperation`] does next.
+///
+pub(super) enum SendStep {
+    /// Wait until the socket has any of the events, an expired timeout is reported with
+    /// [`SendOperation::timed_out`].
+    Wait(zmq::PollEvents, Duration),
+    Done(WriterResult),
+}
+
+enum State {
+    /// Waiting until the messages sent in the reliable mode are acknowledged or lost before
+    /// sending the end of stream.
+    Flushing(Instant),
+    /// Waiting for room in the delivery window.
+    MakingRoom,
+    Sending,
+    Confirming,
+}
+
+/// The send of a message split into the steps made without blocking: the flush before the end
+/// of stream, the wait for room in the delivery window, the send and the wait for the
+/// confirmation. The sync and async writers drive the same operation and differ only in how
+/// they wait for the socket readiness between the steps.
+///
+pub(super) struct SendOperation<'a> {
+    topic: &'a [u8],
+    m: &'a Message,
+    extra_parts: &'a [&'a [u8]],
+    serialized_message: Vec<u8>,
+    encoded: Option<EncodedParts<'a>>,
+    reliable_seq_id: Option<u64>,
+    state: State,
+    send_timeout: Duration,
+    receive_timeout: Duration,
+    send_retries: i32,
+    receive_retries: i32,
+    started: Instant,
+}
+
+impl<'a> SendOperation<'a> {
+    pub fn new<R: MockSocketResponder, P: SocketProvider<R> + Default>(
+        writer: &Writer<R, P>,
+        topic: &'a [u8],
+        m: &'a Message,
+        serialized_message: Vec<u8>,
+        extra_parts: &'a [&'a [u8]],
+    ) -> anyhow::Result<Self> {
+        if !writer.is_started() {
+            anyhow::bail!("ZeroMQ socket is no longer alive");
+        }
+        let reliable_seq_id = writer.reliable_seq_id(m);
+        let state = match writer.flush_deadline() {
+            Some(deadline) if m.is_end_of_stream() => State::Flushing(deadline),
+            _ if reliable_seq_id.is_some() => State::MakingRoom,
+            _ => State::Sending,
+        };
+        let config = writer.config();
+        Ok(Self {
+            topic,
+            m,
+            extra_parts,
+            serialized_message,
+            encoded: None,
+            reliable_seq_id,
+            state,
+            send_timeout: Duration::from_millis(*config.send_timeout() as u64),
+            receive_timeout: Duration::from_millis(*config.receive_timeout() as u64),
+            send_retries: *config.send_retries(),
+            receive_retries: *config.receive_retries(),
+            started: Instant::now(),
+        })
+    }
+
+    /// Makes the steps possible without waiting, returns the result or the readiness the
+    /// operation waits for.
+    ///
+    pub fn step<R: MockSocketResponder, P: SocketProvider<R> + Default>(
+        &mut self,
+        writer: &mut Writer<R, P>,
+    ) -> anyhow::Result<SendStep> {
+        loop {
+            match self.state {
+                State::Flushing(deadline) => {
+                    if !writer.try_flush(deadline)? {
+                        let wait = self
+                            .receive_timeout
+                            .min(deadline.saturating_duration_since(Instant::now()));
+                        return Ok(SendStep::Wait(zmq::POLLIN, wait));
+                    }
+                    self.started = Instant::now();
+                    self.state = if self.reliable_seq_id.is_some() {
+                        State::MakingRoom
+                    } else {
+                        State::Sending
+                    };
+                }
+                State::MakingRoom => {
+                    if writer.try_make_room()? {
+                        self.state = State::Sending;
+                    } else if self.receive_retries < 0 {
+                        warn!(
+                            target: "savant_rs::zeromq::writer",
+                            "No acknowledgements received, the delivery window is full");
+                        return Ok(SendStep::Done(WriterResult::AckTimeout(
+                            self.started.elapsed().as_millis(),
+                        )));
+                    } else {
+                        return Ok(SendStep::Wait(zmq::POLLIN, self.receive_timeout));
+                    }
+                }
+                State::Sending => {
+                    if self.encoded.is_none() {
+                        self.encode(writer)?;
+                    }
+                    let (message, compressed_parts) = self.encoded.as_ref().unwrap();
+                    let parts =
+                        assemble_parts(self.topic, message, compressed_parts, self.extra_parts);
+                    if writer.try_send_parts(&parts)? {
+                        writer.register_sent(self.topic, self.reliable_seq_id, &parts);
+                        if !writer.needs_confirmation(self.m) {
+                            return Ok(SendStep::Done(self.sent(*writer.config().send_retries())));
+                        }
+                        self.started = Instant::now();
+                        self.receive_retries = *writer.config().receive_retries();
+                        self.state = State::Confirming;
+                    } else if self.send_retries < 0 {
+                        warn!(
+                            target: "savant_rs::zeromq::writer",
+                            "Failed to send message to ZeroMQ socket. Send retries spent: {}",
+                            *writer.config().send_retries()
+                        );
+                        return Ok(SendStep::Done(WriterResult::SendTimeout));
+                    } else {
+                        return Ok(SendStep::Wait(zmq::POLLOUT, self.send_timeout));
+                    }
+                }
+                State::Confirming => {
+                    match writer.try_receive_confirmation(self.m.is_end_of_stream())? {
+                        Reply::Confirmed => {
+                            let config = writer.config();
+                            return Ok(SendStep::Done(WriterResult::Ack {
+                                send_retries_spent: *config.send_retries() - self.send_retries,
+                                receive_retries_spent: *config.receive_retries()
+                                    - self.receive_retries,
+                                time_spent: self.started.elapsed().as_millis(),
+                            }));
+                        }
+                        Reply::LateAck => {}
+                        Reply::NotReady if self.receive_retries < 0 => {
+                            return Ok(SendStep::Done(WriterResult::AckTimeout(
+                                self.started.elapsed().as_millis(),
+                            )));
+                        }
+                        Reply::NotReady => {
+                            return Ok(SendStep::Wait(zmq::POLLIN, self.receive_timeout));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Spends a retry of the current step after the wait timed out.
+    ///
+    pub fn timed_out(&mut self) {
+        match self.state {
+            State::Flushing(_) => {}
+            State::MakingRoom => self.receive_retries -= 1,
+            State::Sending => {
+                warn!(
+                    target: "savant_rs::zeromq::writer",
+                    "Retrying to send message to ZeroMQ socket, retries left: {}",
+                    self.send_retries
+                );
+                self.send_retries -= 1;
+            }
+            State::Confirming => {
+                warn!(
+                    target: "savant_rs::zeromq::writer",
+                    "Retrying to receive message from ZeroMQ socket, retries left: {}",
+                    self.receive_retries
+                );
+                self.receive_retries -= 1;
+            }
+        }
+    }
+
+    fn encode<R: MockSocketResponder, P: SocketProvider<R> + Default>(
+        &mut self,
+        writer: &Writer<R, P>,
+    ) -> anyhow::Result<()> {
+        let serialized_message = std::mem::take(&mut self.serialized_message);
+        self.encoded =
+            Some(writer.encode(serialized_message, self.extra_parts, self.reliable_seq_id)?);
+        debug!(
+            target: "savant_rs::zeromq::writer",
+            "Sending message to ZeroMQ socket: {} {:?}",
+            from_utf8(self.topic).unwrap_or(&bytes_to_hex_string(self.topic)),
+            self.m);
+        Ok(())
+    }
+
+    fn sent(&self, max_send_retries: i32) -> WriterResult {
+        let spent = self.started.elapsed().as_millis();
+        debug!(
+            target: "savant_rs::zeromq::writer",
+            "Message sent to ZeroMQ socket. Time spent: {} ms", spent);
+        WriterResult::Success {
+            retries_spent: max_send_retries - self.send_retries,
+            time_spent: spent,
+        }
+    }
+}
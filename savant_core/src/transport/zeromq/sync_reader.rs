@@ -1,9 +1,12 @@
 use crate::transport::zeromq::reader::ReaderResult;
-use crate::transport::zeromq::{NoopResponder, Reader, ReaderConfig, ZmqSocketProvider};
+use crate::transport::zeromq::{
+    NoopResponder, Reader, ReaderConfig, SocketEvent, ZmqSocketProvider,
+};
+use crate::transport::MessageSource;
 use std::sync::Arc;
 
 #[derive(Clone)]
-pub struct SyncReader(Arc<Reader<NoopResponder, ZmqSocketProvider>>);
+pub struct SyncReader(pub(super) Arc<Reader<NoopResponder, ZmqSocketProvider>>);
 
 impl SyncReader {
     pub fn new(config: &ReaderConfig) -> anyhow::Result<Self> {
@@ -29,4 +32,26 @@ impl SyncReader {
     pub fn is_blacklisted(&self, source_id: &[u8]) -> bool {
         self.0.is_blacklisted(source_id)
     }
+
+    pub fn is_connected(&self) -> bool {
+        self.0.is_connected()
+    }
+
+    pub fn take_socket_events(&self) -> Vec<SocketEvent> {
+        self.0.take_socket_events()
+    }
+}
+
+impl MessageSource for SyncReader {
+    fn receive(&self) -> anyhow::Result<ReaderResult> {
+        self.0.receive()
+    }
+
+    fn is_started(&self) -> bool {
+        self.0.is_alive()
+    }
+
+    fn shutdown(&mut self) -> anyhow::Result<()> {
+        self.0.destroy()
+    }
 }
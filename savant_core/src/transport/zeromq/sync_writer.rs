@@ -1,11 +1,12 @@
 use crate::transport::zeromq::{
-    NoopResponder, Writer, WriterConfig, WriterResult, ZmqSocketProvider,
+    NoopResponder, SocketEvent, Writer, WriterConfig, WriterResult, ZmqSocketProvider,
 };
+use crate::transport::MessageSink;
 use parking_lot::Mutex;
 use std::sync::Arc;
 
 #[derive(Clone)]
-pub struct SyncWriter(Arc<Mutex<Writer<NoopResponder, ZmqSocketProvider>>>);
+pub struct SyncWriter(pub(super) Arc<Mutex<Writer<NoopResponder, ZmqSocketProvider>>>);
 
 impl SyncWriter {
     pub fn new(config: &WriterConfig) -> anyhow::Result<Self> {
@@ -36,4 +37,37 @@ impl SyncWriter {
         let mut writer = self.0.lock();
         writer.destroy()
     }
+
+    pub fn is_connected(&self) -> bool {
+        let writer = self.0.lock();
+        writer.is_connected()
+    }
+
+    pub fn take_socket_events(&self) -> Vec<SocketEvent> {
+        let writer = self.0.lock();
+        writer.take_socket_events()
+    }
+}
+
+impl MessageSink for SyncWriter {
+    fn send_message(
+        &self,
+        topic: &str,
+        message: &crate::message::Message,
+        data: &[&[u8]],
+    ) -> anyhow::Result<WriterResult> {
+        SyncWriter::send_message(self, topic, message, data)
+    }
+
+    fn send_eos(&self, topic: &str) -> anyhow::Result<WriterResult> {
+        SyncWriter::send_eos(self, topic)
+    }
+
+    fn is_started(&self) -> bool {
+        SyncWriter::is_started(self)
+    }
+
+    fn shutdown(&mut self) -> anyhow::Result<()> {
+        SyncWriter::shutdown(self)
+    }
 }
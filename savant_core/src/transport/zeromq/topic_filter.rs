@@ -0,0 +1,146 @@
+use globset::{Glob, GlobMatcher};
+use hashbrown::HashSet;
+use regex::bytes::Regex;
+
+#[derive(Clone, Debug)]
+enum TopicPattern {
+    Glob(GlobMatcher),
+    Regex(Regex),
+}
+
+impl TopicPattern {
+    fn glob(pattern: &str) -> anyhow::Result<Self> {
+        Ok(Self::Glob(Glob::new(pattern)?.compile_matcher()))
+    }
+
+    /// The regular expression must match the whole topic.
+    ///
+    fn regex(pattern: &str) -> anyhow::Result<Self> {
+        Ok(Self::Regex(Regex::new(&format!("^(?:{})$", pattern))?))
+    }
+
+    fn matches(&self, topic: &[u8]) -> bool {
+        match self {
+            Self::Glob(glob) => glob.is_match(String::from_utf8_lossy(topic).as_ref()),
+            Self::Regex(regex) => regex.is_match(topic),
+        }
+    }
+}
+
+/// Reader-side topic filtering applied before the message is decoded. A topic passes
+/// when it matches any of the allowed source ids or patterns (or no allow rules are
+/// defined) and does not match any of the exclusion rules.
+///
+#[derive(Clone, Debug, Default)]
+pub struct TopicFilter {
+    source_ids: HashSet<Vec<u8>>,
+    patterns: Vec<TopicPattern>,
+    excluded_source_ids: HashSet<Vec<u8>>,
+    excluded_patterns: Vec<TopicPattern>,
+}
+
+impl TopicFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_source_ids<I, S>(mut self, source_ids: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.source_ids.extend(
+            source_ids
+                .into_iter()
+                .map(|s| s.as_ref().as_bytes().to_vec()),
+        );
+        self
+    }
+
+    pub fn with_glob(mut self, pattern: &str) -> anyhow::Result<Self> {
+        self.patterns.push(TopicPattern::glob(pattern)?);
+        Ok(self)
+    }
+
+    pub fn with_regex(mut self, pattern: &str) -> anyhow::Result<Self> {
+        self.patterns.push(TopicPattern::regex(pattern)?);
+        Ok(self)
+    }
+
+    pub fn exclude_source_ids<I, S>(mut self, source_ids: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.excluded_source_ids.extend(
+            source_ids
+                .into_iter()
+                .map(|s| s.as_ref().as_bytes().to_vec()),
+        );
+        self
+    }
+
+    pub fn exclude_glob(mut self, pattern: &str) -> anyhow::Result<Self> {
+        self.excluded_patterns.push(TopicPattern::glob(pattern)?);
+        Ok(self)
+    }
+
+    pub fn exclude_regex(mut self, pattern: &str) -> anyhow::Result<Self> {
+        self.excluded_patterns.push(TopicPattern::regex(pattern)?);
+        Ok(self)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.source_ids.is_empty()
+            && self.patterns.is_empty()
+            && self.excluded_source_ids.is_empty()
+            && self.excluded_patterns.is_empty()
+    }
+
+    pub fn matches(&self, topic: &[u8]) -> bool {
+        if self.excluded_source_ids.contains(topic)
+            || self.excluded_patterns.iter().any(|p| p.matches(topic))
+        {
+            return false;
+        }
+        if self.source_ids.is_empty() && self.patterns.is_empty() {
+            return true;
+        }
+        self.source_ids.contains(topic) || self.patterns.iter().any(|p| p.matches(topic))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TopicFilter;
+
+    #[test]
+    fn test_topic_filter() -> anyhow::Result<()> {
+        let filter = TopicFilter::new();
+        assert!(filter.is_empty());
+        assert!(filter.matches(b"cam-1"));
+
+        let filter = TopicFilter::new()
+            .with_source_ids(["cam-1", "cam-2"])
+            .with_glob("lobby/*")?
+            .with_regex(r"gate-\d+")?
+            .exclude_source_ids(["lobby/3"])
+            .exclude_regex(r".*-test")?;
+        assert!(filter.matches(b"cam-1"));
+        assert!(!filter.matches(b"cam-3"));
+        assert!(filter.matches(b"lobby/1"));
+        assert!(!filter.matches(b"lobby/3"));
+        assert!(filter.matches(b"gate-12"));
+        assert!(!filter.matches(b"gate-12a"));
+        assert!(!filter.matches(b"gate-1-test"));
+        assert!(!filter.matches(&[0xff, 0xfe]));
+
+        let filter = TopicFilter::new().exclude_glob("cam-*")?;
+        assert!(!filter.matches(b"cam-1"));
+        assert!(filter.matches(b"gate-1"));
+
+        assert!(TopicFilter::new().with_regex("(").is_err());
+        assert!(TopicFilter::new().with_glob("[").is_err());
+        Ok(())
+    }
+}
@@ -1,19 +1,33 @@
 use crate::message::Message;
+use crate::metrics::get_or_create_counter_family;
 use crate::primitives::eos::EndOfStream;
 use crate::protobuf::{deserialize, serialize};
+use crate::transport::zeromq::compression::EncodedParts;
+use crate::transport::zeromq::rate_limit::{Admission, RateLimiter};
+use crate::transport::zeromq::reliable::{mark_reliable, parse_ack, DeliveryWindow};
+use crate::transport::zeromq::send_operation::{SendOperation, SendStep};
 use crate::transport::zeromq::{
-    create_ipc_dirs, set_ipc_permissions, MockSocketResponder, Socket, SocketProvider,
-    WriterConfig, WriterSocketType, CONFIRMATION_MESSAGE, ZMQ_LINGER,
+    create_ipc_dirs, set_ipc_permissions, MockSocketResponder, Socket, SocketEvent,
+    SocketEventKind, SocketHealth, SocketMonitor, SocketProvider, SocketReadiness, WriterConfig,
+    WriterSocketType, ZapHandler, CONFIRMATION_MESSAGE, ZMQ_LINGER,
 };
-use crate::utils::bytes_to_hex_string;
 use anyhow::bail;
 use log::{debug, info, warn};
-use std::str::from_utf8;
+use std::borrow::Cow;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 pub struct Writer<R: MockSocketResponder, P: SocketProvider<R>> {
     context: Option<zmq::Context>,
     config: WriterConfig,
     socket: Option<Socket<R>>,
+    readiness: Option<Arc<SocketReadiness>>,
+    zap_handler: Option<ZapHandler>,
+    delivery_window: Option<DeliveryWindow>,
+    rate_limiter: Option<RateLimiter>,
+    health: Arc<SocketHealth>,
+    monitor: Option<SocketMonitor>,
+    failures: u32,
     phony: std::marker::PhantomData<P>,
 }
 
@@ -40,6 +54,11 @@ pub enum WriterResult {
         retries_spent: i32,
         time_spent: u128,
     },
+    /// The video frame is dropped by the rate limit of the source.
+    RateLimited {
+        /// The total number of the dropped frames of the source.
+        dropped: u64,
+    },
 }
 
 #[allow(dead_code)]
@@ -69,10 +88,48 @@ impl<R: MockSocketResponder, P: SocketProvider<R> + Default> Writer<R, P> {
         let p = P::default();
         let socket = p.new_socket(&context, config.socket_type().into())?;
 
+        let mut zap_handler = None;
+        if let Some(curve) = config.curve() {
+            zap_handler = socket.start_zap_handler(&context, curve)?;
+        }
+
+        let health = SocketHealth::register(config.endpoint(), "writer");
+        let monitor = Self::setup_socket(&socket, &context, config, &health)?;
+
+        Ok(Self {
+            context: Some(context),
+            config: config.clone(),
+            socket: Some(socket),
+            readiness: None,
+            zap_handler,
+            delivery_window: config.reliable_delivery().map(DeliveryWindow::new),
+            rate_limiter: RateLimiter::new(
+                *config.rate_limit(),
+                config.source_rate_limits().clone(),
+            ),
+            health,
+            monitor,
+            failures: 0,
+            phony: std::marker::PhantomData,
+        })
+    }
+
+    fn setup_socket(
+        socket: &Socket<R>,
+        context: &zmq::Context,
+        config: &WriterConfig,
+        health: &Arc<SocketHealth>,
+    ) -> anyhow::Result<Option<SocketMonitor>> {
+        let monitor = socket.start_monitor(context, health)?;
+
         socket.set_sndhwm(*config.send_hwm())?;
         socket.set_sndtimeo(*config.send_timeout())?;
         socket.set_linger(ZMQ_LINGER)?;
 
+        if let Some(curve) = config.curve() {
+            socket.set_curve(curve)?;
+        }
+
         if *config.socket_type() != WriterSocketType::Pub {
             socket.set_rcvtimeo(*config.receive_timeout())?;
             socket.set_rcvhwm(*config.receive_hwm())?;
@@ -93,13 +150,32 @@ impl<R: MockSocketResponder, P: SocketProvider<R> + Default> Writer<R, P> {
         } else {
             socket.connect(config.endpoint())?;
         }
+        Ok(monitor)
+    }
 
-        Ok(Self {
-            context: Some(context),
-            config: config.clone(),
-            socket: Some(socket),
-            phony: std::marker::PhantomData,
-        })
+    /// Recreates the socket, the messages queued in the old socket are lost.
+    ///
+    fn reconnect(&mut self) -> anyhow::Result<()> {
+        warn!(
+            target: "savant_rs::zeromq::writer",
+            "Recreating ZeroMQ socket for endpoint {} after {} failures",
+            self.config.endpoint(),
+            self.failures
+        );
+        self.readiness.take();
+        self.socket.take();
+        self.monitor.take();
+        self.failures = 0;
+        self.health.push_event(SocketEvent {
+            kind: SocketEventKind::Reconnected,
+            address: self.config.endpoint().clone(),
+            value: 0,
+        });
+        let context = self.context.as_ref().unwrap();
+        let socket = P::default().new_socket(context, self.config.socket_type().into())?;
+        self.monitor = Self::setup_socket(&socket, context, &self.config, &self.health)?;
+        self.socket = Some(socket);
+        Ok(())
     }
 
     pub fn destroy(&mut self) -> anyhow::Result<()> {
@@ -108,7 +184,15 @@ impl<R: MockSocketResponder, P: SocketProvider<R> + Default> Writer<R, P> {
             "Destroying ZeroMQ socket for endpoint {}",
             self.config.endpoint()
         );
+        if let Err(e) = self.flush() {
+            warn!(
+                target: "savant_rs::zeromq::writer",
+                "Failed to flush unacknowledged messages: {:?}", e);
+        }
+        self.readiness.take();
         self.socket.take();
+        self.monitor.take();
+        self.zap_handler.take();
         self.context.take();
         info!(
             target: "savant_rs::zeromq::writer",
@@ -122,6 +206,40 @@ impl<R: MockSocketResponder, P: SocketProvider<R> + Default> Writer<R, P> {
         self.socket.is_some()
     }
 
+    /// The socket has at least one connected peer.
+    ///
+    pub fn is_connected(&self) -> bool {
+        self.health.is_connected()
+    }
+
+    /// Returns the socket events received since the previous call.
+    ///
+    pub fn take_socket_events(&self) -> Vec<SocketEvent> {
+        self.health.take_events()
+    }
+
+    /// Waits until the messages sent in the reliable mode are acknowledged or lost.
+    ///
+    pub fn flush(&mut self) -> anyhow::Result<()> {
+        if let (Some(socket), Some(window)) = (self.socket.as_mut(), self.delivery_window.as_mut())
+        {
+            window.flush(socket)?;
+        }
+        Ok(())
+    }
+
+    /// The number of messages sent in the reliable mode and not acknowledged yet.
+    ///
+    pub fn get_unacknowledged_count(&self) -> usize {
+        self.delivery_window.as_ref().map_or(0, |w| w.len())
+    }
+
+    /// The number of messages sent in the reliable mode and dropped after the retransmits.
+    ///
+    pub fn get_lost_count(&self) -> u64 {
+        self.delivery_window.as_ref().map_or(0, |w| w.get_lost())
+    }
+
     pub fn send_eos(&mut self, topic: &str) -> anyhow::Result<WriterResult> {
         let m = Message::end_of_stream(EndOfStream::new(topic.to_string()));
         self.send(topic.as_bytes(), &m, &[])
@@ -142,118 +260,327 @@ impl<R: MockSocketResponder, P: SocketProvider<R> + Default> Writer<R, P> {
         m: &Message,
         extra_parts: &[&[u8]],
     ) -> anyhow::Result<WriterResult> {
-        if self.socket.is_none() {
-            bail!("ZeroMQ socket is no longer alive");
-        }
-        let socket = self.socket.as_mut().unwrap();
-        let extra_parts_iter = extra_parts.iter().cloned();
         let serialized_message = serialize(m)?;
-        let parts = vec![topic, &serialized_message]
-            .into_iter()
-            .chain(extra_parts_iter)
-            .collect::<Vec<_>>();
-        debug!(
-            target: "savant_rs::zeromq::writer",
-            "Sending message to ZeroMQ socket: {} {:?}",
-            from_utf8(topic).unwrap_or(&bytes_to_hex_string(topic)),
-            m);
-        let mut send_retries = *self.config.send_retries();
-        while send_retries >= 0 {
-            let res = socket.send_multipart(&parts, 0);
-            if let Err(e) = res {
-                warn!(
-                    target: "savant_rs::zeromq::writer",
-                    "Failed to send message to ZeroMQ socket. Error is [{}] {:?}", e.to_raw(), e);
-                if let zmq::Error::EAGAIN = e {
-                    warn!(
-                        target: "savant_rs::zeromq::writer",
-                        "Retrying to send message to ZeroMQ socket, retries left: {}",
-                        send_retries
-                    );
-                    send_retries -= 1;
-                    continue;
-                } else {
-                    bail!(
-                        "Failed to send message to ZeroMQ socket. Error is [{}] {:?}",
-                        e.to_raw(),
-                        e
-                    );
+        match self.apply_rate_limit(m, &serialized_message, extra_parts) {
+            Admission::Send => {}
+            Admission::Drop(dropped) => return Ok(WriterResult::RateLimited { dropped }),
+            Admission::Wait(wait) => std::thread::sleep(wait),
+        }
+        let res = self.send_once(topic, m, serialized_message, extra_parts)?;
+        self.register_result(&res)?;
+        Ok(res)
+    }
+
+    /// Counts the failed sends and recreates the socket when the peers are gone for too long.
+    ///
+    pub(super) fn register_result(&mut self, res: &WriterResult) -> anyhow::Result<()> {
+        if matches!(res, WriterResult::SendTimeout | WriterResult::AckTimeout(_)) {
+            self.failures += 1;
+            if let Some(reconnect_after) = self.config.reconnect_after() {
+                if self.failures >= *reconnect_after && !self.health.is_connected() {
+                    self.reconnect()?;
                 }
             }
-            break;
+        } else {
+            self.failures = 0;
         }
+        Ok(())
+    }
 
-        if send_retries < 0 {
-            warn!(
-                target: "savant_rs::zeromq::writer",
-                "Failed to send message to ZeroMQ socket. Send retries spent: {}",
-                *self.config.send_retries()
-            );
-            return Ok(WriterResult::SendTimeout);
+    /// Decides whether the message is sent, dropped or delayed by the rate limit. The delay
+    /// is left to the caller, so the async writer does not block the thread.
+    ///
+    pub(super) fn apply_rate_limit(
+        &mut self,
+        m: &Message,
+        serialized_message: &[u8],
+        extra_parts: &[&[u8]],
+    ) -> Admission {
+        let Some(limiter) = self.rate_limiter.as_mut() else {
+            return Admission::Send;
+        };
+        if let Some(eos) = m.as_end_of_stream() {
+            limiter.reset(&eos.source_id);
+            return Admission::Send;
         }
-
-        let start = std::time::Instant::now();
-        if self.config.socket_type() == &WriterSocketType::Req
-            || (m.is_end_of_stream() && self.config.socket_type() != &WriterSocketType::Pub)
-        {
-            let mut receive_retries = *self.config.receive_retries();
-            while receive_retries >= 0 {
-                let res = socket.recv_multipart(0);
+        let Some(frame) = m.as_video_frame() else {
+            return Admission::Send;
+        };
+        let source_id = frame.get_source_id();
+        let bytes = serialized_message.len() + extra_parts.iter().map(|p| p.len()).sum::<usize>();
+        let admission = limiter.admit(&source_id, frame.get_keyframe() == Some(true), bytes);
+        match admission {
+            Admission::Send => {}
+            Admission::Wait(wait) => debug!(
+                target: "savant_rs::zeromq::writer",
+                "Frame of source {} is delayed by the rate limit for {:?}",
+                source_id,
+                wait
+            ),
+            Admission::Drop(dropped) => {
                 debug!(
                     target: "savant_rs::zeromq::writer",
-                    "Received message from ZeroMQ socket: {:?}", res);
+                    "Frame of source {} is dropped by the rate limit, {} frames dropped so far",
+                    source_id,
+                    dropped
+                );
+                let counter = get_or_create_counter_family(
+                    "writer_rate_limited_frame_counter",
+                    Some("Number of frames dropped by the writer rate limit"),
+                    &["endpoint", "source_id"],
+                    None,
+                );
+                let res = counter
+                    .lock()
+                    .inc(1, &[self.config.endpoint().as_str(), source_id.as_str()]);
                 if let Err(e) = res {
                     warn!(
                         target: "savant_rs::zeromq::writer",
-                        "Failed to receive message from ZeroMQ socket. Error is [{}] {:?}",
-                        e.to_raw(),
-                        e
-                    );
-                    if let zmq::Error::EAGAIN = e {
-                        warn!(
-                            target: "savant_rs::zeromq::writer",
-                            "Retrying to receive message from ZeroMQ socket, retries left: {}",
-                            receive_retries
-                        );
-                        receive_retries -= 1;
-                        continue;
-                    } else {
-                        bail!(
-                            "Failed to receive message from ZeroMQ socket. Error is [{}] {:?}",
-                            e.to_raw(),
-                            e
-                        );
-                    }
+                        "Failed to update rate limit counter: {:?}", e);
                 }
-                if m.is_end_of_stream() {
-                    let res = res.as_ref().unwrap();
-                    if res.last().unwrap().as_slice() != CONFIRMATION_MESSAGE {
-                        bail!(
-                            "Failed to receive confirmation message from ZeroMQ socket. \
-                            Received message is {:?}",
-                            res
-                        );
+            }
+        }
+        admission
+    }
+
+    fn send_once(
+        &mut self,
+        topic: &[u8],
+        m: &Message,
+        serialized_message: Vec<u8>,
+        extra_parts: &[&[u8]],
+    ) -> anyhow::Result<WriterResult> {
+        let mut operation = SendOperation::new(self, topic, m, serialized_message, extra_parts)?;
+        loop {
+            match operation.step(self)? {
+                SendStep::Wait(events, timeout) => {
+                    if !self.wait(events, timeout)? {
+                        operation.timed_out();
                     }
                 }
-                return Ok(WriterResult::Ack {
-                    send_retries_spent: *self.config.send_retries() - send_retries,
-                    receive_retries_spent: *self.config.receive_retries() - receive_retries,
-                    time_spent: start.elapsed().as_millis(),
-                });
+                SendStep::Done(res) => return Ok(res),
+            }
+        }
+    }
+
+    /// Blocks until the socket has any of the `events`, returns `false` on timeout.
+    ///
+    fn wait(&self, events: zmq::PollEvents, timeout: Duration) -> anyhow::Result<bool> {
+        match self.socket.as_ref() {
+            Some(socket) => socket.poll(events, timeout),
+            None => bail!("ZeroMQ socket is no longer alive"),
+        }
+    }
+
+    pub(super) fn config(&self) -> &WriterConfig {
+        &self.config
+    }
+
+    /// The `seq_id` the message is sent with in the reliable mode.
+    ///
+    pub(super) fn reliable_seq_id(&self, m: &Message) -> Option<u64> {
+        match self.delivery_window {
+            Some(_) if m.meta.seq_id != 0 && !m.is_end_of_stream() => Some(m.meta.seq_id),
+            _ => None,
+        }
+    }
+
+    /// The writer waits for the reply after sending the message.
+    ///
+    pub(super) fn needs_confirmation(&self, m: &Message) -> bool {
+        self.config.socket_type() == &WriterSocketType::Req
+            || (m.is_end_of_stream() && self.config.socket_type() != &WriterSocketType::Pub)
+    }
+
+    /// Compresses and frames the message, returns the message part and the compressed extra
+    /// parts, which are empty when the compression is off.
+    ///
+    pub(super) fn encode<'a>(
+        &self,
+        mut serialized_message: Vec<u8>,
+        extra_parts: &[&'a [u8]],
+        reliable_seq_id: Option<u64>,
+    ) -> anyhow::Result<EncodedParts<'a>> {
+        let mut compressed_parts = Vec::new();
+        if let Some(compression) = self.config.compression() {
+            (serialized_message, compressed_parts) =
+                compression.compress(&serialized_message, extra_parts)?;
+        }
+        if let Some(seq_id) = reliable_seq_id {
+            mark_reliable(&mut serialized_message, seq_id);
+        }
+        Ok((serialized_message, compressed_parts))
+    }
+
+    /// Sends the parts without waiting, returns `false` when the socket is not ready.
+    ///
+    pub(super) fn try_send_parts(&mut self, parts: &[&[u8]]) -> anyhow::Result<bool> {
+        let Some(socket) = self.socket.as_mut() else {
+            bail!("ZeroMQ socket is no longer alive");
+        };
+        match socket.send_multipart(parts, zmq::DONTWAIT) {
+            Ok(()) => Ok(true),
+            Err(zmq::Error::EAGAIN) => Ok(false),
+            Err(e) => {
+                warn!(
+                    target: "savant_rs::zeromq::writer",
+                    "Failed to send message to ZeroMQ socket. Error is [{}] {:?}", e.to_raw(), e);
+                bail!(
+                    "Failed to send message to ZeroMQ socket. Error is [{}] {:?}",
+                    e.to_raw(),
+                    e
+                );
             }
-            return Ok(WriterResult::AckTimeout(start.elapsed().as_millis()));
         }
-        let spent = start.elapsed().as_millis();
+    }
+
+    /// Keeps the message sent in the reliable mode until it is acknowledged.
+    ///
+    pub(super) fn register_sent(
+        &mut self,
+        topic: &[u8],
+        reliable_seq_id: Option<u64>,
+        parts: &[&[u8]],
+    ) {
+        if let (Some(seq_id), Some(window)) = (reliable_seq_id, self.delivery_window.as_mut()) {
+            window.push(topic, seq_id, parts);
+        }
+    }
+
+    /// Receives the reply to the sent message without waiting.
+    ///
+    pub(super) fn try_receive_confirmation(
+        &mut self,
+        is_end_of_stream: bool,
+    ) -> anyhow::Result<Reply> {
+        let Some(socket) = self.socket.as_mut() else {
+            bail!("ZeroMQ socket is no longer alive");
+        };
+        let res = match socket.recv_multipart(zmq::DONTWAIT) {
+            Ok(res) => res,
+            Err(zmq::Error::EAGAIN) => return Ok(Reply::NotReady),
+            Err(e) => {
+                warn!(
+                    target: "savant_rs::zeromq::writer",
+                    "Failed to receive message from ZeroMQ socket. Error is [{}] {:?}",
+                    e.to_raw(),
+                    e
+                );
+                bail!(
+                    "Failed to receive message from ZeroMQ socket. Error is [{}] {:?}",
+                    e.to_raw(),
+                    e
+                );
+            }
+        };
         debug!(
             target: "savant_rs::zeromq::writer",
-            "Message sent to ZeroMQ socket. Time spent: {} ms", spent);
-        Ok(WriterResult::Success {
-            retries_spent: *self.config.send_retries() - send_retries,
-            time_spent: spent,
-        })
+            "Received message from ZeroMQ socket: {:?}", res);
+        if is_end_of_stream {
+            if let Some((topic, seq_id)) = parse_ack(&res) {
+                debug!(
+                    target: "savant_rs::zeromq::writer",
+                    "Late acknowledgement of message {} received while waiting for EOS confirmation", seq_id);
+                if let Some(window) = self.delivery_window.as_mut() {
+                    window.acknowledge(topic, seq_id);
+                }
+                return Ok(Reply::LateAck);
+            }
+            if res.last().map(|p| p.as_slice()) != Some(CONFIRMATION_MESSAGE) {
+                bail!(
+                    "Failed to receive confirmation message from ZeroMQ socket. \
+                    Received message is {:?}",
+                    res
+                );
+            }
+        }
+        Ok(Reply::Confirmed)
+    }
+
+    /// Processes the received acknowledgements and retransmits the expired messages without
+    /// waiting, returns `true` when the delivery window has room for a message.
+    ///
+    pub(super) fn try_make_room(&mut self) -> anyhow::Result<bool> {
+        match (self.socket.as_mut(), self.delivery_window.as_mut()) {
+            (Some(socket), Some(window)) => {
+                window.receive_acks(socket, false)?;
+                window.retransmit(socket)?;
+                Ok(!window.is_full())
+            }
+            _ => Ok(true),
+        }
+    }
+
+    /// The time until which a flush waits for the acknowledgements.
+    ///
+    pub(super) fn flush_deadline(&self) -> Option<Instant> {
+        self.delivery_window.as_ref().map(|w| w.flush_deadline())
+    }
+
+    /// Makes a flush step without waiting, returns `true` when all the messages are
+    /// acknowledged or, after the `deadline`, dropped as lost.
+    ///
+    pub(super) fn try_flush(&mut self, deadline: Instant) -> anyhow::Result<bool> {
+        match (self.socket.as_mut(), self.delivery_window.as_mut()) {
+            (Some(socket), Some(window)) => {
+                window.receive_acks(socket, false)?;
+                window.retransmit(socket)?;
+                if !window.is_empty() && Instant::now() >= deadline {
+                    window.expire();
+                }
+                Ok(window.is_empty())
+            }
+            _ => Ok(true),
+        }
+    }
+
+    /// The registration of the current socket used by the async waits, the same one until the
+    /// socket is recreated.
+    ///
+    pub(super) fn readiness(&mut self) -> anyhow::Result<Arc<SocketReadiness>> {
+        let Some(socket) = self.socket.as_ref() else {
+            bail!("ZeroMQ socket is no longer alive");
+        };
+        if let Some(readiness) = self.readiness.as_ref() {
+            return Ok(readiness.clone());
+        }
+        let readiness = Arc::new(SocketReadiness::new(socket.get_fd()?)?);
+        self.readiness = Some(readiness.clone());
+        Ok(readiness)
+    }
+
+    pub(super) fn get_events(&self) -> anyhow::Result<zmq::PollEvents> {
+        match self.socket.as_ref() {
+            Some(socket) => socket.get_events(),
+            None => bail!("ZeroMQ socket is no longer alive"),
+        }
     }
 }
 
+/// The reply received by the writer after sending a message.
+///
+pub(super) enum Reply {
+    Confirmed,
+    /// The acknowledgement of a message sent in the reliable mode.
+    LateAck,
+    NotReady,
+}
+
+pub(super) fn assemble_parts<'a>(
+    topic: &'a [u8],
+    message: &'a [u8],
+    compressed_parts: &'a [Cow<'a, [u8]>],
+    extra_parts: &[&'a [u8]],
+) -> Vec<&'a [u8]> {
+    let mut parts = vec![topic, message];
+    if compressed_parts.is_empty() {
+        parts.extend_from_slice(extra_parts);
+    } else {
+        parts.extend(compressed_parts.iter().map(|p| p.as_ref()));
+    }
+    parts
+}
+
 #[cfg(test)]
 mod tests {
     mod tests_with_response {
@@ -316,9 +643,12 @@ mod tests {
 
     mod tests_without_response {
         use crate::message::Message;
+        use crate::metrics::get_counter_family;
         use crate::test::gen_frame;
         use crate::transport::zeromq::writer::MockResponder;
-        use crate::transport::zeromq::{MockSocketProvider, Writer, WriterConfig, WriterResult};
+        use crate::transport::zeromq::{
+            MockSocketProvider, RateLimit, RateLimitPolicy, Writer, WriterConfig, WriterResult,
+        };
 
         #[test]
         fn test_dealer_op() -> anyhow::Result<()> {
@@ -336,5 +666,94 @@ mod tests {
             } if retries_spent == 0));
             Ok(())
         }
+
+        #[test]
+        fn test_rate_limit() -> anyhow::Result<()> {
+            let mut writer = Writer::<MockResponder, MockSocketProvider>::new(
+                &WriterConfig::new()
+                    .url("dealer+bind:ipc:///tmp/test")?
+                    .with_rate_limit(RateLimit {
+                        frames_per_second: Some(1.0),
+                        bytes_per_second: None,
+                        policy: RateLimitPolicy::DropNonKeyframes,
+                    })?
+                    .build()?,
+            )?;
+            let m = Message::video_frame(&gen_frame());
+            let res = writer.send_message("test", &m, &[])?;
+            assert!(matches!(res, WriterResult::Success { .. }));
+            let res = writer.send_message("test", &m, &[])?;
+            assert!(matches!(res, WriterResult::RateLimited { dropped: 1 }));
+            let res = writer.send_message("test", &Message::unknown("user".into()), &[])?;
+            assert!(matches!(res, WriterResult::Success { .. }));
+            let dropped = get_counter_family("writer_rate_limited_frame_counter")
+                .unwrap()
+                .lock()
+                .get(&["ipc:///tmp/test", "test"])?
+                .unwrap();
+            assert!(dropped >= 1);
+            Ok(())
+        }
+    }
+
+    mod tests_reliable {
+        use crate::message::Message;
+        use crate::test::gen_frame;
+        use crate::transport::zeromq::reliable::{ack_parts, strip_reliable_marker};
+        use crate::transport::zeromq::{
+            MockSocketProvider, MockSocketResponder, NoopResponder, ReliableDelivery, Writer,
+            WriterConfig, WriterResult, CONFIRMATION_MESSAGE,
+        };
+
+        #[derive(Default)]
+        struct AckResponder;
+
+        impl MockSocketResponder for AckResponder {
+            fn fix(&mut self, data: &mut Vec<Vec<u8>>) {
+                match strip_reliable_marker(&data[1]) {
+                    Some((seq_id, _)) => *data = ack_parts(&data[0], seq_id).to_vec(),
+                    None => *data = vec![CONFIRMATION_MESSAGE.to_vec()],
+                }
+            }
+        }
+
+        fn config() -> anyhow::Result<WriterConfig> {
+            WriterConfig::new()
+                .url("dealer+bind:ipc:///tmp/test")?
+                .with_receive_retries(0)?
+                .with_reliable_delivery(ReliableDelivery {
+                    window_size: 1,
+                    ..Default::default()
+                })?
+                .build()
+        }
+
+        #[test]
+        fn test_acknowledged() -> anyhow::Result<()> {
+            let mut writer = Writer::<AckResponder, MockSocketProvider>::new(&config()?)?;
+            for _ in 0..3 {
+                let m = Message::video_frame(&gen_frame());
+                let res = writer.send_message("test", &m, &[b"abc"])?;
+                assert!(matches!(res, WriterResult::Success { .. }));
+            }
+            let res = writer.send_eos("test")?;
+            assert!(matches!(res, WriterResult::Ack { .. }));
+            assert_eq!(writer.get_unacknowledged_count(), 0);
+            assert_eq!(writer.get_lost_count(), 0);
+            Ok(())
+        }
+
+        #[test]
+        fn test_window_full() -> anyhow::Result<()> {
+            let mut writer = Writer::<NoopResponder, MockSocketProvider>::new(&config()?)?;
+            let m = Message::video_frame(&gen_frame());
+            let res = writer.send_message("test", &m, &[])?;
+            assert!(matches!(res, WriterResult::Success { .. }));
+            assert_eq!(writer.get_unacknowledged_count(), 1);
+            let m = Message::video_frame(&gen_frame());
+            let res = writer.send_message("test", &m, &[])?;
+            assert!(matches!(res, WriterResult::AckTimeout(_)));
+            Ok(())
+        }
     }
 }
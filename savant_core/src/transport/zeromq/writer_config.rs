@@ -1,9 +1,12 @@
 use super::{
-    parse_zmq_socket_uri, SocketType, WriterSocketType, ACK_RECEIVE_RETRIES, IPC_PERMISSIONS,
-    RECEIVE_HWM, SENDER_RECEIVE_TIMEOUT, SEND_HWM, SEND_RETRIES, SEND_TIMEOUT,
+    parse_zmq_socket_uri, Compression, CurveConfig, RateLimit, ReliableDelivery, SocketType,
+    WriterSocketType, ACK_RECEIVE_RETRIES, IPC_PERMISSIONS, RECEIVE_HWM, SENDER_RECEIVE_TIMEOUT,
+    SEND_HWM, SEND_RETRIES, SEND_TIMEOUT,
 };
 use crate::utils::default_once::DefaultOnceCell;
 use anyhow::bail;
+use hashbrown::HashMap;
+use std::num::NonZeroU32;
 
 #[derive(Clone, Debug, Default)]
 pub struct WriterConfig(WriterConfigBuilder);
@@ -53,6 +56,30 @@ impl WriterConfig {
     pub fn fix_ipc_permissions(&self) -> &Option<u32> {
         self.0.fix_ipc_permissions.get_or_init()
     }
+
+    pub fn curve(&self) -> &Option<CurveConfig> {
+        self.0.curve.get_or_init()
+    }
+
+    pub fn compression(&self) -> &Option<Compression> {
+        self.0.compression.get_or_init()
+    }
+
+    pub fn reliable_delivery(&self) -> &Option<ReliableDelivery> {
+        self.0.reliable_delivery.get_or_init()
+    }
+
+    pub fn reconnect_after(&self) -> &Option<u32> {
+        self.0.reconnect_after.get_or_init()
+    }
+
+    pub fn rate_limit(&self) -> &Option<RateLimit> {
+        self.0.rate_limit.get_or_init()
+    }
+
+    pub fn source_rate_limits(&self) -> &HashMap<String, RateLimit> {
+        self.0.source_rate_limits.get_or_init()
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -67,6 +94,12 @@ pub struct WriterConfigBuilder {
     send_hwm: DefaultOnceCell<i32>,
     receive_hwm: DefaultOnceCell<i32>,
     fix_ipc_permissions: DefaultOnceCell<Option<u32>>,
+    curve: DefaultOnceCell<Option<CurveConfig>>,
+    compression: DefaultOnceCell<Option<Compression>>,
+    reliable_delivery: DefaultOnceCell<Option<ReliableDelivery>>,
+    reconnect_after: DefaultOnceCell<Option<u32>>,
+    rate_limit: DefaultOnceCell<Option<RateLimit>>,
+    source_rate_limits: DefaultOnceCell<HashMap<String, RateLimit>>,
 }
 
 impl Default for WriterConfigBuilder {
@@ -82,6 +115,12 @@ impl Default for WriterConfigBuilder {
             send_hwm: DefaultOnceCell::new(SEND_HWM),
             receive_hwm: DefaultOnceCell::new(RECEIVE_HWM),
             fix_ipc_permissions: DefaultOnceCell::new(Some(IPC_PERMISSIONS)),
+            curve: DefaultOnceCell::new(None),
+            compression: DefaultOnceCell::new(None),
+            reliable_delivery: DefaultOnceCell::new(None),
+            reconnect_after: DefaultOnceCell::new(None),
+            rate_limit: DefaultOnceCell::new(None),
+            source_rate_limits: DefaultOnceCell::new(HashMap::new()),
         }
     }
 }
@@ -91,6 +130,14 @@ impl WriterConfigBuilder {
         if self.endpoint.get_or_init().is_empty() {
             bail!("ZeroMQ endpoint is not set");
         }
+        if self.reliable_delivery.get_or_init().is_some()
+            && self.socket_type.get_or_init() != &WriterSocketType::Dealer
+        {
+            bail!("Reliable delivery is only supported for Dealer sockets");
+        }
+        if self.reconnect_after.get_or_init().is_some() && *self.bind.get_or_init() {
+            bail!("Reconnect is only supported for connect sockets");
+        }
         Ok(WriterConfig(self))
     }
     pub fn url(self, url: &str) -> anyhow::Result<Self> {
@@ -105,6 +152,9 @@ impl WriterConfigBuilder {
                 _ => bail!("Invalid socket type for writer: {:?}", socket_type),
             })?;
         }
+        if let Some(curve) = uri.curve {
+            self.curve.set(Some(curve))?;
+        }
         Ok(self)
     }
 
@@ -173,12 +223,70 @@ impl WriterConfigBuilder {
         self.fix_ipc_permissions.set(permissions)?;
         Ok(self)
     }
+
+    pub fn with_curve(self, curve: CurveConfig) -> anyhow::Result<Self> {
+        self.curve.set(Some(curve))?;
+        Ok(self)
+    }
+
+    pub fn with_compression(self, compression: Compression) -> anyhow::Result<Self> {
+        if !compression.message && !compression.extra_parts {
+            bail!("Compression must be enabled for the message or the extra parts");
+        }
+        self.compression.set(Some(compression))?;
+        Ok(self)
+    }
+
+    pub fn with_reliable_delivery(
+        self,
+        reliable_delivery: ReliableDelivery,
+    ) -> anyhow::Result<Self> {
+        if reliable_delivery.window_size == 0 {
+            bail!("Reliable delivery window size must be positive");
+        }
+        if reliable_delivery.retransmit_timeout.is_zero() {
+            bail!("Reliable delivery retransmit timeout must be positive");
+        }
+        self.reliable_delivery.set(Some(reliable_delivery))?;
+        Ok(self)
+    }
+
+    /// Recreates the socket after the number of consecutive failed sends when there is no
+    /// connected peer.
+    ///
+    pub fn with_reconnect_after(self, failures: NonZeroU32) -> anyhow::Result<Self> {
+        self.reconnect_after.set(Some(failures.get()))?;
+        Ok(self)
+    }
+
+    /// The limit applied to the video frames of every source separately, the dropped frames
+    /// are reported as `WriterResult::RateLimited`.
+    ///
+    pub fn with_rate_limit(self, limit: RateLimit) -> anyhow::Result<Self> {
+        limit.validate()?;
+        self.rate_limit.set(Some(limit))?;
+        Ok(self)
+    }
+
+    /// The limits of the particular sources, they override the limit set with
+    /// `with_rate_limit`.
+    ///
+    pub fn with_source_rate_limits(
+        self,
+        limits: HashMap<String, RateLimit>,
+    ) -> anyhow::Result<Self> {
+        for limit in limits.values() {
+            limit.validate()?;
+        }
+        self.source_rate_limits.set(limits)?;
+        Ok(self)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::transport::zeromq::writer_config::WriterConfig;
-    use crate::transport::zeromq::WriterSocketType;
+    use crate::transport::zeromq::{ReliableDelivery, WriterSocketType};
 
     #[test]
     fn test_duplicate_configuration_fails() -> anyhow::Result<()> {
@@ -230,4 +338,36 @@ mod tests {
             .with_fix_ipc_permissions(Some(0777))?;
         Ok(())
     }
+
+    #[test]
+    fn test_reliable_delivery_requires_dealer() -> anyhow::Result<()> {
+        let config = WriterConfig::new()
+            .url("pub+bind:ipc:///abc/def")?
+            .with_reliable_delivery(ReliableDelivery::default())?;
+        assert!(config.build().is_err());
+        let config = WriterConfig::new()
+            .url("dealer+bind:ipc:///abc/def")?
+            .with_reliable_delivery(ReliableDelivery::default())?
+            .build()?;
+        assert_eq!(
+            config.reliable_delivery(),
+            &Some(ReliableDelivery::default())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_reconnect_requires_connect() -> anyhow::Result<()> {
+        let failures = std::num::NonZeroU32::new(3).unwrap();
+        let config = WriterConfig::new()
+            .url("dealer+bind:ipc:///abc/def")?
+            .with_reconnect_after(failures)?;
+        assert!(config.build().is_err());
+        let config = WriterConfig::new()
+            .url("dealer+connect:ipc:///abc/def")?
+            .with_reconnect_after(failures)?
+            .build()?;
+        assert_eq!(config.reconnect_after(), &Some(3));
+        Ok(())
+    }
 }
@@ -1,8 +1,14 @@
 pub mod kvs;
 mod kvs_handlers;
+mod kvs_persistence;
+mod kvs_replication;
 mod kvs_subscription;
 
-use std::sync::{Arc, OnceLock};
+pub use kvs_persistence::{set_kvs_persistence, KvsPersistence};
+pub use kvs_replication::{start_kvs_replication, stop_kvs_replication, KvsReplication};
+pub use kvs_subscription::KvsSubscriptionFilter;
+
+use std::sync::{Arc, OnceLock, Weak};
 use std::time::{Duration, Instant, SystemTime};
 use tokio::sync::mpsc::{channel, Receiver, Sender};
 use tokio::sync::Mutex;
@@ -14,19 +20,23 @@ use crate::pipeline::implementation;
 use crate::primitives::rust::AttributeSet;
 use crate::primitives::Attribute;
 use crate::protobuf::ToProtobuf;
+use crate::transport::zeromq::get_socket_statuses;
 use crate::webserver::kvs_handlers::{
-    delete_handler, delete_single_handler, get_handler, search_handler, search_keys_handler,
-    set_handler, set_handler_ttl,
+    compare_and_set_handler, compare_and_set_handler_ttl, delete_handler, delete_single_handler,
+    dump_handler, get_handler, increment_handler, increment_handler_ttl, search_handler,
+    search_keys_handler, set_handler, set_handler_ttl, set_if_absent_handler,
+    set_if_absent_handler_ttl,
 };
-use actix_web::error::ErrorInternalServerError;
+use actix_web::error::{ErrorBadRequest, ErrorInternalServerError};
 use actix_web::{get, post, web, App, Error, HttpRequest, HttpResponse, HttpServer, Responder};
-use actix_ws::AggregatedMessage;
+use actix_ws::{AggregatedMessage, CloseCode, CloseReason};
 use anyhow::bail;
-use futures_util::StreamExt;
+use futures_util::{FutureExt, StreamExt};
 use hashbrown::HashMap;
 use lazy_static::lazy_static;
 use log::{debug, error, info, warn};
 use moka::future::Cache;
+use moka::notification::{ListenerFuture, RemovalCause};
 use moka::Expiry;
 use prometheus_client::encoding::text::encode;
 use serde::{Deserialize, Serialize};
@@ -38,17 +48,34 @@ struct RecordExpiration;
 
 const HOUSKEEPING_PERIOD: Duration = Duration::from_secs(1);
 const MAX_WS_INFLIGHT_OPS: usize = 100;
+/// The reason the subscriber which missed operations is disconnected with.
+const KVS_RESYNC_REQUIRED: &str = "resync required";
 
 pub type KvsSubscription = Receiver<KvsOperation>;
 
-impl Expiry<(String, String), (Option<u64>, Attribute)> for RecordExpiration {
+/// The records are stored with the expiration time, so the remaining TTL survives the
+/// persistence.
+///
+impl Expiry<(String, String), (Option<SystemTime>, Attribute)> for RecordExpiration {
     fn expire_after_create(
         &self,
         _: &(String, String),
-        value: &(Option<u64>, Attribute),
+        value: &(Option<SystemTime>, Attribute),
         _created_at: Instant,
     ) -> Option<Duration> {
-        value.0.map(Duration::from_millis)
+        value
+            .0
+            .map(|e| e.duration_since(SystemTime::now()).unwrap_or_default())
+    }
+
+    fn expire_after_update(
+        &self,
+        key: &(String, String),
+        value: &(Option<SystemTime>, Attribute),
+        updated_at: Instant,
+        _duration_until_expiry: Option<Duration>,
+    ) -> Option<Duration> {
+        self.expire_after_create(key, value, updated_at)
     }
 }
 
@@ -68,6 +95,30 @@ const MAX_TTL_KVS_CAPACITY: u64 = 100_000;
 pub enum KvsOperationKind {
     Set(Vec<Attribute>, Option<u64>),
     Delete(Vec<Attribute>),
+    /// The TTL of the attributes is over.
+    Expired(Vec<Attribute>),
+    /// The attributes are removed because the store is full.
+    Evicted(Vec<Attribute>),
+}
+
+impl KvsOperationKind {
+    pub fn attributes(&self) -> &[Attribute] {
+        match self {
+            KvsOperationKind::Set(attributes, _)
+            | KvsOperationKind::Delete(attributes)
+            | KvsOperationKind::Expired(attributes)
+            | KvsOperationKind::Evicted(attributes) => attributes,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            KvsOperationKind::Set(..) => "set",
+            KvsOperationKind::Delete(_) => "delete",
+            KvsOperationKind::Expired(_) => "expired",
+            KvsOperationKind::Evicted(_) => "evicted",
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -76,50 +127,53 @@ pub struct KvsOperation {
     pub operation: KvsOperationKind,
 }
 
+struct KvsSubscriber {
+    filter: KvsSubscriptionFilter,
+    tx: Sender<KvsOperation>,
+}
+
 #[allow(clippy::type_complexity)]
 struct WsData {
-    pipelines: Arc<Mutex<Vec<Arc<implementation::Pipeline>>>>,
+    pipelines: Arc<Mutex<Vec<Weak<implementation::Pipeline>>>>,
     status: Arc<Mutex<PipelineStatus>>,
     shutdown_token: Arc<OnceLock<String>>,
     shutdown_status: Arc<OnceLock<bool>>,
-    kvs: Arc<Cache<(String, String), (Option<u64>, Attribute)>>,
-    kvs_subscribers: Arc<Mutex<HashMap<String, Sender<KvsOperation>>>>,
+    kvs: Arc<Cache<(String, String), (Option<SystemTime>, Attribute)>>,
+    kvs_subscribers: Arc<Mutex<HashMap<String, KvsSubscriber>>>,
 }
 
 impl WsData {
     pub fn new() -> Self {
         let subscribers = Arc::new(Mutex::new(HashMap::new()));
-        // let eviction_subscribers = subscribers.clone();
-        // let eviction_listener = move |k: Arc<(String, String)>, _v, cause| -> ListenerFuture {
-        //     let scoped_subscribers = eviction_subscribers.clone();
-        //     async move {
-        //         if matches!(cause, RemovalCause::Replaced | RemovalCause::Expired) {
-        //             return;
-        //         }
-        //         if cause != RemovalCause::Replaced {
-        //             let (namespace, name) = k.deref();
-        //             let op = KvsOperation {
-        //                 namespace: namespace.clone(),
-        //                 name: name.clone(),
-        //                 timestamp: SystemTime::now(),
-        //                 operation: match cause {
-        //                     RemovalCause::Expired => KvsOperationKind::Expiration,
-        //                     RemovalCause::Explicit => KvsOperationKind::Delete,
-        //                     RemovalCause::Replaced => {
-        //                         unreachable!("Replaced should not be possible")
-        //                     }
-        //                     RemovalCause::Size => KvsOperationKind::SizeLimit,
-        //                 },
-        //             };
-        //             Self::broadcast_kvs_operation(scoped_subscribers, op).await;
-        //         }
-        //     }
-        //     .boxed()
-        // };
+        let eviction_subscribers = subscribers.clone();
+        // the explicit removals and replacements are reported by the KVS API
+        let eviction_listener = move |_k: Arc<(String, String)>,
+                                      (_, attribute): (Option<SystemTime>, Attribute),
+                                      cause|
+              -> ListenerFuture {
+            let scoped_subscribers = eviction_subscribers.clone();
+            async move {
+                let operation = match cause {
+                    RemovalCause::Expired => KvsOperationKind::Expired(vec![attribute]),
+                    RemovalCause::Size => {
+                        kvs_persistence::journal_delete(std::slice::from_ref(&attribute)).await;
+                        KvsOperationKind::Evicted(vec![attribute])
+                    }
+                    RemovalCause::Explicit | RemovalCause::Replaced => return,
+                };
+                let op = KvsOperation {
+                    timestamp: SystemTime::now(),
+                    operation,
+                };
+                Self::broadcast_kvs_operation(scoped_subscribers, op).await;
+            }
+            .boxed()
+        };
         let kvs = Arc::new(
             Cache::builder()
                 .max_capacity(MAX_TTL_KVS_CAPACITY)
-                .expire_after(RecordExpiration {}) //.async_eviction_listener(eviction_listener)
+                .expire_after(RecordExpiration {})
+                .async_eviction_listener(eviction_listener)
                 .build(),
         );
         let async_rt = get_or_init_async_runtime();
@@ -168,19 +222,24 @@ impl WsData {
     }
 
     pub async fn broadcast_kvs_operation(
-        kvs_subscribers: Arc<Mutex<HashMap<String, Sender<KvsOperation>>>>,
+        kvs_subscribers: Arc<Mutex<HashMap<String, KvsSubscriber>>>,
         op: KvsOperation,
     ) {
         let mut to_remove = Vec::new();
         let mut subscribers = kvs_subscribers.lock().await;
-        for (subscriber, tx) in subscribers.iter() {
-            if let Err(e) = tx.try_send(op.clone()) {
+        for (subscriber, KvsSubscriber { filter, tx }) in subscribers.iter() {
+            let Some(op) = filter.apply(&op) else {
+                continue;
+            };
+            if let Err(e) = tx.try_send(op) {
                 match e {
                     TrySendError::Full(m) => {
+                        // the subscriber missed the operation, it is disconnected to resync
                         warn!(
-                            "Subscriber {} is full, dropping message: {:?}",
+                            "Subscriber {} is full, disconnecting it, dropped message: {:?}",
                             subscriber, m
                         );
+                        to_remove.push(subscriber.clone());
                     }
                     TrySendError::Closed(_) => {
                         info!("Subscriber {} is closed, removing from list.", subscriber);
@@ -198,6 +257,7 @@ impl WsData {
         &self,
         subscriber: &str,
         max_ops: usize,
+        filter: KvsSubscriptionFilter,
     ) -> anyhow::Result<Receiver<KvsOperation>> {
         if max_ops == 0 {
             bail!("Max operations cannot be zero.");
@@ -208,16 +268,27 @@ impl WsData {
         }
 
         let (tx, rx) = channel(max_ops);
-        subscribers.insert(subscriber.to_string(), tx);
+        subscribers.insert(subscriber.to_string(), KvsSubscriber { filter, tx });
         Ok(rx)
     }
 }
 
 pub fn subscribe(subscriber: &str, max_ops: usize) -> anyhow::Result<KvsSubscription> {
+    subscribe_with_filter(subscriber, max_ops, KvsSubscriptionFilter::default())
+}
+
+/// Subscribes to the operations on the keys matching the filter, the operations on several
+/// keys are delivered with the matching attributes only.
+///
+pub fn subscribe_with_filter(
+    subscriber: &str,
+    max_ops: usize,
+    filter: KvsSubscriptionFilter,
+) -> anyhow::Result<KvsSubscription> {
     let runtime = get_or_init_async_runtime();
     runtime.block_on(async {
         let data = WS_DATA.clone();
-        data.subscribe(subscriber, max_ops).await
+        data.subscribe(subscriber, max_ops, filter).await
     })
 }
 
@@ -233,7 +304,7 @@ pub(crate) fn register_pipeline(pipeline: Arc<implementation::Pipeline>) {
     let stats = WS_DATA.pipelines.clone();
     runtime.block_on(async move {
         let mut bind = stats.lock().await;
-        bind.push(pipeline);
+        bind.push(Arc::downgrade(&pipeline));
         info!("Pipeline registered in stats.");
     });
 }
@@ -245,7 +316,8 @@ pub(crate) fn unregister_pipeline(pipeline: Arc<implementation::Pipeline>) {
         let mut bind = stats.lock().await;
         let prev_len = bind.len();
         debug!("Removing pipeline from stats.");
-        bind.retain(|p| !Arc::ptr_eq(p, &pipeline));
+        let pipeline = Arc::downgrade(&pipeline);
+        bind.retain(|p| p.strong_count() > 0 && !Weak::ptr_eq(p, &pipeline));
         if bind.len() == prev_len {
             error!("Failed to remove pipeline from stats.");
         }
@@ -254,7 +326,7 @@ pub(crate) fn unregister_pipeline(pipeline: Arc<implementation::Pipeline>) {
 
 pub(crate) async fn get_registered_pipelines() -> Vec<Arc<implementation::Pipeline>> {
     let s = WS_DATA.pipelines.lock().await;
-    s.clone()
+    s.iter().filter_map(Weak::upgrade).collect()
 }
 
 pub fn set_status(s: PipelineStatus) -> anyhow::Result<()> {
@@ -292,12 +364,24 @@ fn shutdown() -> anyhow::Result<()> {
     Ok(())
 }
 
+/// The pipeline status. The response stays the bare status value expected by the existing
+/// clients, the state of the sockets is served separately by `/status/sockets`.
+///
 #[get("/status")]
 async fn status_handler() -> impl Responder {
     let s = get_status().await;
     HttpResponse::Ok().json(s)
 }
 
+/// The connection state of the ZeroMQ readers and writers: the connected peers, the failed
+/// handshakes, the socket recreations of the readers and writers configured with
+/// `with_reconnect_after` and the last monitor event.
+///
+#[get("/status/sockets")]
+async fn socket_status_handler() -> impl Responder {
+    HttpResponse::Ok().json(get_socket_statuses())
+}
+
 #[derive(Deserialize)]
 enum ShutdownMode {
     #[serde(rename = "graceful")]
@@ -355,19 +439,74 @@ async fn shutdown_handler(params: web::Path<ShutdownParams>) -> HttpResponse {
     HttpResponse::Ok().json("ok")
 }
 
-async fn events_meta(req: HttpRequest, stream: web::Payload) -> Result<HttpResponse, Error> {
-    events_internal(req, stream, false).await
+/// The optional namespace and name globs of the subscription.
+///
+#[derive(Deserialize)]
+struct EventsParams {
+    ns: Option<String>,
+    name: Option<String>,
+}
+
+async fn events_meta(
+    req: HttpRequest,
+    stream: web::Payload,
+    params: web::Query<EventsParams>,
+) -> Result<HttpResponse, Error> {
+    events_internal(req, stream, params.into_inner(), false).await
+}
+
+async fn events_full(
+    req: HttpRequest,
+    stream: web::Payload,
+    params: web::Query<EventsParams>,
+) -> Result<HttpResponse, Error> {
+    events_internal(req, stream, params.into_inner(), true).await
 }
 
-async fn events_full(req: HttpRequest, stream: web::Payload) -> Result<HttpResponse, Error> {
-    events_internal(req, stream, true).await
+/// The frames of the operation sent to the subscribers: the JSON text with the operation on
+/// every attribute and, for the full subscriptions, the binary one with the attributes.
+///
+fn kvs_operation_frames(
+    msg: KvsOperation,
+    full: bool,
+) -> anyhow::Result<(String, Option<Vec<u8>>)> {
+    let operation = msg.operation.name();
+    let (attrs, ttl) = match msg.operation {
+        KvsOperationKind::Set(attrs, ttl) => (attrs, Some(ttl)),
+        KvsOperationKind::Delete(attrs)
+        | KvsOperationKind::Expired(attrs)
+        | KvsOperationKind::Evicted(attrs) => (attrs, None),
+    };
+    let mut response = Vec::with_capacity(attrs.len());
+    for attr in &attrs {
+        let mut event = json!({
+            "timestamp": msg.timestamp,
+            "operation": operation,
+            "namespace": attr.namespace,
+            "name": attr.name,
+        });
+        if let Some(ttl) = ttl {
+            event["ttl"] = json!(ttl);
+        }
+        response.push(event);
+    }
+    let text = serde_json::to_string(&response)?;
+    let binary = if full {
+        Some(AttributeSet::from(attrs).to_pb()?)
+    } else {
+        None
+    };
+    Ok((text, binary))
 }
 
 async fn events_internal(
     req: HttpRequest,
     stream: web::Payload,
+    params: EventsParams,
     full: bool,
 ) -> Result<HttpResponse, Error> {
+    let filter = KvsSubscriptionFilter::new(&params.ns, &params.name)
+        .map_err(|e| ErrorBadRequest(format!("Invalid subscription filter: {}", e)))?;
     let (res, mut session, stream) = actix_ws::handle(&req, stream)?;
     let mut stream = stream
         .aggregate_continuations()
@@ -377,7 +516,7 @@ async fn events_internal(
     let my_name = uuid::Uuid::new_v4().to_string();
     info!("New websocket connection: subscriber={}", &my_name);
     let mut rs = WS_DATA
-        .subscribe(&my_name, MAX_WS_INFLIGHT_OPS)
+        .subscribe(&my_name, MAX_WS_INFLIGHT_OPS, filter)
         .await
         .map_err(|e| {
             error!("Failed to subscribe to KVS events: {}", e);
@@ -403,49 +542,22 @@ async fn events_internal(
     });
 
     actix_web::rt::spawn(async move {
+        // the subscription ends when the subscriber falls behind and misses an operation
         while let Some(msg) = rs.recv().await {
-            let (msg, attrs) = match msg.operation {
-                KvsOperationKind::Set(attrs, ttl) => {
-                    let mut response = Vec::with_capacity(attrs.len());
-                    for attr in &attrs {
-                        response.push(json!({
-                            "timestamp": msg.timestamp,
-                            "operation": "set",
-                            "namespace": attr.namespace,
-                            "name": attr.name,
-                            "ttl": ttl,
-                        }));
-                    }
-                    (response, if full { Some(attrs) } else { None })
-                }
-                KvsOperationKind::Delete(attrs) => {
-                    let mut response = Vec::with_capacity(attrs.len());
-                    for attr in &attrs {
-                        response.push(json!({
-                            "timestamp": msg.timestamp,
-                            "operation": "delete",
-                            "namespace": attr.namespace,
-                            "name": attr.name,
-                        }));
-                    }
-                    (response, if full { Some(attrs) } else { None })
+            let (text, binary) = match kvs_operation_frames(msg, full) {
+                Ok(frames) => frames,
+                Err(e) => {
+                    warn!("Failed to serialize attributes: {}", e);
+                    return;
                 }
             };
-            let msg = serde_json::to_string(&msg).expect("Failed to serialize message");
-            let res = session.text(msg.as_str()).await;
+            let res = session.text(text).await;
             if let Err(e) = res {
                 warn!("Failed to send message to subscriber={}: {}", &my_name, e);
                 return;
             }
-            if let Some(attrs) = attrs {
-                let set = AttributeSet::from(attrs);
-                let serialized = set.to_pb();
-                if let Err(e) = serialized {
-                    warn!("Failed to serialize attributes: {}", e);
-                    return;
-                }
-                let serialized = serialized.unwrap();
-                let res = session.binary(serialized).await;
+            if let Some(binary) = binary {
+                let res = session.binary(binary).await;
                 if let Err(e) = res {
                     warn!(
                         "Failed to send serialized attributes to subscriber={}: {}",
@@ -455,6 +567,17 @@ async fn events_internal(
                 }
             }
         }
+        info!(
+            "Subscriber={} missed KVS operations, closing the connection to resync",
+            &my_name
+        );
+        let reason = CloseReason {
+            code: CloseCode::Again,
+            description: Some(KVS_RESYNC_REQUIRED.to_string()),
+        };
+        if let Err(e) = session.close(Some(reason)).await {
+            warn!("Failed to close subscriber={}: {}", &my_name, e);
+        }
     });
 
     Ok(res)
@@ -493,12 +616,17 @@ pub fn init_webserver(port: u16) -> anyhow::Result<()> {
     if WS_JOB.get().is_some() {
         return Ok(());
     }
+    if let Some(persistence) = kvs_persistence::get_kvs_persistence() {
+        rt.block_on(kvs_persistence::restore(persistence))?;
+        kvs_persistence::start_snapshots(persistence);
+    }
     let job_id = rt.spawn(async move {
         HttpServer::new(move || {
             App::new()
                 .route("/kvs/events/meta", web::get().to(events_meta))
                 .route("/kvs/events/full", web::get().to(events_full))
                 .service(status_handler)
+                .service(socket_status_handler)
                 .service(shutdown_handler)
                 .service(metrics_handler)
                 .service(set_handler)
@@ -508,6 +636,13 @@ pub fn init_webserver(port: u16) -> anyhow::Result<()> {
                 .service(search_handler)
                 .service(get_handler)
                 .service(search_keys_handler)
+                .service(dump_handler)
+                .service(compare_and_set_handler)
+                .service(compare_and_set_handler_ttl)
+                .service(set_if_absent_handler)
+                .service(set_if_absent_handler_ttl)
+                .service(increment_handler)
+                .service(increment_handler_ttl)
         })
         .bind(("0.0.0.0", port))
         .expect("Failed to bind to host:port")
@@ -524,6 +659,9 @@ pub fn stop_webserver() {
     let rt = get_or_init_async_runtime();
     let ws_job = WS_JOB.get().expect("Web server job not started");
     ws_job.abort();
+    if let Some(persistence) = kvs_persistence::get_kvs_persistence() {
+        rt.block_on(kvs_persistence::stop(persistence));
+    }
     rt.block_on(async { WS_DATA.kvs_subscribers.lock().await.clear() });
 }
 
@@ -562,6 +700,8 @@ mod tests {
         assert_eq!(r.status(), 200);
         let s: PipelineStatus = r.json()?;
         assert!(matches!(s, PipelineStatus::Running));
+        let r = reqwest::blocking::get("http://localhost:8888/status/sockets")?;
+        assert_eq!(r.status(), 200);
         stop_webserver();
         Ok(())
     }
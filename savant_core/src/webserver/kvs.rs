@@ -1,29 +1,218 @@
 pub mod asynchronous {
     use crate::primitives::attribute::Attribute;
+    use crate::primitives::attribute_value::{AttributeValue, AttributeValueVariant};
+    use crate::webserver::kvs_persistence::{
+        journal_delete, journal_set, journal_set_before_change,
+    };
     use crate::webserver::{KvsOperation, KvsOperationKind, WsData, WS_DATA};
+    use anyhow::{anyhow, bail};
     use globset::Glob;
-    use std::time::SystemTime;
+    use moka::ops::compute::{CompResult, Op};
+    use std::sync::Arc;
+    use std::time::{Duration, SystemTime};
+
+    type Record = (Option<SystemTime>, Attribute);
+
+    fn expiration_after(ttl: Option<u64>) -> Option<SystemTime> {
+        ttl.map(|ttl| SystemTime::now() + Duration::from_millis(ttl))
+    }
+
+    fn remaining_ttl(expiration: Option<SystemTime>) -> Option<u64> {
+        expiration.map(|e| {
+            e.duration_since(SystemTime::now())
+                .unwrap_or_default()
+                .as_millis() as u64
+        })
+    }
 
     pub async fn set_attributes(attributes: &[Attribute], ttl: Option<u64>) {
+        insert_attributes(attributes, expiration_after(ttl), ttl).await
+    }
+
+    /// Sets the attributes expiring at the given time, e.g. the ones replicated from another
+    /// node.
+    ///
+    pub(crate) async fn set_attributes_until(
+        attributes: &[Attribute],
+        expiration: Option<SystemTime>,
+    ) {
+        insert_attributes(attributes, expiration, remaining_ttl(expiration)).await
+    }
+
+    async fn insert_attributes(
+        attributes: &[Attribute],
+        expiration: Option<SystemTime>,
+        ttl: Option<u64>,
+    ) {
         for attr in attributes {
             let namespace = attr.namespace.clone();
             let name = attr.name.clone();
             WS_DATA
                 .kvs
-                .insert((namespace.clone(), name.clone()), (ttl, attr.clone()))
+                .insert(
+                    (namespace.clone(), name.clone()),
+                    (expiration, attr.clone()),
+                )
                 .await;
         }
+        notify_set(attributes, expiration, ttl).await;
+    }
+
+    async fn notify_set(
+        attributes: &[Attribute],
+        expiration: Option<SystemTime>,
+        ttl: Option<u64>,
+    ) {
+        journal_set(attributes, expiration).await;
+        broadcast_set(attributes, ttl, SystemTime::now()).await;
+    }
+
+    async fn broadcast_set(attributes: &[Attribute], ttl: Option<u64>, timestamp: SystemTime) {
         let subscribers = WS_DATA.kvs_subscribers.clone();
         WsData::broadcast_kvs_operation(
             subscribers,
             KvsOperation {
-                timestamp: SystemTime::now(),
+                timestamp,
                 operation: KvsOperationKind::Set(attributes.to_vec(), ttl),
             },
         )
         .await;
     }
 
+    /// Atomically replaces the record of the attribute when the condition on the current one
+    /// holds, returns if the attribute is set. The change is journaled and timestamped while
+    /// the key is locked, so the concurrent changes are persisted and replicated in the order
+    /// they are made.
+    ///
+    async fn set_if<F>(attribute: &Attribute, ttl: Option<u64>, condition: F) -> bool
+    where
+        F: FnOnce(Option<&Record>) -> bool,
+    {
+        let expiration = expiration_after(ttl);
+        let key = (attribute.namespace.clone(), attribute.name.clone());
+        let mut change = None;
+        WS_DATA
+            .kvs
+            .entry(key)
+            .and_compute_with(|entry| {
+                let put = condition(entry.as_ref().map(|e| e.value()));
+                let change = &mut change;
+                async move {
+                    if !put {
+                        return Op::Nop;
+                    }
+                    let gate =
+                        journal_set_before_change(std::slice::from_ref(attribute), expiration)
+                            .await;
+                    *change = Some((SystemTime::now(), gate));
+                    Op::Put((expiration, attribute.clone()))
+                }
+            })
+            .await;
+        let Some((timestamp, gate)) = change else {
+            return false;
+        };
+        drop(gate);
+        broadcast_set(std::slice::from_ref(attribute), ttl, timestamp).await;
+        true
+    }
+
+    /// Sets the attribute if its current values are equal to the expected ones.
+    ///
+    pub async fn compare_and_set(
+        attribute: &Attribute,
+        expected: &[AttributeValue],
+        ttl: Option<u64>,
+    ) -> bool {
+        set_if(attribute, ttl, |current| {
+            current.is_some_and(|(_, current)| current.values.as_slice() == expected)
+        })
+        .await
+    }
+
+    /// Sets the attribute if there is no attribute with the same namespace and name.
+    ///
+    pub async fn set_if_absent(attribute: &Attribute, ttl: Option<u64>) -> bool {
+        set_if(attribute, ttl, |current| current.is_none()).await
+    }
+
+    fn incremented(
+        current: Option<Record>,
+        ns: &str,
+        name: &str,
+        delta: i64,
+        ttl: Option<u64>,
+    ) -> anyhow::Result<Record> {
+        let Some((expiration, attribute)) = current else {
+            let value = AttributeValue::integer(delta, None);
+            let attribute = Attribute::persistent(ns, name, vec![value], &None, false);
+            return Ok((expiration_after(ttl), attribute));
+        };
+        let [value] = attribute.values.as_slice() else {
+            bail!("Attribute {}/{} must have a single integer value", ns, name);
+        };
+        let AttributeValueVariant::Integer(current) = value.value else {
+            bail!("Attribute {}/{} must have a single integer value", ns, name);
+        };
+        let value = AttributeValue::new(
+            AttributeValueVariant::Integer(current.checked_add(delta).ok_or(anyhow!(
+                "Attribute {}/{} overflows",
+                ns,
+                name
+            ))?),
+            value.confidence,
+        );
+        Ok((
+            expiration,
+            Attribute {
+                values: Arc::new(vec![value]),
+                ..attribute
+            },
+        ))
+    }
+
+    /// Atomically adds the delta to the single integer value of the attribute and returns the
+    /// result. The absent attribute is created with the delta and the TTL, the existing one
+    /// keeps its expiration time. Like `set_if`, the result is journaled and timestamped while
+    /// the key is locked.
+    ///
+    pub async fn increment(
+        ns: &str,
+        name: &str,
+        delta: i64,
+        ttl: Option<u64>,
+    ) -> anyhow::Result<i64> {
+        let key = (ns.to_string(), name.to_string());
+        let mut change = None;
+        let res = WS_DATA
+            .kvs
+            .entry(key)
+            .and_try_compute_with(|entry| {
+                let record = incremented(entry.map(|e| e.into_value()), ns, name, delta, ttl);
+                let change = &mut change;
+                async move {
+                    let (expiration, attribute) = record?;
+                    let gate =
+                        journal_set_before_change(std::slice::from_ref(&attribute), expiration)
+                            .await;
+                    *change = Some((SystemTime::now(), remaining_ttl(expiration), gate));
+                    anyhow::Ok(Op::Put((expiration, attribute)))
+                }
+            })
+            .await?;
+        let (_, attribute) = match res {
+            CompResult::Inserted(entry) | CompResult::ReplacedWith(entry) => entry.into_value(),
+            _ => unreachable!("Increment always puts the attribute"),
+        };
+        let AttributeValueVariant::Integer(value) = attribute.values[0].value else {
+            unreachable!("Incremented attribute is always an integer")
+        };
+        let (timestamp, ttl, gate) = change.expect("Increment always puts the attribute");
+        drop(gate);
+        broadcast_set(&[attribute], ttl, timestamp).await;
+        Ok(value)
+    }
+
     pub async fn search_attributes(ns: &Option<String>, name: &Option<String>) -> Vec<Attribute> {
         let ns_glob = ns
             .as_ref()
@@ -76,7 +265,9 @@ pub mod asynchronous {
         keys
     }
 
-    pub async fn del_attributes(ns: &Option<String>, name: &Option<String>) {
+    /// Deletes the attributes matching the globs, returns the deleted ones.
+    ///
+    pub async fn del_attributes(ns: &Option<String>, name: &Option<String>) -> Vec<Attribute> {
         let mut keys_to_delete = Vec::new();
         let ns_glob = ns
             .as_ref()
@@ -103,18 +294,20 @@ pub mod asynchronous {
         let mut attrs = Vec::with_capacity(keys_to_delete.len());
         for key in keys_to_delete {
             let res = WS_DATA.kvs.remove(&key).await;
-            if let Some((_ttl, attr)) = res {
+            if let Some((_, attr)) = res {
                 attrs.push(attr);
             }
         }
+        journal_delete(&attrs).await;
         WsData::broadcast_kvs_operation(
             subscribers.clone(),
             KvsOperation {
                 timestamp: SystemTime::now(),
-                operation: KvsOperationKind::Delete(attrs),
+                operation: KvsOperationKind::Delete(attrs.clone()),
             },
         )
         .await;
+        attrs
     }
 
     pub async fn get_attribute(ns: &str, name: &str) -> Option<Attribute> {
@@ -132,6 +325,7 @@ pub mod asynchronous {
             .await
             .map(|(_, attr)| attr);
         if let Some(attr) = res {
+            journal_delete(std::slice::from_ref(&attr)).await;
             let subscribers = WS_DATA.kvs_subscribers.clone();
             WsData::broadcast_kvs_operation(
                 subscribers,
@@ -151,6 +345,7 @@ pub mod asynchronous {
 pub mod synchronous {
     use crate::get_or_init_async_runtime;
     use crate::primitives::attribute::Attribute;
+    use crate::primitives::attribute_value::AttributeValue;
 
     pub fn set_attributes(attributes: &[Attribute], ttl: Option<u64>) {
         let rt = get_or_init_async_runtime();
@@ -159,6 +354,31 @@ pub mod synchronous {
         });
     }
 
+    pub fn compare_and_set(
+        attribute: &Attribute,
+        expected: &[AttributeValue],
+        ttl: Option<u64>,
+    ) -> bool {
+        let rt = get_or_init_async_runtime();
+        rt.block_on(async {
+            crate::webserver::kvs::asynchronous::compare_and_set(attribute, expected, ttl).await
+        })
+    }
+
+    pub fn set_if_absent(attribute: &Attribute, ttl: Option<u64>) -> bool {
+        let rt = get_or_init_async_runtime();
+        rt.block_on(async {
+            crate::webserver::kvs::asynchronous::set_if_absent(attribute, ttl).await
+        })
+    }
+
+    pub fn increment(ns: &str, name: &str, delta: i64, ttl: Option<u64>) -> anyhow::Result<i64> {
+        let rt = get_or_init_async_runtime();
+        rt.block_on(async {
+            crate::webserver::kvs::asynchronous::increment(ns, name, delta, ttl).await
+        })
+    }
+
     pub fn search_attributes(ns: &Option<String>, name: &Option<String>) -> Vec<Attribute> {
         let rt = get_or_init_async_runtime();
         rt.block_on(async {
@@ -196,8 +416,8 @@ mod tests {
     use crate::protobuf::{from_pb, ToProtobuf};
     use crate::webserver::kvs::synchronous::*;
     use crate::webserver::{
-        init_webserver, set_status, stop_webserver, KvsOperation, KvsOperationKind, PipelineStatus,
-        HOUSKEEPING_PERIOD, WS_DATA,
+        init_webserver, set_status, stop_webserver, KvsOperation, KvsOperationKind,
+        KvsSubscriptionFilter, PipelineStatus, HOUSKEEPING_PERIOD, WS_DATA,
     };
     use savant_protobuf::generated;
     use std::thread::sleep;
@@ -272,7 +492,11 @@ mod tests {
         del_attributes(&None, &None);
         sleep(HOUSKEEPING_PERIOD + Duration::from_millis(10)); // wait until subscription handler clear the op queue
         let rt = get_or_init_async_runtime();
-        let mut subscription = rt.block_on(async { WS_DATA.subscribe("me", 10).await })?;
+        let mut subscription = rt.block_on(async {
+            WS_DATA
+                .subscribe("me", 10, KvsSubscriptionFilter::default())
+                .await
+        })?;
 
         let ttl_attribute_set = vec![Attribute::persistent("jkl", "yay", vec![], &None, false)];
         let attribute_set = vec![Attribute::persistent("ghi", "yay", vec![], &None, false)];
@@ -315,6 +539,153 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    #[serial_test::serial]
+    fn test_filtered_expiration() -> anyhow::Result<()> {
+        del_attributes(&None, &None);
+        sleep(HOUSKEEPING_PERIOD + Duration::from_millis(10));
+        let rt = get_or_init_async_runtime();
+        let filter = KvsSubscriptionFilter::new(&Some("exp".to_string()), &None)?;
+        let mut subscription = rt.block_on(async { WS_DATA.subscribe("exp", 10, filter).await })?;
+
+        set_attributes(
+            &[
+                Attribute::persistent("exp", "a", vec![], &None, false),
+                Attribute::persistent("other", "a", vec![], &None, false),
+            ],
+            Some(10),
+        );
+        let received = rt.block_on(async { subscription.recv().await }).unwrap();
+        assert!(matches!(
+            received.operation,
+            KvsOperationKind::Set(attr, Some(10)) if attr.len() == 1 && attr[0].namespace == "exp"
+        ));
+        let received = rt
+            .block_on(async {
+                tokio::time::timeout(HOUSKEEPING_PERIOD * 3, subscription.recv()).await
+            })?
+            .unwrap();
+        assert!(matches!(
+            received.operation,
+            KvsOperationKind::Expired(attr) if attr.len() == 1 && attr[0].namespace == "exp"
+        ));
+        Ok(())
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_slow_subscriber_is_disconnected() -> anyhow::Result<()> {
+        let rt = get_or_init_async_runtime();
+        let filter = KvsSubscriptionFilter::new(&Some("slow".to_string()), &None)?;
+        let mut subscription = rt.block_on(async { WS_DATA.subscribe("slow", 1, filter).await })?;
+        for name in ["a", "b"] {
+            set_attributes(
+                &[Attribute::persistent("slow", name, vec![], &None, false)],
+                None,
+            );
+        }
+        // the buffered operation is delivered, the missed one ends the subscription
+        let received = rt.block_on(async { subscription.recv().await }).unwrap();
+        assert!(matches!(
+            received.operation,
+            KvsOperationKind::Set(attr, None) if attr[0].name == "a"
+        ));
+        assert!(rt.block_on(async { subscription.recv().await }).is_none());
+        // the name is free for the new subscription
+        rt.block_on(async {
+            WS_DATA
+                .subscribe("slow", 1, KvsSubscriptionFilter::default())
+                .await
+        })?;
+        Ok(())
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_atomic_operations() {
+        del_attributes(&None, &None);
+        let owner = |id| {
+            Attribute::persistent(
+                "camera",
+                "owner",
+                vec![AttributeValue::integer(id, None)],
+                &None,
+                false,
+            )
+        };
+        assert!(!compare_and_set(&owner(1), &[], None));
+        assert!(set_if_absent(&owner(1), None));
+        assert!(!set_if_absent(&owner(2), None));
+        assert!(!compare_and_set(&owner(3), &owner(2).values, None));
+        assert!(compare_and_set(&owner(3), &owner(1).values, None));
+        assert_eq!(get_attribute("camera", "owner"), Some(owner(3)));
+
+        assert_eq!(increment("camera", "owner", 2, None).unwrap(), 5);
+        assert_eq!(increment("counter", "frames", -1, Some(50)).unwrap(), -1);
+        assert_eq!(increment("counter", "frames", 3, None).unwrap(), 2);
+        sleep(Duration::from_millis(60));
+        assert!(get_attribute("counter", "frames").is_none());
+
+        set_attributes(&[owner(i64::MAX)], None);
+        assert!(increment("camera", "owner", 1, None).is_err());
+        set_attributes(
+            &[Attribute::persistent(
+                "camera",
+                "owner",
+                vec![],
+                &None,
+                false,
+            )],
+            None,
+        );
+        assert!(increment("camera", "owner", 1, None).is_err());
+        del_attributes(&None, &None);
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_concurrent_increments_are_ordered() -> anyhow::Result<()> {
+        del_attributes(&None, &None);
+        sleep(HOUSKEEPING_PERIOD + Duration::from_millis(10)); // wait until subscription handler clear the op queue
+        let rt = get_or_init_async_runtime();
+        let mut subscription = rt.block_on(async {
+            WS_DATA
+                .subscribe(
+                    "incrementer",
+                    100,
+                    KvsSubscriptionFilter::new(&Some("counter".into()), &Some("hits".into()))?,
+                )
+                .await
+        })?;
+        let tasks = (0..50)
+            .map(|_| {
+                rt.spawn(async {
+                    crate::webserver::kvs::asynchronous::increment("counter", "hits", 1, None).await
+                })
+            })
+            .collect::<Vec<_>>();
+        for task in tasks {
+            rt.block_on(task)??;
+        }
+        let mut operations = (0..50)
+            .map(|_| rt.block_on(subscription.recv()).unwrap())
+            .collect::<Vec<_>>();
+        operations.sort_by_key(|op| op.timestamp);
+        let values = operations
+            .into_iter()
+            .map(|op| match op.operation {
+                KvsOperationKind::Set(attrs, _) => attrs[0].values[0].clone(),
+                _ => unreachable!(),
+            })
+            .collect::<Vec<_>>();
+        let expected = (1..=50)
+            .map(|v| AttributeValue::integer(v, None))
+            .collect::<Vec<_>>();
+        assert_eq!(values, expected);
+        del_attributes(&None, &None);
+        Ok(())
+    }
+
     #[test]
     #[serial_test::serial]
     fn test_abi_to_api() -> anyhow::Result<()> {
@@ -1,20 +1,64 @@
 use crate::primitives::attribute_set::AttributeSet;
+use crate::primitives::Attribute;
 use crate::protobuf::{from_pb, ToProtobuf};
 use crate::webserver::kvs::asynchronous::{
-    del_attribute, del_attributes, get_attribute, search_attributes, search_keys, set_attributes,
+    compare_and_set, del_attribute, del_attributes, get_attribute, increment, search_attributes,
+    search_keys, set_attributes, set_if_absent,
 };
-use actix_web::{get, post, web, HttpResponse};
-use lazy_static::lazy_static;
+use crate::webserver::kvs_persistence::{dump_timestamp, write_store};
+use crate::webserver::kvs_replication::KVS_TIMESTAMP_HEADER;
+use crate::webserver::WS_DATA;
+use actix_web::http::header::{Accept, Header};
+use actix_web::{get, mime, post, web, HttpMessage, HttpRequest, HttpResponse};
+use log::error;
 use savant_protobuf::generated;
+use serde_json::json;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-lazy_static! {
-    static ref EMPTY_SERIALIZED_ATTRIBUTE_SET: Vec<u8> = AttributeSet::new().to_pb().unwrap();
+const PROTOBUF_CONTENT_TYPE: &str = "application/x-protobuf";
+
+/// The attributes of the payload, a JSON array of the serde representation of `Attribute` for
+/// `application/json` or the protobuf `AttributeSet` otherwise.
+///
+fn parse_attributes(req: &HttpRequest, payload: &[u8]) -> anyhow::Result<Vec<Attribute>> {
+    if req.content_type() == mime::APPLICATION_JSON.essence_str() {
+        Ok(serde_json::from_slice(payload)?)
+    } else {
+        Ok(from_pb::<generated::AttributeSet, AttributeSet>(payload)?.attributes)
+    }
+}
+
+/// Checks if the type is the preferred accepted one, `*/*` when there is no `Accept` header.
+///
+fn prefers(req: &HttpRequest, content_type: &str) -> bool {
+    Accept::parse(req).is_ok_and(|a| a.preference().essence_str() == content_type)
 }
 
-async fn set_attributes_with_ttl(payload: web::Bytes, ttl: Option<u64>) -> HttpResponse {
-    let attribute_set = from_pb::<generated::AttributeSet, AttributeSet>(&payload);
-    if let Ok(attribute_set) = attribute_set {
-        set_attributes(&attribute_set.attributes, ttl).await;
+/// Responds with a JSON array when `application/json` is the preferred accepted type and with
+/// the protobuf `AttributeSet` otherwise.
+///
+fn attributes_response(req: &HttpRequest, attributes: Vec<Attribute>) -> HttpResponse {
+    if prefers(req, mime::APPLICATION_JSON.essence_str()) {
+        return HttpResponse::Ok().json(attributes);
+    }
+    match AttributeSet::from(attributes).to_pb() {
+        Ok(pb) => HttpResponse::Ok()
+            .content_type(PROTOBUF_CONTENT_TYPE)
+            .body(pb),
+        Err(e) => {
+            error!("Failed to serialize attributes: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+async fn set_attributes_with_ttl(
+    req: HttpRequest,
+    payload: web::Bytes,
+    ttl: Option<u64>,
+) -> HttpResponse {
+    if let Ok(attributes) = parse_attributes(&req, &payload) {
+        set_attributes(&attributes, ttl).await;
         HttpResponse::Ok().finish()
     } else {
         HttpResponse::BadRequest().finish()
@@ -22,49 +66,129 @@ async fn set_attributes_with_ttl(payload: web::Bytes, ttl: Option<u64>) -> HttpR
 }
 
 #[post("/kvs/set-with-ttl/{ttl}")]
-async fn set_handler_ttl(payload: web::Bytes, ttl: web::Path<u64>) -> HttpResponse {
-    set_attributes_with_ttl(payload, Some(ttl.into_inner())).await
+async fn set_handler_ttl(
+    req: HttpRequest,
+    payload: web::Bytes,
+    ttl: web::Path<u64>,
+) -> HttpResponse {
+    set_attributes_with_ttl(req, payload, Some(ttl.into_inner())).await
 }
 
 #[post("/kvs/set")]
-async fn set_handler(payload: web::Bytes) -> HttpResponse {
-    set_attributes_with_ttl(payload, None).await
+async fn set_handler(req: HttpRequest, payload: web::Bytes) -> HttpResponse {
+    set_attributes_with_ttl(req, payload, None).await
+}
+
+/// The payload holds the attribute with the expected values followed by the new attribute,
+/// responds with `true` when the attribute is set.
+///
+async fn compare_and_set_with_ttl(
+    req: HttpRequest,
+    payload: web::Bytes,
+    ttl: Option<u64>,
+) -> HttpResponse {
+    let attributes = parse_attributes(&req, &payload);
+    match attributes.as_deref() {
+        Ok([expected, attribute])
+            if expected.namespace == attribute.namespace && expected.name == attribute.name =>
+        {
+            let is_set = compare_and_set(attribute, &expected.values, ttl).await;
+            HttpResponse::Ok().json(is_set)
+        }
+        _ => HttpResponse::BadRequest().finish(),
+    }
+}
+
+#[post("/kvs/compare-and-set")]
+async fn compare_and_set_handler(req: HttpRequest, payload: web::Bytes) -> HttpResponse {
+    compare_and_set_with_ttl(req, payload, None).await
+}
+
+#[post("/kvs/compare-and-set-with-ttl/{ttl}")]
+async fn compare_and_set_handler_ttl(
+    req: HttpRequest,
+    payload: web::Bytes,
+    ttl: web::Path<u64>,
+) -> HttpResponse {
+    compare_and_set_with_ttl(req, payload, Some(ttl.into_inner())).await
+}
+
+/// The payload holds a single attribute, responds with `true` when the attribute is set.
+///
+async fn set_if_absent_with_ttl(
+    req: HttpRequest,
+    payload: web::Bytes,
+    ttl: Option<u64>,
+) -> HttpResponse {
+    let attributes = parse_attributes(&req, &payload);
+    match attributes.as_deref() {
+        Ok([attribute]) => HttpResponse::Ok().json(set_if_absent(attribute, ttl).await),
+        _ => HttpResponse::BadRequest().finish(),
+    }
+}
+
+#[post("/kvs/set-if-absent")]
+async fn set_if_absent_handler(req: HttpRequest, payload: web::Bytes) -> HttpResponse {
+    set_if_absent_with_ttl(req, payload, None).await
+}
+
+#[post("/kvs/set-if-absent-with-ttl/{ttl}")]
+async fn set_if_absent_handler_ttl(
+    req: HttpRequest,
+    payload: web::Bytes,
+    ttl: web::Path<u64>,
+) -> HttpResponse {
+    set_if_absent_with_ttl(req, payload, Some(ttl.into_inner())).await
+}
+
+async fn increment_with_ttl(ns: &str, name: &str, delta: i64, ttl: Option<u64>) -> HttpResponse {
+    match increment(ns, name, delta, ttl).await {
+        Ok(value) => HttpResponse::Ok().json(value),
+        Err(e) => HttpResponse::BadRequest().body(e.to_string()),
+    }
+}
+
+#[post("/kvs/increment/{ns}/{name}/{delta}")]
+async fn increment_handler(path: web::Path<(String, String, i64)>) -> HttpResponse {
+    let (ns, name, delta) = path.into_inner();
+    increment_with_ttl(&ns, &name, delta, None).await
+}
+
+#[post("/kvs/increment-with-ttl/{ns}/{name}/{delta}/{ttl}")]
+async fn increment_handler_ttl(path: web::Path<(String, String, i64, u64)>) -> HttpResponse {
+    let (ns, name, delta, ttl) = path.into_inner();
+    increment_with_ttl(&ns, &name, delta, Some(ttl)).await
 }
 
+/// Responds with the deleted attributes when JSON or protobuf is explicitly accepted and with
+/// the empty body otherwise.
+///
 #[post("/kvs/delete/{ns}/{name}")]
-async fn delete_handler(path: web::Path<(String, String)>) -> HttpResponse {
+async fn delete_handler(req: HttpRequest, path: web::Path<(String, String)>) -> HttpResponse {
     let (ns, name) = path.into_inner();
-    del_attributes(&Some(ns), &Some(name)).await;
-    HttpResponse::Ok().finish()
+    let attrs = del_attributes(&Some(ns), &Some(name)).await;
+    if prefers(&req, mime::APPLICATION_JSON.essence_str()) || prefers(&req, PROTOBUF_CONTENT_TYPE) {
+        attributes_response(&req, attrs)
+    } else {
+        HttpResponse::Ok().finish()
+    }
 }
 
 #[post("/kvs/delete-single/{ns}/{name}")]
-async fn delete_single_handler(path: web::Path<(String, String)>) -> HttpResponse {
+async fn delete_single_handler(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+) -> HttpResponse {
     let (ns, name) = path.into_inner();
     let attr_opt = del_attribute(&ns, &name).await;
-    if attr_opt.is_none() {
-        return HttpResponse::Ok().body(EMPTY_SERIALIZED_ATTRIBUTE_SET.clone());
-    }
-    let attr_set = AttributeSet::from(vec![attr_opt.unwrap()]);
-    let pb = attr_set.to_pb();
-    if let Ok(pb) = pb {
-        HttpResponse::Ok().body(pb)
-    } else {
-        HttpResponse::InternalServerError().finish()
-    }
+    attributes_response(&req, attr_opt.into_iter().collect())
 }
 
 #[get("/kvs/search/{ns}/{name}")]
-async fn search_handler(path: web::Path<(String, String)>) -> HttpResponse {
+async fn search_handler(req: HttpRequest, path: web::Path<(String, String)>) -> HttpResponse {
     let (ns, name) = path.into_inner();
     let attrs = search_attributes(&Some(ns), &Some(name)).await;
-    let attr_set = AttributeSet::from(attrs);
-    let pb = attr_set.to_pb();
-    if let Ok(pb) = pb {
-        HttpResponse::Ok().body(pb)
-    } else {
-        HttpResponse::InternalServerError().finish()
-    }
+    attributes_response(&req, attrs)
 }
 
 #[get("/kvs/search-keys/{ns}/{name}")]
@@ -75,17 +199,198 @@ async fn search_keys_handler(path: web::Path<(String, String)>) -> HttpResponse
 }
 
 #[get("/kvs/get/{ns}/{name}")]
-async fn get_handler(path: web::Path<(String, String)>) -> HttpResponse {
+async fn get_handler(req: HttpRequest, path: web::Path<(String, String)>) -> HttpResponse {
     let (ns, name) = path.into_inner();
     let attr_opt = get_attribute(&ns, &name).await;
-    if attr_opt.is_none() {
-        return HttpResponse::Ok().body(EMPTY_SERIALIZED_ATTRIBUTE_SET.clone());
+    attributes_response(&req, attr_opt.into_iter().collect())
+}
+
+fn unix_millis(time: SystemTime) -> u128 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis()
+}
+
+/// The whole store in the persistence record format, used by the replicas for the full
+/// synchronization. The time of the dump in unix millis is in the `x-kvs-timestamp` header.
+///
+/// When `application/json` is the preferred accepted type, the store is a JSON array of the
+/// attributes with their expiration time in unix millis, `null` for no TTL.
+///
+#[get("/kvs/dump")]
+async fn dump_handler(req: HttpRequest) -> HttpResponse {
+    let timestamp = unix_millis(dump_timestamp().await);
+    if prefers(&req, mime::APPLICATION_JSON.essence_str()) {
+        let records = WS_DATA
+            .kvs
+            .iter()
+            .map(|(_, (expiration, attribute))| {
+                json!({
+                    "expiration": expiration.map(unix_millis),
+                    "attribute": attribute,
+                })
+            })
+            .collect::<Vec<_>>();
+        return HttpResponse::Ok()
+            .insert_header((KVS_TIMESTAMP_HEADER, timestamp.to_string()))
+            .json(records);
     }
-    let attr_set = AttributeSet::from(vec![attr_opt.unwrap()]);
-    let pb = attr_set.to_pb();
-    if let Ok(pb) = pb {
-        HttpResponse::Ok().body(pb)
-    } else {
-        HttpResponse::InternalServerError().finish()
+    let mut body = Vec::new();
+    match write_store(&mut body) {
+        Ok(_) => HttpResponse::Ok()
+            .content_type(mime::APPLICATION_OCTET_STREAM)
+            .insert_header((KVS_TIMESTAMP_HEADER, timestamp.to_string()))
+            .body(body),
+        Err(e) => {
+            error!("Failed to dump KVS: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        delete_handler, dump_handler, get_handler, search_handler, set_handler,
+        PROTOBUF_CONTENT_TYPE,
+    };
+    use crate::primitives::attribute_set::AttributeSet;
+    use crate::primitives::attribute_value::AttributeValue;
+    use crate::primitives::Attribute;
+    use crate::protobuf::{from_pb, ToProtobuf};
+    use crate::webserver::kvs::asynchronous::{del_attributes, search_attributes, set_attributes};
+    use crate::webserver::kvs_persistence::parse_records;
+    use crate::webserver::kvs_replication::KVS_TIMESTAMP_HEADER;
+    use actix_web::http::header::{ACCEPT, CONTENT_TYPE};
+    use actix_web::{test, App};
+    use savant_protobuf::generated;
+
+    #[actix_web::test]
+    #[serial_test::serial]
+    async fn test_content_negotiation() {
+        del_attributes(&None, &None).await;
+        let app = test::init_service(
+            App::new()
+                .service(set_handler)
+                .service(get_handler)
+                .service(search_handler),
+        )
+        .await;
+        let json_attribute = Attribute::persistent(
+            "ns",
+            "json",
+            vec![AttributeValue::integer(1, None)],
+            &None,
+            false,
+        );
+        let pb_attribute = Attribute::persistent("ns", "pb", vec![], &None, false);
+
+        let req = test::TestRequest::post()
+            .uri("/kvs/set")
+            .set_json(vec![json_attribute.clone()])
+            .to_request();
+        assert!(test::call_service(&app, req).await.status().is_success());
+        let req = test::TestRequest::post()
+            .uri("/kvs/set")
+            .insert_header((CONTENT_TYPE, PROTOBUF_CONTENT_TYPE))
+            .set_payload(
+                AttributeSet::from(vec![pb_attribute.clone()])
+                    .to_pb()
+                    .unwrap(),
+            )
+            .to_request();
+        assert!(test::call_service(&app, req).await.status().is_success());
+        let req = test::TestRequest::post()
+            .uri("/kvs/set")
+            .insert_header((CONTENT_TYPE, "application/json"))
+            .set_payload("{}")
+            .to_request();
+        assert!(test::call_service(&app, req)
+            .await
+            .status()
+            .is_client_error());
+
+        let req = test::TestRequest::get()
+            .uri("/kvs/get/ns/pb")
+            .insert_header((ACCEPT, "application/json"))
+            .to_request();
+        let attributes: Vec<Attribute> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(attributes, vec![pb_attribute]);
+
+        let req = test::TestRequest::get()
+            .uri("/kvs/get/ns/absent")
+            .insert_header((ACCEPT, "application/json"))
+            .to_request();
+        let attributes: Vec<Attribute> = test::call_and_read_body_json(&app, req).await;
+        assert!(attributes.is_empty());
+
+        let req = test::TestRequest::get()
+            .uri("/kvs/search/ns/js*")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(
+            resp.headers().get(CONTENT_TYPE).unwrap(),
+            PROTOBUF_CONTENT_TYPE
+        );
+        let body = test::read_body(resp).await;
+        let attributes = from_pb::<generated::AttributeSet, AttributeSet>(&body)
+            .unwrap()
+            .attributes;
+        assert_eq!(attributes, vec![json_attribute]);
+        del_attributes(&None, &None).await;
+    }
+
+    #[actix_web::test]
+    #[serial_test::serial]
+    async fn test_delete_and_dump_negotiation() {
+        del_attributes(&None, &None).await;
+        let app =
+            test::init_service(App::new().service(delete_handler).service(dump_handler)).await;
+        let attribute = |name| Attribute::persistent("ns", name, vec![], &None, false);
+        set_attributes(&[attribute("a"), attribute("b"), attribute("c")], None).await;
+
+        let req = test::TestRequest::get()
+            .uri("/kvs/dump")
+            .insert_header((ACCEPT, "application/json"))
+            .to_request();
+        let records: Vec<serde_json::Value> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(records.len(), 3);
+        assert!(records.iter().all(|r| r["expiration"].is_null()));
+        let mut names = records
+            .iter()
+            .map(|r| serde_json::from_value::<Attribute>(r["attribute"].clone()).unwrap())
+            .map(|a| a.name)
+            .collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, vec!["a", "b", "c"]);
+
+        let req = test::TestRequest::get().uri("/kvs/dump").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.headers().contains_key(KVS_TIMESTAMP_HEADER));
+        let body = test::read_body(resp).await;
+        assert_eq!(parse_records(&body, "dump").unwrap().len(), 1);
+
+        let req = test::TestRequest::post()
+            .uri("/kvs/delete/ns/a")
+            .insert_header((ACCEPT, "application/json"))
+            .to_request();
+        let attributes: Vec<Attribute> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(attributes, vec![attribute("a")]);
+
+        let req = test::TestRequest::post()
+            .uri("/kvs/delete/ns/b")
+            .insert_header((ACCEPT, PROTOBUF_CONTENT_TYPE))
+            .to_request();
+        let body = test::call_and_read_body(&app, req).await;
+        let attributes = from_pb::<generated::AttributeSet, AttributeSet>(&body)
+            .unwrap()
+            .attributes;
+        assert_eq!(attributes, vec![attribute("b")]);
+
+        let req = test::TestRequest::post()
+            .uri("/kvs/delete/ns/c")
+            .to_request();
+        assert!(test::call_and_read_body(&app, req).await.is_empty());
+        assert!(search_attributes(&None, &None).await.is_empty());
     }
 }
@@ -0,0 +1,416 @@
+use crate::get_or_init_async_runtime;
+use crate::primitives::attribute_set::AttributeSet;
+use crate::primitives::Attribute;
+use crate::protobuf::{from_pb, ToProtobuf};
+use crate::webserver::WS_DATA;
+use anyhow::bail;
+use hashbrown::HashMap;
+use lazy_static::lazy_static;
+use log::{error, info, warn};
+use savant_protobuf::generated;
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::{Mutex, RwLock, RwLockReadGuard};
+use tokio::task::JoinHandle;
+
+pub(super) const RECORD_SET: u8 = b'S';
+const RECORD_DELETE: u8 = b'D';
+const RECORD_HEADER_SIZE: usize = 13;
+
+/// Persistence of the KVS: the periodic snapshots of the whole store and the append-only
+/// journal of the changes made after the last snapshot, kept in `<snapshot_path>.journal`.
+///
+/// Both files consist of the records `[kind: u8][expiration: u64 LE, unix millis, 0 - no
+/// TTL][length: u32 LE][AttributeSet protobuf]`.
+///
+#[derive(Clone, Debug)]
+pub struct KvsPersistence {
+    pub snapshot_path: PathBuf,
+    pub snapshot_period: Duration,
+}
+
+impl KvsPersistence {
+    pub fn new(snapshot_path: &Path, snapshot_period: Duration) -> Self {
+        Self {
+            snapshot_path: snapshot_path.to_path_buf(),
+            snapshot_period,
+        }
+    }
+
+    fn journal_path(&self) -> PathBuf {
+        let mut path = self.snapshot_path.clone().into_os_string();
+        path.push(".journal");
+        path.into()
+    }
+}
+
+static KVS_PERSISTENCE: OnceLock<KvsPersistence> = OnceLock::new();
+
+lazy_static! {
+    static ref JOURNAL: Mutex<Option<File>> = Mutex::new(None);
+    static ref SNAPSHOT_GATE: RwLock<()> = RwLock::new(());
+    static ref SNAPSHOT_JOB: parking_lot::Mutex<Option<JoinHandle<()>>> =
+        parking_lot::Mutex::new(None);
+}
+
+/// Enables the persistence of the KVS, must be called before `init_webserver` which
+/// restores the store.
+///
+pub fn set_kvs_persistence(persistence: KvsPersistence) -> anyhow::Result<()> {
+    if persistence.snapshot_period.is_zero() {
+        bail!("KVS snapshot period must be positive");
+    }
+    KVS_PERSISTENCE
+        .set(persistence)
+        .map_err(|p| anyhow::anyhow!("KVS persistence already set: {:?}", p))
+}
+
+pub(super) fn get_kvs_persistence() -> Option<&'static KvsPersistence> {
+    KVS_PERSISTENCE.get()
+}
+
+#[derive(Debug, PartialEq)]
+pub(super) struct Record {
+    pub kind: u8,
+    pub expiration: Option<SystemTime>,
+    pub attributes: Vec<Attribute>,
+}
+
+fn to_millis(time: Option<SystemTime>) -> u64 {
+    time.map(|t| {
+        t.duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis()
+            .max(1) as u64
+    })
+    .unwrap_or(0)
+}
+
+fn from_millis(millis: u64) -> Option<SystemTime> {
+    (millis != 0).then(|| UNIX_EPOCH + Duration::from_millis(millis))
+}
+
+pub(super) fn write_record<W: Write>(
+    writer: &mut W,
+    kind: u8,
+    expiration: Option<SystemTime>,
+    attributes: &[Attribute],
+) -> anyhow::Result<()> {
+    let payload = AttributeSet::from(attributes.to_vec()).to_pb()?;
+    let mut record = Vec::with_capacity(RECORD_HEADER_SIZE + payload.len());
+    record.push(kind);
+    record.extend_from_slice(&to_millis(expiration).to_le_bytes());
+    record.extend_from_slice(&u32::try_from(payload.len())?.to_le_bytes());
+    record.extend_from_slice(&payload);
+    writer.write_all(&record)?;
+    Ok(())
+}
+
+/// Reads the records of the file, a truncated record at the end (e.g. after a crash while
+/// appending to the journal) is ignored.
+///
+fn read_records(path: &Path) -> anyhow::Result<Vec<Record>> {
+    let mut data = Vec::new();
+    match File::open(path) {
+        Ok(mut file) => file.read_to_end(&mut data)?,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    parse_records(&data, &path.display().to_string())
+}
+
+pub(super) fn parse_records(data: &[u8], origin: &str) -> anyhow::Result<Vec<Record>> {
+    let mut records = Vec::new();
+    let mut rest = data;
+    while !rest.is_empty() {
+        if rest.len() < RECORD_HEADER_SIZE {
+            warn!("Truncated KVS record header found in {}", origin);
+            break;
+        }
+        let kind = rest[0];
+        let expiration = u64::from_le_bytes(rest[1..9].try_into()?);
+        let len = u32::from_le_bytes(rest[9..RECORD_HEADER_SIZE].try_into()?) as usize;
+        let Some(payload) = rest.get(RECORD_HEADER_SIZE..RECORD_HEADER_SIZE + len) else {
+            warn!("Truncated KVS record found in {}", origin);
+            break;
+        };
+        if kind != RECORD_SET && kind != RECORD_DELETE {
+            bail!("Unknown KVS record kind {} in {}", kind, origin);
+        }
+        let set = from_pb::<generated::AttributeSet, AttributeSet>(payload)?;
+        records.push(Record {
+            kind,
+            expiration: from_millis(expiration),
+            attributes: set.attributes,
+        });
+        rest = &rest[RECORD_HEADER_SIZE + len..];
+    }
+    Ok(records)
+}
+
+async fn append_to_journal(kind: u8, expiration: Option<SystemTime>, attributes: &[Attribute]) {
+    let mut journal = JOURNAL.lock().await;
+    if let Some(file) = journal.as_mut() {
+        if let Err(e) = write_record(file, kind, expiration, attributes) {
+            error!("Failed to append to KVS journal: {}", e);
+        }
+    }
+}
+
+pub(super) async fn journal_set(attributes: &[Attribute], expiration: Option<SystemTime>) {
+    append_to_journal(RECORD_SET, expiration, attributes).await;
+}
+
+/// Appends the change which is applied to the store after the append, e.g. from within an
+/// atomic operation. The snapshots wait until the guard is dropped, so the change is not lost
+/// with the truncated journal.
+///
+pub(super) async fn journal_set_before_change(
+    attributes: &[Attribute],
+    expiration: Option<SystemTime>,
+) -> RwLockReadGuard<'static, ()> {
+    let gate = SNAPSHOT_GATE.read().await;
+    journal_set(attributes, expiration).await;
+    gate
+}
+
+pub(super) async fn journal_delete(attributes: &[Attribute]) {
+    if !attributes.is_empty() {
+        append_to_journal(RECORD_DELETE, None, attributes).await;
+    }
+}
+
+/// The time the dump of the store is taken at. The changes stamped before it are applied to
+/// the store by then, so they are in the dump, and the later ones are stamped after it.
+///
+pub(super) async fn dump_timestamp() -> SystemTime {
+    let _gate = SNAPSHOT_GATE.write().await;
+    SystemTime::now()
+}
+
+/// Writes the whole store as the records grouped by the expiration time.
+///
+pub(super) fn write_store<W: Write>(writer: &mut W) -> anyhow::Result<usize> {
+    let mut groups: HashMap<Option<SystemTime>, Vec<Attribute>> = HashMap::new();
+    let mut count = 0;
+    for (_, (expiration, attribute)) in WS_DATA.kvs.iter() {
+        groups.entry(expiration).or_default().push(attribute);
+        count += 1;
+    }
+    for (expiration, attributes) in groups {
+        write_record(writer, RECORD_SET, expiration, &attributes)?;
+    }
+    Ok(count)
+}
+
+fn write_snapshot(snapshot_path: &Path) -> anyhow::Result<usize> {
+    let mut tmp_path = snapshot_path.to_path_buf().into_os_string();
+    tmp_path.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_path);
+    let mut writer = BufWriter::new(File::create(&tmp_path)?);
+    let count = write_store(&mut writer)?;
+    writer.into_inner()?.sync_all()?;
+    std::fs::rename(&tmp_path, snapshot_path)?;
+    Ok(count)
+}
+
+/// Writes the snapshot of the store and truncates the journal. The journal is locked for the
+/// whole operation, so the changes made concurrently end up in the new journal; their
+/// writers wait for the lock without blocking the runtime threads.
+///
+pub(super) async fn take_snapshot(persistence: &KvsPersistence) -> anyhow::Result<usize> {
+    let _gate = SNAPSHOT_GATE.write().await;
+    let mut journal = JOURNAL.lock().await;
+    let snapshot_path = persistence.snapshot_path.clone();
+    // the lock moves along, so an aborted snapshot still completes before the next one
+    tokio::task::spawn_blocking(move || {
+        let count = write_snapshot(&snapshot_path)?;
+        if let Some(file) = journal.as_mut() {
+            file.set_len(0)?;
+        }
+        Ok(count)
+    })
+    .await?
+}
+
+/// Loads the snapshot and replays the journal into the store, then compacts them into a new
+/// snapshot and opens the journal for the following changes.
+///
+pub(super) async fn restore(persistence: &KvsPersistence) -> anyhow::Result<usize> {
+    let now = SystemTime::now();
+    let records = read_records(&persistence.snapshot_path)?
+        .into_iter()
+        .chain(read_records(&persistence.journal_path())?);
+    for record in records {
+        for attribute in record.attributes {
+            let key = (attribute.namespace.clone(), attribute.name.clone());
+            match record.kind {
+                RECORD_SET if record.expiration.is_none_or(|e| e > now) => {
+                    WS_DATA
+                        .kvs
+                        .insert(key, (record.expiration, attribute))
+                        .await
+                }
+                RECORD_SET => {}
+                _ => WS_DATA.kvs.invalidate(&key).await,
+            }
+        }
+    }
+    *JOURNAL.lock().await = Some(
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(persistence.journal_path())?,
+    );
+    let count = take_snapshot(persistence).await?;
+    info!(
+        "KVS restored from {}, {} records",
+        persistence.snapshot_path.display(),
+        count
+    );
+    Ok(count)
+}
+
+pub(super) fn start_snapshots(persistence: &'static KvsPersistence) {
+    let rt = get_or_init_async_runtime();
+    let job = rt.spawn(async move {
+        loop {
+            tokio::time::sleep(persistence.snapshot_period).await;
+            if let Err(e) = take_snapshot(persistence).await {
+                error!("Failed to take KVS snapshot: {}", e);
+            }
+        }
+    });
+    if let Some(previous) = SNAPSHOT_JOB.lock().replace(job) {
+        previous.abort();
+    }
+}
+
+/// Stops the periodic snapshots, takes the final snapshot and closes the journal.
+///
+pub(super) async fn stop(persistence: &KvsPersistence) {
+    let job = SNAPSHOT_JOB.lock().take();
+    if let Some(job) = job {
+        job.abort();
+        let _ = job.await;
+    }
+    if let Err(e) = take_snapshot(persistence).await {
+        error!("Failed to take KVS snapshot: {}", e);
+    }
+    *JOURNAL.lock().await = None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        read_records, restore, start_snapshots, stop, write_record, KvsPersistence, Record,
+        JOURNAL, RECORD_DELETE, RECORD_SET, SNAPSHOT_JOB,
+    };
+    use crate::get_or_init_async_runtime;
+    use crate::primitives::attribute_value::AttributeValue;
+    use crate::primitives::Attribute;
+    use crate::webserver::kvs::asynchronous::increment;
+    use crate::webserver::kvs::synchronous::{
+        del_attribute, del_attributes, get_attribute, search_attributes, set_attributes,
+    };
+    use std::fs::OpenOptions;
+    use std::io::Write;
+    use std::time::{Duration, SystemTime};
+
+    fn attribute(ns: &str, name: &str) -> Attribute {
+        Attribute::persistent(ns, name, vec![], &None, false)
+    }
+
+    #[test]
+    fn test_records() -> anyhow::Result<()> {
+        let path = std::env::temp_dir().join(format!("kvs-records-{}", std::process::id()));
+        let expiration = SystemTime::UNIX_EPOCH + Duration::from_millis(1_000);
+        let mut file = std::fs::File::create(&path)?;
+        write_record(
+            &mut file,
+            RECORD_SET,
+            Some(expiration),
+            &[attribute("a", "b")],
+        )?;
+        write_record(&mut file, RECORD_DELETE, None, &[attribute("a", "b")])?;
+        file.write_all(&[RECORD_SET, 0, 0])?;
+        drop(file);
+
+        let records = read_records(&path)?;
+        assert_eq!(
+            records,
+            vec![
+                Record {
+                    kind: RECORD_SET,
+                    expiration: Some(expiration),
+                    attributes: vec![attribute("a", "b")],
+                },
+                Record {
+                    kind: RECORD_DELETE,
+                    expiration: None,
+                    attributes: vec![attribute("a", "b")],
+                },
+            ]
+        );
+        std::fs::remove_file(&path)?;
+        assert!(read_records(&path)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_restore() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("kvs-restore-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let persistence = KvsPersistence::new(&dir.join("kvs.snapshot"), Duration::from_secs(1));
+        let rt = get_or_init_async_runtime();
+
+        del_attributes(&None, &None);
+        rt.block_on(restore(&persistence))?;
+        start_snapshots(Box::leak(Box::new(persistence.clone())));
+        let snapshots = SNAPSHOT_JOB.lock().as_ref().unwrap().abort_handle();
+        set_attributes(&[attribute("ns", "kept"), attribute("ns", "deleted")], None);
+        set_attributes(&[attribute("ns", "with-ttl")], Some(60_000));
+        set_attributes(&[attribute("ns", "expired")], Some(1));
+        del_attribute("ns", "deleted");
+        let increments = (0..20)
+            .map(|_| rt.spawn(async { increment("ns", "counter", 1, None).await }))
+            .collect::<Vec<_>>();
+        for increment in increments {
+            rt.block_on(increment)??;
+        }
+        assert!(std::fs::metadata(persistence.journal_path())?.len() > 0);
+
+        // a crash while appending to the journal
+        *JOURNAL.blocking_lock() = None;
+        OpenOptions::new()
+            .append(true)
+            .open(persistence.journal_path())?
+            .write_all(&[RECORD_SET, 1])?;
+
+        del_attributes(&None, &None);
+        std::thread::sleep(Duration::from_millis(2));
+        let restored = rt.block_on(restore(&persistence))?;
+        assert_eq!(restored, 3);
+        assert!(get_attribute("ns", "kept").is_some());
+        assert_eq!(
+            get_attribute("ns", "counter").unwrap().values[0],
+            AttributeValue::integer(20, None)
+        );
+        assert!(get_attribute("ns", "with-ttl").is_some());
+        assert!(get_attribute("ns", "deleted").is_none());
+        assert!(get_attribute("ns", "expired").is_none());
+        assert_eq!(std::fs::metadata(persistence.journal_path())?.len(), 0);
+
+        rt.block_on(stop(&persistence));
+        assert!(snapshots.is_finished());
+        assert!(SNAPSHOT_JOB.lock().is_none());
+        del_attributes(&None, &None);
+        assert!(search_attributes(&None, &None).is_empty());
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,503 @@
+use crate::get_or_init_async_runtime;
+use crate::primitives::attribute_set::AttributeSet;
+use crate::primitives::Attribute;
+use crate::protobuf::from_pb;
+use crate::webserver::kvs::asynchronous::{del_attribute, search_keys, set_attributes_until};
+use crate::webserver::kvs_persistence::{parse_records, Record};
+use crate::webserver::WS_DATA;
+use anyhow::{anyhow, bail};
+use futures_util::StreamExt;
+use hashbrown::{HashMap, HashSet};
+use lazy_static::lazy_static;
+use log::{info, warn};
+use parking_lot::Mutex;
+use savant_protobuf::generated;
+use serde::Deserialize;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::net::TcpStream;
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+pub(super) const KVS_TIMESTAMP_HEADER: &str = "x-kvs-timestamp";
+const DEFAULT_RECONNECT_INTERVAL: Duration = Duration::from_secs(1);
+const DEFAULT_RESYNC_PERIOD: Duration = Duration::from_secs(300);
+/// How long the versions of the deleted keys are kept to reject the older operations on them.
+const TOMBSTONE_TTL: Duration = Duration::from_secs(60);
+
+lazy_static! {
+    static ref REPLICATION_JOB: Mutex<Option<JoinHandle<()>>> = Mutex::new(None);
+}
+
+/// Replication of the KVS of the leader webserver: the replica subscribes to
+/// `/kvs/events/full`, loads the whole store from `/kvs/dump` and applies the following
+/// operations. The full synchronization is repeated after every reconnect, the leader
+/// disconnects a replica which falls behind, and periodically as a safety net.
+///
+/// The replica is expected to be read-only, its own changes are overwritten by the leader.
+///
+#[derive(Clone, Debug)]
+pub struct KvsReplication {
+    /// The `host:port` of the leader webserver.
+    pub leader: String,
+    pub reconnect_interval: Duration,
+    /// The period of the full synchronization, `None` disables it.
+    pub resync_period: Option<Duration>,
+}
+
+impl KvsReplication {
+    pub fn new(leader: &str) -> Self {
+        Self {
+            leader: leader.to_string(),
+            reconnect_interval: DEFAULT_RECONNECT_INTERVAL,
+            resync_period: Some(DEFAULT_RESYNC_PERIOD),
+        }
+    }
+}
+
+/// The operation as sent by the leader to `/kvs/events/full`, followed by the binary frame
+/// with the attributes.
+///
+#[derive(Debug, Deserialize, PartialEq)]
+struct ReplicatedOperation {
+    timestamp: SystemTime,
+    operation: String,
+    namespace: String,
+    name: String,
+    #[serde(default)]
+    ttl: Option<u64>,
+}
+
+/// The state of the replica used to resolve conflicts: the operations made before the last
+/// full synchronization are already in the dump, and an operation older than the last one
+/// applied to the key is ignored.
+///
+#[derive(Default)]
+struct Replica {
+    synchronized_at: Option<SystemTime>,
+    versions: HashMap<(String, String), SystemTime>,
+    pruned_at: Option<SystemTime>,
+}
+
+impl Replica {
+    /// Forgets the versions of the keys which are no longer in the store, except the recent
+    /// deletions.
+    ///
+    fn prune_versions(&mut self, now: SystemTime) {
+        let horizon = now - TOMBSTONE_TTL;
+        self.versions
+            .retain(|key, version| *version > horizon || WS_DATA.kvs.contains_key(key));
+        self.pruned_at = Some(now);
+    }
+
+    fn accept(&mut self, key: (String, String), timestamp: SystemTime) -> bool {
+        if self.synchronized_at.is_some_and(|s| timestamp < s) {
+            return false;
+        }
+        match self.versions.get(&key) {
+            Some(version) if *version > timestamp => false,
+            _ => {
+                self.versions.insert(key, timestamp);
+                true
+            }
+        }
+    }
+
+    /// Replaces the store with the dump of the leader taken at `timestamp`.
+    ///
+    async fn synchronize(&mut self, timestamp: SystemTime, records: Vec<Record>) -> usize {
+        self.synchronized_at = Some(timestamp);
+        self.versions.clear();
+        let now = SystemTime::now();
+        let mut present = HashSet::new();
+        let mut count = 0;
+        for record in records {
+            for attribute in &record.attributes {
+                present.insert((attribute.namespace.clone(), attribute.name.clone()));
+            }
+            if record.expiration.is_none_or(|e| e > now) {
+                count += record.attributes.len();
+                set_attributes_until(&record.attributes, record.expiration).await;
+            }
+        }
+        for (namespace, name) in search_keys(&None, &None).await {
+            if !present.contains(&(namespace.clone(), name.clone())) {
+                del_attribute(&namespace, &name).await;
+            }
+        }
+        count
+    }
+
+    async fn apply(&mut self, operations: Vec<ReplicatedOperation>, attributes: Vec<Attribute>) {
+        if operations.len() != attributes.len() {
+            warn!(
+                "KVS replication received {} operations with {} attributes, ignoring them",
+                operations.len(),
+                attributes.len()
+            );
+            return;
+        }
+        for (op, attribute) in operations.into_iter().zip(attributes) {
+            if !self.accept((op.namespace.clone(), op.name.clone()), op.timestamp) {
+                continue;
+            }
+            match op.operation.as_str() {
+                "set" => {
+                    let expiration = op.ttl.map(|ttl| op.timestamp + Duration::from_millis(ttl));
+                    if expiration.is_none_or(|e| e > SystemTime::now()) {
+                        set_attributes_until(&[attribute], expiration).await;
+                    }
+                }
+                "delete" | "expired" | "evicted" => {
+                    del_attribute(&op.namespace, &op.name).await;
+                }
+                other => warn!("Unknown KVS operation {} received from the leader", other),
+            }
+        }
+        let now = SystemTime::now();
+        if self.pruned_at.is_none_or(|p| p + TOMBSTONE_TTL < now) {
+            self.prune_versions(now);
+        }
+    }
+}
+
+async fn connect_events(
+    leader: &str,
+) -> anyhow::Result<WebSocketStream<MaybeTlsStream<TcpStream>>> {
+    let url = format!("ws://{}/kvs/events/full", leader);
+    let (stream, _) = tokio_tungstenite::connect_async(url.as_str())
+        .await
+        .map_err(|e| anyhow!("Websocket connection to {} failed: {}", leader, e))?;
+    Ok(stream)
+}
+
+async fn next_frame(
+    events: &mut WebSocketStream<MaybeTlsStream<TcpStream>>,
+) -> anyhow::Result<Message> {
+    Ok(events
+        .next()
+        .await
+        .ok_or(anyhow!("Connection is closed by the leader"))??)
+}
+
+async fn fetch_dump(leader: &str) -> anyhow::Result<(SystemTime, Vec<Record>)> {
+    let response = reqwest::get(format!("http://{}/kvs/dump", leader))
+        .await?
+        .error_for_status()?;
+    let timestamp = response
+        .headers()
+        .get(KVS_TIMESTAMP_HEADER)
+        .ok_or(anyhow!("KVS dump has no {} header", KVS_TIMESTAMP_HEADER))?
+        .to_str()?
+        .parse::<u64>()?;
+    let body = response.bytes().await?;
+    let records = parse_records(&body, leader)?;
+    Ok((UNIX_EPOCH + Duration::from_millis(timestamp), records))
+}
+
+/// The operations and the following attributes received from the leader.
+///
+#[derive(Default)]
+struct ReplicatedFrames {
+    operations: Option<Vec<ReplicatedOperation>>,
+}
+
+impl ReplicatedFrames {
+    async fn apply(&mut self, replica: &mut Replica, frame: Message) -> anyhow::Result<()> {
+        match frame {
+            Message::Text(text) => self.operations = Some(serde_json::from_str(&text)?),
+            Message::Binary(data) => {
+                let operations = self
+                    .operations
+                    .take()
+                    .ok_or(anyhow!("Attributes are received without operations"))?;
+                let set = from_pb::<generated::AttributeSet, AttributeSet>(&data)?;
+                replica.apply(operations, set.attributes).await;
+            }
+            Message::Close(reason) => bail!("Connection is closed by the leader: {:?}", reason),
+            // the pings are answered by the stream itself
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/// Replicates the store until the connection fails or the resync period passes. The replica
+/// subscribes to the operations before the leader takes the dump, the operations received
+/// while the dump is loaded are buffered and the ones newer than the dump are applied after
+/// it.
+///
+async fn replicate(replication: &KvsReplication, replica: &mut Replica) -> anyhow::Result<()> {
+    let mut events = connect_events(&replication.leader).await?;
+    let dump = fetch_dump(&replication.leader);
+    tokio::pin!(dump);
+    let mut buffered = Vec::new();
+    let (timestamp, records) = loop {
+        tokio::select! {
+            dump = &mut dump => break dump?,
+            frame = next_frame(&mut events) => buffered.push(frame?),
+        }
+    };
+    let count = replica.synchronize(timestamp, records).await;
+    info!(
+        "KVS is synchronized with {}, {} records, {} frames buffered",
+        replication.leader,
+        count,
+        buffered.len()
+    );
+    let mut frames = ReplicatedFrames::default();
+    for frame in buffered {
+        frames.apply(replica, frame).await?;
+    }
+    let resync = async {
+        match replication.resync_period {
+            Some(period) => tokio::time::sleep(period).await,
+            None => std::future::pending().await,
+        }
+    };
+    tokio::pin!(resync);
+    loop {
+        let frame = tokio::select! {
+            _ = &mut resync => return Ok(()),
+            frame = next_frame(&mut events) => frame?,
+        };
+        frames.apply(replica, frame).await?;
+    }
+}
+
+/// Starts the replication of the KVS from the leader in the background.
+///
+pub fn start_kvs_replication(replication: KvsReplication) -> anyhow::Result<()> {
+    let mut job = REPLICATION_JOB.lock();
+    if job.is_some() {
+        bail!("KVS replication is already started");
+    }
+    let rt = get_or_init_async_runtime();
+    *job = Some(rt.spawn(async move {
+        let mut replica = Replica::default();
+        loop {
+            if let Err(e) = replicate(&replication, &mut replica).await {
+                warn!(
+                    "KVS replication from {} failed: {}, reconnecting in {:?}",
+                    replication.leader, e, replication.reconnect_interval
+                );
+                tokio::time::sleep(replication.reconnect_interval).await;
+            }
+        }
+    }));
+    Ok(())
+}
+
+pub fn stop_kvs_replication() {
+    if let Some(job) = REPLICATION_JOB.lock().take() {
+        job.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        start_kvs_replication, stop_kvs_replication, KvsReplication, Replica, ReplicatedOperation,
+        KVS_TIMESTAMP_HEADER, TOMBSTONE_TTL,
+    };
+    use crate::get_or_init_async_runtime;
+    use crate::primitives::Attribute;
+    use crate::webserver::kvs::synchronous::{del_attributes, get_attribute, set_attributes};
+    use crate::webserver::kvs_persistence::{write_record, Record, RECORD_SET};
+    use crate::webserver::{kvs_operation_frames, KvsOperation, KvsOperationKind};
+    use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer};
+    use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+    fn attribute(name: &str) -> Attribute {
+        Attribute::persistent("ns", name, vec![], &None, false)
+    }
+
+    fn operation(operation: &str, name: &str, timestamp: SystemTime) -> ReplicatedOperation {
+        ReplicatedOperation {
+            timestamp,
+            operation: operation.to_string(),
+            namespace: "ns".to_string(),
+            name: name.to_string(),
+            ttl: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_operations() -> anyhow::Result<()> {
+        let timestamp = SystemTime::now();
+        let text = serde_json::json!([
+            {"timestamp": timestamp, "operation": "set", "namespace": "ns", "name": "a", "ttl": 10},
+            {"timestamp": timestamp, "operation": "delete", "namespace": "ns", "name": "b"},
+        ])
+        .to_string();
+        let ops: Vec<ReplicatedOperation> = serde_json::from_str(&text)?;
+        assert_eq!(ops[0].ttl, Some(10));
+        assert_eq!(
+            ops[1],
+            ReplicatedOperation {
+                ttl: None,
+                ..operation("delete", "b", timestamp)
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_replica() {
+        let rt = get_or_init_async_runtime();
+        del_attributes(&None, &None);
+        set_attributes(&[attribute("local")], None);
+
+        let synchronized_at = SystemTime::now();
+        let before = synchronized_at - Duration::from_secs(1);
+        let after = synchronized_at + Duration::from_secs(1);
+        let mut replica = Replica::default();
+        let records = vec![Record {
+            kind: RECORD_SET,
+            expiration: None,
+            attributes: vec![attribute("a"), attribute("b")],
+        }];
+        assert_eq!(
+            rt.block_on(replica.synchronize(synchronized_at, records)),
+            2
+        );
+        assert!(get_attribute("ns", "local").is_none());
+        assert!(get_attribute("ns", "a").is_some());
+
+        // already in the dump
+        rt.block_on(replica.apply(vec![operation("delete", "a", before)], vec![attribute("a")]));
+        assert!(get_attribute("ns", "a").is_some());
+
+        rt.block_on(replica.apply(
+            vec![
+                operation("delete", "a", after + Duration::from_secs(1)),
+                operation("set", "c", after),
+            ],
+            vec![attribute("a"), attribute("c")],
+        ));
+        assert!(get_attribute("ns", "a").is_none());
+        assert!(get_attribute("ns", "c").is_some());
+
+        // older than the last operation on the key
+        rt.block_on(replica.apply(vec![operation("set", "a", after)], vec![attribute("a")]));
+        assert!(get_attribute("ns", "a").is_none());
+
+        // mismatched frames are ignored
+        rt.block_on(replica.apply(vec![], vec![attribute("d")]));
+        assert!(get_attribute("ns", "d").is_none());
+        del_attributes(&None, &None);
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_prune_versions() {
+        let rt = get_or_init_async_runtime();
+        del_attributes(&None, &None);
+        let now = SystemTime::now();
+        let old = now - 2 * TOMBSTONE_TTL;
+        let mut replica = Replica::default();
+        rt.block_on(replica.apply(
+            vec![
+                operation("set", "live", old),
+                operation("delete", "old", old),
+                operation("delete", "recent", now),
+            ],
+            vec![attribute("live"), attribute("old"), attribute("recent")],
+        ));
+        replica.prune_versions(now);
+        let mut keys = replica
+            .versions
+            .keys()
+            .map(|(_, name)| name.as_str())
+            .collect::<Vec<_>>();
+        keys.sort();
+        assert_eq!(keys, vec!["live", "recent"]);
+        del_attributes(&None, &None);
+    }
+
+    /// The dump of the leader with the attributes `a` and `b`, served slowly, so the events
+    /// are received while the dump is loaded.
+    async fn leader_dump() -> HttpResponse {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let mut body = Vec::new();
+        write_record(
+            &mut body,
+            RECORD_SET,
+            None,
+            &[attribute("a"), attribute("b")],
+        )
+        .unwrap();
+        HttpResponse::Ok()
+            .insert_header((KVS_TIMESTAMP_HEADER, timestamp.as_millis().to_string()))
+            .body(body)
+    }
+
+    /// The events of the leader made after the dump: `b` is deleted and `c` is set.
+    async fn leader_events(
+        req: HttpRequest,
+        stream: web::Payload,
+    ) -> Result<HttpResponse, actix_web::Error> {
+        let (res, mut session, _) = actix_ws::handle(&req, stream)?;
+        actix_web::rt::spawn(async move {
+            let timestamp = SystemTime::now() + Duration::from_secs(1);
+            for operation in [
+                KvsOperationKind::Delete(vec![attribute("b")]),
+                KvsOperationKind::Set(vec![attribute("c")], None),
+            ] {
+                let (text, binary) = kvs_operation_frames(
+                    KvsOperation {
+                        timestamp,
+                        operation,
+                    },
+                    true,
+                )
+                .unwrap();
+                session.text(text).await.unwrap();
+                session.binary(binary.unwrap()).await.unwrap();
+            }
+            // the connection is kept open
+            std::future::pending::<()>().await;
+        });
+        Ok(res)
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_replication_from_leader() -> anyhow::Result<()> {
+        let rt = get_or_init_async_runtime();
+        del_attributes(&None, &None);
+        set_attributes(&[attribute("local")], None);
+
+        let (leader, server) = rt.block_on(async {
+            let server = HttpServer::new(|| {
+                App::new()
+                    .route("/kvs/dump", web::get().to(leader_dump))
+                    .route("/kvs/events/full", web::get().to(leader_events))
+            })
+            .workers(1)
+            .bind(("127.0.0.1", 0))?;
+            let leader = server.addrs()[0].to_string();
+            let server = server.run();
+            let handle = server.handle();
+            tokio::spawn(server);
+            anyhow::Ok((leader, handle))
+        })?;
+        start_kvs_replication(KvsReplication::new(&leader))?;
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while get_attribute("ns", "c").is_none() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        stop_kvs_replication();
+        rt.block_on(server.stop(false));
+
+        assert!(get_attribute("ns", "c").is_some());
+        assert!(get_attribute("ns", "a").is_some());
+        assert!(get_attribute("ns", "b").is_none());
+        assert!(get_attribute("ns", "local").is_none());
+        del_attributes(&None, &None);
+        Ok(())
+    }
+}
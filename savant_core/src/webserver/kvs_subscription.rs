@@ -1 +1,100 @@
+use crate::primitives::Attribute;
+use crate::webserver::{KvsOperation, KvsOperationKind};
+use globset::{Glob, GlobMatcher};
 
+/// The namespace and name globs of the keys delivered to a subscriber, `None` matches any.
+///
+#[derive(Clone, Debug, Default)]
+pub struct KvsSubscriptionFilter {
+    namespace: Option<GlobMatcher>,
+    name: Option<GlobMatcher>,
+}
+
+impl KvsSubscriptionFilter {
+    pub fn new(namespace: &Option<String>, name: &Option<String>) -> anyhow::Result<Self> {
+        let compile = |glob: &Option<String>| -> anyhow::Result<Option<GlobMatcher>> {
+            Ok(match glob {
+                Some(glob) => Some(Glob::new(glob)?.compile_matcher()),
+                None => None,
+            })
+        };
+        Ok(Self {
+            namespace: compile(namespace)?,
+            name: compile(name)?,
+        })
+    }
+
+    pub fn matches(&self, attribute: &Attribute) -> bool {
+        self.namespace
+            .as_ref()
+            .is_none_or(|g| g.is_match(&attribute.namespace))
+            && self
+                .name
+                .as_ref()
+                .is_none_or(|g| g.is_match(&attribute.name))
+    }
+
+    /// The operation with the matching attributes only, `None` when none of them match.
+    ///
+    pub(super) fn apply(&self, op: &KvsOperation) -> Option<KvsOperation> {
+        if self.namespace.is_none() && self.name.is_none() {
+            return Some(op.clone());
+        }
+        let select = |attributes: &[Attribute]| -> Vec<Attribute> {
+            attributes
+                .iter()
+                .filter(|a| self.matches(a))
+                .cloned()
+                .collect()
+        };
+        let operation = match &op.operation {
+            KvsOperationKind::Set(attributes, ttl) => {
+                KvsOperationKind::Set(select(attributes), *ttl)
+            }
+            KvsOperationKind::Delete(attributes) => KvsOperationKind::Delete(select(attributes)),
+            KvsOperationKind::Expired(attributes) => KvsOperationKind::Expired(select(attributes)),
+            KvsOperationKind::Evicted(attributes) => KvsOperationKind::Evicted(select(attributes)),
+        };
+        (!operation.attributes().is_empty()).then_some(KvsOperation {
+            timestamp: op.timestamp,
+            operation,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::KvsSubscriptionFilter;
+    use crate::primitives::Attribute;
+    use crate::webserver::{KvsOperation, KvsOperationKind};
+    use std::time::SystemTime;
+
+    #[test]
+    fn test_filter() -> anyhow::Result<()> {
+        let attributes = vec![
+            Attribute::persistent("cam-1", "zone", vec![], &None, false),
+            Attribute::persistent("cam-1", "line", vec![], &None, false),
+            Attribute::persistent("cam-2", "zone", vec![], &None, false),
+        ];
+        let op = KvsOperation {
+            timestamp: SystemTime::now(),
+            operation: KvsOperationKind::Expired(attributes.clone()),
+        };
+        let any = KvsSubscriptionFilter::default();
+        assert_eq!(
+            any.apply(&op).unwrap().operation,
+            KvsOperationKind::Expired(attributes.clone())
+        );
+
+        let filter = KvsSubscriptionFilter::new(&Some("cam-*".into()), &Some("zone".into()))?;
+        assert_eq!(
+            filter.apply(&op).unwrap().operation,
+            KvsOperationKind::Expired(vec![attributes[0].clone(), attributes[2].clone()])
+        );
+
+        let filter = KvsSubscriptionFilter::new(&Some("cam-3".into()), &None)?;
+        assert!(filter.apply(&op).is_none());
+        assert!(KvsSubscriptionFilter::new(&None, &Some("[".into())).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,254 @@
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use savant_core::analytics::zones as rust;
+
+use crate::match_query::MatchQuery;
+use crate::pipeline::StageFunction;
+use crate::primitives::frame::VideoFrame;
+use crate::primitives::point::Point;
+use crate::primitives::polygonal_area::PolygonalArea;
+use crate::release_gil;
+
+/// The point of the track box representing the object position.
+///
+#[pyclass(eq, eq_int)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TrackAnchor {
+    Center,
+    BottomCenter,
+}
+
+impl From<TrackAnchor> for rust::TrackAnchor {
+    fn from(anchor: TrackAnchor) -> Self {
+        match anchor {
+            TrackAnchor::Center => rust::TrackAnchor::Center,
+            TrackAnchor::BottomCenter => rust::TrackAnchor::BottomCenter,
+        }
+    }
+}
+
+impl From<rust::TrackAnchor> for TrackAnchor {
+    fn from(anchor: rust::TrackAnchor) -> Self {
+        match anchor {
+            rust::TrackAnchor::Center => TrackAnchor::Center,
+            rust::TrackAnchor::BottomCenter => TrackAnchor::BottomCenter,
+        }
+    }
+}
+
+#[pyclass(eq, eq_int)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum ZoneEventKind {
+    Enter,
+    Leave,
+    Cross,
+}
+
+impl From<rust::ZoneEventKind> for ZoneEventKind {
+    fn from(kind: rust::ZoneEventKind) -> Self {
+        match kind {
+            rust::ZoneEventKind::Enter => ZoneEventKind::Enter,
+            rust::ZoneEventKind::Leave => ZoneEventKind::Leave,
+            rust::ZoneEventKind::Cross => ZoneEventKind::Cross,
+        }
+    }
+}
+
+/// Zone analytics parameters.
+///
+/// Parameters
+/// ----------
+/// zones : List[Tuple[str, PolygonalArea]]
+///   Named zones, a two-vertex area acts as a line which can only be crossed.
+/// namespace : str
+///   The namespace of the attributes written to frames and objects.
+/// anchor : TrackAnchor
+///   The point of the track box representing the object position.
+/// trajectory_length : int
+///   The number of trajectory points kept per track.
+/// track_timeout : int
+///   Tracks not seen for the number of source frames are forgotten.
+///
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct ZoneAnalyticsConfig(rust::ZoneAnalyticsConfig);
+
+#[pymethods]
+impl ZoneAnalyticsConfig {
+    #[new]
+    #[pyo3(signature = (zones, namespace = "analytics".to_string(), anchor = TrackAnchor::BottomCenter, trajectory_length = 30, track_timeout = 30))]
+    pub fn new(
+        zones: Vec<(String, PolygonalArea)>,
+        namespace: String,
+        anchor: TrackAnchor,
+        trajectory_length: usize,
+        track_timeout: u64,
+    ) -> Self {
+        Self(rust::ZoneAnalyticsConfig {
+            zones: zones
+                .into_iter()
+                .map(|(name, area)| rust::Zone { name, area: area.0 })
+                .collect(),
+            namespace,
+            anchor: anchor.into(),
+            trajectory_length,
+            track_timeout,
+        })
+    }
+
+    #[getter]
+    pub fn zones(&self) -> Vec<(String, PolygonalArea)> {
+        self.0
+            .zones
+            .iter()
+            .map(|z| (z.name.clone(), PolygonalArea(z.area.clone())))
+            .collect()
+    }
+
+    #[getter]
+    pub fn namespace(&self) -> String {
+        self.0.namespace.clone()
+    }
+
+    #[getter]
+    pub fn anchor(&self) -> TrackAnchor {
+        self.0.anchor.into()
+    }
+
+    #[getter]
+    pub fn trajectory_length(&self) -> usize {
+        self.0.trajectory_length
+    }
+
+    #[getter]
+    pub fn track_timeout(&self) -> u64 {
+        self.0.track_timeout
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self.0)
+    }
+}
+
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct ZoneEvent(rust::ZoneEvent);
+
+#[pymethods]
+impl ZoneEvent {
+    #[getter]
+    pub fn source_id(&self) -> String {
+        self.0.source_id.clone()
+    }
+
+    #[getter]
+    pub fn track_id(&self) -> i64 {
+        self.0.track_id
+    }
+
+    #[getter]
+    pub fn object_id(&self) -> i64 {
+        self.0.object_id
+    }
+
+    #[getter]
+    pub fn zone(&self) -> String {
+        self.0.zone.clone()
+    }
+
+    #[getter]
+    pub fn kind(&self) -> ZoneEventKind {
+        self.0.kind.clone().into()
+    }
+
+    #[getter]
+    pub fn edges(&self) -> Vec<(usize, Option<String>)> {
+        self.0.edges.clone()
+    }
+
+    #[getter]
+    pub fn dwell(&self) -> Option<f64> {
+        self.0.dwell
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self.0)
+    }
+}
+
+/// Zone and line analytics over tracked objects, keeps per-track trajectories, emits
+/// enter, leave and cross events and computes the dwell time.
+///
+/// Parameters
+/// ----------
+/// config : ZoneAnalyticsConfig
+///   The analytics parameters.
+/// query : MatchQuery
+///   Selects the objects to process, objects without the track info are skipped.
+///
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct ZoneAnalytics(Arc<Mutex<rust::ZoneAnalytics>>);
+
+#[pymethods]
+impl ZoneAnalytics {
+    #[new]
+    pub fn new(config: ZoneAnalyticsConfig, query: MatchQuery) -> PyResult<Self> {
+        let analytics = rust::ZoneAnalytics::new(config.0, query.0)
+            .map_err(|e| PyValueError::new_err(e.to_string()))?;
+        Ok(Self(Arc::new(Mutex::new(analytics))))
+    }
+
+    /// Processes the tracked objects of the frame and writes the results as attributes.
+    ///
+    /// Parameters
+    /// ----------
+    /// frame : VideoFrame
+    ///   The frame to process.
+    /// no_gil : bool
+    ///   Whether to release the GIL.
+    ///
+    /// Returns
+    /// -------
+    /// List[ZoneEvent]
+    ///   The events of the frame.
+    ///
+    #[pyo3(signature = (frame, no_gil = true))]
+    pub fn process(&self, frame: &VideoFrame, no_gil: bool) -> Vec<ZoneEvent> {
+        release_gil!(no_gil, || self.0.lock().process(&frame.0))
+            .into_iter()
+            .map(ZoneEvent)
+            .collect()
+    }
+
+    pub fn clear_source(&self, source_id: &str) {
+        self.0.lock().clear_source(source_id)
+    }
+
+    pub fn get_trajectory(&self, source_id: &str, track_id: i64) -> Option<Vec<Point>> {
+        self.0
+            .lock()
+            .get_trajectory(source_id, track_id)
+            .map(|t| t.into_iter().map(Point).collect())
+    }
+
+    pub fn get_zones(&self, source_id: &str, track_id: i64) -> Vec<String> {
+        self.0.lock().get_zones(source_id, track_id)
+    }
+
+    /// Returns the stage function sharing the state with this object.
+    ///
+    /// Returns
+    /// -------
+    /// StageFunction
+    ///   The function to use as the stage ingress or egress function.
+    ///
+    pub fn to_stage_function(&self) -> StageFunction {
+        StageFunction::new(Box::new(
+            rust::ZoneAnalyticsFunction::with_shared_analytics(self.0.clone()),
+        ))
+    }
+}
@@ -1,3 +1,4 @@
+pub mod analytics;
 pub mod atomic_counter;
 pub mod capi;
 /// The draw specification used to draw objects on the frame when they are visualized.
@@ -11,6 +12,7 @@ pub mod pipeline;
 pub mod primitives;
 pub mod telemetry;
 pub mod test;
+pub mod tracker;
 /// # Utility functions
 ///
 pub mod utils;
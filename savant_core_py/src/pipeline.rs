@@ -1,9 +1,11 @@
 use parking_lot::Mutex;
 use std::collections::HashMap;
+use std::time::Duration;
 
-use pyo3::exceptions::{PySystemError, PyValueError};
+use pyo3::exceptions::{PyRuntimeError, PySystemError, PyValueError};
 use pyo3::prelude::*;
 
+use savant_core::pipeline::spec::PipelineSpec;
 use savant_core::pipeline::stage_function_loader::load_stage_function_plugin as rust_load_stage_function_plugin;
 use savant_core::pipeline::PipelineStageFunction as RustPipelineStageFunction;
 use savant_core::pipeline::PluginParams;
@@ -74,6 +76,16 @@ pub enum VideoPipelineStagePayloadType {
     Batch,
 }
 
+/// Defines what happens when a payload is moved to a stage whose queue is full.
+///
+#[pyclass(eq, eq_int)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum VideoPipelineStageQueuePolicy {
+    Reject,
+    Block,
+    DropOldestOfSource,
+}
+
 #[pyclass(eq, eq_int)]
 #[derive(Debug, Clone, PartialEq)]
 pub enum FrameProcessingStatRecordType {
@@ -191,6 +203,16 @@ impl StageProcessingStat {
         self.0.batch_counter
     }
 
+    #[getter]
+    fn dropped_counter(&self) -> usize {
+        self.0.dropped_counter
+    }
+
+    #[getter]
+    fn rejected_counter(&self) -> usize {
+        self.0.rejected_counter
+    }
+
     fn __repr__(&self) -> String {
         format!("{:?}", self.0)
     }
@@ -317,6 +339,57 @@ impl PipelineConfiguration {
         self.0.collection_history = v;
     }
 
+    #[setter]
+    pub fn tick_period_ms(&mut self, v: Option<u64>) {
+        self.0.tick_period_ms = v;
+    }
+
+    /// Limits the number of frames or batches in the stage queue.
+    ///
+    /// Parameters
+    /// ----------
+    /// stage : str
+    ///   The name of the stage.
+    /// capacity : int
+    ///   The maximum number of payloads in the stage.
+    /// policy : VideoPipelineStageQueuePolicy
+    ///   What to do when the queue is full.
+    /// timeout_ms : Optional[int]
+    ///   How long to wait for free space, required for the ``Block`` policy.
+    ///
+    /// Raises
+    /// ------
+    /// ValueError
+    ///   If the timeout is missing for the ``Block`` policy.
+    ///
+    #[pyo3(signature = (stage, capacity, policy, timeout_ms = None))]
+    pub fn set_stage_queue_limit(
+        &mut self,
+        stage: String,
+        capacity: usize,
+        policy: VideoPipelineStageQueuePolicy,
+        timeout_ms: Option<u64>,
+    ) -> PyResult<()> {
+        let policy = match (policy, timeout_ms) {
+            (VideoPipelineStageQueuePolicy::Reject, _) => rust::StageQueuePolicy::Reject,
+            (VideoPipelineStageQueuePolicy::Block, Some(timeout_ms)) => {
+                rust::StageQueuePolicy::Block(Duration::from_millis(timeout_ms))
+            }
+            (VideoPipelineStageQueuePolicy::Block, None) => {
+                return Err(PyValueError::new_err(
+                    "The Block policy requires timeout_ms to be set",
+                ))
+            }
+            (VideoPipelineStageQueuePolicy::DropOldestOfSource, _) => {
+                rust::StageQueuePolicy::DropOldestOfSource
+            }
+        };
+        self.0
+            .stage_queue_limits
+            .insert(stage, rust::StageQueueLimit::new(capacity, policy));
+        Ok(())
+    }
+
     fn __repr__(&self) -> String {
         format!("{:?}", self.0)
     }
@@ -326,6 +399,14 @@ impl PipelineConfiguration {
     }
 }
 
+impl Pipeline {
+    fn from_spec(spec: anyhow::Result<PipelineSpec>) -> PyResult<Self> {
+        spec.and_then(|s| s.build())
+            .map(Self)
+            .map_err(|e| PyValueError::new_err(format!("Failed to create pipeline: {}", e)))
+    }
+}
+
 #[pymethods]
 impl Pipeline {
     #[new]
@@ -354,6 +435,34 @@ impl Pipeline {
         Ok(Self(p))
     }
 
+    /// Creates the pipeline from a YAML spec listing the stages, their stage
+    /// function plugins and the configuration.
+    ///
+    /// Raises
+    /// ------
+    /// ValueError
+    ///   If the spec is invalid or a plugin cannot be loaded.
+    ///
+    #[staticmethod]
+    pub fn from_yaml(yaml: &str) -> PyResult<Self> {
+        Self::from_spec(PipelineSpec::from_yaml(yaml))
+    }
+
+    /// Creates the pipeline from a JSON spec, see :py:meth:`from_yaml`.
+    ///
+    #[staticmethod]
+    pub fn from_json(json: &str) -> PyResult<Self> {
+        Self::from_spec(PipelineSpec::from_json(json))
+    }
+
+    /// Creates the pipeline from a spec file, ``.json`` files are parsed as JSON,
+    /// others as YAML.
+    ///
+    #[staticmethod]
+    pub fn from_file(path: &str) -> PyResult<Self> {
+        Self::from_spec(PipelineSpec::from_file(path))
+    }
+
     pub fn get_keyframe_history(&self, f: &VideoFrame) -> Option<Vec<(u128, i64)>> {
         self.0.get_keyframe_history(&f.0)
     }
@@ -378,7 +487,8 @@ impl Pipeline {
         self.0.log_final_fps();
     }
 
-    /// Clears the ordering for source, called on dead stream eviction.
+    /// Clears the ordering for source, called on dead stream eviction. Stage
+    /// functions are notified about the end of the source.
     ///
     /// Parameters
     /// ----------
@@ -396,6 +506,48 @@ impl Pipeline {
             .map_err(|e| PyValueError::new_err(e.to_string()))
     }
 
+    /// Notifies stage functions about the end of the source, e.g. on EOS message.
+    ///
+    /// Parameters
+    /// ----------
+    /// source_id : str
+    ///   The id of the source.
+    ///
+    /// Raises
+    /// ------
+    /// RuntimeError
+    ///   If a stage function fails.
+    ///
+    pub fn notify_source_eos(&self, source_id: &str) -> PyResult<()> {
+        self.0
+            .notify_source_eos(source_id)
+            .map_err(|e| PyRuntimeError::new_err(e.to_string()))
+    }
+
+    /// Calls the periodic tick of stage functions.
+    ///
+    pub fn tick(&self) -> PyResult<()> {
+        self.0
+            .tick()
+            .map_err(|e| PyRuntimeError::new_err(e.to_string()))
+    }
+
+    /// Asks stage functions to emit the buffered results.
+    ///
+    pub fn flush(&self) -> PyResult<()> {
+        self.0
+            .flush()
+            .map_err(|e| PyRuntimeError::new_err(e.to_string()))
+    }
+
+    /// Flushes and shuts down stage functions, subsequent calls do nothing.
+    ///
+    pub fn shutdown(&self) -> PyResult<()> {
+        self.0
+            .shutdown()
+            .map_err(|e| PyRuntimeError::new_err(e.to_string()))
+    }
+
     /// Allows receiving a raw pointer to Rust inner Pipeline struct.
     ///
     #[getter]
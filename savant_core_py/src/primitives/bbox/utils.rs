@@ -1,6 +1,8 @@
 use crate::primitives::bbox::{BBoxMetricType, RBBox};
 use crate::with_gil;
+use pyo3::exceptions::PyValueError;
 use pyo3::prelude::*;
+use savant_core::primitives::bbox::utils as rust;
 use std::collections::HashMap;
 
 #[pyfunction]
@@ -27,3 +29,149 @@ pub fn associate_bboxes(
         )
     })
 }
+
+/// Non-maximum suppression.
+///
+/// Parameters
+/// ----------
+/// bboxes : List[RBBox]
+///   The boxes, rotated boxes are supported.
+/// scores : List[float]
+///   The scores of the boxes.
+/// classes : Optional[List[int]]
+///   When set, only boxes of the same class suppress each other.
+/// metric : BBoxMetricType
+///   The overlap metric.
+/// threshold : float
+///   Boxes overlapping a better one above the threshold are suppressed.
+///
+/// Returns
+/// -------
+/// List[int]
+///   Indices of the kept boxes ordered by descending score.
+///
+/// Raises
+/// ------
+/// ValueError
+///   If the lengths of the arguments do not match.
+///
+#[pyfunction]
+#[pyo3(signature = (bboxes, scores, classes = None, metric = BBoxMetricType::IoU, threshold = 0.5))]
+pub fn nms(
+    bboxes: Vec<RBBox>,
+    scores: Vec<f32>,
+    classes: Option<Vec<i64>>,
+    metric: BBoxMetricType,
+    threshold: f32,
+) -> PyResult<Vec<usize>> {
+    let boxes = bboxes.iter().map(|b| &b.0).collect::<Vec<_>>();
+    rust::nms(
+        &boxes,
+        &scores,
+        classes.as_deref(),
+        metric.into(),
+        threshold,
+    )
+    .map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+/// Soft non-maximum suppression, the scores of overlapping boxes decay instead of
+/// the boxes being removed.
+///
+/// Parameters
+/// ----------
+/// bboxes : List[RBBox]
+///   The boxes, rotated boxes are supported.
+/// scores : List[float]
+///   The scores of the boxes.
+/// classes : Optional[List[int]]
+///   When set, only boxes of the same class affect each other.
+/// metric : BBoxMetricType
+///   The overlap metric.
+/// threshold : float
+///   The overlap threshold of the linear decay.
+/// score_threshold : float
+///   Boxes with the decayed score below the value are dropped.
+/// gaussian_sigma : Optional[float]
+///   When set, the gaussian decay is used instead of the linear one, the value must be positive.
+///
+/// Returns
+/// -------
+/// List[Tuple[int, float]]
+///   Indices of the kept boxes with decayed scores ordered by descending score.
+///
+#[pyfunction]
+#[pyo3(signature = (bboxes, scores, classes = None, metric = BBoxMetricType::IoU, threshold = 0.3, score_threshold = 0.001, gaussian_sigma = None))]
+pub fn soft_nms(
+    bboxes: Vec<RBBox>,
+    scores: Vec<f32>,
+    classes: Option<Vec<i64>>,
+    metric: BBoxMetricType,
+    threshold: f32,
+    score_threshold: f32,
+    gaussian_sigma: Option<f32>,
+) -> PyResult<Vec<(usize, f32)>> {
+    let boxes = bboxes.iter().map(|b| &b.0).collect::<Vec<_>>();
+    let decay = match gaussian_sigma {
+        Some(sigma) => rust::SoftNmsDecay::Gaussian(sigma),
+        None => rust::SoftNmsDecay::Linear,
+    };
+    rust::soft_nms(
+        &boxes,
+        &scores,
+        classes.as_deref(),
+        metric.into(),
+        decay,
+        threshold,
+        score_threshold,
+    )
+    .map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+/// Weighted box fusion, overlapping boxes of the same class are merged into the
+/// score-weighted average box.
+///
+/// Parameters
+/// ----------
+/// bboxes : List[RBBox]
+///   The boxes, rotated boxes are supported.
+/// scores : List[float]
+///   The scores of the boxes.
+/// classes : Optional[List[int]]
+///   When set, only boxes of the same class are fused.
+/// metric : BBoxMetricType
+///   The overlap metric.
+/// threshold : float
+///   Boxes overlapping the cluster box above the threshold join the cluster.
+///
+/// Returns
+/// -------
+/// List[Tuple[RBBox, float, Optional[int], List[int]]]
+///   Fused boxes with the average score, the class and indices of the source boxes.
+///
+#[pyfunction]
+#[pyo3(signature = (bboxes, scores, classes = None, metric = BBoxMetricType::IoU, threshold = 0.55))]
+#[allow(clippy::type_complexity)]
+pub fn weighted_boxes_fusion(
+    bboxes: Vec<RBBox>,
+    scores: Vec<f32>,
+    classes: Option<Vec<i64>>,
+    metric: BBoxMetricType,
+    threshold: f32,
+) -> PyResult<Vec<(RBBox, f32, Option<i64>, Vec<usize>)>> {
+    let boxes = bboxes.iter().map(|b| &b.0).collect::<Vec<_>>();
+    rust::weighted_boxes_fusion(
+        &boxes,
+        &scores,
+        classes.as_deref(),
+        metric.into(),
+        threshold,
+    )
+    .map(|fused| {
+        fused
+            .into_iter()
+            .map(|f| (RBBox(f.bbox), f.score, f.class, f.members))
+            .collect()
+    })
+    .map_err(|e| PyValueError::new_err(e.to_string()))
+}
@@ -2,7 +2,7 @@ use crate::draw_spec::SetDrawLabelKind;
 use crate::match_query::MatchQuery;
 use crate::primitives::attribute::Attribute;
 use crate::primitives::attribute_value::AttributeValue;
-use crate::primitives::bbox::{RBBox, VideoObjectBBoxTransformation};
+use crate::primitives::bbox::{BBoxMetricType, RBBox, VideoObjectBBoxTransformation};
 use crate::primitives::frame_update::VideoFrameUpdate;
 use crate::primitives::message::Message;
 use crate::primitives::object::{BorrowedVideoObject, IdCollisionResolutionPolicy, VideoObject};
@@ -1012,6 +1012,44 @@ impl VideoFrame {
             .collect())
     }
 
+    /// Applies non-maximum suppression to the objects selected by the query and
+    /// deletes the suppressed ones.
+    ///
+    /// Parameters
+    /// ----------
+    /// q : MatchQuery
+    ///   Selects the objects.
+    /// metric : BBoxMetricType
+    ///   The overlap metric of the detection boxes.
+    /// threshold : float
+    ///   Objects overlapping a more confident one above the threshold are suppressed.
+    /// class_aware : bool
+    ///   Only objects with the same namespace and label suppress each other.
+    /// no_gil : bool
+    ///   Whether to release the GIL.
+    ///
+    /// Returns
+    /// -------
+    /// List[VideoObject]
+    ///   The deleted objects.
+    ///
+    #[pyo3(name = "apply_nms")]
+    #[pyo3(signature = (q, metric, threshold, class_aware = true, no_gil = true))]
+    pub fn apply_nms_gil(
+        &self,
+        q: &MatchQuery,
+        metric: BBoxMetricType,
+        threshold: f32,
+        class_aware: bool,
+        no_gil: bool,
+    ) -> PyResult<Vec<VideoObject>> {
+        release_gil!(no_gil, || self
+            .0
+            .apply_nms(&q.0, metric.into(), threshold, class_aware)
+            .map(|objects| objects.into_iter().map(VideoObject).collect())
+            .map_err(|e| PyValueError::new_err(e.to_string())))
+    }
+
     pub fn delete_objects_with_ids(&self, ids: Vec<i64>) -> Vec<VideoObject> {
         self.0
             .delete_objects_with_ids(&ids)
@@ -1134,4 +1172,39 @@ impl VideoFrame {
             Ok(Self(obj))
         })
     }
+
+    /// Serializes the frame with objects and persistent attributes to versioned JSON. Unlike
+    /// :py:attr:`VideoFrame.json`, the result keeps the frame content and can be loaded back
+    /// with :py:meth:`VideoFrame.from_json`.
+    ///
+    #[pyo3(name = "to_json")]
+    #[pyo3(signature = (pretty = false, no_gil = true))]
+    fn to_json_gil(&self, pretty: bool, no_gil: bool) -> PyResult<String> {
+        release_gil!(no_gil, || self.0.to_json(pretty))
+            .map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+
+    #[staticmethod]
+    #[pyo3(name = "from_json")]
+    #[pyo3(signature = (json, no_gil = true))]
+    fn from_json_gil(json: &str, no_gil: bool) -> PyResult<Self> {
+        release_gil!(no_gil, || rust::VideoFrameProxy::from_json(json))
+            .map(Self)
+            .map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+
+    #[pyo3(name = "to_yaml")]
+    #[pyo3(signature = (no_gil = true))]
+    fn to_yaml_gil(&self, no_gil: bool) -> PyResult<String> {
+        release_gil!(no_gil, || self.0.to_yaml()).map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+
+    #[staticmethod]
+    #[pyo3(name = "from_yaml")]
+    #[pyo3(signature = (yaml, no_gil = true))]
+    fn from_yaml_gil(yaml: &str, no_gil: bool) -> PyResult<Self> {
+        release_gil!(no_gil, || rust::VideoFrameProxy::from_yaml(yaml))
+            .map(Self)
+            .map_err(|e| PyValueError::new_err(e.to_string()))
+    }
 }
@@ -257,6 +257,28 @@ impl VideoFrameUpdate {
             .map_err(|e| PyValueError::new_err(e.to_string())))
     }
 
+    #[getter]
+    pub fn yaml(&self) -> PyResult<String> {
+        release_gil!(true, || self
+            .0
+            .to_yaml()
+            .map_err(|e| PyValueError::new_err(e.to_string())))
+    }
+
+    #[staticmethod]
+    pub fn from_json(json: &str) -> PyResult<Self> {
+        release_gil!(true, || rust::VideoFrameUpdate::from_json(json))
+            .map(Self)
+            .map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+
+    #[staticmethod]
+    pub fn from_yaml(yaml: &str) -> PyResult<Self> {
+        release_gil!(true, || rust::VideoFrameUpdate::from_yaml(yaml))
+            .map(Self)
+            .map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+
     #[pyo3(name = "to_protobuf")]
     #[pyo3(signature = (no_gil = true))]
     fn to_protobuf_gil(&self, no_gil: bool) -> PyResult<PyObject> {
@@ -2,7 +2,7 @@ use crate::primitives::attribute_value::AttributeValue;
 use crate::primitives::bbox::VideoObjectBBoxTransformation;
 use crate::primitives::{Attribute, RBBox};
 use crate::{release_gil, with_gil};
-use pyo3::exceptions::PyRuntimeError;
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
 use pyo3::types::{PyBytes, PyBytesMethods};
 use pyo3::{pyclass, pymethods, Bound, PyObject, PyResult};
 use savant_core::json_api::ToSerdeJsonValue;
@@ -101,6 +101,20 @@ impl VideoObject {
         })
     }
 
+    #[pyo3(signature = (pretty = false))]
+    fn to_json(&self, pretty: bool) -> PyResult<String> {
+        self.0
+            .to_json(pretty)
+            .map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+
+    #[staticmethod]
+    fn from_json(json: &str) -> PyResult<Self> {
+        rust::VideoObject::from_json(json)
+            .map(Self)
+            .map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+
     #[getter]
     fn get_namespace(&self) -> String {
         self.0.get_namespace()
@@ -0,0 +1,121 @@
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use pyo3::prelude::*;
+use savant_core::tracker::sort as rust;
+
+use crate::match_query::MatchQuery;
+use crate::pipeline::StageFunction;
+use crate::primitives::frame::VideoFrame;
+use crate::release_gil;
+
+/// SORT tracker parameters.
+///
+/// Parameters
+/// ----------
+/// max_age : int
+///   The number of frames the track is kept without matching detections.
+/// min_hits : int
+///   The number of consecutive matches before the track id is assigned to objects.
+/// iou_threshold : float
+///   The minimal IoU between the detection and the predicted track box.
+///
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct SortTrackerConfig(rust::SortTrackerConfig);
+
+#[pymethods]
+impl SortTrackerConfig {
+    #[new]
+    #[pyo3(signature = (max_age = 1, min_hits = 3, iou_threshold = 0.3))]
+    pub fn new(max_age: usize, min_hits: usize, iou_threshold: f32) -> Self {
+        Self(rust::SortTrackerConfig {
+            max_age,
+            min_hits,
+            iou_threshold,
+        })
+    }
+
+    #[getter]
+    pub fn max_age(&self) -> usize {
+        self.0.max_age
+    }
+
+    #[getter]
+    pub fn min_hits(&self) -> usize {
+        self.0.min_hits
+    }
+
+    #[getter]
+    pub fn iou_threshold(&self) -> f32 {
+        self.0.iou_threshold
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self.0)
+    }
+}
+
+/// SORT tracker filling ``track_id`` and ``track_box`` of the objects selected by the
+/// query, tracks are kept per source.
+///
+/// Parameters
+/// ----------
+/// config : SortTrackerConfig
+///   The tracker parameters.
+/// query : MatchQuery
+///   Selects the objects to track.
+///
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct SortTracker(Arc<Mutex<rust::SortTracker>>);
+
+#[pymethods]
+impl SortTracker {
+    #[new]
+    pub fn new(config: SortTrackerConfig, query: MatchQuery) -> Self {
+        Self(Arc::new(Mutex::new(rust::SortTracker::new(
+            config.0, query.0,
+        ))))
+    }
+
+    /// Tracks the objects of the frame.
+    ///
+    /// Parameters
+    /// ----------
+    /// frame : VideoFrame
+    ///   The frame to track.
+    /// no_gil : bool
+    ///   Whether to release the GIL.
+    ///
+    /// Returns
+    /// -------
+    /// List[Tuple[int, int]]
+    ///   Pairs of the object id and the track id.
+    ///
+    #[pyo3(signature = (frame, no_gil = true))]
+    pub fn track(&self, frame: &VideoFrame, no_gil: bool) -> Vec<(i64, i64)> {
+        release_gil!(no_gil, || self.0.lock().track(&frame.0))
+    }
+
+    pub fn clear_source(&self, source_id: &str) {
+        self.0.lock().clear_source(source_id)
+    }
+
+    pub fn get_track_count(&self, source_id: &str) -> usize {
+        self.0.lock().get_track_count(source_id)
+    }
+
+    /// Returns the stage function sharing the tracks with this tracker.
+    ///
+    /// Returns
+    /// -------
+    /// StageFunction
+    ///   The function to use as the stage ingress or egress function.
+    ///
+    pub fn to_stage_function(&self) -> StageFunction {
+        StageFunction::new(Box::new(rust::SortTrackerFunction::with_shared_tracker(
+            self.0.clone(),
+        )))
+    }
+}
@@ -2,7 +2,9 @@ pub mod kvs;
 
 use pyo3::exceptions::{PySystemError, PyValueError};
 use pyo3::prelude::*;
-use savant_core::webserver::PipelineStatus;
+use savant_core::webserver::{KvsPersistence, KvsReplication, PipelineStatus};
+use std::path::Path;
+use std::time::Duration;
 
 /// Starts embedded webserver providing status, shutdown and metrics features.
 ///
@@ -17,6 +19,71 @@ pub fn init_webserver(port: u16) -> PyResult<()> {
     Ok(())
 }
 
+/// Enables the persistence of the KVS: the periodic snapshots of the store and the journal
+/// of the changes between them (``<snapshot_path>.journal``). Must be called before
+/// :py:func:`init_webserver` which restores the store.
+///
+/// Parameters
+/// ----------
+/// snapshot_path : str
+///   The snapshot file
+/// snapshot_period : int
+///   The snapshot period in milliseconds
+///
+/// Raises
+/// ------
+/// ValueError
+///   If the period is zero or the persistence is already set
+///
+#[pyfunction]
+pub fn set_kvs_persistence(snapshot_path: &str, snapshot_period: u64) -> PyResult<()> {
+    savant_core::webserver::set_kvs_persistence(KvsPersistence::new(
+        Path::new(snapshot_path),
+        Duration::from_millis(snapshot_period),
+    ))
+    .map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+/// Starts the replication of the KVS from the leader webserver in the background. The
+/// replica loads the whole store from the leader after every (re)connect and then applies
+/// the changes received from ``/kvs/events/full``, the older changes of a key are ignored.
+///
+/// Parameters
+/// ----------
+/// leader : str
+///   The ``host:port`` of the leader webserver
+/// reconnect_interval : int
+///   The interval between the reconnection attempts in milliseconds
+/// resync_period : Optional[int]
+///   The period of the full synchronization in milliseconds, ``None`` disables it
+///
+/// Raises
+/// ------
+/// ValueError
+///   If the replication is already started
+///
+#[pyfunction]
+#[pyo3(signature = (leader, reconnect_interval=1000, resync_period=Some(300_000)))]
+pub fn start_kvs_replication(
+    leader: &str,
+    reconnect_interval: u64,
+    resync_period: Option<u64>,
+) -> PyResult<()> {
+    savant_core::webserver::start_kvs_replication(KvsReplication {
+        reconnect_interval: Duration::from_millis(reconnect_interval),
+        resync_period: resync_period.map(Duration::from_millis),
+        ..KvsReplication::new(leader)
+    })
+    .map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+/// Stops the replication of the KVS.
+///
+#[pyfunction]
+pub fn stop_kvs_replication() {
+    savant_core::webserver::stop_kvs_replication();
+}
+
 /// Stops the embedded webserver.
 ///
 #[pyfunction]
@@ -1,4 +1,5 @@
 use crate::primitives::attribute::Attribute;
+use crate::primitives::attribute_value::AttributeValue;
 use crate::{release_gil, with_gil};
 use pyo3::exceptions::{PySystemError, PyValueError};
 use pyo3::prelude::*;
@@ -8,7 +9,8 @@ use savant_core::primitives::rust::AttributeSet;
 use savant_core::protobuf::ToProtobuf;
 use savant_core::webserver::kvs::synchronous as sync_kvs;
 use savant_core::webserver::{
-    subscribe, KvsOperation, KvsOperationKind, KvsSubscription as RustKvsSubscription,
+    subscribe_with_filter, KvsOperation, KvsOperationKind, KvsSubscription as RustKvsSubscription,
+    KvsSubscriptionFilter,
 };
 use tokio::sync::mpsc::error::TryRecvError;
 
@@ -30,6 +32,89 @@ pub fn set_attributes(attributes: Vec<Attribute>, ttl: Option<u64>) {
     sync_kvs::set_attributes(&attributes, ttl);
 }
 
+/// Atomically set an attribute if its current values are equal to the expected ones.
+///
+/// Parameters
+/// ----------
+/// attribute : Attribute
+///  The attribute to set.
+///
+/// expected : List[AttributeValue]
+///  The expected values of the current attribute.
+///
+/// ttl : Optional[int]
+///  Time-to-live for the attribute.
+///
+/// Returns
+/// -------
+/// bool
+///  True if the attribute is set.
+///
+#[pyfunction]
+#[pyo3(signature = (attribute, expected, ttl=None))]
+pub fn compare_and_set(
+    attribute: &Attribute,
+    expected: Vec<AttributeValue>,
+    ttl: Option<u64>,
+) -> bool {
+    let expected = expected.into_iter().map(|v| v.0).collect::<Vec<_>>();
+    sync_kvs::compare_and_set(&attribute.0, &expected, ttl)
+}
+
+/// Atomically set an attribute if there is no attribute with the same namespace and name.
+///
+/// Parameters
+/// ----------
+/// attribute : Attribute
+///  The attribute to set.
+///
+/// ttl : Optional[int]
+///  Time-to-live for the attribute.
+///
+/// Returns
+/// -------
+/// bool
+///  True if the attribute is set.
+///
+#[pyfunction]
+#[pyo3(signature = (attribute, ttl=None))]
+pub fn set_if_absent(attribute: &Attribute, ttl: Option<u64>) -> bool {
+    sync_kvs::set_if_absent(&attribute.0, ttl)
+}
+
+/// Atomically add a delta to the single integer value of an attribute. The absent attribute
+/// is created with the delta and the TTL, the existing one keeps its expiration time.
+///
+/// Parameters
+/// ----------
+/// ns : str
+///  Namespace of the attribute.
+///
+/// name : str
+///  Name of the attribute.
+///
+/// delta : int
+///  The value to add.
+///
+/// ttl : Optional[int]
+///  Time-to-live for the created attribute.
+///
+/// Returns
+/// -------
+/// int
+///  The new value.
+///
+/// Raises
+/// ------
+/// ValueError
+///  If the attribute is not a single integer or the value overflows.
+///
+#[pyfunction]
+#[pyo3(signature = (ns, name, delta=1, ttl=None))]
+pub fn increment(ns: &str, name: &str, delta: i64, ttl: Option<u64>) -> PyResult<i64> {
+    sync_kvs::increment(ns, name, delta, ttl).map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
 /// Search for attributes in the key-value store.
 ///
 /// Parameters
@@ -202,14 +287,15 @@ pub struct KvsSubscription(RustKvsSubscription);
 
 impl KvsSubscription {
     pub fn to_python(&self, op: KvsOperation) -> PyResult<PyObject> {
+        let timestamp = op
+            .timestamp
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
         with_gil!(|py| {
             Ok(match op.operation {
                 KvsOperationKind::Set(attrs, ttl) => KvsSetOperation {
-                    timestamp: op
-                        .timestamp
-                        .duration_since(std::time::UNIX_EPOCH)
-                        .unwrap()
-                        .as_millis() as u64,
+                    timestamp,
                     ttl,
                     attributes: attrs.into_iter().map(Attribute).collect(),
                 }
@@ -217,11 +303,21 @@ impl KvsSubscription {
                 .into_any()
                 .unbind(),
                 KvsOperationKind::Delete(attrs) => KvsDeleteOperation {
-                    timestamp: op
-                        .timestamp
-                        .duration_since(std::time::UNIX_EPOCH)
-                        .unwrap()
-                        .as_millis() as u64,
+                    timestamp,
+                    attributes: attrs.into_iter().map(Attribute).collect(),
+                }
+                .into_pyobject(py)?
+                .into_any()
+                .unbind(),
+                KvsOperationKind::Expired(attrs) => KvsExpiredOperation {
+                    timestamp,
+                    attributes: attrs.into_iter().map(Attribute).collect(),
+                }
+                .into_pyobject(py)?
+                .into_any()
+                .unbind(),
+                KvsOperationKind::Evicted(attrs) => KvsEvictedOperation {
+                    timestamp,
                     attributes: attrs.into_iter().map(Attribute).collect(),
                 }
                 .into_pyobject(py)?
@@ -234,9 +330,21 @@ impl KvsSubscription {
 
 #[pymethods]
 impl KvsSubscription {
+    /// Subscribes to the operations on the keys matching the namespace and name globs
+    /// (``None`` means "*"), the operations on several keys carry the matching attributes
+    /// only.
+    ///
     #[new]
-    fn new(name: &str, max_inflight_ops: usize) -> PyResult<Self> {
-        subscribe(name, max_inflight_ops)
+    #[pyo3(signature = (name, max_inflight_ops, ns=None, attribute_name=None))]
+    fn new(
+        name: &str,
+        max_inflight_ops: usize,
+        ns: Option<String>,
+        attribute_name: Option<String>,
+    ) -> PyResult<Self> {
+        let filter = KvsSubscriptionFilter::new(&ns, &attribute_name)
+            .map_err(|e| PyValueError::new_err(format!("Invalid subscription filter: {:?}", e)))?;
+        subscribe_with_filter(name, max_inflight_ops, filter)
             .map(KvsSubscription)
             .map_err(|e| PyValueError::new_err(format!("Failed to create subscription: {:?}", e)))
     }
@@ -245,7 +353,7 @@ impl KvsSubscription {
     ///
     /// Returns
     /// -------
-    /// Optional[Union[KvsSetOperation, KvsDeleteOperation]]
+    /// Optional[Union[KvsSetOperation, KvsDeleteOperation, KvsExpiredOperation, KvsEvictedOperation]]
     ///   The next message from the subscription, or None if the subscription is closed.
     ///
     /// Raises
@@ -268,7 +376,7 @@ impl KvsSubscription {
     ///
     /// Returns
     /// -------
-    /// Optional[Union[KvsSetOperation, KvsDeleteOperation]]
+    /// Optional[Union[KvsSetOperation, KvsDeleteOperation, KvsExpiredOperation, KvsEvictedOperation]]
     ///  The next message from the subscription, or None if the subscription is closed.
     ///
     /// Raises
@@ -329,3 +437,43 @@ impl KvsDeleteOperation {
         self.__repr__()
     }
 }
+
+#[pyclass]
+#[derive(Debug)]
+pub struct KvsExpiredOperation {
+    #[pyo3(get)]
+    pub timestamp: u64,
+    #[pyo3(get)]
+    pub attributes: Vec<Attribute>,
+}
+
+#[pymethods]
+impl KvsExpiredOperation {
+    fn __repr__(&self) -> String {
+        format!("{:?}", &self)
+    }
+
+    fn __str__(&self) -> String {
+        self.__repr__()
+    }
+}
+
+#[pyclass]
+#[derive(Debug)]
+pub struct KvsEvictedOperation {
+    #[pyo3(get)]
+    pub timestamp: u64,
+    #[pyo3(get)]
+    pub attributes: Vec<Attribute>,
+}
+
+#[pymethods]
+impl KvsEvictedOperation {
+    fn __repr__(&self) -> String {
+        format!("{:?}", &self)
+    }
+
+    fn __str__(&self) -> String {
+        self.__repr__()
+    }
+}
@@ -1,4 +1,5 @@
-use pyo3::{pyclass, pymethods, Py, PyAny};
+use pyo3::exceptions::PyValueError;
+use pyo3::{pyclass, pymethods, Py, PyAny, PyResult};
 use savant_core::transport::zeromq;
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
@@ -148,3 +149,98 @@ impl TopicPrefixSpec {
         Self(zeromq::TopicPrefixSpec::None)
     }
 }
+
+/// Reader-side topic filtering applied before messages are decoded. A topic passes when it
+/// matches any of the allowed source ids or patterns (or no allow rules are defined) and
+/// does not match any of the exclusion rules. Regular expressions must match the whole topic.
+///
+#[pyclass]
+#[derive(Debug, Clone, Default)]
+pub struct TopicFilter(pub(crate) zeromq::TopicFilter);
+
+impl TopicFilter {
+    fn update(
+        &mut self,
+        f: impl FnOnce(zeromq::TopicFilter) -> anyhow::Result<zeromq::TopicFilter>,
+    ) -> PyResult<()> {
+        self.0 = f(self.0.clone())
+            .map_err(|e| PyValueError::new_err(format!("Invalid topic filter: {:?}", e)))?;
+        Ok(())
+    }
+}
+
+#[pymethods]
+impl TopicFilter {
+    #[classattr]
+    const __hash__: Option<Py<PyAny>> = None;
+
+    #[new]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self.0)
+    }
+
+    fn __str__(&self) -> String {
+        self.__repr__()
+    }
+
+    /// Allows the topics equal to the source ids
+    ///
+    /// Parameters
+    /// ----------
+    /// source_ids: List[str]
+    ///   The source ids to allow
+    ///
+    pub fn with_source_ids(&mut self, source_ids: Vec<String>) -> PyResult<()> {
+        self.update(|f| Ok(f.with_source_ids(source_ids)))
+    }
+
+    /// Allows the topics matching the glob pattern
+    ///
+    /// Raises
+    /// ------
+    /// ValueError
+    ///   If the pattern is invalid
+    ///
+    pub fn with_glob(&mut self, pattern: &str) -> PyResult<()> {
+        self.update(|f| f.with_glob(pattern))
+    }
+
+    /// Allows the topics matching the regular expression
+    ///
+    /// Raises
+    /// ------
+    /// ValueError
+    ///   If the pattern is invalid
+    ///
+    pub fn with_regex(&mut self, pattern: &str) -> PyResult<()> {
+        self.update(|f| f.with_regex(pattern))
+    }
+
+    /// Rejects the topics equal to the source ids
+    ///
+    pub fn exclude_source_ids(&mut self, source_ids: Vec<String>) -> PyResult<()> {
+        self.update(|f| Ok(f.exclude_source_ids(source_ids)))
+    }
+
+    /// Rejects the topics matching the glob pattern
+    ///
+    pub fn exclude_glob(&mut self, pattern: &str) -> PyResult<()> {
+        self.update(|f| f.exclude_glob(pattern))
+    }
+
+    /// Rejects the topics matching the regular expression
+    ///
+    pub fn exclude_regex(&mut self, pattern: &str) -> PyResult<()> {
+        self.update(|f| f.exclude_regex(pattern))
+    }
+
+    /// Checks whether the topic passes the filter
+    ///
+    pub fn matches(&self, topic: &str) -> bool {
+        self.0.matches(topic.as_bytes())
+    }
+}
@@ -34,6 +34,29 @@ impl BlockingWriter {
         writer.is_started()
     }
 
+    /// Returns `true` if the writer socket has at least one connected peer.
+    ///
+    pub fn is_connected(&self) -> bool {
+        self.0.as_ref().is_some_and(|writer| writer.is_connected())
+    }
+
+    /// Returns the socket events received since the previous call.
+    ///
+    /// Returns
+    /// -------
+    /// list[:py:class:`SocketEvent`]
+    ///   The events, at most the last 256 are kept.
+    ///
+    pub fn take_socket_events(&self) -> Vec<results::SocketEvent> {
+        self.0.as_ref().map_or(vec![], |writer| {
+            writer
+                .take_socket_events()
+                .into_iter()
+                .map(results::SocketEvent)
+                .collect()
+        })
+    }
+
     /// Starts the writer. If the writer is already started, returns an error.
     ///
     pub fn start(&mut self) -> PyResult<()> {
@@ -179,6 +202,29 @@ impl BlockingReader {
         reader.is_started()
     }
 
+    /// Returns `true` if the reader socket has at least one connected peer.
+    ///
+    pub fn is_connected(&self) -> bool {
+        self.0.as_ref().is_some_and(|reader| reader.is_connected())
+    }
+
+    /// Returns the socket events received since the previous call.
+    ///
+    /// Returns
+    /// -------
+    /// list[:py:class:`SocketEvent`]
+    ///   The events, at most the last 256 are kept.
+    ///
+    pub fn take_socket_events(&self) -> Vec<results::SocketEvent> {
+        self.0.as_ref().map_or(vec![], |reader| {
+            reader
+                .take_socket_events()
+                .into_iter()
+                .map(results::SocketEvent)
+                .collect()
+        })
+    }
+
     /// Shuts down the reader. If the reader is not started, returns an error.
     ///
     pub fn shutdown(&mut self) -> PyResult<()> {
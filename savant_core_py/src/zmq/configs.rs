@@ -1,8 +1,240 @@
-use crate::zmq::basic_types::{ReaderSocketType, TopicPrefixSpec, WriterSocketType};
+use crate::zmq::basic_types::{ReaderSocketType, TopicFilter, TopicPrefixSpec, WriterSocketType};
 use pyo3::exceptions::PyValueError;
-use pyo3::{pyclass, pymethods, Py, PyAny, PyResult};
+use pyo3::{pyclass, pyfunction, pymethods, Py, PyAny, PyResult};
 use savant_core::transport::zeromq;
-use std::num::NonZeroU64;
+use std::collections::HashMap;
+use std::num::{NonZeroU32, NonZeroU64, NonZeroUsize};
+use std::path::Path;
+use std::time::Duration;
+
+/// CURVE security configuration of a socket, the keys are Z85-encoded. Use
+/// :py:func:`generate_curve_keypair` to create keys.
+///
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct CurveConfig(pub(crate) zeromq::CurveConfig);
+
+fn curve_error(e: anyhow::Error) -> pyo3::PyErr {
+    PyValueError::new_err(format!("Invalid CURVE configuration: {:?}", e))
+}
+
+#[pymethods]
+impl CurveConfig {
+    #[classattr]
+    const __hash__: Option<Py<PyAny>> = None;
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self.0)
+    }
+
+    fn __str__(&self) -> String {
+        self.__repr__()
+    }
+
+    /// Creates the server configuration.
+    ///
+    /// Parameters
+    /// ----------
+    /// secret_key: str
+    ///   The server secret key.
+    /// allowed_clients: Optional[List[str]]
+    ///   The public keys of the accepted clients, all clients are accepted when not set.
+    ///
+    /// Raises
+    /// ------
+    /// ValueError
+    ///   If a key is invalid
+    ///
+    #[staticmethod]
+    #[pyo3(signature = (secret_key, allowed_clients=None))]
+    pub fn server(secret_key: &str, allowed_clients: Option<Vec<String>>) -> PyResult<Self> {
+        Ok(Self(
+            zeromq::CurveConfig::server(secret_key, allowed_clients).map_err(curve_error)?,
+        ))
+    }
+
+    /// Creates the client configuration.
+    ///
+    /// Parameters
+    /// ----------
+    /// public_key: str
+    ///   The client public key.
+    /// secret_key: str
+    ///   The client secret key.
+    /// server_key: str
+    ///   The server public key.
+    ///
+    /// Raises
+    /// ------
+    /// ValueError
+    ///   If a key is invalid
+    ///
+    #[staticmethod]
+    pub fn client(public_key: &str, secret_key: &str, server_key: &str) -> PyResult<Self> {
+        Ok(Self(
+            zeromq::CurveConfig::client(public_key, secret_key, server_key).map_err(curve_error)?,
+        ))
+    }
+
+    /// Creates the server configuration from files. A key file contains either the key
+    /// alone or is a ZeroMQ certificate, the allowed clients file lists one public key per
+    /// line.
+    ///
+    /// Parameters
+    /// ----------
+    /// secret_key_file: str
+    ///   The server secret key file.
+    /// allowed_clients_file: Optional[str]
+    ///   The allowed clients file, all clients are accepted when not set.
+    ///
+    /// Raises
+    /// ------
+    /// ValueError
+    ///   If a file cannot be read or a key is invalid
+    ///
+    #[staticmethod]
+    #[pyo3(signature = (secret_key_file, allowed_clients_file=None))]
+    pub fn server_from_files(
+        secret_key_file: &str,
+        allowed_clients_file: Option<&str>,
+    ) -> PyResult<Self> {
+        Ok(Self(
+            zeromq::CurveConfig::server_from_files(
+                Path::new(secret_key_file),
+                allowed_clients_file.map(Path::new),
+            )
+            .map_err(curve_error)?,
+        ))
+    }
+
+    /// Creates the client configuration from files, the public and the secret keys may
+    /// be loaded from the same certificate file.
+    ///
+    /// Parameters
+    /// ----------
+    /// public_key_file: str
+    ///   The client public key file.
+    /// secret_key_file: str
+    ///   The client secret key file.
+    /// server_key_file: str
+    ///   The server public key file.
+    ///
+    /// Raises
+    /// ------
+    /// ValueError
+    ///   If a file cannot be read or a key is invalid
+    ///
+    #[staticmethod]
+    pub fn client_from_files(
+        public_key_file: &str,
+        secret_key_file: &str,
+        server_key_file: &str,
+    ) -> PyResult<Self> {
+        Ok(Self(
+            zeromq::CurveConfig::client_from_files(
+                Path::new(public_key_file),
+                Path::new(secret_key_file),
+                Path::new(server_key_file),
+            )
+            .map_err(curve_error)?,
+        ))
+    }
+
+    #[getter]
+    fn is_server(&self) -> bool {
+        matches!(self.0, zeromq::CurveConfig::Server { .. })
+    }
+}
+
+/// Generates a new CURVE keypair.
+///
+/// Returns
+/// -------
+/// Tuple[str, str]
+///   The Z85-encoded public and secret keys.
+///
+/// Raises
+/// ------
+/// ValueError
+///   If the ZeroMQ library is built without CURVE support
+///
+#[pyfunction]
+pub fn generate_curve_keypair() -> PyResult<(String, String)> {
+    zeromq::generate_curve_keypair()
+        .map_err(|e| PyValueError::new_err(format!("Failed to generate CURVE keypair: {:?}", e)))
+}
+
+/// Token-bucket limit of the video frames of a source sent by a writer, the bucket holds one
+/// second of the budget.
+///
+/// Parameters
+/// ----------
+/// frames_per_second: Optional[float]
+///   The frame rate limit
+/// bytes_per_second: Optional[float]
+///   The limit of the message size and the extra parts before compression
+/// policy: str
+///   ``drop_non_keyframes`` drops the non-keyframes over the limit first, ``keep_every_nth``
+///   drops the frames over the limit except every ``keep_every`` of them, ``block`` waits
+///   until the frame fits the limit
+/// keep_every: Optional[int]
+///   The period of the kept frames for the ``keep_every_nth`` policy
+///
+/// Raises
+/// ------
+/// ValueError
+///   If the policy is unknown or the limits are invalid
+///
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct RateLimit(pub(crate) zeromq::RateLimit);
+
+#[pymethods]
+impl RateLimit {
+    #[classattr]
+    const __hash__: Option<Py<PyAny>> = None;
+
+    #[new]
+    #[pyo3(signature = (frames_per_second=None, bytes_per_second=None, policy="drop_non_keyframes", keep_every=None))]
+    fn new(
+        frames_per_second: Option<f64>,
+        bytes_per_second: Option<f64>,
+        policy: &str,
+        keep_every: Option<u32>,
+    ) -> PyResult<Self> {
+        let policy = match (policy, keep_every) {
+            ("drop_non_keyframes", None) => zeromq::RateLimitPolicy::DropNonKeyframes,
+            ("block", None) => zeromq::RateLimitPolicy::Block,
+            ("keep_every_nth", Some(n)) => zeromq::RateLimitPolicy::KeepEveryNth(
+                NonZeroU32::new(n).ok_or(PyValueError::new_err("keep_every must be non-zero"))?,
+            ),
+            ("keep_every_nth", None) => {
+                return Err(PyValueError::new_err(
+                    "keep_every is required for the keep_every_nth policy",
+                ))
+            }
+            (policy, _) => {
+                return Err(PyValueError::new_err(format!(
+                    "Unknown rate limit policy {} or unexpected keep_every",
+                    policy
+                )))
+            }
+        };
+        Ok(Self(zeromq::RateLimit {
+            frames_per_second,
+            bytes_per_second,
+            policy,
+        }))
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self.0)
+    }
+
+    fn __str__(&self) -> String {
+        self.__repr__()
+    }
+}
 
 /// Creates a new configuration builder based on the provided URL.
 /// The URL can have the following formats:
@@ -11,6 +243,11 @@ use std::num::NonZeroU64;
 ///   * ``ipc:///tmp/test``
 ///   * ``(pub|req|dealer)+(bind|connect):(tcp|ipc)://...``
 ///
+/// CURVE security may be configured with the URL parameters, the keys are loaded from files:
+///
+///   * ``...?curve=server&secret_key_file=...[&allowed_clients_file=...]``
+///   * ``...?curve=client&public_key_file=...&secret_key_file=...&server_key_file=...``
+///
 /// Parameters
 /// ----------
 /// url: str
@@ -86,6 +323,11 @@ impl WriterConfig {
         *self.0.fix_ipc_permissions()
     }
 
+    #[getter]
+    fn curve(&self) -> Option<CurveConfig> {
+        self.0.curve().clone().map(CurveConfig)
+    }
+
     #[classattr]
     const __hash__: Option<Py<PyAny>> = None;
 
@@ -316,6 +558,223 @@ impl WriterConfigBuilder {
         Ok(())
     }
 
+    /// Sets CURVE security of the socket
+    ///
+    /// Parameters
+    /// ----------
+    /// curve: :py:class:`CurveConfig`
+    ///   The CURVE configuration, CURVE is disabled by default
+    ///
+    /// Raises
+    /// ------
+    /// ValueError
+    ///   If CURVE is double set
+    ///
+    pub fn with_curve(&mut self, curve: &CurveConfig) -> PyResult<()> {
+        self.0 = Some(
+            self.0
+                .take()
+                .unwrap()
+                .with_curve(curve.0.clone())
+                .map_err(|e| {
+                    PyValueError::new_err(format!("Failed to set ZeroMQ socket CURVE: {:?}", e))
+                })?,
+        );
+        Ok(())
+    }
+
+    /// Enables compression of the messages, the readers decompress messages automatically
+    ///
+    /// Parameters
+    /// ----------
+    /// codec: str
+    ///   The codec, ``zstd`` or ``lz4``
+    /// level: Optional[int]
+    ///   The zstd compression level, defaults to ``3``
+    /// message: bool
+    ///   Compress the serialized message
+    /// extra_parts: bool
+    ///   Compress the extra data parts
+    ///
+    /// Raises
+    /// ------
+    /// ValueError
+    ///   If the codec is unknown, nothing is compressed or compression is double set
+    ///
+    #[pyo3(signature = (codec, level=None, message=true, extra_parts=true))]
+    pub fn with_compression(
+        &mut self,
+        codec: &str,
+        level: Option<i32>,
+        message: bool,
+        extra_parts: bool,
+    ) -> PyResult<()> {
+        let codec = match (codec, level) {
+            ("zstd", level) => {
+                zeromq::CompressionCodec::Zstd(level.unwrap_or(zeromq::ZSTD_DEFAULT_LEVEL))
+            }
+            ("lz4", None) => zeromq::CompressionCodec::Lz4,
+            ("lz4", Some(_)) => {
+                return Err(PyValueError::new_err(
+                    "Compression level is not supported for lz4",
+                ))
+            }
+            (codec, _) => {
+                return Err(PyValueError::new_err(format!(
+                    "Unknown compression codec {}",
+                    codec
+                )))
+            }
+        };
+        self.0 = Some(
+            self.0
+                .take()
+                .unwrap()
+                .with_compression(zeromq::Compression {
+                    codec,
+                    message,
+                    extra_parts,
+                })
+                .map_err(|e| {
+                    PyValueError::new_err(format!(
+                        "Failed to set ZeroMQ socket compression: {:?}",
+                        e
+                    ))
+                })?,
+        );
+        Ok(())
+    }
+
+    /// Enables reliable delivery for Dealer sockets: the writer keeps a window of
+    /// unacknowledged messages and retransmits them, the Router reader acknowledges them and
+    /// drops duplicates
+    ///
+    /// Parameters
+    /// ----------
+    /// window_size: int
+    ///   The maximum number of unacknowledged messages
+    /// retransmit_timeout: int
+    ///   The time in milliseconds after which an unacknowledged message is sent again
+    /// max_retransmits: int
+    ///   The number of retransmits after which the message is considered lost
+    ///
+    /// Raises
+    /// ------
+    /// ValueError
+    ///   If the parameters are invalid or reliable delivery is double set
+    ///
+    #[pyo3(signature = (window_size=64, retransmit_timeout=1000, max_retransmits=5))]
+    pub fn with_reliable_delivery(
+        &mut self,
+        window_size: usize,
+        retransmit_timeout: u64,
+        max_retransmits: u32,
+    ) -> PyResult<()> {
+        self.0 = Some(
+            self.0
+                .take()
+                .unwrap()
+                .with_reliable_delivery(zeromq::ReliableDelivery {
+                    window_size,
+                    retransmit_timeout: Duration::from_millis(retransmit_timeout),
+                    max_retransmits,
+                })
+                .map_err(|e| {
+                    PyValueError::new_err(format!(
+                        "Failed to set ZeroMQ socket reliable delivery: {:?}",
+                        e
+                    ))
+                })?,
+        );
+        Ok(())
+    }
+
+    /// Limits the video frames of every source separately, the dropped frames are reported
+    /// as :py:class:`WriterResultRateLimited`
+    ///
+    /// Parameters
+    /// ----------
+    /// limit: :py:class:`RateLimit`
+    ///   The limit
+    ///
+    /// Raises
+    /// ------
+    /// ValueError
+    ///   If the limit is invalid or double set
+    ///
+    pub fn with_rate_limit(&mut self, limit: &RateLimit) -> PyResult<()> {
+        self.0 = Some(
+            self.0
+                .take()
+                .unwrap()
+                .with_rate_limit(limit.0)
+                .map_err(|e| {
+                    PyValueError::new_err(format!(
+                        "Failed to set ZeroMQ socket rate limit: {:?}",
+                        e
+                    ))
+                })?,
+        );
+        Ok(())
+    }
+
+    /// Sets the limits of the particular sources, they override the limit set with
+    /// :py:meth:`with_rate_limit`
+    ///
+    /// Parameters
+    /// ----------
+    /// limits: Dict[str, :py:class:`RateLimit`]
+    ///   The limits by source id
+    ///
+    /// Raises
+    /// ------
+    /// ValueError
+    ///   If a limit is invalid or the limits are double set
+    ///
+    pub fn with_source_rate_limits(&mut self, limits: HashMap<String, RateLimit>) -> PyResult<()> {
+        self.0 = Some(
+            self.0
+                .take()
+                .unwrap()
+                .with_source_rate_limits(limits.into_iter().map(|(k, v)| (k, v.0)).collect())
+                .map_err(|e| {
+                    PyValueError::new_err(format!(
+                        "Failed to set ZeroMQ socket source rate limits: {:?}",
+                        e
+                    ))
+                })?,
+        );
+        Ok(())
+    }
+
+    /// Recreates the socket after the number of consecutive failed sends when there is no
+    /// connected peer. Only connect sockets are supported
+    ///
+    /// Parameters
+    /// ----------
+    /// failures: int
+    ///   The number of failed sends, reconnect is disabled by default
+    ///
+    /// Raises
+    /// ------
+    /// ValueError
+    ///   If the number is zero or reconnect is double set
+    ///
+    pub fn with_reconnect_after(&mut self, failures: u32) -> PyResult<()> {
+        self.0 = Some(
+            self.0
+                .take()
+                .unwrap()
+                .with_reconnect_after(NonZeroU32::new(failures).ok_or(PyValueError::new_err(
+                    "Failed to set ZeroMQ socket reconnect: the number must be non-zero",
+                ))?)
+                .map_err(|e| {
+                    PyValueError::new_err(format!("Failed to set ZeroMQ socket reconnect: {:?}", e))
+                })?,
+        );
+        Ok(())
+    }
+
     /// Builds the configuration
     ///
     /// Returns
@@ -347,6 +806,11 @@ impl WriterConfigBuilder {
 ///   * ``ipc:///tmp/test``
 ///   * ``(sub|rep|router)+(bind|connect):(tcp|ipc)://...``
 ///
+/// CURVE security may be configured with the URL parameters, the keys are loaded from files:
+///
+///   * ``...?curve=server&secret_key_file=...[&allowed_clients_file=...]``
+///   * ``...?curve=client&public_key_file=...&secret_key_file=...&server_key_file=...``
+///
 /// Parameters
 /// ----------
 /// url: str
@@ -413,6 +877,11 @@ impl ReaderConfig {
         TopicPrefixSpec(self.0.topic_prefix_spec().clone())
     }
 
+    #[getter]
+    fn topic_filter(&self) -> TopicFilter {
+        TopicFilter(self.0.topic_filter().clone())
+    }
+
     #[getter]
     fn routing_cache_size(&self) -> usize {
         *self.0.routing_cache_size()
@@ -423,6 +892,11 @@ impl ReaderConfig {
         *self.0.fix_ipc_permissions()
     }
 
+    #[getter]
+    fn curve(&self) -> Option<CurveConfig> {
+        self.0.curve().clone().map(CurveConfig)
+    }
+
     #[getter]
     fn source_blacklist_size(&self) -> u64 {
         *self.0.source_blacklist_size()
@@ -576,6 +1050,35 @@ impl ReaderConfigBuilder {
         Ok(())
     }
 
+    /// Sets the topic filter applied before messages are decoded, the filtered out
+    /// messages are reported as ``ReaderResultPrefixMismatch``.
+    ///
+    /// Parameters
+    /// ----------
+    /// topic_filter: :py:class:`TopicFilter`
+    ///   The topic filter, passes all topics by default
+    ///
+    /// Raises
+    /// ------
+    /// ValueError
+    ///   If the topic filter is double set
+    ///
+    pub fn with_topic_filter(&mut self, topic_filter: &TopicFilter) -> PyResult<()> {
+        self.0 = Some(
+            self.0
+                .take()
+                .unwrap()
+                .with_topic_filter(topic_filter.0.clone())
+                .map_err(|e| {
+                    PyValueError::new_err(format!(
+                        "Failed to set ZeroMQ socket topic filter: {:?}",
+                        e
+                    ))
+                })?,
+        );
+        Ok(())
+    }
+
     /// Sets the routing cache size for the ZeroMQ socket. The cache is used to track dealer-router connections
     /// chaos when the dealers reconnect with the same topic.
     ///
@@ -629,6 +1132,31 @@ impl ReaderConfigBuilder {
         Ok(())
     }
 
+    /// Sets CURVE security of the socket
+    ///
+    /// Parameters
+    /// ----------
+    /// curve: :py:class:`CurveConfig`
+    ///   The CURVE configuration, CURVE is disabled by default
+    ///
+    /// Raises
+    /// ------
+    /// ValueError
+    ///   If CURVE is double set
+    ///
+    pub fn with_curve(&mut self, curve: &CurveConfig) -> PyResult<()> {
+        self.0 = Some(
+            self.0
+                .take()
+                .unwrap()
+                .with_curve(curve.0.clone())
+                .map_err(|e| {
+                    PyValueError::new_err(format!("Failed to set ZeroMQ socket CURVE: {:?}", e))
+                })?,
+        );
+        Ok(())
+    }
+
     /// Builds the configuration
     ///
     /// Returns
@@ -711,4 +1239,96 @@ impl ReaderConfigBuilder {
         );
         Ok(())
     }
+
+    /// Sets the number of sources whose ``seq_id`` is tracked to detect lost, duplicate
+    /// and reordered messages
+    ///
+    /// Parameters
+    /// ----------
+    /// size: int
+    ///  The number of sources, defaults to ``256``.
+    ///
+    /// Raises
+    /// ------
+    /// ValueError
+    ///  If the size is zero or double set
+    ///
+    pub fn with_seq_store_size(&mut self, size: usize) -> PyResult<()> {
+        self.0 = Some(
+            self.0
+                .take()
+                .unwrap()
+                .with_seq_store_size(NonZeroUsize::new(size).ok_or(PyValueError::new_err(
+                    "Failed to set ZeroMQ socket seq store size: size must be non-zero",
+                ))?)
+                .map_err(|e| {
+                    PyValueError::new_err(format!(
+                        "Failed to set ZeroMQ socket seq store size: {:?}",
+                        e
+                    ))
+                })?,
+        );
+        Ok(())
+    }
+
+    /// Recreates the socket after the number of consecutive receive timeouts when there is no
+    /// connected peer. Only connect sockets are supported
+    ///
+    /// Parameters
+    /// ----------
+    /// timeouts: int
+    ///   The number of receive timeouts, reconnect is disabled by default
+    ///
+    /// Raises
+    /// ------
+    /// ValueError
+    ///   If the number is zero or reconnect is double set
+    ///
+    pub fn with_reconnect_after(&mut self, timeouts: u32) -> PyResult<()> {
+        self.0 = Some(
+            self.0
+                .take()
+                .unwrap()
+                .with_reconnect_after(NonZeroU32::new(timeouts).ok_or(PyValueError::new_err(
+                    "Failed to set ZeroMQ socket reconnect: the number must be non-zero",
+                ))?)
+                .map_err(|e| {
+                    PyValueError::new_err(format!("Failed to set ZeroMQ socket reconnect: {:?}", e))
+                })?,
+        );
+        Ok(())
+    }
+
+    /// Sets the maximum total size of the decompressed message parts. Larger compressed
+    /// messages are reported as ``ReaderResultDecompressionFailed``
+    ///
+    /// Parameters
+    /// ----------
+    /// size: int
+    ///   The size in bytes, defaults to 256 MiB.
+    ///
+    /// Raises
+    /// ------
+    /// ValueError
+    ///   If the size is zero or double set
+    ///
+    pub fn with_max_decompressed_size(&mut self, size: usize) -> PyResult<()> {
+        self.0 = Some(
+            self.0
+                .take()
+                .unwrap()
+                .with_max_decompressed_size(NonZeroUsize::new(size).ok_or(
+                    PyValueError::new_err(
+                        "Failed to set ZeroMQ socket max decompressed size: size must be non-zero",
+                    ),
+                )?)
+                .map_err(|e| {
+                    PyValueError::new_err(format!(
+                        "Failed to set ZeroMQ socket max decompressed size: {:?}",
+                        e
+                    ))
+                })?,
+        );
+        Ok(())
+    }
 }
@@ -2,6 +2,7 @@ use crate::primitives::message::Message;
 use crate::with_gil;
 use pyo3::types::PyBytes;
 use pyo3::{pyclass, pymethods, IntoPyObject, Py, PyAny, PyObject, PyResult};
+use savant_core::message::SeqIdStatus as CoreSeqIdStatus;
 use savant_core::transport::zeromq;
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
@@ -119,6 +120,140 @@ impl WriterResultSuccess {
     }
 }
 
+/// Returned when a writer drops a video frame due to the rate limit of the source.
+/// Contains the total number of the dropped frames of the source.
+///
+#[pyclass]
+#[derive(Debug, Clone, Hash)]
+pub struct WriterResultRateLimited {
+    #[pyo3(get)]
+    pub dropped: u64,
+}
+
+#[pymethods]
+impl WriterResultRateLimited {
+    fn __hash__(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self)
+    }
+
+    fn __str__(&self) -> String {
+        self.__repr__()
+    }
+}
+
+/// The event of the ZeroMQ socket monitor.
+///
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct SocketEvent(pub(crate) zeromq::SocketEvent);
+
+#[pymethods]
+impl SocketEvent {
+    fn __repr__(&self) -> String {
+        format!("{:?}", self.0)
+    }
+
+    fn __str__(&self) -> String {
+        self.__repr__()
+    }
+
+    /// The event kind, e.g. ``connected``, ``disconnected`` or ``handshake_failed``.
+    ///
+    #[getter]
+    fn kind(&self) -> String {
+        match serde_json::to_value(self.0.kind) {
+            Ok(serde_json::Value::String(kind)) => kind,
+            _ => "unknown".to_string(),
+        }
+    }
+
+    /// The peer or the local endpoint the event relates to.
+    ///
+    #[getter]
+    fn address(&self) -> &str {
+        &self.0.address
+    }
+
+    /// The event value, e.g. the error code or the reconnect interval.
+    ///
+    #[getter]
+    fn value(&self) -> u32 {
+        self.0.value
+    }
+}
+
+/// The result of the ``seq_id`` check of a received message.
+///
+#[pyclass]
+#[derive(Debug, Clone, Hash)]
+pub struct SeqIdStatus(pub(crate) savant_core::message::SeqIdStatus);
+
+#[pymethods]
+impl SeqIdStatus {
+    fn __hash__(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self.0)
+    }
+
+    fn __str__(&self) -> String {
+        self.__repr__()
+    }
+
+    /// One of ``valid``, ``reset``, ``gap`` or ``duplicate``.
+    ///
+    #[getter]
+    fn kind(&self) -> &'static str {
+        match self.0 {
+            CoreSeqIdStatus::Valid => "valid",
+            CoreSeqIdStatus::Reset { .. } => "reset",
+            CoreSeqIdStatus::Gap { .. } => "gap",
+            CoreSeqIdStatus::Duplicate { .. } => "duplicate",
+        }
+    }
+
+    /// The expected ``seq_id``, ``None`` for valid messages.
+    ///
+    #[getter]
+    fn expected(&self) -> Option<u64> {
+        match self.0 {
+            CoreSeqIdStatus::Valid => None,
+            CoreSeqIdStatus::Reset { expected, .. }
+            | CoreSeqIdStatus::Gap { expected, .. }
+            | CoreSeqIdStatus::Duplicate { expected, .. } => Some(expected),
+        }
+    }
+
+    /// The received ``seq_id``, ``None`` for valid messages.
+    ///
+    #[getter]
+    fn received(&self) -> Option<u64> {
+        match self.0 {
+            CoreSeqIdStatus::Valid => None,
+            CoreSeqIdStatus::Reset { received, .. }
+            | CoreSeqIdStatus::Gap { received, .. }
+            | CoreSeqIdStatus::Duplicate { received, .. } => Some(received),
+        }
+    }
+
+    /// The number of lost messages.
+    ///
+    #[getter]
+    fn lost(&self) -> u64 {
+        self.0.lost()
+    }
+}
+
 /// Returned when a reader received a message.
 ///
 #[pyclass]
@@ -134,6 +269,10 @@ pub struct ReaderResultMessage {
     #[pyo3(get)]
     pub routing_id: Option<Vec<u8>>,
     pub data: Arc<Vec<Vec<u8>>>,
+    /// The result of the ``seq_id`` check, ``None`` when the reader does not track
+    /// sequences.
+    #[pyo3(get)]
+    pub seq_id_status: Option<SeqIdStatus>,
 }
 
 #[pymethods]
@@ -143,8 +282,8 @@ impl ReaderResultMessage {
 
     fn __repr__(&self) -> String {
         format!(
-            "ReaderResultMessage [ message = {:?}, topic = {:?}, routing_id = {:?}, data = ... ]",
-            &self.message.0, &self.topic, &self.routing_id
+            "ReaderResultMessage [ message = {:?}, topic = {:?}, routing_id = {:?}, seq_id_status = {:?}, data = ... ]",
+            &self.message.0, &self.topic, &self.routing_id, &self.seq_id_status
         )
     }
 
@@ -270,6 +409,74 @@ impl ReaderResultPrefixMismatch {
     }
 }
 
+/// Returned when a reader received a compressed message which cannot be decompressed,
+/// e.g. the codec is not supported.
+///
+#[pyclass]
+#[derive(Debug, Clone, Hash)]
+pub struct ReaderResultDecompressionFailed {
+    /// The topic of the message.
+    #[pyo3(get)]
+    pub topic: Vec<u8>,
+    /// The routing id of the message. The field is only filled for Router socket.
+    #[pyo3(get)]
+    pub routing_id: Option<Vec<u8>>,
+    /// The decompression error.
+    #[pyo3(get)]
+    pub error: String,
+}
+
+#[pymethods]
+impl ReaderResultDecompressionFailed {
+    fn __hash__(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self)
+    }
+
+    fn __str__(&self) -> String {
+        self.__repr__()
+    }
+}
+
+/// Returned when a reader received a message sent in the reliable mode which is already
+/// delivered. The message is acknowledged again, so the writer stops retransmitting it.
+///
+#[pyclass]
+#[derive(Debug, Clone, Hash)]
+pub struct ReaderResultDuplicate {
+    /// The topic of the message.
+    #[pyo3(get)]
+    pub topic: Vec<u8>,
+    /// The routing id of the message. The field is only filled for Router socket.
+    #[pyo3(get)]
+    pub routing_id: Option<Vec<u8>>,
+    /// The sequence id of the message.
+    #[pyo3(get)]
+    pub seq_id: u64,
+}
+
+#[pymethods]
+impl ReaderResultDuplicate {
+    fn __hash__(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self)
+    }
+
+    fn __str__(&self) -> String {
+        self.__repr__()
+    }
+}
+
 pub(crate) fn process_writer_result(res: zeromq::WriterResult) -> PyResult<PyObject> {
     with_gil!(|py| {
         Ok(match res {
@@ -303,6 +510,10 @@ pub(crate) fn process_writer_result(res: zeromq::WriterResult) -> PyResult<PyObj
                 .into_pyobject(py)?
                 .into_any()
                 .unbind(),
+            zeromq::WriterResult::RateLimited { dropped } => WriterResultRateLimited { dropped }
+                .into_pyobject(py)?
+                .into_any()
+                .unbind(),
         })
     })
 }
@@ -319,11 +530,13 @@ pub(crate) fn process_reader_result(res: zeromq::ReaderResult) -> PyResult<PyObj
                 topic,
                 routing_id,
                 data,
+                seq_id_status,
             } => ReaderResultMessage {
                 message: Message(*message),
                 topic,
                 routing_id,
                 data: Arc::new(data),
+                seq_id_status: seq_id_status.map(SeqIdStatus),
             }
             .into_pyobject(py)?
             .into_any()
@@ -345,6 +558,30 @@ pub(crate) fn process_reader_result(res: zeromq::ReaderResult) -> PyResult<PyObj
                     .unbind()
             }
             zeromq::ReaderResult::TooShort(data) => data.into_pyobject(py)?.into_any().unbind(),
+            zeromq::ReaderResult::DecompressionFailed {
+                topic,
+                routing_id,
+                error,
+            } => ReaderResultDecompressionFailed {
+                topic,
+                routing_id,
+                error,
+            }
+            .into_pyobject(py)?
+            .into_any()
+            .unbind(),
+            zeromq::ReaderResult::Duplicate {
+                topic,
+                routing_id,
+                seq_id,
+            } => ReaderResultDuplicate {
+                topic,
+                routing_id,
+                seq_id,
+            }
+            .into_pyobject(py)?
+            .into_any()
+            .unbind(),
         })
     })
 }
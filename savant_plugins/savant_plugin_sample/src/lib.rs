@@ -10,6 +10,8 @@ use savant_core_py::primitives::frame::VideoFrame;
 use savant_core_py::primitives::object::BorrowedVideoObject;
 use std::collections::HashMap;
 
+savant_core::declare_stage_function_plugin!("savant_plugin_sample", [("attr", Any, false)]);
+
 #[no_mangle]
 pub fn init_plugin(_: &str, pp: PluginParams) -> *mut dyn PipelineStageFunction {
     let plugin = Plugin {
@@ -31,6 +33,9 @@ impl PipelineStageFunction for Plugin {
     fn get_pipeline(&self) -> &Option<Pipeline> {
         &self.pipeline
     }
+    fn get_params(&self) -> Option<&PluginParams> {
+        Some(&self.params)
+    }
     fn call(
         &self,
         id: i64,
@@ -53,6 +58,15 @@ impl PipelineStageFunction for Plugin {
         );
         Ok(())
     }
+    fn on_source_eos(&self, source_id: &str) -> anyhow::Result<()> {
+        savant_core_py::logging::log_message(
+            LogLevel::Trace,
+            "savant_plugin_sample",
+            format!("Source {} ended", source_id).as_str(),
+            None,
+        );
+        Ok(())
+    }
 }
 
 #[pyfunction]
@@ -326,10 +326,11 @@ pub struct ResultingSize {
 }
 #[derive(Clone, Copy, PartialEq, ::prost::Message)]
 pub struct VideoFrameTransformation {
-    #[prost(oneof = "video_frame_transformation::Transformation", tags = "1, 2, 3, 4")]
-    pub transformation: ::core::option::Option<
-        video_frame_transformation::Transformation,
-    >,
+    #[prost(
+        oneof = "video_frame_transformation::Transformation",
+        tags = "1, 2, 3, 4"
+    )]
+    pub transformation: ::core::option::Option<video_frame_transformation::Transformation>,
 }
 /// Nested message and enum types in `VideoFrameTransformation`.
 pub mod video_frame_transformation {
@@ -416,10 +417,8 @@ pub struct Message {
     #[prost(string, repeated, tag = "2")]
     pub routing_labels: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
     #[prost(map = "string, string", tag = "3")]
-    pub propagated_context: ::std::collections::HashMap<
-        ::prost::alloc::string::String,
-        ::prost::alloc::string::String,
-    >,
+    pub propagated_context:
+        ::std::collections::HashMap<::prost::alloc::string::String, ::prost::alloc::string::String>,
     #[prost(uint64, tag = "4")]
     pub seq_id: u64,
     #[prost(oneof = "message::Content", tags = "5, 6, 7, 8, 9, 10, 11")]
@@ -0,0 +1 @@
+from .analytics import *
@@ -0,0 +1,57 @@
+from enum import Enum
+from typing import List, Optional, Tuple
+
+from savant_rs.match_query import MatchQuery
+from savant_rs.pipeline import StageFunction
+from savant_rs.primitives import VideoFrame
+from savant_rs.primitives.geometry import Point, PolygonalArea
+
+
+class TrackAnchor(Enum):
+    Center = ...
+    BottomCenter = ...
+
+
+class ZoneAnalyticsConfig:
+    zones: List[Tuple[str, PolygonalArea]]
+    namespace: str
+    anchor: TrackAnchor
+    trajectory_length: int
+    track_timeout: int
+
+    def __init__(self,
+                 zones: List[Tuple[str, PolygonalArea]],
+                 namespace: str = "analytics",
+                 anchor: TrackAnchor = TrackAnchor.BottomCenter,
+                 trajectory_length: int = 30,
+                 track_timeout: int = 30): ...
+
+
+class ZoneEventKind(Enum):
+    Enter = ...
+    Leave = ...
+    Cross = ...
+
+
+class ZoneEvent:
+    source_id: str
+    track_id: int
+    object_id: int
+    zone: str
+    kind: ZoneEventKind
+    edges: List[Tuple[int, Optional[str]]]
+    dwell: Optional[float]
+
+
+class ZoneAnalytics:
+    def __init__(self, config: ZoneAnalyticsConfig, query: MatchQuery): ...
+
+    def process(self, frame: VideoFrame, no_gil: bool = True) -> List[ZoneEvent]: ...
+
+    def clear_source(self, source_id: str): ...
+
+    def get_trajectory(self, source_id: str, track_id: int) -> Optional[List[Point]]: ...
+
+    def get_zones(self, source_id: str, track_id: int) -> List[str]: ...
+
+    def to_stage_function(self) -> StageFunction: ...
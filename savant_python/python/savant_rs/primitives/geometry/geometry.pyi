@@ -1,6 +1,7 @@
 from enum import Enum
 from typing import Optional, Tuple, List
 from savant_rs.draw_spec import PaddingDraw
+from savant_rs.utils import BBoxMetricType
 
 class Point:
     x: float
@@ -159,3 +160,26 @@ def solely_owned_areas(bboxes: List[RBBox], parallel: bool) -> List[float]: ...
 def associate_bboxes(
     candidates: List[RBBox], owners: List[RBBox], metric: str, threshold: float
 ) -> dict[int, list[tuple[int, float]]]: ...
+def nms(
+    bboxes: List[RBBox],
+    scores: List[float],
+    classes: Optional[List[int]] = None,
+    metric: BBoxMetricType = BBoxMetricType.IoU,
+    threshold: float = 0.5,
+) -> List[int]: ...
+def soft_nms(
+    bboxes: List[RBBox],
+    scores: List[float],
+    classes: Optional[List[int]] = None,
+    metric: BBoxMetricType = BBoxMetricType.IoU,
+    threshold: float = 0.3,
+    score_threshold: float = 0.001,
+    gaussian_sigma: Optional[float] = None,
+) -> List[Tuple[int, float]]: ...
+def weighted_boxes_fusion(
+    bboxes: List[RBBox],
+    scores: List[float],
+    classes: Optional[List[int]] = None,
+    metric: BBoxMetricType = BBoxMetricType.IoU,
+    threshold: float = 0.55,
+) -> List[Tuple[RBBox, float, Optional[int], List[int]]]: ...
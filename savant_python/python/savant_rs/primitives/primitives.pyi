@@ -1,10 +1,10 @@
 from enum import Enum
-from typing import Optional
+from typing import List, Optional
 
 from savant_rs.draw_spec import SetDrawLabelKind
 from savant_rs.match_query import MatchQuery
 from savant_rs.primitives.geometry import Intersection, RBBox, Point, PolygonalArea
-from savant_rs.utils import VideoObjectBBoxTransformation
+from savant_rs.utils import BBoxMetricType, VideoObjectBBoxTransformation
 from savant_rs.utils.serialization import Message
 
 
@@ -548,6 +548,13 @@ class VideoFrame:
 
     def delete_objects_with_ids(self, ids: list[int]) -> VideoObjectsView: ...
 
+    def apply_nms(self,
+                  q: MatchQuery,
+                  metric: BBoxMetricType,
+                  threshold: float,
+                  class_aware: bool = True,
+                  no_gil: bool = True) -> List[VideoObject]: ...
+
     def set_parent(self,
                    q: MatchQuery,
                    parent: VideoObject,
@@ -576,6 +583,16 @@ class VideoFrame:
                       protobuf: bytes,
                       no_gil: bool = True) -> VideoFrame: ...
 
+    def to_json(self, pretty: bool = False, no_gil: bool = True) -> str: ...
+
+    @classmethod
+    def from_json(cls, json: str, no_gil: bool = True) -> VideoFrame: ...
+
+    def to_yaml(self, no_gil: bool = True) -> str: ...
+
+    @classmethod
+    def from_yaml(cls, yaml: str, no_gil: bool = True) -> VideoFrame: ...
+
 
 class VideoFrameBatch:
     def __init__(self): ...
@@ -628,6 +645,15 @@ class VideoFrameUpdate:
                       protobuf: bytes,
                       no_gil: bool = True) -> VideoFrameUpdate: ...
 
+    @property
+    def yaml(self) -> str: ...
+
+    @classmethod
+    def from_json(cls, json: str) -> VideoFrameUpdate: ...
+
+    @classmethod
+    def from_yaml(cls, yaml: str) -> VideoFrameUpdate: ...
+
 
 class IdCollisionResolutionPolicy(Enum):
     GenerateNewId: ...
@@ -730,6 +756,11 @@ class VideoObject:
                       protobuf: bytes,
                       no_gil: bool = True) -> VideoObject: ...
 
+    def to_json(self, pretty: bool = False) -> str: ...
+
+    @classmethod
+    def from_json(cls, json: str) -> VideoObject: ...
+
     @property
     def namespace(self) -> str: ...
 
@@ -0,0 +1,25 @@
+from typing import List, Tuple
+
+from savant_rs.match_query import MatchQuery
+from savant_rs.pipeline import StageFunction
+from savant_rs.primitives import VideoFrame
+
+
+class SortTrackerConfig:
+    max_age: int
+    min_hits: int
+    iou_threshold: float
+
+    def __init__(self, max_age: int = 1, min_hits: int = 3, iou_threshold: float = 0.3): ...
+
+
+class SortTracker:
+    def __init__(self, config: SortTrackerConfig, query: MatchQuery): ...
+
+    def track(self, frame: VideoFrame, no_gil: bool = True) -> List[Tuple[int, int]]: ...
+
+    def clear_source(self, source_id: str): ...
+
+    def get_track_count(self, source_id: str) -> int: ...
+
+    def to_stage_function(self) -> StageFunction: ...
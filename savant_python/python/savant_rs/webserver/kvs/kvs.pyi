@@ -1,11 +1,24 @@
 from typing import List, Optional, Union
-from savant_rs.primitives import Attribute
+from savant_rs.primitives import Attribute, AttributeValue
 
 
 # pub fn set_attributes(attributes: Vec<Attribute>, ttl: Option<u64>)
 def set_attributes(attributes: List[Attribute], ttl: Optional[int]) -> None: ...
 
 
+def compare_and_set(
+    attribute: Attribute,
+    expected: List[AttributeValue],
+    ttl: Optional[int] = None,
+) -> bool: ...
+
+
+def set_if_absent(attribute: Attribute, ttl: Optional[int] = None) -> bool: ...
+
+
+def increment(ns: str, name: str, delta: int = 1, ttl: Optional[int] = None) -> int: ...
+
+
 def search_attributes(ns: Optional[str], name: Optional[str], no_gil: bool) -> List[Attribute]: ...
 
 
@@ -44,9 +57,29 @@ class KvsDeleteOperation:
     attributes: List[Attribute]
 
 
-class KvsSubscription:
-    def __init__(self, name: str, max_inflight_ops: int): ...
+class KvsExpiredOperation:
+    timestamp: int
+    attributes: List[Attribute]
 
-    def recv(self) -> Optional[Union[KvsSetOperation, KvsDeleteOperation]]: ...
 
-    def try_recv(self) -> Optional[Union[KvsSetOperation, KvsDeleteOperation]]: ...
+class KvsEvictedOperation:
+    timestamp: int
+    attributes: List[Attribute]
+
+
+class KvsSubscription:
+    def __init__(
+        self,
+        name: str,
+        max_inflight_ops: int,
+        ns: Optional[str] = None,
+        attribute_name: Optional[str] = None,
+    ): ...
+
+    def recv(self) -> Optional[
+        Union[KvsSetOperation, KvsDeleteOperation, KvsExpiredOperation, KvsEvictedOperation]
+    ]: ...
+
+    def try_recv(self) -> Optional[
+        Union[KvsSetOperation, KvsDeleteOperation, KvsExpiredOperation, KvsEvictedOperation]
+    ]: ...
@@ -1,3 +1,6 @@
+from typing import Optional
+
+
 def init_webserver(port: int) -> None: ...
 
 
@@ -14,3 +17,16 @@ def set_status_running() -> None: ...
 
 
 def set_shutdown_signal(signal: int) -> None: ...
+
+
+def set_kvs_persistence(snapshot_path: str, snapshot_period: int) -> None: ...
+
+
+def start_kvs_replication(
+    leader: str,
+    reconnect_interval: int = 1000,
+    resync_period: Optional[int] = 300000,
+) -> None: ...
+
+
+def stop_kvs_replication() -> None: ...
@@ -1,5 +1,5 @@
 from enum import Enum
-from typing import Optional, Union
+from typing import Dict, List, Optional, Tuple, Union
 
 from savant_rs.utils.serialization import Message
 
@@ -27,6 +27,55 @@ class TopicPrefixSpec:
     def none() -> TopicPrefixSpec: ...
 
 
+class TopicFilter:
+    def __init__(self): ...
+
+    def with_source_ids(self, source_ids: List[str]): ...
+
+    def with_glob(self, pattern: str): ...
+
+    def with_regex(self, pattern: str): ...
+
+    def exclude_source_ids(self, source_ids: List[str]): ...
+
+    def exclude_glob(self, pattern: str): ...
+
+    def exclude_regex(self, pattern: str): ...
+
+    def matches(self, topic: str) -> bool: ...
+
+
+class CurveConfig:
+    @staticmethod
+    def server(secret_key: str, allowed_clients: Optional[List[str]] = None) -> CurveConfig: ...
+
+    @staticmethod
+    def client(public_key: str, secret_key: str, server_key: str) -> CurveConfig: ...
+
+    @staticmethod
+    def server_from_files(secret_key_file: str,
+                          allowed_clients_file: Optional[str] = None) -> CurveConfig: ...
+
+    @staticmethod
+    def client_from_files(public_key_file: str,
+                          secret_key_file: str,
+                          server_key_file: str) -> CurveConfig: ...
+
+    @property
+    def is_server(self) -> bool: ...
+
+
+def generate_curve_keypair() -> Tuple[str, str]: ...
+
+
+class RateLimit:
+    def __init__(self,
+                 frames_per_second: Optional[float] = None,
+                 bytes_per_second: Optional[float] = None,
+                 policy: str = "drop_non_keyframes",
+                 keep_every: Optional[int] = None): ...
+
+
 class WriterConfig:
     @property
     def endpoint(self) -> str: ...
@@ -58,6 +107,9 @@ class WriterConfig:
     @property
     def fix_ipc_permissions(self) -> Optional[int]: ...
 
+    @property
+    def curve(self) -> Optional[CurveConfig]: ...
+
 
 class WriterConfigBuilder:
     def __init__(self, url: str): ...
@@ -80,6 +132,25 @@ class WriterConfigBuilder:
 
     def with_fix_ipc_permissions(self, fix_ipc_permissions: Optional[int]): ...
 
+    def with_curve(self, curve: CurveConfig): ...
+
+    def with_compression(self,
+                         codec: str,
+                         level: Optional[int] = None,
+                         message: bool = True,
+                         extra_parts: bool = True): ...
+
+    def with_reliable_delivery(self,
+                               window_size: int = 64,
+                               retransmit_timeout: int = 1000,
+                               max_retransmits: int = 5): ...
+
+    def with_reconnect_after(self, failures: int): ...
+
+    def with_rate_limit(self, limit: RateLimit): ...
+
+    def with_source_rate_limits(self, limits: Dict[str, RateLimit]): ...
+
     def build(self) -> WriterConfig: ...
 
 
@@ -102,12 +173,18 @@ class ReaderConfig:
     @property
     def topic_prefix_spec(self) -> TopicPrefixSpec: ...
 
+    @property
+    def topic_filter(self) -> TopicFilter: ...
+
     @property
     def routing_cache_size(self) -> int: ...
 
     @property
     def fix_ipc_permissions(self) -> Optional[int]: ...
 
+    @property
+    def curve(self) -> Optional[CurveConfig]: ...
+
 
 class ReaderConfigBuilder:
     def __init__(self, url: str): ...
@@ -122,10 +199,20 @@ class ReaderConfigBuilder:
 
     def with_topic_prefix_spec(self, topic_prefix: TopicPrefixSpec): ...
 
+    def with_topic_filter(self, topic_filter: TopicFilter): ...
+
     def with_routing_cache_size(self, routing_cache_size: int): ...
 
     def with_fix_ipc_permissions(self, fix_ipc_permissions: Optional[int]): ...
 
+    def with_curve(self, curve: CurveConfig): ...
+
+    def with_seq_store_size(self, size: int): ...
+
+    def with_reconnect_after(self, timeouts: int): ...
+
+    def with_max_decompressed_size(self, size: int): ...
+
     def build(self) -> ReaderConfig: ...
 
 
@@ -148,10 +235,28 @@ class WriterResultSuccess:
     time_spent: int
 
 
+class WriterResultRateLimited:
+    dropped: int
+
+
+class SocketEvent:
+    kind: str
+    address: str
+    value: int
+
+
+class SeqIdStatus:
+    kind: str
+    expected: Optional[int]
+    received: Optional[int]
+    lost: int
+
+
 class ReaderResultMessage:
     message: Message
     topic: bytes
     routing_id: Optional[bytes]
+    seq_id_status: Optional[SeqIdStatus]
 
     def data_len(self) -> int: ...
 
@@ -172,11 +277,27 @@ class ReaderResultPrefixMismatch:
     routing_id: Optional[bytes]
 
 
+class ReaderResultDecompressionFailed:
+    topic: bytes
+    routing_id: Optional[bytes]
+    error: str
+
+
+class ReaderResultDuplicate:
+    topic: bytes
+    routing_id: Optional[bytes]
+    seq_id: int
+
+
 class BlockingWriter:
     def __init__(self, config: WriterConfig): ...
 
     def is_started(self) -> bool: ...
 
+    def is_connected(self) -> bool: ...
+
+    def take_socket_events(self) -> List[SocketEvent]: ...
+
     def start(self) -> None: ...
 
     def shutdown(self) -> None: ...
@@ -184,7 +305,8 @@ class BlockingWriter:
     def send_eos(self, topic: str) -> None: ...
 
     def send_message(self, topic: str, message: Message) -> Union[
-        WriterResultSendTimeout, WriterResultActTimeout, WriterResultAck, WriterResultSuccess]: ...
+        WriterResultSendTimeout, WriterResultActTimeout, WriterResultAck, WriterResultSuccess,
+        WriterResultRateLimited]: ...
 
 
 class BlockingReader:
@@ -192,19 +314,26 @@ class BlockingReader:
 
     def is_started(self) -> bool: ...
 
+    def is_connected(self) -> bool: ...
+
+    def take_socket_events(self) -> List[SocketEvent]: ...
+
     def start(self) -> None: ...
 
     def shutdown(self) -> None: ...
 
     def receive(self) -> Union[
-        ReaderResultMessage, ReaderResultEndOfStream, ReaderResultTimeout, ReaderResultPrefixMismatch]: ...
+        ReaderResultMessage, ReaderResultEndOfStream, ReaderResultTimeout, ReaderResultPrefixMismatch,
+        ReaderResultDecompressionFailed, ReaderResultDuplicate]: ...
 
 
 class WriteOperationResult:
-    def get(self) -> Union[WriterResultSendTimeout, WriterResultActTimeout, WriterResultAck, WriterResultSuccess]: ...
+    def get(self) -> Union[WriterResultSendTimeout, WriterResultActTimeout, WriterResultAck, WriterResultSuccess,
+        WriterResultRateLimited]: ...
 
     def try_get(self) -> Optional[
-        Union[WriterResultSendTimeout, WriterResultActTimeout, WriterResultAck, WriterResultSuccess]]: ...
+        Union[WriterResultSendTimeout, WriterResultActTimeout, WriterResultAck, WriterResultSuccess,
+        WriterResultRateLimited]]: ...
 
 
 class NonBlockingWriter:
@@ -237,9 +366,11 @@ class NonBlockingReader:
     def shutdown(self) -> None: ...
 
     def receive(self) -> Union[
-        ReaderResultMessage, ReaderResultEndOfStream, ReaderResultTimeout, ReaderResultPrefixMismatch]: ...
+        ReaderResultMessage, ReaderResultEndOfStream, ReaderResultTimeout, ReaderResultPrefixMismatch,
+        ReaderResultDecompressionFailed, ReaderResultDuplicate]: ...
 
     def try_receive(self) -> Optional[
-        Union[ReaderResultMessage, ReaderResultEndOfStream, ReaderResultTimeout, ReaderResultPrefixMismatch]]: ...
+        Union[ReaderResultMessage, ReaderResultEndOfStream, ReaderResultTimeout, ReaderResultPrefixMismatch,
+        ReaderResultDecompressionFailed, ReaderResultDuplicate]]: ...
 
     def enqueued_results(self) -> int: ...
@@ -3,6 +3,9 @@ use pyo3::prelude::*;
 use pyo3::types::PyDict;
 use pyo3::wrap_pymodule;
 
+use savant_core_py::analytics::{
+    TrackAnchor, ZoneAnalytics, ZoneAnalyticsConfig, ZoneEvent, ZoneEventKind,
+};
 use savant_core_py::atomic_counter::AtomicCounter;
 use savant_core_py::draw_spec::*;
 use savant_core_py::logging::*;
@@ -11,7 +14,7 @@ use savant_core_py::metrics::*;
 use savant_core_py::pipeline::{
     load_stage_function_plugin, FrameProcessingStatRecord, FrameProcessingStatRecordType, Pipeline,
     PipelineConfiguration, StageFunction, StageLatencyMeasurements, StageLatencyStat,
-    StageProcessingStat, VideoPipelineStagePayloadType,
+    StageProcessingStat, VideoPipelineStagePayloadType, VideoPipelineStageQueuePolicy,
 };
 use savant_core_py::primitives::attribute::Attribute;
 use savant_core_py::primitives::attribute_value::{
@@ -45,6 +48,7 @@ use savant_core_py::primitives::shutdown::Shutdown;
 use savant_core_py::primitives::user_data::UserData;
 use savant_core_py::telemetry::*;
 use savant_core_py::test::utils::*;
+use savant_core_py::tracker::{SortTracker, SortTrackerConfig};
 use savant_core_py::utils::byte_buffer::ByteBuffer;
 use savant_core_py::utils::eval_resolvers::*;
 use savant_core_py::utils::otlp::*;
@@ -52,13 +56,18 @@ use savant_core_py::utils::symbol_mapper::*;
 use savant_core_py::utils::*;
 use savant_core_py::webserver::kvs::*;
 use savant_core_py::webserver::*;
-use savant_core_py::zmq::basic_types::{ReaderSocketType, TopicPrefixSpec, WriterSocketType};
+use savant_core_py::zmq::basic_types::{
+    ReaderSocketType, TopicFilter, TopicPrefixSpec, WriterSocketType,
+};
 use savant_core_py::zmq::configs::{
-    ReaderConfig, ReaderConfigBuilder, WriterConfig, WriterConfigBuilder,
+    generate_curve_keypair, CurveConfig, RateLimit, ReaderConfig, ReaderConfigBuilder,
+    WriterConfig, WriterConfigBuilder,
 };
 use savant_core_py::zmq::results::{
-    ReaderResultBlacklisted, ReaderResultMessage, ReaderResultPrefixMismatch, ReaderResultTimeout,
-    WriterResultAck, WriterResultAckTimeout, WriterResultSendTimeout, WriterResultSuccess,
+    ReaderResultBlacklisted, ReaderResultDecompressionFailed, ReaderResultDuplicate,
+    ReaderResultMessage, ReaderResultPrefixMismatch, ReaderResultTimeout, SeqIdStatus, SocketEvent,
+    WriterResultAck, WriterResultAckTimeout, WriterResultRateLimited, WriterResultSendTimeout,
+    WriterResultSuccess,
 };
 use savant_core_py::zmq::{blocking, nonblocking};
 use savant_core_py::*;
@@ -80,6 +89,9 @@ pub fn webserver(_py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(is_shutdown_set, m)?)?;
     m.add_function(wrap_pyfunction!(set_status_running, m)?)?;
     m.add_function(wrap_pyfunction!(set_shutdown_signal, m)?)?;
+    m.add_function(wrap_pyfunction!(set_kvs_persistence, m)?)?;
+    m.add_function(wrap_pyfunction!(start_kvs_replication, m)?)?;
+    m.add_function(wrap_pyfunction!(stop_kvs_replication, m)?)?;
     m.add_wrapped(wrap_pymodule!(self::kvs))?;
     Ok(())
 }
@@ -90,6 +102,9 @@ pub fn kvs(_py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(get_attribute, m)?)?;
     m.add_function(wrap_pyfunction!(search_attributes, m)?)?;
     m.add_function(wrap_pyfunction!(search_keys, m)?)?;
+    m.add_function(wrap_pyfunction!(compare_and_set, m)?)?;
+    m.add_function(wrap_pyfunction!(set_if_absent, m)?)?;
+    m.add_function(wrap_pyfunction!(increment, m)?)?;
     m.add_function(wrap_pyfunction!(del_attributes, m)?)?;
     m.add_function(wrap_pyfunction!(del_attribute, m)?)?;
     m.add_function(wrap_pyfunction!(serialize_attributes, m)?)?;
@@ -98,12 +113,18 @@ pub fn kvs(_py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<KvsSubscription>()?;
     m.add_class::<KvsSetOperation>()?;
     m.add_class::<KvsDeleteOperation>()?;
+    m.add_class::<KvsExpiredOperation>()?;
+    m.add_class::<KvsEvictedOperation>()?;
 
     Ok(())
 }
 
 #[pymodule(gil_used = false)]
 pub fn zmq(_py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<CurveConfig>()?; // PYI
+    m.add_class::<RateLimit>()?; // PYI
+    m.add_function(wrap_pyfunction!(generate_curve_keypair, m)?)?; // PYI
+
     m.add_class::<WriterSocketType>()?; // PYI
     m.add_class::<WriterConfigBuilder>()?; // PYI
     m.add_class::<WriterConfig>()?; // PYI
@@ -111,6 +132,7 @@ pub fn zmq(_py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<WriterResultAckTimeout>()?; // PYI
     m.add_class::<WriterResultAck>()?; // PYI
     m.add_class::<WriterResultSuccess>()?; // PYI
+    m.add_class::<WriterResultRateLimited>()?; // PYI
 
     m.add_class::<blocking::BlockingWriter>()?; // PYI
     m.add_class::<nonblocking::NonBlockingWriter>()?; // PYI
@@ -118,12 +140,17 @@ pub fn zmq(_py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
 
     m.add_class::<ReaderSocketType>()?; // PYI
     m.add_class::<TopicPrefixSpec>()?; // PYI
+    m.add_class::<TopicFilter>()?; // PYI
     m.add_class::<ReaderConfigBuilder>()?; // PYI
     m.add_class::<ReaderConfig>()?; // PYI
+    m.add_class::<SeqIdStatus>()?; // PYI
+    m.add_class::<SocketEvent>()?; // PYI
     m.add_class::<ReaderResultMessage>()?; // PYI
     m.add_class::<ReaderResultBlacklisted>()?;
     m.add_class::<ReaderResultTimeout>()?; // PYI
     m.add_class::<ReaderResultPrefixMismatch>()?; // PYI
+    m.add_class::<ReaderResultDecompressionFailed>()?; // PYI
+    m.add_class::<ReaderResultDuplicate>()?; // PYI
 
     m.add_class::<blocking::BlockingReader>()?; // PYI
     m.add_class::<nonblocking::NonBlockingReader>()?;
@@ -207,6 +234,9 @@ pub fn geometry(_py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
 
     m.add_function(wrap_pyfunction!(solely_owned_areas, m)?)?;
     m.add_function(wrap_pyfunction!(associate_bboxes, m)?)?;
+    m.add_function(wrap_pyfunction!(nms, m)?)?;
+    m.add_function(wrap_pyfunction!(soft_nms, m)?)?;
+    m.add_function(wrap_pyfunction!(weighted_boxes_fusion, m)?)?;
 
     Ok(())
 }
@@ -243,6 +273,7 @@ pub fn primitives(_py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
 #[pymodule(gil_used = false)]
 pub(crate) fn pipeline(_py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<VideoPipelineStagePayloadType>()?;
+    m.add_class::<VideoPipelineStageQueuePolicy>()?;
     m.add_class::<PipelineConfiguration>()?;
     m.add_class::<Pipeline>()?;
     m.add_class::<FrameProcessingStatRecord>()?;
@@ -255,6 +286,23 @@ pub(crate) fn pipeline(_py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
     Ok(())
 }
 
+#[pymodule(gil_used = false)]
+pub fn tracker(_py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<SortTrackerConfig>()?;
+    m.add_class::<SortTracker>()?;
+    Ok(())
+}
+
+#[pymodule(gil_used = false)]
+pub fn analytics(_py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<TrackAnchor>()?;
+    m.add_class::<ZoneAnalyticsConfig>()?;
+    m.add_class::<ZoneEventKind>()?;
+    m.add_class::<ZoneEvent>()?;
+    m.add_class::<ZoneAnalytics>()?;
+    Ok(())
+}
+
 #[pymodule(gil_used = false)]
 pub fn match_query(_py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<FloatExpression>()?;
@@ -334,6 +382,8 @@ fn savant_rs(py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
 
     m.add_wrapped(wrap_pymodule!(self::primitives))?;
     m.add_wrapped(wrap_pymodule!(self::pipeline))?;
+    m.add_wrapped(wrap_pymodule!(self::tracker))?;
+    m.add_wrapped(wrap_pymodule!(self::analytics))?;
     m.add_wrapped(wrap_pymodule!(self::geometry))?;
     m.add_wrapped(wrap_pymodule!(self::draw_spec))?; // PYI
     m.add_wrapped(wrap_pymodule!(self::utils))?; // PYI
@@ -354,6 +404,8 @@ fn savant_rs(py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
     sys_modules.set_item("savant_rs.primitives", m.getattr("primitives")?)?;
     sys_modules.set_item("savant_rs.pipeline", m.getattr("pipeline")?)?;
     sys_modules.set_item("savant_rs.pipeline2", m.getattr("pipeline")?)?;
+    sys_modules.set_item("savant_rs.tracker", m.getattr("tracker")?)?;
+    sys_modules.set_item("savant_rs.analytics", m.getattr("analytics")?)?;
 
     sys_modules.set_item("savant_rs.primitives.geometry", m.getattr("geometry")?)?;
     sys_modules.set_item("savant_rs.draw_spec", m.getattr("draw_spec")?)?;